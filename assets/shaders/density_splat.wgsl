@@ -0,0 +1,86 @@
+// Body density on the GPU, in three dispatches: clear the bins, count the
+// bodies in each texel with atomics, then shade the counts through the
+// colormap into the output texture.
+
+struct Params {
+    // World position of the texture's lower-left corner
+    origin: vec2<f32>,
+    texels_per_unit: vec2<f32>,
+    size: u32,
+    count: u32,
+    // 0: heatmap, 1: isocontours
+    contours: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> positions: array<vec2<f32>>;
+// size² bins, then the largest bin
+@group(0) @binding(2) var<storage, read_write> bins: array<atomic<u32>>;
+@group(0) @binding(3) var<storage, read> lut: array<vec4<f32>>;
+@group(0) @binding(4) var output: texture_storage_2d<rgba8unorm, write>;
+
+// Contour levels over the normalized log density
+const LEVELS: f32 = 8.0;
+
+@compute @workgroup_size(8, 8, 1)
+fn clear(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.size || id.y >= params.size {
+        return;
+    }
+    atomicStore(&bins[id.y * params.size + id.x], 0u);
+    if id.x == 0u && id.y == 0u {
+        atomicStore(&bins[params.size * params.size], 0u);
+    }
+}
+
+@compute @workgroup_size(64, 1, 1)
+fn splat(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.count {
+        return;
+    }
+    let texel = floor((positions[id.x] - params.origin) * params.texels_per_unit);
+    if any(texel < vec2<f32>(0.0)) || any(texel >= vec2<f32>(f32(params.size))) {
+        return;
+    }
+    let n = atomicAdd(&bins[u32(texel.y) * params.size + u32(texel.x)], 1u) + 1u;
+    atomicMax(&bins[params.size * params.size], n);
+}
+
+// log(1 + count) over log(1 + largest count), clamped to the texture edge
+fn density(x: i32, y: i32) -> f32 {
+    let last = i32(params.size) - 1;
+    let bin = u32(clamp(y, 0, last)) * params.size + u32(clamp(x, 0, last));
+    let peak = max(atomicLoad(&bins[params.size * params.size]), 1u);
+    return log2(1.0 + f32(atomicLoad(&bins[bin]))) / log2(1.0 + f32(peak));
+}
+
+fn colormap(t: f32) -> vec3<f32> {
+    let last = arrayLength(&lut) - 1u;
+    return lut[min(u32(t * f32(last) + 0.5), last)].rgb;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn shade(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.size || id.y >= params.size {
+        return;
+    }
+    let x = i32(id.x);
+    let y = i32(id.y);
+    let t = density(x, y);
+    var color = vec4<f32>(0.0);
+    if params.contours == 0u {
+        if t > 0.0 {
+            color = vec4<f32>(colormap(t), mix(0.3, 0.9, t));
+        }
+    } else {
+        // A texel is on a contour where its band differs from a neighbour's
+        let band = floor(t * LEVELS);
+        let top = max(band, max(floor(density(x + 1, y) * LEVELS), floor(density(x, y + 1) * LEVELS)));
+        let low = min(band, min(floor(density(x + 1, y) * LEVELS), floor(density(x, y + 1) * LEVELS)));
+        if top != low {
+            color = vec4<f32>(colormap(min(top / LEVELS, 1.0)), 1.0);
+        }
+    }
+    // Texture rows run top to bottom, world y bottom to top
+    textureStore(output, vec2<i32>(x, i32(params.size) - 1 - y), color);
+}
@@ -0,0 +1,52 @@
+// Bodies as round, soft-edged points. Every body is a quad whose four
+// vertices share its center; the vertex stage pushes them out to the corners.
+
+#import bevy_sprite::mesh2d_view_bindings::view
+
+struct PointParams {
+    // 0: size fixed in world units (grows when zooming in), 1: fixed on screen
+    attenuation: f32,
+    // Width of the faded rim, as a fraction of the radius
+    softness: f32,
+    // Multiplies every point's alpha
+    intensity: f32,
+};
+
+@group(2) @binding(0) var<uniform> params: PointParams;
+
+struct Vertex {
+    @location(0) center: vec3<f32>,
+    @location(1) corner: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) size: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vertex(v: Vertex) -> VertexOutput {
+    // World units per pixel in this view; 1 at the default zoom
+    let world_per_px = 2.0 / (view.clip_from_world[0][0] * view.viewport.z);
+    // Never narrower than two pixels, so the smallest bodies don't vanish
+    let half = max(0.5 * v.size * pow(world_per_px, params.attenuation), world_per_px);
+    let world = vec4<f32>(v.center.xy + v.corner * half, v.center.z, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = view.clip_from_world * world;
+    out.corner = v.corner;
+    out.color = v.color;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let alpha = 1.0 - smoothstep(1.0 - params.softness, 1.0, length(in.corner));
+    if alpha <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * alpha * params.intensity);
+}
@@ -0,0 +1,13 @@
+// The trail image, added onto the background. The blend state is set up on
+// the Rust side; the texture is black wherever no trail has been drawn.
+
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(2) @binding(0) var trail_texture: texture_2d<f32>;
+@group(2) @binding(1) var trail_sampler: sampler;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(trail_texture, trail_sampler, in.uv);
+    return vec4<f32>(color.rgb, 1.0);
+}
@@ -0,0 +1,137 @@
+//! Color-vision-deficiency palettes for categorical coloring and a
+//! high-contrast mode for the UI. Both are saved with the user settings.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::colormap::LegendSegment;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum Palette {
+    /// Evenly spread hues; hard to tell apart with red-green deficiency
+    #[default]
+    Standard,
+    /// Okabe & Ito's palette
+    Deuteranopia,
+    /// Paul Tol's "bright" scheme
+    Protanopia,
+}
+
+// Okabe-Ito without black, which vanishes on the dark background
+const OKABE_ITO: [[u8; 3]; 7] = [
+    [0xE6, 0x9F, 0x00],
+    [0x56, 0xB4, 0xE9],
+    [0x00, 0x9E, 0x73],
+    [0xF0, 0xE4, 0x42],
+    [0x00, 0x72, 0xB2],
+    [0xD5, 0x5E, 0x00],
+    [0xCC, 0x79, 0xA7],
+];
+const TOL_BRIGHT: [[u8; 3]; 6] = [
+    [0x44, 0x77, 0xAA],
+    [0x66, 0xCC, 0xEE],
+    [0x22, 0x88, 0x33],
+    [0xCC, 0xBB, 0x44],
+    [0xEE, 0x66, 0x77],
+    [0xAA, 0x33, 0x77],
+];
+
+impl Palette {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(Palette::Standard),
+            "deuteranopia" => Some(Palette::Deuteranopia),
+            "protanopia" => Some(Palette::Protanopia),
+            _ => None,
+        }
+    }
+
+    /// Stable color for category `k` (e.g. a group index)
+    pub fn category(self, k: usize) -> Color {
+        let table: &[[u8; 3]] = match self {
+            Palette::Standard => {
+                // Golden-angle hue steps keep neighbouring indices apart
+                let hue = (k as f32 * 137.508) % 360.0;
+                return Color::hsl(hue, 0.85, 0.6);
+            }
+            Palette::Deuteranopia => &OKABE_ITO,
+            Palette::Protanopia => &TOL_BRIGHT,
+        };
+        let [r, g, b] = table[k % table.len()];
+        Color::srgb_u8(r, g, b)
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Accessibility {
+    pub palette: Palette,
+    /// Opaque black behind all UI text, which is drawn in pure white
+    pub high_contrast: bool,
+}
+
+/// Colors a UI node had before high contrast replaced them
+#[derive(Component)]
+pub struct OriginalColors {
+    text: Vec<Color>,
+    background: Option<Color>,
+}
+
+/// Apply or undo high contrast on every UI node, and on nodes spawned later
+pub fn apply_high_contrast(
+    mut commands: Commands,
+    access: Res<Accessibility>,
+    mut nodes: Query<
+        (
+            Entity,
+            Option<&mut Text>,
+            Option<&mut BackgroundColor>,
+            Option<&OriginalColors>,
+        ),
+        (With<Node>, Without<LegendSegment>),
+    >,
+) {
+    for (entity, text, background, original) in nodes.iter_mut() {
+        match (access.high_contrast, original) {
+            (true, None) => {
+                let mut saved = OriginalColors {
+                    text: Vec::new(),
+                    background: background.as_ref().map(|bg| bg.0),
+                };
+                if let Some(mut text) = text {
+                    for section in text.sections.iter_mut() {
+                        saved.text.push(section.style.color);
+                        section.style.color = Color::WHITE;
+                    }
+                    commands
+                        .entity(entity)
+                        .insert(BackgroundColor(Color::BLACK));
+                } else if let Some(mut bg) = background {
+                    // Panels: keep their tint's hue, drop the translucency
+                    if bg.0.alpha() > 0.0 {
+                        bg.0.set_alpha(1.0);
+                    }
+                }
+                commands.entity(entity).insert(saved);
+            }
+            (false, Some(saved)) => {
+                if let Some(mut text) = text {
+                    for (section, &color) in text.sections.iter_mut().zip(&saved.text) {
+                        section.style.color = color;
+                    }
+                }
+                match saved.background {
+                    Some(color) => {
+                        commands.entity(entity).insert(BackgroundColor(color));
+                    }
+                    None => {
+                        commands.entity(entity).remove::<BackgroundColor>();
+                    }
+                }
+                commands.entity(entity).remove::<OriginalColors>();
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Optional second OS window for the plots and diagnostics. While it is
+//! open, the analysis overlays (structure, binaries, groups, Lyapunov
+//! text, speed histogram and radial profiles) are drawn there by a camera
+//! of their own, and the main window shows only the particles and the
+//! energy readouts. Closing it, by key or from the OS, moves them back.
+
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::view::RenderLayers;
+use bevy::window::{WindowRef, WindowResolution};
+
+use crate::keybindings::{Action, KeyBindings};
+use crate::radial_profile::ProfilePanel;
+use crate::speed_histogram::SpeedHistogramPanel;
+use crate::{UiBinaries, UiGroups, UiLyapunov, UiStructure};
+
+/// Layer nothing is drawn on, so the analysis camera renders only UI
+const LAYER: usize = 4;
+
+#[derive(Resource, Default)]
+pub struct AnalysisWindow {
+    window: Option<Entity>,
+    camera: Option<Entity>,
+}
+
+impl AnalysisWindow {
+    pub fn is_open(&self) -> bool {
+        self.window.is_some()
+    }
+}
+
+type AnalysisOverlay = Or<(
+    With<UiStructure>,
+    With<UiBinaries>,
+    With<UiGroups>,
+    With<UiLyapunov>,
+    With<SpeedHistogramPanel>,
+    With<ProfilePanel>,
+)>;
+
+fn open(commands: &mut Commands, analysis: &mut AnalysisWindow) {
+    let window = commands
+        .spawn(Window {
+            title: "Analysis".to_string(),
+            resolution: WindowResolution::new(1000.0, 800.0),
+            ..Default::default()
+        })
+        .id();
+    let camera = commands
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    target: RenderTarget::Window(WindowRef::Entity(window)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderLayers::layer(LAYER),
+        ))
+        .id();
+    *analysis = AnalysisWindow {
+        window: Some(window),
+        camera: Some(camera),
+    };
+}
+
+pub fn toggle_analysis_window(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Query<(), With<Window>>,
+    mut commands: Commands,
+    mut analysis: ResMut<AnalysisWindow>,
+) {
+    // Closed from the OS: Bevy has already despawned the window
+    let closed = analysis.window.is_some_and(|w| windows.get(w).is_err());
+    let pressed = bindings.just_pressed(&keys, Action::ToggleAnalysisWindow);
+    if !closed && !pressed {
+        return;
+    }
+    if analysis.is_open() {
+        for entity in [analysis.window, analysis.camera].into_iter().flatten() {
+            if let Some(mut e) = commands.get_entity(entity) {
+                e.despawn();
+            }
+        }
+        *analysis = AnalysisWindow::default();
+    } else {
+        open(&mut commands, &mut analysis);
+    }
+    info!("Analysis window: {}", analysis.is_open());
+}
+
+/// Point the analysis overlays at the camera of the window they belong in
+pub fn route_analysis_overlays(
+    analysis: Res<AnalysisWindow>,
+    mut commands: Commands,
+    overlays: Query<Entity, AnalysisOverlay>,
+) {
+    if !analysis.is_changed() {
+        return;
+    }
+    for entity in &overlays {
+        match analysis.camera {
+            Some(camera) => commands.entity(entity).insert(TargetCamera(camera)),
+            None => commands.entity(entity).remove::<TargetCamera>(),
+        };
+    }
+}
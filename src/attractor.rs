@@ -0,0 +1,109 @@
+//! Mouse attractor: while the mode is on, holding the left button puts a
+//! temporary massive body at the cursor, the right button a repulsive one.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::external::ExternalField;
+use crate::keybindings::{Action, KeyBindings};
+use crate::units::SI;
+use crate::{MainCamera, world_scale};
+
+#[derive(Resource)]
+pub struct MouseAttractor {
+    pub enabled: bool,
+    /// Mass of the attractor; the repeller uses the negative
+    pub mass: f32,
+    /// Plummer softening length
+    pub softening: f32,
+    /// Simulation position and signed mass while a button is held
+    active: Option<((f32, f32), f32)>,
+}
+
+impl Default for MouseAttractor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mass: SI.mass(2.0E31),
+            softening: SI.length(2.0E13),
+            active: None,
+        }
+    }
+}
+
+impl MouseAttractor {
+    /// The attractor as a fixed point mass for the next force pass
+    pub fn field(&self) -> Option<ExternalField> {
+        self.active.map(|(pos, mass)| ExternalField::Perturber {
+            mass,
+            start: pos,
+            velocity: (0.0, 0.0),
+            softening: self.softening,
+        })
+    }
+}
+
+/// Run condition: clicks pick bodies only while the attractor mode is off
+pub fn attractor_disabled(attractor: Res<MouseAttractor>) -> bool {
+    !attractor.enabled
+}
+
+pub fn toggle_attractor(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut attractor: ResMut<MouseAttractor>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleAttractor) {
+        attractor.enabled = !attractor.enabled;
+        info!("Mouse attractor: {}", attractor.enabled);
+    }
+}
+
+/// Follow the cursor while a mouse button is held
+pub fn update_attractor(
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut attractor: ResMut<MouseAttractor>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let sign = if !attractor.enabled {
+        None
+    } else if buttons.pressed(MouseButton::Left) {
+        Some(1.0)
+    } else if buttons.pressed(MouseButton::Right) {
+        Some(-1.0)
+    } else {
+        None
+    };
+    let (Some(sign), Ok(window), Ok((camera, cam_tf))) =
+        (sign, win_q.get_single(), cam_q.get_single())
+    else {
+        attractor.active = None;
+        return;
+    };
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|c| camera.viewport_to_world_2d(cam_tf, c).ok())
+    else {
+        attractor.active = None;
+        return;
+    };
+    let pos = cursor / world_scale(window);
+    attractor.active = Some(((pos.x, pos.y), sign * attractor.mass));
+}
+
+pub fn draw_attractor(
+    mut gizmos: Gizmos,
+    attractor: Res<MouseAttractor>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+) {
+    let (Some(((x, y), mass)), Ok(window)) = (attractor.active, win_q.get_single()) else {
+        return;
+    };
+    let color = if mass > 0.0 {
+        Color::srgb(0.4, 0.7, 1.0)
+    } else {
+        Color::srgb(1.0, 0.3, 0.3)
+    };
+    gizmos.circle_2d(Vec2::new(x, y) * world_scale(window), 10.0, color);
+}
@@ -0,0 +1,237 @@
+//! `batch <sweep.ron> <out_dir>`: run every combination of a parameter sweep
+//! headlessly, one diagnostics file per run plus `summary.csv`.
+//!
+//! ```ron
+//! (
+//!     bodies: [100, 200],
+//!     dt: [1.0e7, 2.0e7],
+//!     softening: [0.0, 1.0e12],
+//!     seeds: [1, 2, 3],
+//!     steps: 2000,
+//!     parallel: true,
+//! )
+//! ```
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use crate::constants::PhysicsConstants;
+use crate::headless::{RunConfig, Simulation};
+use crate::units::{CODE, SI};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct SweepSpec {
+    pub bodies: Vec<usize>,
+    /// Timesteps (s)
+    pub dt: Vec<f32>,
+    /// Softening lengths (m)
+    pub softening: Vec<f32>,
+    pub seeds: Vec<u64>,
+    /// Steps per run
+    pub steps: u64,
+    /// Plummer scale radius (m)
+    pub scale_radius: f32,
+    /// Steps between diagnostics rows
+    pub output_interval: u64,
+    /// Run several combinations at once, one per core
+    pub parallel: bool,
+}
+
+impl Default for SweepSpec {
+    fn default() -> Self {
+        Self {
+            bodies: vec![100],
+            dt: vec![2.0E07],
+            softening: vec![0.0],
+            seeds: vec![0],
+            steps: 1000,
+            scale_radius: 5.0E13,
+            output_interval: 10,
+            parallel: false,
+        }
+    }
+}
+
+impl SweepSpec {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        ron::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Every combination, seeds varying fastest, in code units
+    pub fn runs(&self) -> Vec<RunConfig> {
+        let mut out = Vec::new();
+        for &bodies in &self.bodies {
+            for &dt in &self.dt {
+                for &softening in &self.softening {
+                    for &seed in &self.seeds {
+                        out.push(RunConfig {
+                            bodies,
+                            dt: SI.time(dt),
+                            softening: SI.length(softening),
+                            seed,
+                            scale_radius: SI.length(self.scale_radius),
+                        });
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// What a finished run reports to the summary
+struct RunSummary {
+    final_drift: f64,
+    max_drift: f64,
+    wall_seconds: f64,
+}
+
+/// Integrate one run, writing `step,time,energies,drift` rows to `path`
+fn run_one(
+    config: &RunConfig,
+    spec: &SweepSpec,
+    constants: &PhysicsConstants,
+    path: &Path,
+) -> std::io::Result<RunSummary> {
+    let start = Instant::now();
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(
+        w,
+        "# bodies={} dt={:e} softening={:e} seed={}",
+        config.bodies,
+        CODE.time(config.dt),
+        CODE.length(config.softening),
+        config.seed
+    )?;
+    writeln!(
+        w,
+        "step,time_s,kinetic_J,potential_J,total_J,relative_drift"
+    )?;
+    let mut sim = Simulation::new(config, constants);
+    let mut e0 = None;
+    let (mut drift, mut max_drift) = (0.0, 0.0f64);
+    loop {
+        let last = sim.step >= spec.steps;
+        if last || sim.step % spec.output_interval.max(1) == 0 {
+            let (ke, pe) = (sim.kinetic_energy(), sim.potential_energy());
+            let total = ke + pe;
+            let e0 = *e0.get_or_insert(total);
+            drift = if e0 != 0.0 {
+                (total - e0) / e0.abs()
+            } else {
+                0.0
+            };
+            max_drift = max_drift.max(drift.abs());
+            writeln!(
+                w,
+                "{},{:.6e},{:.6e},{:.6e},{:.6e},{drift:.6e}",
+                sim.step,
+                sim.time * CODE.time,
+                CODE.energy_f64(ke),
+                CODE.energy_f64(pe),
+                CODE.energy_f64(total)
+            )?;
+        }
+        if last {
+            break;
+        }
+        sim.step();
+    }
+    w.flush()?;
+    Ok(RunSummary {
+        final_drift: drift,
+        max_drift,
+        wall_seconds: start.elapsed().as_secs_f64(),
+    })
+}
+
+/// Call `job` for 0..count, on one thread per core if `parallel`, and
+/// collect the results in order
+pub fn run_all<T: Send>(count: usize, parallel: bool, job: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let workers = if parallel {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        1
+    };
+    println!("{count} runs on {} thread(s)", workers.min(count).max(1));
+    // Workers take the next unclaimed index until none are left
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new((0..count).map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..workers.min(count) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= count {
+                        break;
+                    }
+                    let result = job(i);
+                    results.lock().unwrap()[i] = Some(result);
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every index is run"))
+        .collect()
+}
+
+pub fn run(spec_path: &Path, out_dir: &Path) -> Result<(), String> {
+    let spec = SweepSpec::load(spec_path)?;
+    let runs = spec.runs();
+    fs::create_dir_all(out_dir).map_err(|e| format!("{}: {e}", out_dir.display()))?;
+    let constants = PhysicsConstants::read_asset_file();
+    let results = run_all(runs.len(), spec.parallel, |i| {
+        let path = out_dir.join(format!("run_{i:04}.csv"));
+        let result = run_one(&runs[i], &spec, &constants, &path)
+            .map_err(|e| format!("{}: {e}", path.display()));
+        match &result {
+            Ok(s) => println!(
+                "run {i}: drift {:.3e} ({:.1} s)",
+                s.final_drift, s.wall_seconds
+            ),
+            Err(e) => eprintln!("run {i} failed: {e}"),
+        }
+        result
+    });
+
+    let summary_path = out_dir.join("summary.csv");
+    let write_summary = || -> std::io::Result<()> {
+        let mut w = BufWriter::new(File::create(&summary_path)?);
+        writeln!(
+            w,
+            "run,bodies,dt_s,softening_m,seed,final_drift,max_abs_drift,wall_s"
+        )?;
+        for (i, (config, result)) in runs.iter().zip(results).enumerate() {
+            let Ok(s) = result else {
+                continue;
+            };
+            writeln!(
+                w,
+                "{i},{},{:e},{:e},{},{:.6e},{:.6e},{:.3}",
+                config.bodies,
+                CODE.time(config.dt),
+                CODE.length(config.softening),
+                config.seed,
+                s.final_drift,
+                s.max_drift,
+                s.wall_seconds
+            )?;
+        }
+        w.flush()
+    };
+    write_summary().map_err(|e| format!("{}: {e}", summary_path.display()))?;
+    println!("summary written to {}", summary_path.display());
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! `bench-direct [n ...]`: time the tiled direct sum against the untiled
+//! per-body loop on Plummer spheres of each size.
+
+use std::time::Instant;
+
+use rand::SeedableRng;
+
+use crate::constants::PhysicsConstants;
+use crate::force_law::ForceLaw;
+use crate::neighbors::CellGrid;
+use crate::physics::{self, Boundary};
+use crate::sim_rng::Xoshiro256PlusPlus;
+use crate::units::{CODE, SI};
+use crate::{MAX_MASS, MIN_MASS, ic};
+
+const DEFAULT_SIZES: [usize; 4] = [256, 1024, 4096, 8192];
+/// Repetitions per size; the fastest is reported
+const REPEATS: usize = 3;
+
+/// Fastest of `REPEATS` runs of `f`, in milliseconds
+fn best_ms(mut f: impl FnMut()) -> f64 {
+    (0..REPEATS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed().as_secs_f64() * 1e3
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+pub fn run(sizes: &[usize]) {
+    let sizes = if sizes.is_empty() {
+        &DEFAULT_SIZES[..]
+    } else {
+        sizes
+    };
+    // No cutoff, so both versions do the full N^2 sum
+    let constants = PhysicsConstants {
+        cutoff_radius: f32::INFINITY,
+        ..PhysicsConstants::read_asset_file()
+    };
+    let mean_mass = 0.5 * (MAX_MASS + MIN_MASS);
+    println!(
+        "{:>8} {:>12} {:>12} {:>8} {:>12}",
+        "bodies", "untiled ms", "tiled ms", "speedup", "max |diff|"
+    );
+    for &n in sizes {
+        let snap: physics::Snapshot = ic::plummer(
+            &mut Xoshiro256PlusPlus::seed_from_u64(n as u64),
+            n,
+            mean_mass * n as f32,
+            SI.length(5.0E13),
+            (0.0, 0.0),
+            (0.0, 0.0),
+            constants.gravitation,
+        )
+        .iter()
+        .map(|b| [b.x, b.y, b.mass, b.charge])
+        .collect();
+        let (boundary, law) = (Boundary::Open, ForceLaw::Gravity);
+
+        let mut untiled = Vec::new();
+        let untiled_ms =
+            best_ms(|| untiled = physics::accelerations_untiled(&snap, boundary, law, &constants));
+        let (mut grid, mut tiled) = (CellGrid::default(), Vec::new());
+        let tiled_ms = best_ms(|| {
+            physics::accelerations(&snap, boundary, law, &constants, &mut grid, &mut tiled)
+        });
+        let max_diff = untiled
+            .iter()
+            .zip(&tiled)
+            .map(|(a, b)| (a[0] - b[0]).abs().max((a[1] - b[1]).abs()))
+            .fold(0.0f32, f32::max);
+        let max_diff = CODE.acceleration(max_diff);
+        println!(
+            "{n:>8} {untiled_ms:>12.2} {tiled_ms:>12.2} {:>7.2}x {max_diff:>12.3e}",
+            untiled_ms / tiled_ms
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Bound-pair (binary) detection and highlighting.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::diagnostics::is_due;
+use bevy::window::PrimaryWindow;
+
+use crate::constants::PhysicsConstants;
+use crate::locale::{Language, tr};
+use crate::units::{CODE, SI};
+use crate::{Bodies, BodyState, UiBinaries};
+
+const LISTED_BINARIES: usize = 5;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BoundPair {
+    /// Lighter member
+    pub i: usize,
+    /// Heavier member
+    pub j: usize,
+    pub separation: f32,
+    /// Relative orbital energy, negative for bound pairs
+    pub energy: f64,
+    /// Semi-major axis of the relative orbit
+    pub semi_major_axis: f64,
+}
+
+#[derive(Resource)]
+pub struct BinaryScan {
+    /// Rescan every `interval` physics steps
+    pub interval: u64,
+    /// Only pairs closer than this are considered
+    pub max_separation: f32,
+    pub last_step: Option<u64>,
+    /// Detected pairs, tightest (smallest semi-major axis) first
+    pub pairs: Vec<BoundPair>,
+}
+
+impl Default for BinaryScan {
+    fn default() -> Self {
+        Self {
+            interval: 15,
+            max_separation: SI.length(1.0E13),
+            last_step: None,
+            pairs: Vec::new(),
+        }
+    }
+}
+
+/// Each body paired with its nearest bound partner closer than `max_separation`.
+/// A pair is reported once, whichever of its members found it.
+pub fn find_bound_pairs(data: &[BodyState], max_separation: f32, g: f32) -> Vec<BoundPair> {
+    let cell_of = |b: &BodyState| {
+        (
+            (b.x / max_separation).floor() as i64,
+            (b.y / max_separation).floor() as i64,
+        )
+    };
+    let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, b) in data.iter().enumerate() {
+        cells.entry(cell_of(b)).or_default().push(i);
+    }
+
+    let mut pairs = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, bi) in data.iter().enumerate() {
+        let (cx, cy) = cell_of(bi);
+        let mut best: Option<(usize, f32, f64)> = None;
+        for ny in cy - 1..=cy + 1 {
+            for nx in cx - 1..=cx + 1 {
+                let Some(members) = cells.get(&(nx, ny)) else {
+                    continue;
+                };
+                for &j in members {
+                    if j == i {
+                        continue;
+                    }
+                    let r = separation(bi, &data[j]);
+                    if r >= max_separation || best.is_some_and(|(_, rb, _)| r >= rb) {
+                        continue;
+                    }
+                    let e = two_body_energy(bi, &data[j], g);
+                    if e < 0.0 {
+                        best = Some((j, r, e));
+                    }
+                }
+            }
+        }
+        let Some((j, r, e)) = best else {
+            continue;
+        };
+        if !seen.insert((i.min(j), i.max(j))) {
+            continue;
+        }
+        let (lighter, heavier) = if (bi.mass, i) <= (data[j].mass, j) {
+            (i, j)
+        } else {
+            (j, i)
+        };
+        let gmm = g as f64 * bi.mass as f64 * data[j].mass as f64;
+        pairs.push(BoundPair {
+            i: lighter,
+            j: heavier,
+            separation: r,
+            energy: e,
+            semi_major_axis: -gmm / (2.0 * e),
+        });
+    }
+    pairs
+}
+
+pub fn separation(a: &BodyState, b: &BodyState) -> f32 {
+    ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt()
+}
+
+/// Relative orbital energy of a pair: ½μv² − G m_a m_b / r
+pub fn two_body_energy(a: &BodyState, b: &BodyState, g: f32) -> f64 {
+    let (ma, mb) = (a.mass as f64, b.mass as f64);
+    let mu = ma * mb / (ma + mb);
+    let dvx = (b.vx - a.vx) as f64;
+    let dvy = (b.vy - a.vy) as f64;
+    let r = separation(a, b) as f64;
+    0.5 * mu * (dvx * dvx + dvy * dvy) - g as f64 * ma * mb / r
+}
+
+pub fn scan_binaries(
+    bodies: Res<Bodies>,
+    constants: Res<PhysicsConstants>,
+    mut scan: ResMut<BinaryScan>,
+) {
+    let step = bodies.step;
+    if !is_due(scan.last_step, step, scan.interval) {
+        return;
+    }
+    scan.last_step = Some(step);
+    let mut pairs = find_bound_pairs(&bodies.data, scan.max_separation, constants.gravitation);
+    pairs.sort_by(|a, b| a.semi_major_axis.total_cmp(&b.semi_major_axis));
+    scan.pairs = pairs;
+}
+
+/// Connect the members of every detected pair
+pub fn draw_binaries(
+    mut gizmos: Gizmos,
+    scan: Res<BinaryScan>,
+    bodies: Res<Bodies>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(window) = win_q.get_single() else {
+        return;
+    };
+    let half = Vec2::new(window.width(), window.height()) / 2.0;
+    let world = |b: &BodyState| Vec2::new(b.disp_x, b.disp_y) - half;
+    for p in &scan.pairs {
+        let (Some(a), Some(b)) = (bodies.data.get(p.i), bodies.data.get(p.j)) else {
+            continue;
+        };
+        gizmos.line_2d(world(a), world(b), Color::srgb(0.3, 1.0, 0.6));
+    }
+}
+
+pub fn update_binary_text(
+    scan: Res<BinaryScan>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<UiBinaries>>,
+) {
+    if !scan.is_changed() && !lang.is_changed() {
+        return;
+    }
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    let mut out = format!(
+        "{}: {}",
+        tr("bound pairs", "속박 쌍").get(*lang),
+        scan.pairs.len()
+    );
+    for p in scan.pairs.iter().take(LISTED_BINARIES) {
+        out += &format!(
+            "\n#{} – #{}  a = {:.2E} m  E = {:.2E} J",
+            p.i,
+            p.j,
+            p.semi_major_axis * CODE.length,
+            CODE.energy_f64(p.energy)
+        );
+    }
+    t.sections[0].value = out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(x: f32, mass: f32) -> BodyState {
+        let mut b = BodyState::new();
+        b.x = x;
+        b.x_prev = x;
+        b.mass = mass;
+        b
+    }
+
+    #[test]
+    fn pairs_found_by_the_heavier_member_are_kept_once() {
+        // The heavy body at +3 is nearest to the light one, whose own
+        // nearest partner is the heavy body at -2
+        let data = [
+            body(0.0, 1.0E20),
+            body(3.0E12, 1.0E30),
+            body(-2.0E12, 1.0E30),
+        ];
+        let mut pairs: Vec<_> = find_bound_pairs(&data, 1.0E13, 6.674E-11)
+            .iter()
+            .map(|p| (p.i, p.j))
+            .collect();
+        pairs.sort();
+        assert_eq!(pairs, [(0, 1), (0, 2)]);
+    }
+}
@@ -0,0 +1,195 @@
+//! History of the selected body: its distance from the center of mass, its
+//! speed in the center-of-mass frame and its specific orbital energy, in a
+//! bounded ring buffer. Built with `--features inspector`, a window plots
+//! the three against time; without it nothing is recorded.
+//!
+//! The sample is taken once per rendered frame, from the latest finished
+//! step. When a frame runs several steps the ones in between are skipped,
+//! so the samples are spaced by whole frames, not by steps; each carries its
+//! own simulated time, which the plots use as their axis.
+
+use bevy::prelude::*;
+
+/// Body whose history is shown, kept across body reordering
+#[derive(Resource, Default)]
+pub struct BodyHistory {
+    pub body: Option<usize>,
+}
+
+pub struct BodyHistoryPlugin;
+
+impl Plugin for BodyHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BodyHistory>();
+        #[cfg(feature = "inspector")]
+        app.init_resource::<window::Samples>().add_systems(
+            Update,
+            (window::record_body_history, window::show_body_history).chain(),
+        );
+    }
+}
+
+#[cfg(feature = "inspector")]
+mod window {
+    use std::collections::VecDeque;
+
+    use bevy::prelude::*;
+    use bevy_inspector_egui::bevy_egui::EguiContexts;
+    use bevy_inspector_egui::egui;
+
+    use super::BodyHistory;
+    use crate::constants::PhysicsConstants;
+    use crate::selection::Selection;
+    use crate::summation::CompensatedSum;
+    use crate::units::CODE;
+    use crate::{Bodies, BodyState};
+
+    /// Samples kept; the oldest are dropped first
+    const CAPACITY: usize = 4096;
+
+    #[derive(Clone, Copy, Debug)]
+    pub struct Sample {
+        /// Simulated time (s)
+        pub time: f64,
+        /// Distance from the center of mass (m)
+        pub distance: f64,
+        /// Speed relative to the center of mass (m/s)
+        pub speed: f64,
+        /// Kinetic plus potential energy per unit mass (J/kg)
+        pub energy: f64,
+    }
+
+    /// Samples of `BodyHistory::body`
+    #[derive(Resource, Default)]
+    pub struct Samples {
+        samples: VecDeque<Sample>,
+        last_step: Option<u64>,
+    }
+
+    /// Sample of body `i` at code time `time`, converted to SI; O(N) for
+    /// the center of mass and the potential
+    pub fn sample(data: &[BodyState], i: usize, constants: &PhysicsConstants, time: f64) -> Sample {
+        let mut sums: [CompensatedSum; 5] = Default::default();
+        for b in data {
+            let m = b.mass as f64;
+            sums[0] += m;
+            sums[1] += m * b.x as f64;
+            sums[2] += m * b.y as f64;
+            sums[3] += m * b.vx as f64;
+            sums[4] += m * b.vy as f64;
+        }
+        let [mass, mx, my, mvx, mvy] = sums.map(|s| s.value());
+        let mass = mass.max(f64::MIN_POSITIVE);
+        let b = &data[i];
+        let (dx, dy) = (b.x as f64 - mx / mass, b.y as f64 - my / mass);
+        let (vx, vy) = (b.vx as f64 - mvx / mass, b.vy as f64 - mvy / mass);
+
+        let eps2 = (constants.softening as f64).powi(2);
+        let potential: f64 = data
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, o)| {
+                let r2 = ((o.x - b.x) as f64).powi(2) + ((o.y - b.y) as f64).powi(2);
+                -(constants.gravitation as f64) * o.mass as f64
+                    / (r2 + eps2).sqrt().max(f64::MIN_POSITIVE)
+            })
+            .sum();
+        let speed2 = vx * vx + vy * vy;
+        let speed_unit = CODE.length / CODE.time;
+        Sample {
+            time: time * CODE.time,
+            distance: (dx * dx + dy * dy).sqrt() * CODE.length,
+            speed: speed2.sqrt() * speed_unit,
+            energy: (0.5 * speed2 + potential) * speed_unit * speed_unit,
+        }
+    }
+
+    /// One sample per frame that finished a new step; a new selection or a
+    /// rewind starts over
+    pub fn record_body_history(
+        bodies: Res<Bodies>,
+        selection: Res<Selection>,
+        constants: Res<PhysicsConstants>,
+        mut history: ResMut<BodyHistory>,
+        mut samples: ResMut<Samples>,
+    ) {
+        let selected = selection.0.filter(|&i| i < bodies.data.len());
+        if selected != history.body || samples.last_step.is_some_and(|last| bodies.step < last) {
+            history.body = selected;
+            samples.samples.clear();
+            samples.last_step = None;
+        }
+        let Some(i) = selected else {
+            return;
+        };
+        if samples.last_step == Some(bodies.step) {
+            return;
+        }
+        samples.last_step = Some(bodies.step);
+        let s = sample(&bodies.data, i, &constants, bodies.elapsed_time as f64);
+        samples.samples.push_back(s);
+        while samples.samples.len() > CAPACITY {
+            samples.samples.pop_front();
+        }
+    }
+
+    const PLOT_SIZE: egui::Vec2 = egui::vec2(320.0, 90.0);
+
+    /// One quantity against time, scaled to its own range
+    fn plot(
+        ui: &mut egui::Ui,
+        label: &str,
+        unit: &str,
+        samples: &[Sample],
+        value: fn(&Sample) -> f64,
+    ) {
+        let (lo, hi) = samples
+            .iter()
+            .map(value)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(v), hi.max(v))
+            });
+        let last = samples.last().map_or(0.0, value);
+        ui.label(format!("{label}: {last:.3E} {unit}   [{lo:.2E}, {hi:.2E}]"));
+        let (rect, _) = ui.allocate_exact_size(PLOT_SIZE, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+        let (Some(first), Some(end)) = (samples.first(), samples.last()) else {
+            return;
+        };
+        let span = (end.time - first.time).max(f64::MIN_POSITIVE);
+        let range = (hi - lo).max(f64::MIN_POSITIVE);
+        let points: Vec<egui::Pos2> = samples
+            .iter()
+            .map(|s| {
+                let u = ((s.time - first.time) / span) as f32;
+                let v = ((value(s) - lo) / range) as f32;
+                egui::pos2(
+                    rect.left() + u * rect.width(),
+                    rect.bottom() - v * rect.height(),
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            egui::Stroke::new(1.0, egui::Color32::LIGHT_BLUE),
+        ));
+    }
+
+    pub fn show_body_history(
+        history: Res<BodyHistory>,
+        samples: Res<Samples>,
+        mut contexts: EguiContexts,
+    ) {
+        let Some(body) = history.body else {
+            return;
+        };
+        let samples: Vec<Sample> = samples.samples.iter().copied().collect();
+        egui::Window::new(format!("Body {body}")).show(contexts.ctx_mut(), |ui| {
+            plot(ui, "distance from COM", "m", &samples, |s| s.distance);
+            plot(ui, "speed", "m/s", &samples, |s| s.speed);
+            plot(ui, "specific energy", "J/kg", &samples, |s| s.energy);
+        });
+    }
+}
@@ -0,0 +1,164 @@
+//! Bound/unbound classification for the boundness coloring mode: a body is
+//! bound when its kinetic energy in the center-of-mass frame is less than
+//! the depth of the whole system's (softened) gravitational potential at
+//! its position, i.e. it moves slower than the local escape velocity.
+
+use bevy::prelude::*;
+use bevy::tasks::ComputeTaskPool;
+
+use crate::coloring::ColorMode;
+use crate::constants::PhysicsConstants;
+use crate::diagnostics::is_due;
+use crate::hud::{HudItem, Overlay};
+use crate::locale::{Language, fill, tr};
+use crate::{Bodies, BodyState};
+
+#[derive(Resource)]
+pub struct Boundness {
+    /// Reclassify every this many steps
+    pub interval: u64,
+    pub last_step: Option<u64>,
+    /// Per body, indexed like `Bodies::data`
+    pub bound: Vec<bool>,
+}
+
+impl Default for Boundness {
+    fn default() -> Self {
+        Self {
+            interval: 30,
+            last_step: None,
+            bound: Vec::new(),
+        }
+    }
+}
+
+impl Boundness {
+    pub fn bound_count(&self) -> usize {
+        self.bound.iter().filter(|&&b| b).count()
+    }
+}
+
+/// Whether each body is bound to the rest; O(N²), split across the compute pool
+pub fn classify(data: &[BodyState], constants: &PhysicsConstants) -> Vec<bool> {
+    let n = data.len();
+    let mass: f64 = data.iter().map(|b| b.mass as f64).sum();
+    if n == 0 || mass <= 0.0 {
+        return Vec::new();
+    }
+    let drift = data.iter().fold((0.0f64, 0.0f64), |(px, py), b| {
+        (
+            px + b.mass as f64 * b.vx as f64,
+            py + b.mass as f64 * b.vy as f64,
+        )
+    });
+    let drift = (drift.0 / mass, drift.1 / mass);
+    let g = constants.gravitation as f64;
+    let eps2 = (constants.softening as f64).powi(2);
+
+    let pool = ComputeTaskPool::get();
+    let chunk = n.div_ceil(pool.thread_num().max(1));
+    pool.scope(|scope| {
+        for start in (0..n).step_by(chunk) {
+            scope.spawn(async move {
+                data[start..(start + chunk).min(n)]
+                    .iter()
+                    .enumerate()
+                    .map(|(k, b)| {
+                        let i = start + k;
+                        // Depth of the potential, per unit mass
+                        let depth: f64 = data
+                            .iter()
+                            .enumerate()
+                            .filter(|&(j, _)| j != i)
+                            .map(|(_, o)| {
+                                let r2 =
+                                    ((o.x - b.x) as f64).powi(2) + ((o.y - b.y) as f64).powi(2);
+                                g * o.mass as f64 / (r2 + eps2).sqrt().max(f64::MIN_POSITIVE)
+                            })
+                            .sum();
+                        let v2 = (b.vx as f64 - drift.0).powi(2) + (b.vy as f64 - drift.1).powi(2);
+                        0.5 * v2 < depth
+                    })
+                    .collect::<Vec<bool>>()
+            });
+        }
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Reclassify periodically while the boundness coloring is shown
+pub fn update_boundness(
+    bodies: Res<Bodies>,
+    mode: Res<ColorMode>,
+    constants: Res<PhysicsConstants>,
+    mut boundness: ResMut<Boundness>,
+) {
+    let resized = boundness.bound.len() != bodies.data.len();
+    if *mode != ColorMode::Boundness
+        || (!resized && !is_due(boundness.last_step, bodies.step, boundness.interval))
+    {
+        return;
+    }
+    boundness.last_step = Some(bodies.step);
+    boundness.bound = classify(&bodies.data, &constants);
+}
+
+#[derive(Component)]
+pub struct BoundText;
+
+pub fn spawn_bound_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 14.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            bottom: Val::Px(70.0),
+            ..Default::default()
+        }),
+        Visibility::Hidden,
+        BoundText,
+        HudItem(Overlay::Legend),
+    ));
+}
+
+/// Bound fraction, shown in place of the colormap legend
+pub fn update_bound_text(
+    mode: Res<ColorMode>,
+    boundness: Res<Boundness>,
+    lang: Res<Language>,
+    mut q: Query<(&mut Text, &mut Visibility), With<BoundText>>,
+) {
+    if !mode.is_changed() && !boundness.is_changed() && !lang.is_changed() {
+        return;
+    }
+    let Ok((mut t, mut vis)) = q.get_single_mut() else {
+        return;
+    };
+    if *mode != ColorMode::Boundness {
+        *vis = Visibility::Hidden;
+        return;
+    }
+    *vis = Visibility::Inherited;
+    let (bound, total) = (boundness.bound_count(), boundness.bound.len());
+    t.sections[0].value = fill(
+        tr(
+            "bound (blue): {}% ({} / {}), unbound in red",
+            "속박 (파란색): {}% ({} / {}), 비속박은 빨간색",
+        )
+        .get(*lang),
+        &[
+            &format!("{:.1}", 100.0 * bound as f64 / total.max(1) as f64),
+            &bound,
+            &total,
+        ],
+    );
+}
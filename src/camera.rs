@@ -0,0 +1,49 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+
+use crate::MainCamera;
+use crate::keybindings::{Action, KeyBindings};
+
+const ZOOM_STEP: f32 = 1.1;
+const MIN_ZOOM: f32 = 0.01;
+const MAX_ZOOM: f32 = 10.0;
+const PAN_SPEED: f32 = 400.0; // screen px per second
+
+/// Mouse wheel zooms the 2D camera, the pan keys move it.
+pub fn camera_controls(
+    mut wheel: EventReader<MouseWheel>,
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    mut q: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let Ok((mut tf, mut proj)) = q.get_single_mut() else {
+        return;
+    };
+
+    for ev in wheel.read() {
+        let steps = match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / 40.0,
+        };
+        proj.scale = (proj.scale * ZOOM_STEP.powf(-steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    let mut dir = Vec2::ZERO;
+    if bindings.pressed(&keys, Action::PanLeft) {
+        dir.x -= 1.0;
+    }
+    if bindings.pressed(&keys, Action::PanRight) {
+        dir.x += 1.0;
+    }
+    if bindings.pressed(&keys, Action::PanDown) {
+        dir.y -= 1.0;
+    }
+    if bindings.pressed(&keys, Action::PanUp) {
+        dir.y += 1.0;
+    }
+    // Pan speed is in screen pixels so it feels the same at every zoom level
+    let delta = dir * PAN_SPEED * proj.scale * time.delta_secs();
+    tf.translation.x += delta.x;
+    tf.translation.y += delta.y;
+}
@@ -0,0 +1,112 @@
+//! Center of mass of all bodies: a crosshair drawn at it and a follow mode
+//! that keeps it centered, so a drifting cluster stays on screen.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::keybindings::{Action, KeyBindings};
+use crate::summation::CompensatedSum;
+use crate::{Bodies, BodyState, MainCamera, world_scale};
+
+/// Half-length of the crosshair arms (screen px)
+const MARKER_SIZE: f32 = 10.0;
+
+#[derive(Resource, Default)]
+pub struct CenterOfMass {
+    /// Keep the main camera on the center of mass
+    pub follow: bool,
+    /// Center of mass before and after the last step, `None` without mass
+    previous: Option<Vec2>,
+    current: Option<Vec2>,
+    last_step: Option<u64>,
+}
+
+impl CenterOfMass {
+    /// Position between the last two steps, matching the interpolated bodies
+    pub fn interpolated(&self, alpha: f32) -> Option<Vec2> {
+        let current = self.current?;
+        Some(self.previous.unwrap_or(current).lerp(current, alpha))
+    }
+}
+
+/// Mass-weighted mean of the positions `at` picks from each body
+fn weighted_mean(data: &[BodyState], at: impl Fn(&BodyState) -> (f32, f32)) -> Option<Vec2> {
+    let (mut mx, mut my, mut mass) = (
+        CompensatedSum::default(),
+        CompensatedSum::default(),
+        CompensatedSum::default(),
+    );
+    for b in data {
+        let (x, y) = at(b);
+        mx += b.mass as f64 * x as f64;
+        my += b.mass as f64 * y as f64;
+        mass += b.mass as f64;
+    }
+    let m = mass.value();
+    (m > 0.0).then(|| Vec2::new((mx.value() / m) as f32, (my.value() / m) as f32))
+}
+
+/// Recompute once per physics step
+pub fn update_center_of_mass(bodies: Res<Bodies>, mut com: ResMut<CenterOfMass>) {
+    if com.last_step == Some(bodies.step) {
+        return;
+    }
+    com.last_step = Some(bodies.step);
+    com.previous = weighted_mean(&bodies.data, |b| (b.x_prev, b.y_prev));
+    com.current = weighted_mean(&bodies.data, |b| (b.x, b.y));
+}
+
+pub fn toggle_follow(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut com: ResMut<CenterOfMass>,
+) {
+    if bindings.just_pressed(&keys, Action::FollowCenterOfMass) {
+        com.follow = !com.follow;
+        info!("Follow center of mass: {}", com.follow);
+    }
+}
+
+/// Center the main camera on the interpolated center of mass. Runs after
+/// the pan keys, so they have no lasting effect while following.
+pub fn follow_center_of_mass(
+    com: Res<CenterOfMass>,
+    fixed_time: Res<Time<Fixed>>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    mut cam_q: Query<&mut Transform, With<MainCamera>>,
+) {
+    if !com.follow {
+        return;
+    }
+    let (Ok(window), Ok(mut tf)) = (win_q.get_single(), cam_q.get_single_mut()) else {
+        return;
+    };
+    let Some(p) = com.interpolated(fixed_time.overstep_fraction()) else {
+        return;
+    };
+    let p = p * world_scale(window);
+    tf.translation.x = p.x;
+    tf.translation.y = p.y;
+}
+
+/// Crosshair at the center of mass, constant on screen at every zoom
+pub fn draw_center_of_mass(
+    mut gizmos: Gizmos,
+    com: Res<CenterOfMass>,
+    fixed_time: Res<Time<Fixed>>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    let (Ok(window), Ok(proj)) = (win_q.get_single(), cam_q.get_single()) else {
+        return;
+    };
+    let Some(p) = com.interpolated(fixed_time.overstep_fraction()) else {
+        return;
+    };
+    let p = p * world_scale(window);
+    let arm = MARKER_SIZE * proj.scale;
+    let color = Color::srgb(1.0, 0.85, 0.3);
+    gizmos.line_2d(p - Vec2::X * arm, p + Vec2::X * arm, color);
+    gizmos.line_2d(p - Vec2::Y * arm, p + Vec2::Y * arm, color);
+    gizmos.circle_2d(p, 0.4 * arm, color);
+}
@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+/// Command-line options
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    /// `--scenario <file.ron>`
+    pub scenario: Option<PathBuf>,
+    /// `--ic <name[:key=value,...]>`: start from a registered generator
+    pub ic: Option<String>,
+    /// `ic-list`: print the registered generators and their parameters
+    pub ic_list: bool,
+    /// `--diagnostics <file.csv>`
+    pub diagnostics: Option<PathBuf>,
+    /// `--plot-output <dir>`
+    pub plot_output: Option<PathBuf>,
+    /// `--npz <dir>`
+    pub npz: Option<PathBuf>,
+    /// `--npz-interval <steps>`
+    pub npz_interval: Option<u64>,
+    /// `--parquet <dir>`
+    pub parquet: Option<PathBuf>,
+    /// `--video <file.mp4|file.webm>`: record through ffmpeg
+    pub video: Option<PathBuf>,
+    /// `--video-size <WxH>`: window size while recording
+    pub video_size: Option<(u32, u32)>,
+    /// `--video-fps <fps>`
+    pub video_fps: Option<u32>,
+    /// `--video-duration <seconds>`: stop recording and exit after this long
+    pub video_duration: Option<f64>,
+    /// `compare <a.ron> <b.ron>`: report snapshot differences instead of running
+    pub compare: Option<(PathBuf, PathBuf)>,
+    /// `batch <sweep.ron> <out_dir>`: run a parameter sweep headlessly
+    pub batch: Option<(PathBuf, PathBuf)>,
+    /// `ensemble <spec.ron> <out_dir>`: seeded realizations and their statistics
+    pub ensemble: Option<(PathBuf, PathBuf)>,
+    /// `--record <file.ron>`: save the seed and every input, for `--replay`
+    pub record: Option<PathBuf>,
+    /// `--replay <file.ron>`: rerun a recorded session
+    pub replay: Option<PathBuf>,
+    /// `--metrics <addr>`: serve Prometheus metrics (feature `metrics`)
+    pub metrics: Option<String>,
+    /// `--connect <addr>`: show the bodies streamed by a `serve` instance
+    pub connect: Option<String>,
+    /// `serve <addr> [bodies] [seed]`: simulate headlessly for remote viewers
+    pub serve: Option<(String, Option<usize>, Option<u64>)>,
+    /// `--seed <n>`: seed of the random draws, entropy if left out
+    pub seed: Option<u64>,
+    /// `bench-direct [n ...]`: time the direct-sum kernels for these body counts
+    pub bench_direct: Option<Vec<usize>>,
+    /// `check-determinism [steps] [seed]`: rerun one setup and compare bits
+    pub check_determinism: Option<(Option<u64>, Option<u64>)>,
+}
+
+impl CliArgs {
+    pub fn parse() -> Self {
+        let mut out = Self::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scenario" => out.scenario = args.next().map(PathBuf::from),
+                "--ic" => out.ic = args.next(),
+                "ic-list" => out.ic_list = true,
+                "--diagnostics" => out.diagnostics = args.next().map(PathBuf::from),
+                "--plot-output" => out.plot_output = args.next().map(PathBuf::from),
+                "--npz" => out.npz = args.next().map(PathBuf::from),
+                "--npz-interval" => out.npz_interval = args.next().and_then(|s| s.parse().ok()),
+                "--parquet" => out.parquet = args.next().map(PathBuf::from),
+                "--video" => out.video = args.next().map(PathBuf::from),
+                "--video-size" => {
+                    out.video_size = args.next().and_then(|s| {
+                        let (w, h) = s.split_once('x')?;
+                        Some((w.parse().ok()?, h.parse().ok()?))
+                    })
+                }
+                "--video-fps" => out.video_fps = args.next().and_then(|s| s.parse().ok()),
+                "--video-duration" => out.video_duration = args.next().and_then(|s| s.parse().ok()),
+                "--record" => out.record = args.next().map(PathBuf::from),
+                "--replay" => out.replay = args.next().map(PathBuf::from),
+                "--metrics" => out.metrics = args.next(),
+                "--connect" => out.connect = args.next(),
+                "--seed" => out.seed = args.next().and_then(|s| s.parse().ok()),
+                "compare" => match (args.next(), args.next()) {
+                    (Some(a), Some(b)) => out.compare = Some((a.into(), b.into())),
+                    _ => {
+                        eprintln!("usage: compare <a.ron> <b.ron>");
+                        std::process::exit(2);
+                    }
+                },
+                "batch" => match (args.next(), args.next()) {
+                    (Some(spec), Some(dir)) => out.batch = Some((spec.into(), dir.into())),
+                    _ => {
+                        eprintln!("usage: batch <sweep.ron> <out_dir>");
+                        std::process::exit(2);
+                    }
+                },
+                "ensemble" => match (args.next(), args.next()) {
+                    (Some(spec), Some(dir)) => out.ensemble = Some((spec.into(), dir.into())),
+                    _ => {
+                        eprintln!("usage: ensemble <spec.ron> <out_dir>");
+                        std::process::exit(2);
+                    }
+                },
+                "bench-direct" => {
+                    out.bench_direct = Some(args.by_ref().filter_map(|s| s.parse().ok()).collect())
+                }
+                "serve" => match args.next() {
+                    Some(addr) => {
+                        let mut numbers = args.by_ref().map(|s| s.parse::<u64>().ok());
+                        let bodies = numbers.next().flatten().map(|n| n as usize);
+                        out.serve = Some((addr, bodies, numbers.next().flatten()))
+                    }
+                    None => {
+                        eprintln!("usage: serve <addr> [bodies] [seed]");
+                        std::process::exit(2);
+                    }
+                },
+                "check-determinism" => {
+                    let mut numbers = args.by_ref().filter_map(|s| s.parse().ok());
+                    out.check_determinism = Some((numbers.next(), numbers.next()))
+                }
+                other => eprintln!("ignoring unknown argument: {other}"),
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,289 @@
+//! Two-level integration of dense clumps. Friends-of-friends groups are
+//! handed to the force pass as one composite mass each, so the rest of the
+//! system sees a clump as a point at its center of mass and every member
+//! feels the same external pull. The members' motion about the center of
+//! mass is then integrated separately, with the clump's own forces only and
+//! a timestep `substeps` times smaller than the global one.
+//!
+//! Like the regularized pair, a clump's center of mass follows the leapfrog
+//! step and the tidal stretch across it is neglected.
+
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+use crate::BodyState;
+use crate::constants::PhysicsConstants;
+use crate::fof::FofGroups;
+use crate::force_law::ForceLaw;
+use crate::physics::{Boundary, PairKernel, Snapshot};
+
+#[derive(Clone, Copy, Debug, Reflect)]
+pub struct ClumpConfig {
+    pub enabled: bool,
+    /// Internal steps per global step
+    pub substeps: u32,
+    /// Larger groups stay in the global pair sum, since their internal
+    /// sum grows with the square of the members
+    pub max_members: usize,
+}
+
+impl Default for ClumpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            substeps: 8,
+            max_members: 64,
+        }
+    }
+}
+
+/// Clumps treated during one step, with their members as they were at the
+/// start of the step. The storage is kept from step to step, so choosing
+/// and subcycling the clumps doesn't allocate once it has grown.
+#[derive(Clone, Debug, Default)]
+pub struct ClumpStep {
+    /// Members of every clump, one clump after the other
+    members: Vec<usize>,
+    /// End of each clump in `members`
+    ends: Vec<usize>,
+    /// State of each entry of `members` at the start of the step
+    start: Vec<BodyState>,
+    substeps: u32,
+    /// Start of each friends-of-friends group in `by_group`, then the next
+    /// free entry while it is filled
+    first: Vec<usize>,
+    /// Bodies sorted by group
+    by_group: Vec<usize>,
+    /// One clump's members relative to its center of mass
+    rel: Vec<BodyState>,
+    snap: Snapshot,
+    accel: Vec<[f32; 2]>,
+}
+
+impl ClumpStep {
+    /// Groups of the latest friends-of-friends pass small enough for the
+    /// config, without fixed bodies or any body in `exclude`
+    pub fn choose(
+        &mut self,
+        data: &[BodyState],
+        fof: &FofGroups,
+        config: &ClumpConfig,
+        exclude: &[usize],
+    ) {
+        self.clear();
+        if !config.enabled || config.substeps == 0 {
+            return;
+        }
+        self.substeps = config.substeps;
+        // Counting sort of the bodies by group, each group in index order
+        let groups = fof.groups.len();
+        let group_of = || {
+            fof.group_of
+                .iter()
+                .take(data.len())
+                .enumerate()
+                .filter_map(|(i, g)| g.filter(|&g| g < groups).map(|g| (i, g)))
+        };
+        self.first.clear();
+        self.first.resize(groups + 1, 0);
+        for (_, g) in group_of() {
+            self.first[g + 1] += 1;
+        }
+        for g in 0..groups {
+            self.first[g + 1] += self.first[g];
+        }
+        self.by_group.clear();
+        self.by_group.resize(self.first[groups], 0);
+        for (i, g) in group_of() {
+            self.by_group[self.first[g]] = i;
+            self.first[g] += 1;
+        }
+        // `first[g]` is now the end of group g
+        let mut begin = 0;
+        for &end in &self.first[..groups] {
+            let group = &self.by_group[begin..end];
+            begin = end;
+            if group.len() < 2
+                || group.len() > config.max_members
+                || group.iter().any(|&i| data[i].fixed || exclude.contains(&i))
+            {
+                continue;
+            }
+            self.members.extend_from_slice(group);
+            self.start.extend(group.iter().map(|&i| data[i]));
+            self.ends.push(self.members.len());
+        }
+    }
+
+    /// No clumps, keeping the storage
+    pub fn clear(&mut self) {
+        self.members.clear();
+        self.ends.clear();
+        self.start.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Members of every clump, one after the other, and the end of each clump
+    pub fn layout(&self) -> (&[usize], &[usize]) {
+        (&self.members, &self.ends)
+    }
+
+    /// Range of each clump in `members` and `start`
+    fn spans(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let mut begin = 0;
+        self.ends.iter().map(move |&end| {
+            let span = begin..end;
+            begin = end;
+            span
+        })
+    }
+
+    /// Follow a reordering of the bodies
+    pub fn remap(&mut self, remap: impl Fn(usize) -> usize) {
+        for i in self.members.iter_mut() {
+            *i = remap(*i);
+        }
+    }
+
+    /// One composite [x, y, mass, charge] per clump, followed by the bodies
+    /// outside every clump; `slot[i]` is the entry body `i` is part of
+    pub fn reduce(&self, snap: &[[f32; 4]], reduced: &mut Snapshot, slot: &mut Vec<usize>) {
+        reduced.clear();
+        slot.clear();
+        slot.resize(snap.len(), usize::MAX);
+        for (c, span) in self.spans().enumerate() {
+            let members = &self.members[span];
+            let (mut x, mut y, mut mass, mut charge) = (0.0f64, 0.0f64, 0.0f64, 0.0f32);
+            for &i in members {
+                let [bx, by, m, q] = snap[i];
+                x += m as f64 * bx as f64;
+                y += m as f64 * by as f64;
+                mass += m as f64;
+                charge += q;
+                slot[i] = c;
+            }
+            let (x, y) = if mass > 0.0 {
+                (x / mass, y / mass)
+            } else {
+                (snap[members[0]][0] as f64, snap[members[0]][1] as f64)
+            };
+            reduced.push([x as f32, y as f32, mass as f32, charge]);
+        }
+        for (i, s) in slot.iter_mut().enumerate() {
+            if *s == usize::MAX {
+                *s = reduced.len();
+                reduced.push(snap[i]);
+            }
+        }
+    }
+
+    /// Accelerations of every body from those of the reduced snapshot
+    pub fn expand(slot: &[usize], reduced: &[[f32; 2]], accel: &mut Vec<[f32; 2]>) {
+        accel.clear();
+        accel.extend(slot.iter().map(|&s| reduced[s]));
+    }
+
+    /// Replace the motion of each clump about its center of mass in `data`
+    /// (already advanced by `dt`) with the subcycled internal solution from
+    /// the start of the step
+    pub fn apply(
+        &mut self,
+        data: &mut [BodyState],
+        dt: f32,
+        law: ForceLaw,
+        constants: &PhysicsConstants,
+    ) {
+        let Self {
+            members,
+            ends,
+            start,
+            substeps,
+            rel,
+            snap,
+            accel,
+            ..
+        } = self;
+        let h = dt / *substeps as f32;
+        let mut begin = 0;
+        for &end in ends.iter() {
+            let (members, start) = (&members[begin..end], &start[begin..end]);
+            begin = end;
+            if members.iter().any(|&i| i >= data.len()) {
+                continue;
+            }
+            let com_start = center_of_mass(start.iter().copied());
+            let com_end = center_of_mass(members.iter().map(|&i| data[i]));
+            rel.clear();
+            rel.extend(start.iter().map(|b| {
+                let mut r = *b;
+                r.x -= com_start[0];
+                r.y -= com_start[1];
+                r.vx -= com_start[2];
+                r.vy -= com_start[3];
+                r
+            }));
+            internal_accelerations(rel, law, constants, snap, accel);
+            for _ in 0..*substeps {
+                // Kick-drift-kick with the clump's own forces only
+                for (b, a) in rel.iter_mut().zip(accel.iter()) {
+                    b.vx += 0.5 * h * a[0];
+                    b.vy += 0.5 * h * a[1];
+                    b.x += h * b.vx;
+                    b.y += h * b.vy;
+                }
+                internal_accelerations(rel, law, constants, snap, accel);
+                for (b, a) in rel.iter_mut().zip(accel.iter()) {
+                    b.vx += 0.5 * h * a[0];
+                    b.vy += 0.5 * h * a[1];
+                }
+            }
+            for (&i, r) in members.iter().zip(rel.iter()) {
+                let b = &mut data[i];
+                b.x = com_end[0] + r.x;
+                b.y = com_end[1] + r.y;
+                b.vx = com_end[2] + r.vx;
+                b.vy = com_end[3] + r.vy;
+            }
+        }
+    }
+}
+
+/// Mass-weighted [x, y, vx, vy]
+fn center_of_mass(bodies: impl Iterator<Item = BodyState>) -> [f32; 4] {
+    let mut sum = [0.0f64; 4];
+    let mut mass = 0.0f64;
+    for b in bodies {
+        let m = b.mass as f64;
+        for (s, v) in sum.iter_mut().zip([b.x, b.y, b.vx, b.vy]) {
+            *s += m * v as f64;
+        }
+        mass += m;
+    }
+    if mass <= 0.0 {
+        return [0.0; 4];
+    }
+    sum.map(|s| (s / mass) as f32)
+}
+
+/// Pair sum within one clump, written to `accel`; `snap` is scratch space
+fn internal_accelerations(
+    rel: &[BodyState],
+    law: ForceLaw,
+    constants: &PhysicsConstants,
+    snap: &mut Snapshot,
+    accel: &mut Vec<[f32; 2]>,
+) {
+    snap.clear();
+    snap.extend(rel.iter().map(|b| [b.x, b.y, b.mass, b.charge]));
+    let kernel = PairKernel::new(snap, Boundary::Open, law, constants);
+    accel.clear();
+    accel.extend((0..snap.len()).map(|i| kernel.body(i, None)));
+}
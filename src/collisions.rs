@@ -0,0 +1,134 @@
+//! Contact detection. Each body is treated as a disc whose radius grows with
+//! the cube root of its mass. Candidate pairs come from the spatial index,
+//! so only nearby bodies get the exact overlap test, and every overlapping
+//! pair is sent as a `Collision` event once per step.
+
+use bevy::prelude::*;
+use bevy::tasks::ComputeTaskPool;
+
+use crate::neighbors::SpatialIndex;
+use crate::selection::Selection;
+use crate::units::SI;
+use crate::{Bodies, BodyState, MAX_MASS};
+
+/// Default radius of a body of `MAX_MASS`
+const DEFAULT_RADIUS: f32 = SI.length(1.0E12);
+
+#[derive(Resource)]
+pub struct Collisions {
+    pub enabled: bool,
+    /// Radius of a body of `MAX_MASS`
+    pub radius: f32,
+    /// Pairs in contact at the last checked step
+    pub contacts: usize,
+    /// Contacts reported since detection was switched on
+    pub total: u64,
+    last_step: Option<u64>,
+}
+
+impl Default for Collisions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: DEFAULT_RADIUS,
+            contacts: 0,
+            total: 0,
+            last_step: None,
+        }
+    }
+}
+
+impl Collisions {
+    pub fn radius_of(&self, mass: f32) -> f32 {
+        self.radius * (mass.max(0.0) / MAX_MASS).cbrt()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.contacts = 0;
+        self.total = 0;
+        self.last_step = None;
+    }
+}
+
+/// Bodies `a` < `b` overlap after step `step`
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Collision {
+    pub a: usize,
+    pub b: usize,
+    pub step: u64,
+}
+
+/// Overlapping pairs of `data`, each once with the lower index first and
+/// sorted. `index` must have been built from the same positions.
+pub fn find_contacts(
+    data: &[BodyState],
+    index: &SpatialIndex,
+    radius_of: impl Fn(f32) -> f32 + Sync,
+) -> Vec<(usize, usize)> {
+    let n = data.len();
+    if n < 2 {
+        return Vec::new();
+    }
+    // No partner can reach farther than the largest radius
+    let largest = data.iter().map(|b| radius_of(b.mass)).fold(0.0, f32::max);
+    let radius_of = &radius_of;
+    let pool = ComputeTaskPool::get();
+    let chunk = n.div_ceil(pool.thread_num().max(1));
+    pool.scope(|scope| {
+        for start in (0..n).step_by(chunk) {
+            scope.spawn(async move {
+                let mut pairs = Vec::new();
+                for (i, a) in data.iter().enumerate().skip(start).take(chunk) {
+                    let pa = Vec2::new(a.x, a.y);
+                    let ra = radius_of(a.mass);
+                    for j in index.neighbors_within(pa, ra + largest) {
+                        let Some(b) = data.get(j).filter(|_| j > i) else {
+                            continue;
+                        };
+                        let reach = ra + radius_of(b.mass);
+                        if pa.distance_squared(Vec2::new(b.x, b.y)) <= reach * reach {
+                            pairs.push((i, j));
+                        }
+                    }
+                }
+                pairs
+            });
+        }
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Check once per step, right after the spatial index is rebuilt
+pub fn detect_collisions(
+    bodies: Res<Bodies>,
+    index: Res<SpatialIndex>,
+    mut collisions: ResMut<Collisions>,
+    mut events: EventWriter<Collision>,
+) {
+    if !collisions.enabled || collisions.last_step == Some(bodies.step) {
+        return;
+    }
+    collisions.last_step = Some(bodies.step);
+    let pairs = find_contacts(&bodies.data, &index, |m| collisions.radius_of(m));
+    collisions.contacts = pairs.len();
+    collisions.total += pairs.len() as u64;
+    let step = bodies.step;
+    events.send_batch(pairs.into_iter().map(|(a, b)| Collision { a, b, step }));
+}
+
+/// Log the contacts of the selected body
+pub fn report_selected_collisions(selection: Res<Selection>, mut events: EventReader<Collision>) {
+    let Some(selected) = selection.0 else {
+        events.clear();
+        return;
+    };
+    for c in events.read() {
+        if c.a == selected || c.b == selected {
+            let other = if c.a == selected { c.b } else { c.a };
+            info!("Body {selected} touched body {other} at step {}", c.step);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Per-body point coloring modes.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::Bodies;
+use crate::accessibility::Accessibility;
+use crate::boundness::Boundness;
+use crate::colormap::Colormap;
+use crate::density::DensityField;
+use crate::fof::FofGroups;
+use crate::keybindings::{Action, KeyBindings};
+
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    White,
+    /// Friends-of-friends group membership
+    Group,
+    /// Local surface density (log scale)
+    Density,
+    /// Bound (blue) or unbound (red) to the whole system
+    Boundness,
+}
+
+impl ColorMode {
+    fn next(self) -> Self {
+        match self {
+            ColorMode::White => ColorMode::Group,
+            ColorMode::Group => ColorMode::Density,
+            ColorMode::Density => ColorMode::Boundness,
+            ColorMode::Boundness => ColorMode::White,
+        }
+    }
+}
+
+pub fn cycle_color_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut mode: ResMut<ColorMode>,
+) {
+    if bindings.just_pressed(&keys, Action::CycleColorMode) {
+        *mode = mode.next();
+        info!("Coloring: {:?}", *mode);
+    }
+}
+
+/// Per-body point colors (linear RGBA), indexed like `Bodies::data`
+#[derive(Resource, Default)]
+pub struct BodyColors(pub Vec<[f32; 4]>);
+
+pub fn apply_colors(
+    bodies: Res<Bodies>,
+    mode: Res<ColorMode>,
+    map: Res<Colormap>,
+    access: Res<Accessibility>,
+    fof: Res<FofGroups>,
+    density: Res<DensityField>,
+    boundness: Res<Boundness>,
+    mut colors: ResMut<BodyColors>,
+) {
+    let resized = colors.0.len() != bodies.data.len();
+    if !resized
+        && !mode.is_changed()
+        && !map.is_changed()
+        && !access.is_changed()
+        && !fof.is_changed()
+        && !density.is_changed()
+        && !boundness.is_changed()
+    {
+        return;
+    }
+    let (lo, hi) = density.range;
+    let span = (hi - lo).max(f32::EPSILON);
+    let color_of = |i: usize| match *mode {
+        ColorMode::White => Color::WHITE,
+        ColorMode::Group => match fof.group_of.get(i).copied().flatten() {
+            Some(g) => access.palette.category(g),
+            None => Color::srgb(0.35, 0.35, 0.35),
+        },
+        ColorMode::Density => match density.log_density.get(i) {
+            Some(&v) => map.sample((v - lo) / span),
+            None => Color::WHITE,
+        },
+        ColorMode::Boundness => match boundness.bound.get(i) {
+            Some(true) => Color::srgb(0.3, 0.55, 1.0),
+            Some(false) => Color::srgb(1.0, 0.3, 0.25),
+            None => Color::WHITE,
+        },
+    };
+    colors.0.clear();
+    colors
+        .0
+        .extend((0..bodies.data.len()).map(|i| color_of(i).to_linear().to_f32_array()));
+}
@@ -0,0 +1,196 @@
+//! Colormaps for value-based coloring and the on-screen legend bar.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::coloring::ColorMode;
+use crate::density::DensityField;
+use crate::hud::{HudItem, Overlay};
+use crate::keybindings::{Action, KeyBindings};
+use crate::units::CODE;
+
+const LEGEND_SEGMENTS: usize = 32;
+
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Inferno,
+    Coolwarm,
+}
+
+// Control points sampled evenly over [0, 1] from the matplotlib maps
+const VIRIDIS: [[f32; 3]; 9] = [
+    [0.267, 0.005, 0.329],
+    [0.279, 0.175, 0.483],
+    [0.230, 0.322, 0.546],
+    [0.173, 0.449, 0.558],
+    [0.128, 0.567, 0.551],
+    [0.135, 0.659, 0.518],
+    [0.360, 0.786, 0.388],
+    [0.668, 0.862, 0.196],
+    [0.993, 0.906, 0.144],
+];
+const INFERNO: [[f32; 3]; 9] = [
+    [0.001, 0.000, 0.014],
+    [0.122, 0.047, 0.282],
+    [0.336, 0.060, 0.430],
+    [0.533, 0.134, 0.416],
+    [0.735, 0.216, 0.330],
+    [0.902, 0.364, 0.184],
+    [0.978, 0.558, 0.035],
+    [0.975, 0.783, 0.235],
+    [0.988, 0.998, 0.645],
+];
+const COOLWARM: [[f32; 3]; 9] = [
+    [0.230, 0.299, 0.754],
+    [0.348, 0.466, 0.888],
+    [0.484, 0.622, 0.975],
+    [0.619, 0.744, 0.999],
+    [0.865, 0.865, 0.865],
+    [0.958, 0.769, 0.678],
+    [0.969, 0.633, 0.513],
+    [0.915, 0.469, 0.355],
+    [0.706, 0.016, 0.150],
+];
+
+impl Colormap {
+    fn next(self) -> Self {
+        match self {
+            Colormap::Viridis => Colormap::Inferno,
+            Colormap::Inferno => Colormap::Coolwarm,
+            Colormap::Coolwarm => Colormap::Viridis,
+        }
+    }
+
+    /// Color at t in [0, 1] (clamped), linear between control points
+    pub fn sample(self, t: f32) -> Color {
+        let table = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Inferno => &INFERNO,
+            Colormap::Coolwarm => &COOLWARM,
+        };
+        let x = t.clamp(0.0, 1.0) * (table.len() - 1) as f32;
+        let i = (x as usize).min(table.len() - 2);
+        let f = x - i as f32;
+        let (a, b) = (table[i], table[i + 1]);
+        Color::srgb(
+            a[0] + (b[0] - a[0]) * f,
+            a[1] + (b[1] - a[1]) * f,
+            a[2] + (b[2] - a[2]) * f,
+        )
+    }
+}
+
+#[derive(Component)]
+pub struct Legend;
+
+#[derive(Component)]
+pub struct LegendSegment(usize);
+
+#[derive(Component)]
+pub struct LegendText;
+
+pub fn cycle_colormap(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut map: ResMut<Colormap>,
+) {
+    if bindings.just_pressed(&keys, Action::CycleColormap) {
+        *map = map.next();
+        info!("Colormap: {:?}", *map);
+    }
+}
+
+pub fn spawn_legend(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 14.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.0),
+                    bottom: Val::Px(70.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..Default::default()
+                },
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            Legend,
+            HudItem(Overlay::Legend),
+        ))
+        .with_children(|legend| {
+            legend
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(200.0),
+                        height: Val::Px(12.0),
+                        flex_direction: FlexDirection::Row,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|bar| {
+                    for k in 0..LEGEND_SEGMENTS {
+                        bar.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    flex_grow: 1.0,
+                                    height: Val::Percent(100.0),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            LegendSegment(k),
+                        ));
+                    }
+                });
+            legend.spawn((TextBundle::from_section("", style), LegendText));
+        });
+}
+
+pub fn update_legend(
+    mode: Res<ColorMode>,
+    map: Res<Colormap>,
+    density: Res<DensityField>,
+    mut legend_q: Query<&mut Visibility, With<Legend>>,
+    mut seg_q: Query<(&LegendSegment, &mut BackgroundColor)>,
+    mut text_q: Query<&mut Text, With<LegendText>>,
+) {
+    if !mode.is_changed() && !map.is_changed() && !density.is_changed() {
+        return;
+    }
+    let (label, range) = match *mode {
+        ColorMode::Density => {
+            let shift = CODE.surface_density(1.0).log10() as f32;
+            (
+                "log10 Σ (kg/m²)",
+                (density.range.0 + shift, density.range.1 + shift),
+            )
+        }
+        _ => {
+            for mut vis in legend_q.iter_mut() {
+                *vis = Visibility::Hidden;
+            }
+            return;
+        }
+    };
+
+    for mut vis in legend_q.iter_mut() {
+        *vis = Visibility::Inherited;
+    }
+    for (seg, mut bg) in seg_q.iter_mut() {
+        let t = (seg.0 as f32 + 0.5) / LEGEND_SEGMENTS as f32;
+        *bg = BackgroundColor(map.sample(t));
+    }
+    if let Ok(mut t) = text_q.get_single_mut() {
+        t.sections[0].value = format!("{label}: {:.2} … {:.2}", range.0, range.1);
+    }
+}
@@ -0,0 +1,143 @@
+//! `compare <a.ron> <b.ron>`: per-body and global differences between two
+//! snapshots of the same system, for checking that a refactor or another
+//! backend reproduces a reference run.
+
+use std::path::Path;
+
+use crate::constants::PhysicsConstants;
+use crate::force_law::ForceLaw;
+use crate::physics::{self, Boundary};
+use crate::snapshot::{BodyRecord, Snapshot};
+use crate::units::CODE;
+
+/// Conserved and summary quantities of one snapshot
+struct Invariants {
+    mass: f64,
+    kinetic: f64,
+    potential: f64,
+    px: f64,
+    py: f64,
+    lz: f64,
+    com_x: f64,
+    com_y: f64,
+}
+
+impl Invariants {
+    fn of(bodies: &[BodyRecord], constants: &PhysicsConstants) -> Self {
+        let mut out = Self {
+            mass: 0.0,
+            kinetic: 0.0,
+            potential: 0.0,
+            px: 0.0,
+            py: 0.0,
+            lz: 0.0,
+            com_x: 0.0,
+            com_y: 0.0,
+        };
+        for b in bodies {
+            let (m, x, y) = (b.mass as f64, b.x as f64, b.y as f64);
+            let (vx, vy) = (b.vx as f64, b.vy as f64);
+            out.mass += m;
+            out.kinetic += 0.5 * m * (vx * vx + vy * vy);
+            out.px += m * vx;
+            out.py += m * vy;
+            out.lz += m * (x * vy - y * vx);
+            out.com_x += m * x;
+            out.com_y += m * y;
+        }
+        if out.mass > 0.0 {
+            out.com_x /= out.mass;
+            out.com_y /= out.mass;
+        }
+        let snap: Vec<[f32; 4]> = bodies
+            .iter()
+            .map(|b| [b.x, b.y, b.mass, b.charge])
+            .collect();
+        out.potential =
+            physics::potential_energy(&snap, Boundary::Open, ForceLaw::Gravity, constants);
+        out
+    }
+
+    fn rows(&self) -> [(&'static str, f64); 9] {
+        [
+            ("total mass (kg)", self.mass),
+            ("kinetic energy (J)", self.kinetic),
+            ("potential energy (J)", self.potential),
+            ("total energy (J)", self.kinetic + self.potential),
+            ("momentum x (kg m/s)", self.px),
+            ("momentum y (kg m/s)", self.py),
+            ("angular momentum (kg m^2/s)", self.lz),
+            ("center of mass x (m)", self.com_x),
+            ("center of mass y (m)", self.com_y),
+        ]
+    }
+}
+
+/// Root mean square and largest (index, value) of per-body distances
+fn rms_and_max(distances: impl Iterator<Item = f64>) -> (f64, usize, f64) {
+    let (mut sum, mut n, mut max_i, mut max) = (0.0, 0usize, 0, 0.0);
+    for (i, d) in distances.enumerate() {
+        sum += d * d;
+        n += 1;
+        if d > max {
+            (max_i, max) = (i, d);
+        }
+    }
+    ((sum / n.max(1) as f64).sqrt(), max_i, max)
+}
+
+/// Print the comparison report; an error if either file can't be read or
+/// the body counts differ
+pub fn run(path_a: &Path, path_b: &Path) -> Result<(), String> {
+    let a = Snapshot::load(path_a)?;
+    let b = Snapshot::load(path_b)?;
+    if a.bodies.len() != b.bodies.len() {
+        return Err(format!(
+            "body counts differ: {} has {}, {} has {}",
+            path_a.display(),
+            a.bodies.len(),
+            path_b.display(),
+            b.bodies.len()
+        ));
+    }
+    println!(
+        "A: {} (step {}, t = {:.6e} s)",
+        path_a.display(),
+        a.step,
+        a.time
+    );
+    println!(
+        "B: {} (step {}, t = {:.6e} s)",
+        path_b.display(),
+        b.step,
+        b.time
+    );
+    println!("bodies: {}", a.bodies.len());
+
+    let pairs = || a.bodies.iter().zip(&b.bodies);
+    let (x_rms, x_i, x_max) =
+        rms_and_max(pairs().map(|(p, q)| ((p.x - q.x) as f64).hypot((p.y - q.y) as f64)));
+    let (v_rms, v_i, v_max) =
+        rms_and_max(pairs().map(|(p, q)| ((p.vx - q.vx) as f64).hypot((p.vy - q.vy) as f64)));
+    println!();
+    println!("position difference: rms {x_rms:.6e} m, max {x_max:.6e} m (body {x_i})");
+    println!("velocity difference: rms {v_rms:.6e} m/s, max {v_max:.6e} m/s (body {v_i})");
+
+    // Snapshots are in SI
+    let constants = PhysicsConstants::read_asset_file().convert(&CODE);
+    let inv_a = Invariants::of(&a.bodies, &constants);
+    let inv_b = Invariants::of(&b.bodies, &constants);
+    println!();
+    println!(
+        "{:<28} {:>14} {:>14} {:>14} {:>12}",
+        "quantity", "A", "B", "B - A", "relative"
+    );
+    for ((name, va), (_, vb)) in inv_a.rows().into_iter().zip(inv_b.rows()) {
+        let rel = if va != 0.0 { (vb - va) / va.abs() } else { 0.0 };
+        println!(
+            "{name:<28} {va:>14.6e} {vb:>14.6e} {:>14.6e} {rel:>12.3e}",
+            vb - va
+        );
+    }
+    Ok(())
+}
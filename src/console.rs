@@ -0,0 +1,868 @@
+//! Drop-down developer console with a small command interpreter.
+
+use std::path::PathBuf;
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use crate::accessibility::{Accessibility, Palette};
+use crate::collisions::Collisions;
+use crate::constants::PhysicsConstants;
+use crate::core_collapse::CoreCollapseMonitor;
+use crate::correlation::Correlation;
+use crate::emitter::Emitter;
+use crate::force_law::ForceLaw;
+use crate::hud::{Corner, HudLayout, Overlay};
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::Language;
+use crate::lyapunov::Lyapunov;
+use crate::menu::MAX_BODY_COUNT;
+use crate::momentum::MomentumCorrection;
+use crate::morton::MortonOrder;
+use crate::npz;
+use crate::orbit_path::OrbitPaths;
+use crate::phase_view::{PhaseAxis, PhaseView};
+use crate::physics::{PendingBodies, PhysicsSettings};
+use crate::poincare::{Axis, PoincareSection, Surface};
+use crate::quality::QualityGovernor;
+use crate::radial_profile::RadialProfiles;
+use crate::realtime::RealTimeFactor;
+use crate::reversal::Rewind;
+use crate::rotating_frame::{FrameRate, RotatingFrame};
+use crate::selection::Selection;
+use crate::sim_rng::SimRng;
+use crate::snapshot::Snapshot;
+use crate::speed_histogram::SpeedHistogram;
+use crate::springs::{Spring, Springs};
+use crate::trails::{self, Trails};
+use crate::units::{CODE, SI};
+use crate::{Bodies, MAX_MASS, MAX_V, MAX_X, MIN_MASS, ic};
+
+/// Output lines kept on screen
+const HISTORY_LINES: usize = 8;
+
+#[derive(Resource, Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    history: Vec<String>,
+    /// Lines entered this frame, run by `run_console_commands`
+    submitted: Vec<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.history.push(line.into());
+        let excess = self.history.len().saturating_sub(HISTORY_LINES);
+        self.history.drain(..excess);
+    }
+}
+
+/// A parsed command, with its values in SI as typed
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Help,
+    SetDt(f32),
+    /// Switch to MOND with this a₀ (m/s²)
+    SetMondA0(f32),
+    /// Auto real-time factor target, `None` for off
+    SetRealTimeFactor(Option<f64>),
+    /// Main-thread physics time per frame (s), `None` for no limit
+    SetBudget(Option<f32>),
+    Spawn {
+        count: usize,
+        kind: SpawnKind,
+    },
+    Save(PathBuf),
+    Select(Option<usize>),
+    /// Spring between two bodies, stiffness (N/m) defaulted when absent
+    Spring {
+        i: usize,
+        j: usize,
+        stiffness: Option<f32>,
+    },
+    ClearSprings,
+    /// Emitter setting: rate, speed, spread, mass or cap
+    Emitter(String, f64),
+    /// Recorded orbit paths to a .csv or .svg file
+    ExportPaths(PathBuf),
+    ClearPaths,
+    /// Surface of section, `None` for off
+    Section(Option<Surface>),
+    /// Lyapunov estimate for a body, `None` for off
+    Lyapunov(Option<usize>),
+    /// Momentum correction interval in steps, `None` for off
+    Momentum(Option<u64>),
+    /// Morton reordering interval in steps, `None` for off
+    Morton(Option<u64>),
+    /// Far-field refresh interval in steps (1 for off), near-field radius (m)
+    FarField {
+        interval: u32,
+        radius: Option<f32>,
+    },
+    /// Internal steps per global step for clumps, `None` for off
+    Clumps(Option<u32>),
+    /// Integrate backward, this many steps or the configured number
+    Rewind(Option<u64>),
+    /// Trail decay per frame, `None` for off
+    Trails(Option<f32>),
+    /// Contact detection, with the radius of a `MAX_MASS` body (m) if given
+    Collisions {
+        enabled: bool,
+        radius: Option<f32>,
+    },
+    /// Speed histogram recount interval in steps
+    Histogram(u64),
+    /// Maxwell–Boltzmann fit over the speed histogram
+    HistogramFit(bool),
+    /// Two-point correlation now (`None`), every this many steps, or
+    /// `Some(None)` for on request only
+    Correlation(Option<Option<u64>>),
+    /// Central density growth that counts as core collapse, `None` to
+    /// restart from the current density
+    Collapse(Option<f64>),
+    /// Coordinate plotted by the phase-space view
+    Phase(PhaseAxis),
+    /// Radial profile panel on or off
+    Profiles(bool),
+    /// Latest radial profiles to a .csv file
+    ExportProfiles(PathBuf),
+    /// Rotating view, `None` for the inertial one
+    Rotate(Option<FrameRate>),
+    Language(Language),
+    Palette(Palette),
+    /// List the HUD layout (`None`) or change one overlay
+    Hud(Option<(Overlay, HudChange)>),
+    /// Back to the default HUD layout
+    HudReset,
+    /// Frame rate the quality governor holds, `None` for off
+    Quality(Option<f32>),
+    HighContrast(bool),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HudChange {
+    Show(bool),
+    Move(Corner),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpawnKind {
+    Plummer,
+    Uniform,
+}
+
+const HELP: &str = "commands: set dt <s> | set a0 <m/s²> | set rtf <x|off> | set budget <ms|off> | quality <fps|off> | spawn <n> [plummer|uniform] | save <file.ron|file.npz> | select <i|none> | spring <i> <j> [k] | springs clear | emitter <rate|speed|spread|mass|cap> <v> | paths export <file.csv|file.svg> | paths clear | section <x|y> <v> | section off | lyapunov <i|off> | momentum <steps|off> | morton <steps|off> | farfield <steps> [radius] | farfield off | clumps <substeps|off> | rewind [steps] | trails <decay|off> | collisions <on [radius]|off> | rotate <rad/s|binary|off> | histogram <steps> | histogram fit <on|off> | profiles <on|off> | phase <x|y> | correlation [every <steps>|off] | collapse <growth|reset> | profiles export <file.csv> | language <en|ko> | hud [<overlay> <on|off|tl|tr|bl|br>|reset] | palette <standard|deuteranopia|protanopia> | contrast <on|off>";
+
+pub fn parse(line: &str) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let number = |s: Option<&&str>, what: &str| -> Result<f64, String> {
+        s.ok_or(format!("missing {what}"))?
+            .parse::<f64>()
+            .map_err(|_| format!("invalid {what}"))
+    };
+    let whole = |s: Option<&&str>, what: &str| -> Result<usize, String> {
+        s.ok_or(format!("missing {what}"))?
+            .parse::<usize>()
+            .map_err(|_| format!("{what} must be a whole number"))
+    };
+    match words.as_slice() {
+        ["help"] => Ok(Command::Help),
+        ["set", "dt", rest @ ..] => {
+            let dt = number(rest.first(), "dt")?;
+            if dt > 0.0 {
+                Ok(Command::SetDt(dt as f32))
+            } else {
+                Err("dt must be positive".into())
+            }
+        }
+        ["set", "a0", rest @ ..] => {
+            let a0 = number(rest.first(), "a0")?;
+            if a0 > 0.0 {
+                Ok(Command::SetMondA0(a0 as f32))
+            } else {
+                Err("a0 must be positive".into())
+            }
+        }
+        ["set", "rtf", "off"] => Ok(Command::SetRealTimeFactor(None)),
+        ["set", "rtf", rest @ ..] => {
+            let rtf = number(rest.first(), "real-time factor")?;
+            if rtf > 0.0 {
+                Ok(Command::SetRealTimeFactor(Some(rtf)))
+            } else {
+                Err("real-time factor must be positive".into())
+            }
+        }
+        ["set", "budget", "off"] => Ok(Command::SetBudget(None)),
+        ["set", "budget", rest @ ..] => {
+            let ms = number(rest.first(), "budget")?;
+            if ms > 0.0 {
+                Ok(Command::SetBudget(Some(ms as f32 * 1e-3)))
+            } else {
+                Err("budget must be positive".into())
+            }
+        }
+        ["spawn", rest @ ..] => {
+            if rest.len() > 2 {
+                return Err("usage: spawn <n> [plummer|uniform]".into());
+            }
+            let count = whole(rest.first(), "count")?;
+            if count > MAX_BODY_COUNT {
+                return Err(format!("count must be at most {MAX_BODY_COUNT}"));
+            }
+            let kind = match rest.get(1).copied() {
+                None | Some("plummer") => SpawnKind::Plummer,
+                Some("uniform") => SpawnKind::Uniform,
+                Some(other) => return Err(format!("unknown distribution '{other}'")),
+            };
+            Ok(Command::Spawn { count, kind })
+        }
+        ["save", path] => Ok(Command::Save(PathBuf::from(path))),
+        ["select", "none"] => Ok(Command::Select(None)),
+        ["select", rest @ ..] => {
+            if rest.len() > 1 {
+                return Err("usage: select <i|none>".into());
+            }
+            Ok(Command::Select(Some(whole(rest.first(), "index")?)))
+        }
+        ["springs", "clear"] => Ok(Command::ClearSprings),
+        ["paths", "export", path] => Ok(Command::ExportPaths(PathBuf::from(path))),
+        ["paths", "clear"] => Ok(Command::ClearPaths),
+        ["section", "off"] => Ok(Command::Section(None)),
+        ["lyapunov", "off"] => Ok(Command::Lyapunov(None)),
+        ["farfield", "off"] => Ok(Command::FarField {
+            interval: 1,
+            radius: None,
+        }),
+        ["farfield", rest @ ..] => {
+            let interval = number(rest.first(), "interval")?;
+            if interval < 1.0 {
+                return Err("interval must be at least one step".into());
+            }
+            let radius = match rest.get(1) {
+                Some(r) => Some(number(Some(r), "radius")? as f32),
+                None => None,
+            };
+            if radius.is_some_and(|r| r <= 0.0) {
+                return Err("radius must be positive".into());
+            }
+            Ok(Command::FarField {
+                interval: interval as u32,
+                radius,
+            })
+        }
+        ["clumps", "off"] => Ok(Command::Clumps(None)),
+        ["clumps", rest @ ..] => {
+            let substeps = number(rest.first(), "substeps")?;
+            if substeps < 1.0 {
+                return Err("substeps must be at least one".into());
+            }
+            Ok(Command::Clumps(Some(substeps as u32)))
+        }
+        ["rewind"] => Ok(Command::Rewind(None)),
+        ["rewind", rest @ ..] => {
+            let steps = number(rest.first(), "steps")?;
+            if steps < 1.0 {
+                return Err("steps must be at least one".into());
+            }
+            Ok(Command::Rewind(Some(steps as u64)))
+        }
+        ["trails", "off"] => Ok(Command::Trails(None)),
+        ["histogram", "fit", "on"] => Ok(Command::HistogramFit(true)),
+        ["histogram", "fit", "off"] => Ok(Command::HistogramFit(false)),
+        ["histogram", rest @ ..] => {
+            let steps = number(rest.first(), "interval")?;
+            if steps < 1.0 {
+                return Err("interval must be at least one step".into());
+            }
+            Ok(Command::Histogram(steps as u64))
+        }
+        ["correlation"] => Ok(Command::Correlation(None)),
+        ["correlation", "off"] => Ok(Command::Correlation(Some(None))),
+        ["correlation", "every", rest @ ..] => {
+            let steps = number(rest.first(), "interval")?;
+            if steps < 1.0 {
+                return Err("interval must be at least one step".into());
+            }
+            Ok(Command::Correlation(Some(Some(steps as u64))))
+        }
+        ["collapse", "reset"] => Ok(Command::Collapse(None)),
+        ["collapse", rest @ ..] => {
+            let growth = number(rest.first(), "density growth")?;
+            if growth <= 1.0 {
+                return Err("density growth must be above 1".into());
+            }
+            Ok(Command::Collapse(Some(growth as f64)))
+        }
+        ["phase", "x"] => Ok(Command::Phase(PhaseAxis::X)),
+        ["phase", "y"] => Ok(Command::Phase(PhaseAxis::Y)),
+        ["profiles", "on"] => Ok(Command::Profiles(true)),
+        ["profiles", "off"] => Ok(Command::Profiles(false)),
+        ["profiles", "export", path] => Ok(Command::ExportProfiles(PathBuf::from(path))),
+        ["rotate", "off"] => Ok(Command::Rotate(None)),
+        ["rotate", "binary"] => Ok(Command::Rotate(Some(FrameRate::TightestBinary))),
+        ["rotate", rest @ ..] => {
+            let omega = number(rest.first(), "angular velocity")?;
+            if omega == 0.0 || !omega.is_finite() {
+                return Err("angular velocity must be nonzero".into());
+            }
+            Ok(Command::Rotate(Some(FrameRate::Fixed(omega as f32))))
+        }
+        ["collisions", "off"] => Ok(Command::Collisions {
+            enabled: false,
+            radius: None,
+        }),
+        ["collisions", "on", rest @ ..] => {
+            let radius = match rest.first() {
+                Some(r) => Some(number(Some(r), "radius")? as f32),
+                None => None,
+            };
+            if radius.is_some_and(|r| r <= 0.0) {
+                return Err("radius must be positive".into());
+            }
+            Ok(Command::Collisions {
+                enabled: true,
+                radius,
+            })
+        }
+        ["palette", name] => Palette::from_name(name)
+            .map(Command::Palette)
+            .ok_or(format!("unknown palette '{name}'")),
+        ["quality", "off"] => Ok(Command::Quality(None)),
+        ["quality", rest @ ..] => {
+            let fps = number(rest.first(), "frame rate")?;
+            if fps <= 0.0 {
+                return Err("frame rate must be positive".into());
+            }
+            Ok(Command::Quality(Some(fps as f32)))
+        }
+        ["hud"] => Ok(Command::Hud(None)),
+        ["hud", "reset"] => Ok(Command::HudReset),
+        ["hud", name, place] => {
+            let overlay = Overlay::from_name(name).ok_or(format!(
+                "unknown overlay '{name}' ({})",
+                Overlay::ALL.map(Overlay::name).join(", ")
+            ))?;
+            let change = match *place {
+                "on" => HudChange::Show(true),
+                "off" => HudChange::Show(false),
+                corner => HudChange::Move(Corner::from_name(corner).ok_or(format!(
+                    "unknown place '{corner}' (on, off, tl, tr, bl or br)"
+                ))?),
+            };
+            Ok(Command::Hud(Some((overlay, change))))
+        }
+        ["contrast", "on"] => Ok(Command::HighContrast(true)),
+        ["contrast", "off"] => Ok(Command::HighContrast(false)),
+        ["language", code] => Language::from_code(code)
+            .map(Command::Language)
+            .ok_or(format!("unknown language '{code}' (en or ko)")),
+        ["trails", rest @ ..] => {
+            let decay = number(rest.first(), "decay")? as f32;
+            let (lo, hi) = trails::DECAY_RANGE;
+            if (lo..=hi).contains(&decay) {
+                Ok(Command::Trails(Some(decay)))
+            } else {
+                Err(format!("decay must be between {lo} and {hi}"))
+            }
+        }
+        ["momentum" | "morton", "off"] => Ok(match words[0] {
+            "momentum" => Command::Momentum(None),
+            _ => Command::Morton(None),
+        }),
+        [key @ ("momentum" | "morton"), rest @ ..] => {
+            let steps = number(rest.first(), "interval")?;
+            if steps < 1.0 {
+                return Err("interval must be at least one step".into());
+            }
+            Ok(match *key {
+                "momentum" => Command::Momentum(Some(steps as u64)),
+                _ => Command::Morton(Some(steps as u64)),
+            })
+        }
+        ["lyapunov", rest @ ..] => Ok(Command::Lyapunov(Some(
+            number(rest.first(), "index")? as usize
+        ))),
+        ["section", axis @ ("x" | "y"), rest @ ..] => Ok(Command::Section(Some(Surface {
+            axis: if *axis == "x" { Axis::X } else { Axis::Y },
+            value: number(rest.first(), "section value")? as f32,
+        }))),
+        ["spring", rest @ ..] => {
+            let i = number(rest.first(), "first index")? as usize;
+            let j = number(rest.get(1), "second index")? as usize;
+            let stiffness = match rest.get(2) {
+                Some(k) => Some(number(Some(k), "stiffness")? as f32),
+                None => None,
+            };
+            Ok(Command::Spring { i, j, stiffness })
+        }
+        [
+            "emitter",
+            key @ ("rate" | "speed" | "spread" | "mass" | "cap"),
+            rest @ ..,
+        ] => {
+            let value = number(rest.first(), key)?;
+            if value >= 0.0 {
+                Ok(Command::Emitter(key.to_string(), value))
+            } else {
+                Err(format!("{key} must not be negative"))
+            }
+        }
+        [] => Err(String::new()),
+        _ => Err(format!("unknown command '{line}' (try 'help')")),
+    }
+}
+
+/// Toggle with the console key; while open, keystrokes go to the console only
+pub fn console_input(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut events: EventReader<KeyboardInput>,
+    mut console: ResMut<Console>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleConsole) {
+        console.open = !console.open;
+        events.clear();
+        keys.reset_all();
+        return;
+    }
+    if !console.open {
+        events.clear();
+        return;
+    }
+
+    for ev in events.read() {
+        if ev.state != ButtonState::Pressed {
+            continue;
+        }
+        match &ev.logical_key {
+            Key::Character(s) => console.input.push_str(s),
+            Key::Space => console.input.push(' '),
+            Key::Backspace => {
+                console.input.pop();
+            }
+            Key::Enter => {
+                let line = std::mem::take(&mut console.input);
+                console.print(format!("> {line}"));
+                console.submitted.push(line);
+            }
+            Key::Escape => console.open = false,
+            _ => {}
+        }
+    }
+    // Swallow the keystrokes so shortcuts don't fire while typing
+    keys.reset_all();
+}
+
+/// Bodies the console adds, and the randomness they are drawn with
+#[derive(SystemParam)]
+pub struct BodySource<'w> {
+    pending: ResMut<'w, PendingBodies>,
+    rng: ResMut<'w, SimRng>,
+}
+
+/// Step bookkeeping the console can change
+#[derive(SystemParam)]
+pub struct StepControls<'w> {
+    momentum: ResMut<'w, MomentumCorrection>,
+    morton: ResMut<'w, MortonOrder>,
+    rewind: ResMut<'w, Rewind>,
+    collisions: ResMut<'w, Collisions>,
+    correlation: ResMut<'w, Correlation>,
+    collapse: ResMut<'w, CoreCollapseMonitor>,
+}
+
+/// Display preferences the console can change
+#[derive(SystemParam)]
+pub struct DisplaySettings<'w> {
+    trails: ResMut<'w, Trails>,
+    language: ResMut<'w, Language>,
+    accessibility: ResMut<'w, Accessibility>,
+    frame: ResMut<'w, RotatingFrame>,
+    histogram: ResMut<'w, SpeedHistogram>,
+    profiles: ResMut<'w, RadialProfiles>,
+    phase: ResMut<'w, PhaseView>,
+    hud: ResMut<'w, HudLayout>,
+    quality: ResMut<'w, QualityGovernor>,
+}
+
+pub fn run_console_commands(
+    mut console: ResMut<Console>,
+    mut settings: ResMut<PhysicsSettings>,
+    mut source: BodySource,
+    mut selection: ResMut<Selection>,
+    bodies: Res<Bodies>,
+    constants: Res<PhysicsConstants>,
+    mut rtf: ResMut<RealTimeFactor>,
+    mut springs: ResMut<Springs>,
+    mut emitter: ResMut<Emitter>,
+    mut paths: ResMut<OrbitPaths>,
+    mut section: ResMut<PoincareSection>,
+    mut lyapunov: ResMut<Lyapunov>,
+    mut steps: StepControls,
+    mut display: DisplaySettings,
+) {
+    for line in std::mem::take(&mut console.submitted) {
+        let reply = match parse(&line) {
+            Ok(Command::Help) => HELP.to_string(),
+            Ok(Command::SetDt(dt)) => {
+                settings.dt = SI.time(dt);
+                format!("dt = {dt:.3E} s")
+            }
+            Ok(Command::SetMondA0(a0)) => {
+                settings.force_law = ForceLaw::Mond {
+                    a0: SI.acceleration(a0),
+                };
+                format!("MOND gravity, a0 = {a0:.3E} m/s²")
+            }
+            Ok(Command::SetRealTimeFactor(target)) => {
+                rtf.set_target(target);
+                match target {
+                    Some(t) => format!("auto real-time factor, target {t:.2E}"),
+                    None => "auto real-time factor off".to_string(),
+                }
+            }
+            Ok(Command::SetBudget(budget)) => {
+                settings.frame_budget = budget;
+                match budget {
+                    Some(b) => format!(
+                        "physics budget {:.1} ms per frame (not while recording or replaying)",
+                        b * 1e3
+                    ),
+                    None => "no physics budget".to_string(),
+                }
+            }
+            Ok(Command::Spawn { count, kind }) => {
+                let rng = &mut **source.rng;
+                let mean_mass = 0.5 * (MAX_MASS + MIN_MASS);
+                let new = match kind {
+                    SpawnKind::Plummer => ic::plummer(
+                        rng,
+                        count,
+                        mean_mass * count as f32,
+                        0.1 * MAX_X,
+                        (0.0, 0.0),
+                        (0.0, 0.0),
+                        constants.gravitation,
+                    ),
+                    SpawnKind::Uniform => {
+                        ic::uniform(rng, count, mean_mass, MAX_X, MAX_V, (0.0, 0.0))
+                    }
+                };
+                source.pending.added.extend(new);
+                format!("spawning {count} bodies ({kind:?})")
+            }
+            Ok(Command::Save(path)) => match if path.extension().is_some_and(|e| e == "npz") {
+                npz::save(&bodies, &path)
+            } else {
+                Snapshot::capture(&bodies).save(&path)
+            } {
+                Ok(()) => format!("saved {}", path.display()),
+                Err(e) => format!("save failed: {e}"),
+            },
+            Ok(Command::Select(index)) => match index {
+                Some(i) if i >= bodies.data.len() => format!("no body #{i}"),
+                _ => {
+                    selection.0 = index;
+                    index.map_or("selection cleared".into(), |i| format!("selected #{i}"))
+                }
+            },
+            Ok(Command::Spring { i, j, stiffness }) => {
+                let n = bodies.data.len();
+                if i >= n || j >= n || i == j {
+                    format!("need two different bodies below #{n}")
+                } else {
+                    let stiffness = stiffness.map(|k| SI.stiffness(k));
+                    let spring = Spring::between(&bodies.data, i, j, stiffness, settings.dt);
+                    springs.links.push(spring);
+                    format!(
+                        "linked #{i} – #{j}, k = {:.2E} N/m",
+                        CODE.stiffness(spring.stiffness)
+                    )
+                }
+            }
+            Ok(Command::ClearSprings) => {
+                springs.links.clear();
+                "springs removed".to_string()
+            }
+            Ok(Command::Emitter(key, value)) => {
+                match key.as_str() {
+                    "rate" => emitter.rate = value as f32,
+                    "speed" => emitter.speed = SI.velocity(value as f32),
+                    "spread" => emitter.spread = value as f32,
+                    "mass" => emitter.mass = SI.mass(value as f32),
+                    _ => emitter.max_bodies = value as usize,
+                }
+                format!("emitter {key} = {value:.3E}")
+            }
+            Ok(Command::ExportPaths(path)) => match paths.export(&path) {
+                Ok(()) => format!(
+                    "exported {} path(s) to {}",
+                    paths.paths.len(),
+                    path.display()
+                ),
+                Err(e) => format!("export failed: {e}"),
+            },
+            Ok(Command::ClearPaths) => {
+                paths.paths.clear();
+                "orbit paths removed".to_string()
+            }
+            Ok(Command::Section(surface)) => {
+                section.set_surface(surface.map(|s| Surface {
+                    value: SI.length(s.value),
+                    ..s
+                }));
+                match surface {
+                    Some(s) => format!("section {:?} = {:.3E} m", s.axis, s.value),
+                    None => "section off".to_string(),
+                }
+            }
+            Ok(Command::Momentum(interval)) => {
+                steps.momentum.interval = interval;
+                match interval {
+                    Some(n) => format!("net momentum removed every {n} steps"),
+                    None => "momentum correction off".to_string(),
+                }
+            }
+            Ok(Command::Morton(interval)) => {
+                steps.morton.interval = interval;
+                match interval {
+                    Some(n) => format!("Morton reordering every {n} steps"),
+                    None => "Morton reordering off".to_string(),
+                }
+            }
+            Ok(Command::FarField { interval, radius }) => {
+                let far_field = &mut settings.far_field;
+                far_field.interval = interval;
+                if let Some(r) = radius {
+                    far_field.radius = SI.length(r);
+                }
+                if !far_field.is_active() {
+                    "far field summed every step".to_string()
+                } else if !settings.solver.is_direct() {
+                    "far-field caching only applies to the direct solvers".to_string()
+                } else {
+                    format!(
+                        "far field refreshed every {interval} steps, radius {:.3E} m",
+                        CODE.length(settings.far_field.radius)
+                    )
+                }
+            }
+            Ok(Command::Clumps(substeps)) => {
+                let clumps = &mut settings.clumps;
+                clumps.enabled = substeps.is_some();
+                match substeps {
+                    Some(n) => {
+                        clumps.substeps = n;
+                        format!("friends-of-friends clumps subcycled, {n} substeps")
+                    }
+                    None => "clump subcycling off".to_string(),
+                }
+            }
+            Ok(Command::Rewind(count)) => {
+                if let Some(n) = count {
+                    steps.rewind.steps = n;
+                }
+                steps.rewind.request();
+                format!("rewinding {} steps", steps.rewind.steps)
+            }
+            Ok(Command::Trails(decay)) => {
+                display.trails.enabled = decay.is_some();
+                match decay {
+                    Some(d) => {
+                        display.trails.set_decay(d);
+                        format!("trails on, decay {d} per frame")
+                    }
+                    None => "trails off".to_string(),
+                }
+            }
+            Ok(Command::Collisions { enabled, radius }) => {
+                let collisions = &mut steps.collisions;
+                if let Some(r) = radius {
+                    collisions.radius = SI.length(r);
+                }
+                if enabled {
+                    collisions.set_enabled(true);
+                    format!(
+                        "collision detection on, radius {:.3E} m at the largest mass",
+                        CODE.length(collisions.radius)
+                    )
+                } else {
+                    let total = collisions.total;
+                    collisions.set_enabled(false);
+                    format!("collision detection off, {total} contacts seen")
+                }
+            }
+            Ok(Command::Histogram(steps)) => {
+                display.histogram.interval = steps;
+                format!("speed histogram every {steps} steps")
+            }
+            Ok(Command::HistogramFit(on)) => {
+                display.histogram.fit = on;
+                format!("Maxwell–Boltzmann fit {}", if on { "on" } else { "off" })
+            }
+            Ok(Command::Correlation(schedule)) => match schedule {
+                None => {
+                    steps.correlation.request();
+                    "estimating the two-point correlation (written to the log)".to_string()
+                }
+                Some(interval) => {
+                    steps.correlation.interval = interval;
+                    match interval {
+                        Some(n) => format!("two-point correlation every {n} steps"),
+                        None => "two-point correlation on request only".to_string(),
+                    }
+                }
+            },
+            Ok(Command::Collapse(Some(growth))) => {
+                steps.collapse.threshold = growth;
+                format!("core collapse alert at ×{growth} central density")
+            }
+            Ok(Command::Collapse(None)) => {
+                steps.collapse.reset();
+                "core collapse baseline reset to the next sample".to_string()
+            }
+            Ok(Command::Phase(axis)) => {
+                display.phase.axis = axis;
+                display.phase.enabled = true;
+                format!("phase-space view of {axis:?}")
+            }
+            Ok(Command::Profiles(on)) => {
+                display.profiles.panel = on;
+                format!("radial profiles {}", if on { "on" } else { "off" })
+            }
+            Ok(Command::ExportProfiles(path)) => match display.profiles.export(&path) {
+                Ok(()) => format!("exported radial profiles to {}", path.display()),
+                Err(e) => format!("export failed: {e}"),
+            },
+            Ok(Command::Rotate(rate)) => {
+                display.frame.set_rate(rate.map(|r| match r {
+                    FrameRate::Fixed(w) => FrameRate::Fixed(SI.rate(w)),
+                    r => r,
+                }));
+                match rate {
+                    Some(FrameRate::Fixed(w)) => format!("view rotating at {w:.3E} rad/s"),
+                    Some(FrameRate::TightestBinary) => {
+                        "view rotating with the tightest binary".to_string()
+                    }
+                    None => "inertial view".to_string(),
+                }
+            }
+            Ok(Command::Language(lang)) => {
+                *display.language = lang;
+                format!("language: {lang:?}")
+            }
+            Ok(Command::Palette(palette)) => {
+                display.accessibility.palette = palette;
+                format!("palette: {palette:?}")
+            }
+            Ok(Command::Hud(None)) => Overlay::ALL
+                .map(|o| {
+                    let slot = display.hud.slot(o);
+                    let place = if slot.visible {
+                        format!("{:?}", slot.corner)
+                    } else {
+                        "hidden".to_string()
+                    };
+                    format!("{}: {place}", o.name())
+                })
+                .join(", "),
+            Ok(Command::Hud(Some((overlay, change)))) => {
+                let slot = display.hud.slot_mut(overlay);
+                match change {
+                    HudChange::Show(on) => slot.visible = on,
+                    HudChange::Move(corner) => {
+                        slot.visible = true;
+                        slot.corner = corner;
+                    }
+                }
+                format!("{}: {change:?}", overlay.name())
+            }
+            Ok(Command::Quality(target)) => {
+                display.quality.set_target(target);
+                match target {
+                    Some(fps) => format!("adaptive quality holding {fps} fps"),
+                    None => "adaptive quality off".to_string(),
+                }
+            }
+            Ok(Command::HudReset) => {
+                *display.hud = HudLayout::default();
+                "default HUD layout".to_string()
+            }
+            Ok(Command::HighContrast(on)) => {
+                display.accessibility.high_contrast = on;
+                format!("high contrast {}", if on { "on" } else { "off" })
+            }
+            Ok(Command::Lyapunov(index)) => match index {
+                Some(i) if i >= bodies.data.len() => format!("no body #{i}"),
+                _ => {
+                    lyapunov.follow(&bodies, index);
+                    index.map_or("Lyapunov estimate off".into(), |i| {
+                        format!("Lyapunov estimate for #{i}")
+                    })
+                }
+            },
+            Err(e) if e.is_empty() => continue,
+            Err(e) => e,
+        };
+        console.print(reply);
+    }
+}
+
+#[derive(Component)]
+pub struct ConsolePanel;
+
+#[derive(Component)]
+pub struct ConsoleText;
+
+pub fn spawn_console(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 16.0,
+        color: Color::srgb(0.8, 1.0, 0.8),
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            ConsolePanel,
+        ))
+        .with_children(|panel| {
+            panel.spawn((TextBundle::from_section("", style), ConsoleText));
+        });
+}
+
+pub fn update_console_panel(
+    console: Res<Console>,
+    mut panel_q: Query<&mut Visibility, With<ConsolePanel>>,
+    mut text_q: Query<&mut Text, With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for mut vis in panel_q.iter_mut() {
+        *vis = if console.open {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if let Ok(mut t) = text_q.get_single_mut() {
+        let mut out = console.history.join("\n");
+        out += &format!("\n> {}_", console.input);
+        t.sections[0].value = out;
+    }
+}
@@ -0,0 +1,204 @@
+//! Physical constants loaded from `assets/physics.ron`.
+//!
+//! The file is polled for changes while the app runs and reloaded through the
+//! asset server, so G, dt, softening and the cutoff can be tuned without a
+//! restart. The compiled-in values are used until (or unless) it loads.
+//! The file is in SI and converted to code units as it is read.
+
+use std::fmt;
+use std::path::Path;
+use std::time::SystemTime;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::PhysicsSettings;
+use crate::softening::SofteningKernel;
+use crate::units::{CODE, SI, UnitScale};
+use crate::{A_RIGHT_YEAR, COULOMB, D_TIME, GRAVITATION};
+
+/// Asset path, relative to the `assets` folder
+pub const CONSTANTS_PATH: &str = "physics.ron";
+
+/// How often the file's modification time is checked (s)
+const POLL_INTERVAL: f32 = 1.0;
+
+/// Constants the force pass and the analyses read. Also a resource holding
+/// the values currently in effect, in code units.
+#[derive(Asset, Resource, Reflect, Serialize, Deserialize, Debug, Clone, Copy)]
+#[reflect(Resource)]
+#[serde(default = "PhysicsConstants::si_default")]
+pub struct PhysicsConstants {
+    /// Gravitational constant
+    pub gravitation: f32,
+    /// Coulomb constant, used by the electrostatic force law
+    pub coulomb: f32,
+    /// Default timestep, used when a scenario doesn't recommend one
+    pub dt: f32,
+    /// Softening length, Plummer-equivalent for every kernel
+    pub softening: f32,
+    /// Shape of the softened force at short range
+    pub softening_kernel: SofteningKernel,
+    /// If set, each body's softening scales as (m / reference mass)^{1/3}
+    pub softening_reference_mass: Option<f32>,
+    /// Pairs farther apart than this are ignored by the direct solver;
+    /// a cutoff shorter than the spread of the bodies is served by a neighbor grid
+    pub cutoff_radius: f32,
+}
+
+impl Default for PhysicsConstants {
+    fn default() -> Self {
+        Self {
+            gravitation: GRAVITATION,
+            coulomb: COULOMB,
+            dt: D_TIME,
+            softening: 0.0,
+            softening_kernel: SofteningKernel::Plummer,
+            softening_reference_mass: None,
+            cutoff_radius: A_RIGHT_YEAR,
+        }
+    }
+}
+
+impl PhysicsConstants {
+    /// Read the constants file directly, for runs without the asset server
+    pub fn read_asset_file() -> Self {
+        std::fs::read_to_string(Path::new("assets").join(CONSTANTS_PATH))
+            .ok()
+            .and_then(|text| ron::from_str::<Self>(&text).ok())
+            .map_or_else(Self::default, |c| c.convert(&SI))
+    }
+
+    /// The compiled-in values in SI, for fields the file leaves out
+    fn si_default() -> Self {
+        Self::default().convert(&CODE)
+    }
+
+    /// Every dimensional value converted by `s`
+    pub fn convert(self, s: &UnitScale) -> Self {
+        Self {
+            gravitation: s.gravitation(self.gravitation),
+            coulomb: s.coulomb(self.coulomb),
+            dt: s.time(self.dt),
+            softening: s.length(self.softening),
+            softening_reference_mass: self.softening_reference_mass.map(|m| s.mass(m)),
+            cutoff_radius: s.length(self.cutoff_radius),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConstantsLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for ConstantsLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstantsLoaderError::Io(e) => write!(f, "could not read physics constants: {e}"),
+            ConstantsLoaderError::Ron(e) => write!(f, "could not parse physics constants: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ConstantsLoaderError {}
+
+#[derive(Default)]
+pub struct PhysicsConstantsLoader;
+
+impl AssetLoader for PhysicsConstantsLoader {
+    type Asset = PhysicsConstants;
+    type Settings = ();
+    type Error = ConstantsLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(ConstantsLoaderError::Io)?;
+        ron::de::from_bytes::<PhysicsConstants>(&bytes)
+            .map(|c| c.convert(&SI))
+            .map_err(ConstantsLoaderError::Ron)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+/// Handle keeping the asset alive, plus the state of the change poll
+#[derive(Resource)]
+pub struct ConstantsWatch {
+    handle: Handle<PhysicsConstants>,
+    modified: Option<SystemTime>,
+    since_poll: f32,
+}
+
+pub fn load_physics_constants(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ConstantsWatch {
+        handle: asset_server.load(CONSTANTS_PATH),
+        modified: file_modified(),
+        since_poll: 0.0,
+    });
+}
+
+fn file_modified() -> Option<SystemTime> {
+    let path = Path::new("assets").join(CONSTANTS_PATH);
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Ask the asset server to reload the file when its modification time changes
+pub fn poll_physics_constants(
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut watch: ResMut<ConstantsWatch>,
+) {
+    watch.since_poll += time.delta_secs();
+    if watch.since_poll < POLL_INTERVAL {
+        return;
+    }
+    watch.since_poll = 0.0;
+    let modified = file_modified();
+    if modified != watch.modified {
+        watch.modified = modified;
+        asset_server.reload(CONSTANTS_PATH);
+    }
+}
+
+/// Copy loaded (or reloaded) constants into the resource. A reload also
+/// replaces the running timestep; the first load only sets the default.
+pub fn apply_physics_constants(
+    mut events: EventReader<AssetEvent<PhysicsConstants>>,
+    assets: Res<Assets<PhysicsConstants>>,
+    watch: Res<ConstantsWatch>,
+    mut constants: ResMut<PhysicsConstants>,
+    mut settings: ResMut<PhysicsSettings>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event
+        else {
+            continue;
+        };
+        if id != watch.handle.id() {
+            continue;
+        }
+        let Some(loaded) = assets.get(id) else {
+            continue;
+        };
+        *constants = *loaded;
+        if matches!(event, AssetEvent::Modified { .. }) {
+            settings.dt = loaded.dt;
+        }
+        info!("Physics constants: {:?}", *constants);
+    }
+}
@@ -0,0 +1,196 @@
+//! Core-collapse detection. Each time the structure diagnostics are
+//! recomputed, the central surface density (mass inside the core radius
+//! over its area) is compared with the one measured at the start of the
+//! run. When it has grown by more than `threshold`, a `CoreCollapse` event
+//! is sent once, written to the diagnostics log and shown as an alert.
+
+use bevy::prelude::*;
+
+use crate::diagnostics::DiagnosticsLog;
+use crate::locale::{Language, fill, tr};
+use crate::structure::{Structure, StructureDiagnostics};
+use crate::units::CODE;
+use crate::{Bodies, BodyState};
+
+/// Default growth of the central density that counts as collapse
+const DEFAULT_THRESHOLD: f64 = 20.0;
+/// How long the alert stays on screen (s)
+const ALERT_SECONDS: f32 = 8.0;
+
+#[derive(Resource)]
+pub struct CoreCollapseMonitor {
+    /// Central density growth, relative to the first sample, that fires the alert
+    pub threshold: f64,
+    /// Central surface density at the first sample after a reset
+    pub initial: Option<f64>,
+    pub latest: Option<f64>,
+    /// Set once the collapse has been reported, until the next reset
+    pub collapsed: bool,
+    last_step: Option<u64>,
+    len: usize,
+}
+
+impl Default for CoreCollapseMonitor {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            initial: None,
+            latest: None,
+            collapsed: false,
+            last_step: None,
+            len: 0,
+        }
+    }
+}
+
+impl CoreCollapseMonitor {
+    /// Take the next sample as the new baseline
+    pub fn reset(&mut self) {
+        self.initial = None;
+        self.latest = None;
+        self.collapsed = false;
+        self.last_step = None;
+    }
+}
+
+/// The central density grew past the threshold
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CoreCollapse {
+    pub step: u64,
+    /// Simulated time
+    pub time: f64,
+    pub core_radius: f32,
+    /// Central density over the initial one
+    pub growth: f64,
+}
+
+/// Mass inside the core radius over the core's area
+pub fn central_density(data: &[BodyState], s: &Structure) -> Option<f64> {
+    let r = s.core_radius as f64;
+    if r <= 0.0 {
+        return None;
+    }
+    let (cx, cy) = (s.center_of_density.x as f64, s.center_of_density.y as f64);
+    let mass: f64 = data
+        .iter()
+        .filter(|b| (b.x as f64 - cx).powi(2) + (b.y as f64 - cy).powi(2) <= r * r)
+        .map(|b| b.mass as f64)
+        .sum();
+    Some(mass / (std::f64::consts::PI * r * r))
+}
+
+/// Runs after `structure::update_structure`, on each of its samples
+pub fn detect_core_collapse(
+    bodies: Res<Bodies>,
+    structure: Res<StructureDiagnostics>,
+    mut monitor: ResMut<CoreCollapseMonitor>,
+    mut log: ResMut<DiagnosticsLog>,
+    mut events: EventWriter<CoreCollapse>,
+) {
+    let Some(step) = structure.last_step else {
+        return;
+    };
+    if monitor.last_step == Some(step) {
+        return;
+    }
+    // A loaded scene or a rewind starts a new baseline
+    if monitor.last_step.is_some_and(|last| step < last) || monitor.len != bodies.data.len() {
+        monitor.reset();
+        monitor.len = bodies.data.len();
+    }
+    monitor.last_step = Some(step);
+    let Some(s) = structure.latest else {
+        return;
+    };
+    let Some(density) = central_density(&bodies.data, &s) else {
+        return;
+    };
+    monitor.latest = Some(density);
+    let initial = *monitor.initial.get_or_insert(density);
+    if initial <= 0.0 || monitor.collapsed {
+        return;
+    }
+    let growth = density / initial;
+    if growth < monitor.threshold {
+        return;
+    }
+    monitor.collapsed = true;
+    log.record(
+        "core_collapse",
+        bodies.elapsed_time,
+        &["step", "r_core", "rho_c", "growth"],
+        &[
+            step as f64,
+            CODE.length(s.core_radius) as f64,
+            CODE.surface_density(density),
+            growth,
+        ],
+    );
+    events.send(CoreCollapse {
+        step,
+        time: bodies.elapsed_time as f64,
+        core_radius: s.core_radius,
+        growth,
+    });
+}
+
+#[derive(Component)]
+pub struct CollapseAlert;
+
+pub fn spawn_collapse_alert(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 22.0,
+                color: Color::srgb(1.0, 0.6, 0.2),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(60.0),
+            left: Val::Percent(35.0),
+            ..Default::default()
+        }),
+        Visibility::Hidden,
+        CollapseAlert,
+    ));
+}
+
+/// Log each collapse and show it for `ALERT_SECONDS`
+pub fn show_collapse_alert(
+    time: Res<Time<Real>>,
+    lang: Res<Language>,
+    mut events: EventReader<CoreCollapse>,
+    mut shown_until: Local<f32>,
+    mut q: Query<(&mut Text, &mut Visibility), With<CollapseAlert>>,
+) {
+    let Ok((mut t, mut vis)) = q.get_single_mut() else {
+        events.clear();
+        return;
+    };
+    for c in events.read() {
+        let seconds = c.time * CODE.time;
+        warn!(
+            "Core collapse at step {} (t = {:.3E} s): central density ×{:.1}, core radius {:.2E} m",
+            c.step,
+            seconds,
+            c.growth,
+            CODE.length(c.core_radius)
+        );
+        t.sections[0].value = fill(
+            tr(
+                "Core collapse at t = {} s (central density ×{})",
+                "코어 붕괴: t = {} s (중심 밀도 ×{})",
+            )
+            .get(*lang),
+            &[&format!("{:.3E}", seconds), &format!("{:.1}", c.growth)],
+        );
+        *shown_until = time.elapsed_secs() + ALERT_SECONDS;
+        *vis = Visibility::Inherited;
+    }
+    if *vis != Visibility::Hidden && time.elapsed_secs() > *shown_until {
+        *vis = Visibility::Hidden;
+    }
+}
@@ -0,0 +1,181 @@
+//! Two-point correlation function ξ(r) of the body positions, by the
+//! Landy–Szalay estimator against uniform random points in the bounding
+//! box. Pairs are counted through cell grids one maximum separation wide,
+//! so only neighbouring cells are visited. Runs on request or every few
+//! steps, and writes a `correlation` record to the diagnostics log.
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+
+use crate::Bodies;
+use crate::diagnostics::{DiagnosticsLog, is_due};
+use crate::neighbors::CellGrid;
+use crate::sim_rng::Xoshiro256PlusPlus;
+use crate::units::CODE;
+
+/// Logarithmic separation bins
+pub const BINS: usize = 12;
+/// Larger runs are subsampled to this many bodies
+const MAX_POINTS: usize = 4000;
+/// Random points per body
+const RANDOM_FACTOR: usize = 2;
+/// Largest separation, as a fraction of the bounding box's longer side
+const MAX_SEPARATION: f32 = 0.25;
+/// Decades of separation the bins cover
+const DECADES: f32 = 2.0;
+
+#[derive(Resource, Default)]
+pub struct Correlation {
+    /// Estimate every this many steps, `None` for on request only
+    pub interval: Option<u64>,
+    requested: bool,
+    last_step: Option<u64>,
+    /// Latest (bin center, ξ) pairs, ξ `None` where no random pairs fell
+    pub latest: Vec<(f32, Option<f64>)>,
+}
+
+impl Correlation {
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+}
+
+/// Log-spaced separation bins from `r_max / 10^DECADES` to `r_max`
+struct Bins {
+    r_min: f32,
+    r_max: f32,
+    log_step: f32,
+}
+
+impl Bins {
+    fn index(&self, r: f32) -> Option<usize> {
+        (r >= self.r_min && r < self.r_max)
+            .then(|| (((r / self.r_min).ln() / self.log_step) as usize).min(BINS - 1))
+    }
+
+    fn center(&self, k: usize) -> f32 {
+        self.r_min * ((k as f32 + 0.5) * self.log_step).exp()
+    }
+}
+
+/// Pairs between `a` and the points binned in `grid`, per bin; within one
+/// set (`same`) each pair is counted once
+fn pair_counts(
+    a: &[[f32; 2]],
+    b: &[[f32; 2]],
+    grid: &CellGrid,
+    same: bool,
+    bins: &Bins,
+) -> [f64; BINS] {
+    let mut counts = [0.0; BINS];
+    for (i, p) in a.iter().enumerate() {
+        for j in grid.candidates(p[0], p[1], bins.r_max) {
+            if same && j <= i {
+                continue;
+            }
+            let r = Vec2::from(*p).distance(Vec2::from(b[j]));
+            if let Some(k) = bins.index(r) {
+                counts[k] += 1.0;
+            }
+        }
+    }
+    counts
+}
+
+pub fn estimate(positions: &[[f32; 2]], seed: u64) -> Vec<(f32, Option<f64>)> {
+    let n = positions.len();
+    if n < 2 {
+        return Vec::new();
+    }
+    let (mut min, mut max) = (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN));
+    for &p in positions {
+        min = min.min(p.into());
+        max = max.max(p.into());
+    }
+    let r_max = MAX_SEPARATION * (max - min).max_element();
+    if !(r_max > 0.0 && r_max.is_finite()) {
+        return Vec::new();
+    }
+    let bins = Bins {
+        r_min: r_max / 10f32.powf(DECADES),
+        r_max,
+        log_step: DECADES * std::f32::consts::LN_10 / BINS as f32,
+    };
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+    let randoms: Vec<[f32; 2]> = (0..RANDOM_FACTOR * n)
+        .map(|_| [rng.gen_range(min.x..=max.x), rng.gen_range(min.y..=max.y)])
+        .collect();
+    let (mut data_grid, mut random_grid) = (CellGrid::default(), CellGrid::default());
+    data_grid.rebuild_with_cell(positions, r_max);
+    random_grid.rebuild_with_cell(&randoms, r_max);
+
+    let nr = randoms.len() as f64;
+    let nd = n as f64;
+    let dd = pair_counts(positions, positions, &data_grid, true, &bins);
+    let dr = pair_counts(positions, &randoms, &random_grid, false, &bins);
+    let rr = pair_counts(&randoms, &randoms, &random_grid, true, &bins);
+    (0..BINS)
+        .map(|k| {
+            let dd = dd[k] / (0.5 * nd * (nd - 1.0));
+            let dr = dr[k] / (nd * nr);
+            let rr = rr[k] / (0.5 * nr * (nr - 1.0));
+            (
+                bins.center(k),
+                (rr > 0.0).then(|| (dd - 2.0 * dr + rr) / rr),
+            )
+        })
+        .collect()
+}
+
+/// Estimate when requested or due, and log the result
+pub fn update_correlation(
+    bodies: Res<Bodies>,
+    mut correlation: ResMut<Correlation>,
+    mut log: ResMut<DiagnosticsLog>,
+) {
+    let due = correlation
+        .interval
+        .is_some_and(|k| is_due(correlation.last_step, bodies.step, k));
+    if !correlation.requested && !due {
+        return;
+    }
+    correlation.requested = false;
+    correlation.last_step = Some(bodies.step);
+
+    let stride = bodies.data.len().div_ceil(MAX_POINTS).max(1);
+    let positions: Vec<[f32; 2]> = bodies
+        .data
+        .iter()
+        .step_by(stride)
+        .map(|b| [b.x, b.y])
+        .collect();
+    correlation.latest = estimate(&positions, bodies.step);
+    if correlation.latest.is_empty() {
+        return;
+    }
+    let summary: Vec<String> = correlation
+        .latest
+        .iter()
+        .filter_map(|&(r, xi)| Some(format!("{:.2E} m: {:.3}", CODE.length(r), xi?)))
+        .collect();
+    info!("Two-point correlation ξ(r): {}", summary.join(", "));
+
+    let columns: Vec<String> = (0..BINS)
+        .map(|k| format!("r{k}"))
+        .chain((0..BINS).map(|k| format!("xi{k}")))
+        .collect();
+    let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let values: Vec<f64> = correlation
+        .latest
+        .iter()
+        .map(|&(r, _)| CODE.length(r) as f64)
+        .chain(
+            correlation
+                .latest
+                .iter()
+                .map(|&(_, xi)| xi.unwrap_or(f64::NAN)),
+        )
+        .collect();
+    log.record("correlation", bodies.elapsed_time, &columns, &values);
+}
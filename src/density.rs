@@ -0,0 +1,51 @@
+//! Local density estimate from the spatial index, used by the density
+//! coloring mode.
+
+use bevy::prelude::*;
+
+use crate::coloring::ColorMode;
+use crate::neighbors::SpatialIndex;
+use crate::{Bodies, BodyState};
+
+#[derive(Resource, Default)]
+pub struct DensityField {
+    pub last_step: Option<u64>,
+    /// log10 surface density around each body
+    pub log_density: Vec<f32>,
+    /// Range of `log_density`, for normalizing colors
+    pub range: (f32, f32),
+}
+
+/// Mass within 1.5 index cells of each body over that disc's area
+pub fn estimate(data: &[BodyState], index: &SpatialIndex) -> Vec<f32> {
+    let radius = 1.5 * index.cell_size();
+    let area = std::f64::consts::PI * (radius as f64) * (radius as f64);
+    data.iter()
+        .map(|b| {
+            let m: f64 = index
+                .neighbors_within(Vec2::new(b.x, b.y), radius)
+                .filter_map(|j| data.get(j))
+                .map(|n| n.mass as f64)
+                .sum();
+            (m / area).max(f64::MIN_POSITIVE).log10() as f32
+        })
+        .collect()
+}
+
+/// Refresh the field once per physics step while the density coloring is shown
+pub fn update_density(
+    bodies: Res<Bodies>,
+    index: Res<SpatialIndex>,
+    mode: Res<ColorMode>,
+    mut field: ResMut<DensityField>,
+) {
+    if *mode != ColorMode::Density || field.last_step == Some(bodies.step) {
+        return;
+    }
+    field.last_step = Some(bodies.step);
+    field.log_density = estimate(&bodies.data, &index);
+    field.range = field
+        .log_density
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+}
@@ -0,0 +1,59 @@
+//! Diagnostics log: one CSV-like line per record, grouped by record kind.
+//!
+//! ```text
+//! # structure: time_s,cod_x,cod_y,r_core,r10,r50,r90
+//! 2.000000e8,structure,1.2e12,...
+//! ```
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::units::CODE;
+
+/// Whether a periodic analysis last run at `last_step` should run again at
+/// `step`. A step count that went backwards (a restarted run) is always due.
+pub fn is_due(last_step: Option<u64>, step: u64, interval: u64) -> bool {
+    match last_step {
+        None => true,
+        Some(last) => step < last || step >= last + interval.max(1),
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct DiagnosticsLog {
+    writer: Option<BufWriter<File>>,
+    headers_written: HashSet<&'static str>,
+}
+
+impl DiagnosticsLog {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            writer: Some(BufWriter::new(File::create(path)?)),
+            headers_written: HashSet::new(),
+        })
+    }
+
+    /// Append a record; `columns` names the values and is written once per
+    /// kind. `time` is converted to seconds, the values are written as given
+    /// and so are converted to SI by the caller.
+    pub fn record(&mut self, kind: &'static str, time: f32, columns: &[&str], values: &[f64]) {
+        let Some(w) = self.writer.as_mut() else {
+            return;
+        };
+        let time = CODE.time(time);
+        let mut result = Ok(());
+        if self.headers_written.insert(kind) {
+            result = writeln!(w, "# {kind}: time_s,{}", columns.join(","));
+        }
+        let row: Vec<String> = values.iter().map(|v| format!("{v:.6e}")).collect();
+        result = result.and_then(|_| writeln!(w, "{time:.6e},{kind},{}", row.join(",")));
+        if let Err(e) = result.and_then(|_| w.flush()) {
+            error!("diagnostics log write failed, disabling: {e}");
+            self.writer = None;
+        }
+    }
+}
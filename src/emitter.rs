@@ -0,0 +1,175 @@
+//! Particle emitter: a nozzle placed on screen that keeps adding small bodies,
+//! recycling escaped ones once the body cap is reached.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use rand::{Rng, distributions::Standard};
+
+use crate::keybindings::{Action, KeyBindings};
+use crate::physics::PendingBodies;
+use crate::sim_rng::{SimRng, Xoshiro256PlusPlus};
+use crate::units::SI;
+use crate::{Bodies, BodyState, MAX_X, MainCamera, world_scale};
+
+/// Bodies farther than this many half-widths from the origin count as escaped
+const ESCAPE_FACTOR: f32 = 3.0;
+/// New bodies start scattered within this radius of the nozzle, so no two
+/// share a position
+const NOZZLE_RADIUS: f32 = SI.length(2.0E12);
+
+#[derive(Resource)]
+pub struct Emitter {
+    pub enabled: bool,
+    /// Nozzle position
+    pub position: (f32, f32),
+    /// Emission direction (rad, counter-clockwise from +x)
+    pub direction: f32,
+    /// Bodies per wall-clock second
+    pub rate: f32,
+    /// Launch speed
+    pub speed: f32,
+    /// Full opening angle of the jet (rad)
+    pub spread: f32,
+    /// Mass of each emitted body
+    pub mass: f32,
+    /// Global body cap; beyond it escaped bodies are reused instead
+    pub max_bodies: usize,
+    /// Fractional bodies carried over between frames
+    owed: f32,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position: (0.0, 0.0),
+            direction: 0.0,
+            rate: 20.0,
+            speed: SI.velocity(5.0E03),
+            spread: 0.5,
+            mass: SI.mass(1.0E27),
+            max_bodies: 2000,
+            owed: 0.0,
+        }
+    }
+}
+
+impl Emitter {
+    fn launch(&self, rng: &mut Xoshiro256PlusPlus) -> BodyState {
+        let mut b = BodyState::new();
+        let (s, c) = (TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+        let r = NOZZLE_RADIUS * rng.sample::<f32, _>(Standard).sqrt();
+        b.mass = self.mass;
+        b.x = self.position.0 + r * c;
+        b.y = self.position.1 + r * s;
+        let angle = self.direction + self.spread * (rng.sample::<f32, _>(Standard) - 0.5);
+        b.vx = self.speed * angle.cos();
+        b.vy = self.speed * angle.sin();
+        b.x_prev = b.x;
+        b.y_prev = b.y;
+        b
+    }
+}
+
+/// The emitter key places the nozzle at the cursor, aimed at the origin;
+/// pressing it again switches the emitter off
+pub fn place_emitter(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut emitter: ResMut<Emitter>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    if !bindings.just_pressed(&keys, Action::PlaceEmitter) {
+        return;
+    }
+    if emitter.enabled {
+        emitter.enabled = false;
+        info!("Emitter off");
+        return;
+    }
+    let (Ok(window), Ok((camera, cam_tf))) = (win_q.get_single(), cam_q.get_single()) else {
+        return;
+    };
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|c| camera.viewport_to_world_2d(cam_tf, c).ok())
+    else {
+        return;
+    };
+    let pos = cursor / world_scale(window);
+    emitter.position = (pos.x, pos.y);
+    emitter.direction = if pos == Vec2::ZERO {
+        0.0
+    } else {
+        (-pos.y).atan2(-pos.x)
+    };
+    emitter.enabled = true;
+    info!("Emitter at ({:.2E}, {:.2E}) m", pos.x, pos.y);
+}
+
+/// Queue this frame's share of new bodies, reusing escaped ones at the cap
+pub fn emit_bodies(
+    time: Res<Time>,
+    bodies: Res<Bodies>,
+    mut emitter: ResMut<Emitter>,
+    mut pending: ResMut<PendingBodies>,
+    mut rng: ResMut<SimRng>,
+) {
+    if !emitter.enabled {
+        return;
+    }
+    emitter.owed += emitter.rate * time.delta_secs();
+    let count = emitter.owed.floor() as usize;
+    if count == 0 {
+        return;
+    }
+    emitter.owed -= count as f32;
+
+    let total = bodies.data.len() + pending.added.len();
+    let fresh = count.min(emitter.max_bodies.saturating_sub(total));
+    for _ in 0..fresh {
+        let b = emitter.launch(&mut rng);
+        pending.added.push(b);
+    }
+
+    // Over the cap: relaunch bodies that have left the field of play
+    let escape = ESCAPE_FACTOR * MAX_X;
+    let mut escaped = bodies.data.iter().enumerate().filter(|(i, b)| {
+        !b.fixed
+            && (b.x.abs() > escape || b.y.abs() > escape)
+            && !pending.replaced.iter().any(|(j, _)| j == i)
+    });
+    let mut recycled = Vec::new();
+    for _ in fresh..count {
+        let Some((i, _)) = escaped.next() else {
+            break;
+        };
+        recycled.push(i);
+    }
+    for i in recycled {
+        let b = emitter.launch(&mut rng);
+        pending.replaced.push((i, b));
+    }
+}
+
+/// Nozzle marker with a short line along the jet
+pub fn draw_emitter(
+    mut gizmos: Gizmos,
+    emitter: Res<Emitter>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(window) = win_q.get_single() else {
+        return;
+    };
+    if !emitter.enabled {
+        return;
+    }
+    let color = Color::srgb(0.6, 1.0, 1.0);
+    let p = Vec2::new(emitter.position.0, emitter.position.1) * world_scale(window);
+    let dir = Vec2::from_angle(emitter.direction);
+    gizmos.circle_2d(p, 5.0, color);
+    gizmos.line_2d(p, p + 20.0 * dir, color);
+}
@@ -0,0 +1,229 @@
+//! `ensemble <spec.ron> <out_dir>`: M realizations of one Plummer setup with
+//! different seeds, aggregated into mean and standard deviation of the
+//! energy drift, escape fraction and core collapse time.
+//!
+//! ```ron
+//! (bodies: 200, dt: 2.0e7, steps: 5000, realizations: 16, parallel: true)
+//! ```
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::batch::run_all;
+use crate::constants::PhysicsConstants;
+use crate::headless::{RunConfig, Simulation};
+use crate::structure;
+use crate::units::{CODE, SI};
+
+/// A core counts as collapsed once its radius drops below this fraction of
+/// the initial value; the collapse time is that of the smallest core
+const COLLAPSE_FRACTION: f32 = 0.5;
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct EnsembleSpec {
+    pub bodies: usize,
+    /// Timestep (s)
+    pub dt: f32,
+    /// Softening length (m)
+    pub softening: f32,
+    /// Steps per realization
+    pub steps: u64,
+    pub realizations: usize,
+    /// Realization k uses seed `first_seed + k`
+    pub first_seed: u64,
+    /// Plummer scale radius (m)
+    pub scale_radius: f32,
+    /// Steps between energy and core radius samples
+    pub sample_interval: u64,
+    /// Run several realizations at once, one per core
+    pub parallel: bool,
+}
+
+impl Default for EnsembleSpec {
+    fn default() -> Self {
+        Self {
+            bodies: 100,
+            dt: 2.0E07,
+            softening: 0.0,
+            steps: 1000,
+            realizations: 8,
+            first_seed: 0,
+            scale_radius: 5.0E13,
+            sample_interval: 10,
+            parallel: false,
+        }
+    }
+}
+
+/// Per-realization results
+struct Realization {
+    seed: u64,
+    final_drift: f64,
+    max_drift: f64,
+    escape_fraction: f64,
+    /// Simulated time of core collapse (s), if it happened
+    collapse_time: Option<f64>,
+}
+
+/// Fraction of bodies unbound at the end: positive energy in the center of
+/// mass frame, with the softened potential of all the others
+fn escape_fraction(sim: &Simulation) -> f64 {
+    let bodies = &sim.bodies;
+    let g = sim.constants.gravitation as f64;
+    let eps2 = (sim.constants.softening as f64).powi(2);
+    let total: f64 = bodies.iter().map(|b| b.mass as f64).sum();
+    if bodies.is_empty() || total <= 0.0 {
+        return 0.0;
+    }
+    let (mut vcx, mut vcy) = (0.0, 0.0);
+    for b in bodies {
+        vcx += b.mass as f64 * b.vx as f64 / total;
+        vcy += b.mass as f64 * b.vy as f64 / total;
+    }
+    let unbound = bodies
+        .iter()
+        .enumerate()
+        .filter(|&(i, bi)| {
+            let phi: f64 = bodies
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, bj)| {
+                    let dx = (bj.x - bi.x) as f64;
+                    let dy = (bj.y - bi.y) as f64;
+                    -g * bj.mass as f64 / (dx * dx + dy * dy + eps2).sqrt()
+                })
+                .sum();
+            let (vx, vy) = (bi.vx as f64 - vcx, bi.vy as f64 - vcy);
+            0.5 * (vx * vx + vy * vy) + phi > 0.0
+        })
+        .count();
+    unbound as f64 / bodies.len() as f64
+}
+
+fn run_one(config: &RunConfig, spec: &EnsembleSpec, constants: &PhysicsConstants) -> Realization {
+    let mut sim = Simulation::new(config, constants);
+    let mut e0 = None;
+    let (mut drift, mut max_drift) = (0.0, 0.0f64);
+    let initial_core = structure::compute(&sim.bodies).map(|s| s.core_radius);
+    let mut smallest_core: Option<(f32, f64)> = None;
+    loop {
+        let last = sim.step >= spec.steps;
+        if last || sim.step % spec.sample_interval.max(1) == 0 {
+            let total = sim.kinetic_energy() + sim.potential_energy();
+            let e0 = *e0.get_or_insert(total);
+            drift = if e0 != 0.0 {
+                (total - e0) / e0.abs()
+            } else {
+                0.0
+            };
+            max_drift = max_drift.max(drift.abs());
+            if let Some(s) = structure::compute(&sim.bodies) {
+                if smallest_core.is_none_or(|(r, _)| s.core_radius < r) {
+                    smallest_core = Some((s.core_radius, sim.time));
+                }
+            }
+        }
+        if last {
+            break;
+        }
+        sim.step();
+    }
+    let collapse_time = match (initial_core, smallest_core) {
+        (Some(r0), Some((r, t))) if r < COLLAPSE_FRACTION * r0 => Some(t * CODE.time),
+        _ => None,
+    };
+    Realization {
+        seed: config.seed,
+        final_drift: drift,
+        max_drift,
+        escape_fraction: escape_fraction(&sim),
+        collapse_time,
+    }
+}
+
+/// Mean and sample standard deviation
+fn mean_std(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    if values.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+    let mean = values.iter().sum::<f64>() / n;
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+    (mean, var.sqrt())
+}
+
+pub fn run(spec_path: &Path, out_dir: &Path) -> Result<(), String> {
+    let text =
+        fs::read_to_string(spec_path).map_err(|e| format!("{}: {e}", spec_path.display()))?;
+    let spec: EnsembleSpec =
+        ron::from_str(&text).map_err(|e| format!("{}: {e}", spec_path.display()))?;
+    fs::create_dir_all(out_dir).map_err(|e| format!("{}: {e}", out_dir.display()))?;
+    let constants = PhysicsConstants::read_asset_file();
+    let realizations = run_all(spec.realizations, spec.parallel, |k| {
+        let config = RunConfig {
+            bodies: spec.bodies,
+            dt: SI.time(spec.dt),
+            softening: SI.length(spec.softening),
+            seed: spec.first_seed + k as u64,
+            scale_radius: SI.length(spec.scale_radius),
+        };
+        let r = run_one(&config, &spec, &constants);
+        println!(
+            "realization {k}: drift {:.3e}, escaped {:.1}%",
+            r.final_drift,
+            100.0 * r.escape_fraction
+        );
+        r
+    });
+
+    let column = |f: fn(&Realization) -> f64| -> Vec<f64> { realizations.iter().map(f).collect() };
+    let collapse_times: Vec<f64> = realizations
+        .iter()
+        .filter_map(|r| r.collapse_time)
+        .collect();
+    let rows = [
+        ("final energy drift", mean_std(&column(|r| r.final_drift))),
+        ("max |energy drift|", mean_std(&column(|r| r.max_drift))),
+        ("escape fraction", mean_std(&column(|r| r.escape_fraction))),
+        ("core collapse time (s)", mean_std(&collapse_times)),
+    ];
+    let mut report = format!(
+        "{} realizations of {} bodies, dt = {:e} s, {} steps\n\n{:<24} {:>14} {:>14}\n",
+        spec.realizations, spec.bodies, spec.dt, spec.steps, "quantity", "mean", "std"
+    );
+    for (name, (mean, std)) in rows {
+        report += &format!("{name:<24} {mean:>14.6e} {std:>14.6e}\n");
+    }
+    report += &format!(
+        "\ncore collapsed in {} of {} realizations\n",
+        collapse_times.len(),
+        spec.realizations
+    );
+    print!("\n{report}");
+
+    let write = || -> std::io::Result<()> {
+        fs::write(out_dir.join("report.txt"), &report)?;
+        let mut w = BufWriter::new(File::create(out_dir.join("realizations.csv"))?);
+        writeln!(
+            w,
+            "seed,final_drift,max_abs_drift,escape_fraction,collapse_time_s"
+        )?;
+        for r in &realizations {
+            let collapse = r
+                .collapse_time
+                .map_or(String::new(), |t| format!("{t:.6e}"));
+            writeln!(
+                w,
+                "{},{:.6e},{:.6e},{:.6e},{collapse}",
+                r.seed, r.final_drift, r.max_drift, r.escape_fraction
+            )?;
+        }
+        w.flush()
+    };
+    write().map_err(|e| format!("{}: {e}", out_dir.display()))
+}
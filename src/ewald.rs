@@ -0,0 +1,125 @@
+//! Ewald correction table for gravity in a doubly periodic box.
+//!
+//! Bodies live in the z = 0 plane of a box that repeats in x and y only, so
+//! the lattice sum uses the slab form of Ewald summation. Instead of summing
+//! per pair at runtime, the difference between the full periodic sum and the
+//! plain minimum-image Newtonian term is tabulated once over a quarter box and
+//! looked up with bilinear interpolation (the approach used by GADGET).
+
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+use crate::special::erfc;
+
+const TABLE_SIZE: usize = 64; // samples per axis over [0, L/2]
+const REAL_IMAGES: i32 = 4;
+const RECIP_IMAGES: i32 = 4;
+
+pub struct EwaldTable {
+    box_size: f64,
+    /// [acc_x, acc_y, φ] corrections per unit G·m, row-major in y.
+    /// A pair's energy changes by -G·m_i·m_j·φ.
+    data: Vec<[f64; 3]>,
+}
+
+impl EwaldTable {
+    pub fn new(box_size: f64) -> Self {
+        let cell = box_size / 2.0 / (TABLE_SIZE - 1) as f64;
+        let mut data = Vec::with_capacity(TABLE_SIZE * TABLE_SIZE);
+        for iy in 0..TABLE_SIZE {
+            for ix in 0..TABLE_SIZE {
+                data.push(correction_at(ix as f64 * cell, iy as f64 * cell, box_size));
+            }
+        }
+        Self { box_size, data }
+    }
+
+    /// Correction for a minimum-image separation (dx, dy) from body i to body j:
+    /// acceleration of i and potential, both per unit G·m_j.
+    pub fn correction(&self, dx: f64, dy: f64) -> [f64; 3] {
+        let cell = self.box_size / 2.0 / (TABLE_SIZE - 1) as f64;
+        let u = (dx.abs() / cell).min((TABLE_SIZE - 1) as f64);
+        let v = (dy.abs() / cell).min((TABLE_SIZE - 1) as f64);
+        let (i, j) = (
+            (u as usize).min(TABLE_SIZE - 2),
+            (v as usize).min(TABLE_SIZE - 2),
+        );
+        let (fx, fy) = (u - i as f64, v - j as f64);
+
+        let at = |x: usize, y: usize| self.data[y * TABLE_SIZE + x];
+        let mut out = [0.0; 3];
+        for (k, o) in out.iter_mut().enumerate() {
+            *o = at(i, j)[k] * (1.0 - fx) * (1.0 - fy)
+                + at(i + 1, j)[k] * fx * (1.0 - fy)
+                + at(i, j + 1)[k] * (1.0 - fx) * fy
+                + at(i + 1, j + 1)[k] * fx * fy;
+        }
+        // The x force component is odd in dx and even in dy (and vice versa)
+        out[0] *= dx.signum();
+        out[1] *= dy.signum();
+        out
+    }
+}
+
+/// Shared table for the simulation box
+pub fn table(box_size: f64) -> &'static EwaldTable {
+    static TABLE: OnceLock<EwaldTable> = OnceLock::new();
+    TABLE.get_or_init(|| EwaldTable::new(box_size))
+}
+
+/// Ewald sum minus the bare minimum-image term at separation (dx, dy)
+fn correction_at(dx: f64, dy: f64, l: f64) -> [f64; 3] {
+    let alpha = 2.0 / l;
+    let area = l * l;
+    let (mut ax, mut ay, mut phi) = (0.0, 0.0, 0.0);
+
+    // Real-space images
+    for nx in -REAL_IMAGES..=REAL_IMAGES {
+        for ny in -REAL_IMAGES..=REAL_IMAGES {
+            let sx = dx + nx as f64 * l;
+            let sy = dy + ny as f64 * l;
+            let s = (sx * sx + sy * sy).sqrt();
+            if s == 0.0 {
+                continue;
+            }
+            let e = erfc(alpha * s);
+            let g = e + 2.0 * alpha * s / PI.sqrt() * (-alpha * alpha * s * s).exp();
+            ax += sx / (s * s * s) * g;
+            ay += sy / (s * s * s) * g;
+            phi += e / s;
+        }
+    }
+
+    // Reciprocal-space sum, slab geometry evaluated at z = 0
+    for mx in -RECIP_IMAGES..=RECIP_IMAGES {
+        for my in -RECIP_IMAGES..=RECIP_IMAGES {
+            if mx == 0 && my == 0 {
+                continue;
+            }
+            let kx = 2.0 * PI * mx as f64 / l;
+            let ky = 2.0 * PI * my as f64 / l;
+            let k = (kx * kx + ky * ky).sqrt();
+            let w = 2.0 * PI / area * erfc(k / (2.0 * alpha)) / k;
+            let kr = kx * dx + ky * dy;
+            ax += w * kx * kr.sin();
+            ay += w * ky * kr.sin();
+            phi += w * kr.cos();
+        }
+    }
+
+    // Background term, so the potential has a fixed zero point
+    phi -= 2.0 * PI.sqrt() / (alpha * area);
+
+    // Remove the bare Newtonian part that the pair loop already adds
+    let r = (dx * dx + dy * dy).sqrt();
+    if r > 0.0 {
+        ax -= dx / (r * r * r);
+        ay -= dy / (r * r * r);
+        phi -= 1.0 / r;
+    } else {
+        // Limit of erfc(αr)/r - 1/r
+        phi -= 2.0 * alpha / PI.sqrt();
+    }
+
+    [ax, ay, phi]
+}
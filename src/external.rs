@@ -0,0 +1,107 @@
+//! Time-dependent external forcing (tidal perturbers) from the scenario file.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::Deserialize;
+
+use crate::{Bodies, world_scale};
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum ExternalField {
+    /// Point mass on a straight line: pos(t) = start + velocity * t
+    Perturber {
+        mass: f32,
+        start: (f32, f32),
+        velocity: (f32, f32),
+        /// Plummer softening length (m)
+        softening: f32,
+    },
+    /// Bar modelled as two equal masses at ±half_length, rotating about
+    /// `center` with angular velocity `pattern_speed` (rad/s)
+    RotatingBar {
+        mass: f32,
+        half_length: f32,
+        pattern_speed: f32,
+        center: (f32, f32),
+        softening: f32,
+    },
+}
+
+impl ExternalField {
+    /// Point masses (x, y, mass, softening) making up this field at time t
+    fn sources(&self, t: f32) -> impl Iterator<Item = [f32; 4]> {
+        match *self {
+            ExternalField::Perturber {
+                mass,
+                start,
+                velocity,
+                softening,
+            } => [
+                Some([
+                    start.0 + velocity.0 * t,
+                    start.1 + velocity.1 * t,
+                    mass,
+                    softening,
+                ]),
+                None,
+            ],
+            ExternalField::RotatingBar {
+                mass,
+                half_length,
+                pattern_speed,
+                center,
+                softening,
+            } => {
+                let (s, c) = (pattern_speed * t).sin_cos();
+                let (dx, dy) = (half_length * c, half_length * s);
+                [
+                    Some([center.0 + dx, center.1 + dy, mass / 2.0, softening]),
+                    Some([center.0 - dx, center.1 - dy, mass / 2.0, softening]),
+                ]
+            }
+        }
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Add the external accelerations at time t to `accel`
+pub fn add_accelerations(
+    fields: &[ExternalField],
+    t: f32,
+    snap: &[[f32; 4]],
+    accel: &mut [[f32; 2]],
+    g: f32,
+) {
+    for field in fields {
+        for [sx, sy, m, eps] in field.sources(t) {
+            for (p, a) in snap.iter().zip(accel.iter_mut()) {
+                let dx = sx - p[0];
+                let dy = sy - p[1];
+                let r2 = dx * dx + dy * dy + eps * eps;
+                let r = r2.sqrt();
+                let a_mag = g * m / r2;
+                a[0] += a_mag * dx / r;
+                a[1] += a_mag * dy / r;
+            }
+        }
+    }
+}
+
+/// Mark the current perturber positions
+pub fn draw_external(
+    mut gizmos: Gizmos,
+    scenario: Res<crate::scenario::Scenario>,
+    bodies: Res<Bodies>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(window) = win_q.get_single() else {
+        return;
+    };
+    let scale = world_scale(window);
+    for field in &scenario.external {
+        for [x, y, _, _] in field.sources(bodies.elapsed_time) {
+            gizmos.circle_2d(Vec2::new(x, y) * scale, 6.0, Color::srgb(1.0, 0.5, 0.2));
+        }
+    }
+}
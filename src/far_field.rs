@@ -0,0 +1,174 @@
+//! Ahmad–Cohen neighbor scheme for the direct sum (Ahmad & Cohen 1973).
+//!
+//! Every `interval` steps the full pair sum is split by distance: pairs
+//! closer than `radius` become each body's neighbor list, the rest are
+//! summed into a cached far-field acceleration. In between, only the
+//! neighbor lists are summed and the cached far field is added unchanged,
+//! so a step costs O(N k) instead of O(N²) for k neighbors per body.
+//!
+//! The approximation error is the drift of the far field over one
+//! interval. Each refresh measures it directly as the largest change of
+//! any body's cached far field relative to its total acceleration, which
+//! bounds the error of the stalest step of the interval just finished.
+
+use bevy::prelude::*;
+use bevy::tasks::ComputeTaskPool;
+
+use crate::physics::{BOX_SIZE, PairKernel};
+
+/// Near-field radius switched on at runtime
+pub const DEFAULT_RADIUS: f32 = BOX_SIZE / 16.0;
+
+#[derive(Clone, Copy, Debug, Reflect)]
+pub struct FarFieldConfig {
+    /// Steps between far-field refreshes; 1 sums every pair every step
+    pub interval: u32,
+    /// Pairs closer than this at a refresh are summed every step
+    pub radius: f32,
+}
+
+impl Default for FarFieldConfig {
+    fn default() -> Self {
+        Self {
+            interval: 1,
+            radius: DEFAULT_RADIUS,
+        }
+    }
+}
+
+impl FarFieldConfig {
+    pub fn is_active(&self) -> bool {
+        self.interval > 1
+    }
+}
+
+/// Neighbor lists and far field carried from one force pass to the next
+#[derive(Default)]
+pub struct FarFieldCache {
+    /// Far-field acceleration of each body as of the last refresh
+    far: Vec<[f32; 2]>,
+    /// Indices within the radius of each body as of the last refresh
+    neighbors: Vec<Vec<u32>>,
+    /// Step of the last pass served, `None` once the bodies changed
+    last_step: Option<u64>,
+    /// Passes left before the next refresh
+    remaining: u32,
+    /// Largest |Δa_far| / |a| found by the last refresh with a valid cache
+    pub error: Option<f32>,
+}
+
+impl FarFieldCache {
+    /// Bodies were added, replaced or reordered: refresh on the next pass
+    pub fn invalidate(&mut self) {
+        self.last_step = None;
+    }
+
+    /// Accelerations of every body for the pass of step `step`, split
+    /// across the compute pool when `chunked`
+    pub fn accelerations(
+        &mut self,
+        kernel: &PairKernel,
+        config: &FarFieldConfig,
+        step: u64,
+        chunked: bool,
+        accel: &mut Vec<[f32; 2]>,
+    ) {
+        let n = kernel.body_count();
+        accel.clear();
+        accel.resize(n, [0.0; 2]);
+        let valid = self.last_step.is_some_and(|s| s + 1 == step) && self.far.len() == n;
+        self.last_step = Some(step);
+        if n == 0 {
+            return;
+        }
+
+        if valid && self.remaining > 0 && self.remaining < config.interval {
+            self.remaining -= 1;
+            let _span = info_span!("near_field").entered();
+            for_chunks(
+                chunked,
+                accel,
+                &mut self.far,
+                &mut self.neighbors,
+                |i, a, far, nb| {
+                    *a = *far;
+                    for &j in nb.iter() {
+                        kernel.add(a, i, j as usize);
+                    }
+                    0.0
+                },
+            );
+            return;
+        }
+
+        let _span = info_span!("far_field_refresh").entered();
+        self.far.resize(n, [0.0; 2]);
+        self.neighbors.resize_with(n, Vec::new);
+        let r2 = config.radius * config.radius;
+        let error = for_chunks(
+            chunked,
+            accel,
+            &mut self.far,
+            &mut self.neighbors,
+            |i, a, far, nb| {
+                nb.clear();
+                let (mut near, mut new_far) = ([0.0f32; 2], [0.0f32; 2]);
+                for j in (0..n).filter(|&j| j != i) {
+                    let (dx, dy) = kernel.separation(i, j);
+                    if dx * dx + dy * dy < r2 {
+                        nb.push(j as u32);
+                        kernel.add(&mut near, i, j);
+                    } else {
+                        kernel.add(&mut new_far, i, j);
+                    }
+                }
+                *a = [near[0] + new_far[0], near[1] + new_far[1]];
+                let drift = (new_far[0] - far[0]).hypot(new_far[1] - far[1]);
+                let total = a[0].hypot(a[1]);
+                *far = new_far;
+                if total > 0.0 { drift / total } else { 0.0 }
+            },
+        );
+        self.error = valid.then_some(error);
+        self.remaining = config.interval.saturating_sub(1);
+    }
+}
+
+/// Run `f(i, accel, far, neighbors)` for every body, in one chunk per
+/// compute-pool thread when `chunked`; returns the largest value `f` gave
+fn for_chunks<F>(
+    chunked: bool,
+    accel: &mut [[f32; 2]],
+    far: &mut [[f32; 2]],
+    neighbors: &mut [Vec<u32>],
+    f: F,
+) -> f32
+where
+    F: Fn(usize, &mut [f32; 2], &mut [f32; 2], &mut Vec<u32>) -> f32 + Sync,
+{
+    let run = |first: usize, accel: &mut [[f32; 2]], far: &mut [[f32; 2]], nb: &mut [Vec<u32>]| {
+        let mut max = 0.0f32;
+        for (k, ((a, far), nb)) in accel.iter_mut().zip(far).zip(nb).enumerate() {
+            max = max.max(f(first + k, a, far, nb));
+        }
+        max
+    };
+    if !chunked {
+        return run(0, accel, far, neighbors);
+    }
+    let pool = ComputeTaskPool::get();
+    let chunk = accel.len().div_ceil(pool.thread_num().max(1)).max(1);
+    let run = &run;
+    pool.scope(|scope| {
+        for (c, ((a, far), nb)) in accel
+            .chunks_mut(chunk)
+            .zip(far.chunks_mut(chunk))
+            .zip(neighbors.chunks_mut(chunk))
+            .enumerate()
+        {
+            scope.spawn(async move { run(c * chunk, a, far, nb) });
+        }
+    })
+    .into_iter()
+    .fold(0.0, f32::max)
+}
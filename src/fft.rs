@@ -0,0 +1,105 @@
+use std::f64::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Self::new(self.re + o.re, self.im + o.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, o: Self) -> Self {
+        Self::new(self.re - o.re, self.im - o.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, o: Self) -> Self {
+        Self::new(
+            self.re * o.re - self.im * o.im,
+            self.re * o.im + self.im * o.re,
+        )
+    }
+}
+
+/// In-place iterative radix-2 FFT. `buf.len()` must be a power of two.
+/// The inverse transform is normalized by 1/n.
+pub fn fft(buf: &mut [Complex], inverse: bool) {
+    let n = buf.len();
+    debug_assert!(n.is_power_of_two());
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+
+    // Butterflies
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let ang = sign * 2.0 * PI / len as f64;
+        let w_len = Complex::new(ang.cos(), ang.sin());
+        for start in (0..n).step_by(len) {
+            let mut w = Complex::new(1.0, 0.0);
+            for k in 0..len / 2 {
+                let u = buf[start + k];
+                let v = buf[start + k + len / 2] * w;
+                buf[start + k] = u + v;
+                buf[start + k + len / 2] = u - v;
+                w = w * w_len;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let inv_n = 1.0 / n as f64;
+        for c in buf.iter_mut() {
+            c.re *= inv_n;
+            c.im *= inv_n;
+        }
+    }
+}
+
+/// 2D FFT over a row-major `n × n` grid (rows, then columns). `col` is
+/// scratch space for one column, kept by the caller between calls.
+pub fn fft_2d(grid: &mut [Complex], n: usize, inverse: bool, col: &mut Vec<Complex>) {
+    debug_assert_eq!(grid.len(), n * n);
+    for row in grid.chunks_exact_mut(n) {
+        fft(row, inverse);
+    }
+    col.resize(n, Complex::default());
+    for x in 0..n {
+        for y in 0..n {
+            col[y] = grid[y * n + x];
+        }
+        fft(col, inverse);
+        for y in 0..n {
+            grid[y * n + x] = col[y];
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Friends-of-friends group finder.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::diagnostics::{DiagnosticsLog, is_due};
+use crate::locale::{Language, fill, tr};
+use crate::units::{CODE, SI};
+use crate::{Bodies, BodyState, UiGroups};
+
+const LISTED_GROUPS: usize = 5;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Group {
+    pub members: usize,
+    pub mass: f64,
+}
+
+#[derive(Resource)]
+pub struct FofGroups {
+    /// Rerun every `interval` physics steps
+    pub interval: u64,
+    /// Bodies closer than this are friends
+    pub linking_length: f32,
+    /// Smaller sets of friends are not reported as groups
+    pub min_members: usize,
+    pub last_step: Option<u64>,
+    /// Group index per body, `None` for field bodies
+    pub group_of: Vec<Option<usize>>,
+    /// Groups sorted by mass, heaviest first
+    pub groups: Vec<Group>,
+}
+
+impl Default for FofGroups {
+    fn default() -> Self {
+        Self {
+            interval: 30,
+            linking_length: SI.length(1.5E13),
+            min_members: 3,
+            last_step: None,
+            group_of: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Union-find over all pairs closer than `linking_length` (cell list, O(N))
+pub fn find_groups(
+    data: &[BodyState],
+    linking_length: f32,
+    min_members: usize,
+) -> (Vec<Option<usize>>, Vec<Group>) {
+    let cell_of = |b: &BodyState| {
+        (
+            (b.x / linking_length).floor() as i64,
+            (b.y / linking_length).floor() as i64,
+        )
+    };
+    let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, b) in data.iter().enumerate() {
+        cells.entry(cell_of(b)).or_default().push(i);
+    }
+
+    let mut parent: Vec<usize> = (0..data.len()).collect();
+    let l2 = linking_length * linking_length;
+    for (i, bi) in data.iter().enumerate() {
+        let (cx, cy) = cell_of(bi);
+        for ny in cy - 1..=cy + 1 {
+            for nx in cx - 1..=cx + 1 {
+                let Some(members) = cells.get(&(nx, ny)) else {
+                    continue;
+                };
+                for &j in members {
+                    if j <= i {
+                        continue;
+                    }
+                    let r2 = (data[j].x - bi.x).powi(2) + (data[j].y - bi.y).powi(2);
+                    if r2 < l2 {
+                        let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                        if ri != rj {
+                            parent[ri] = rj;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Collect roots with enough members
+    let mut by_root: HashMap<usize, Group> = HashMap::new();
+    for (i, b) in data.iter().enumerate() {
+        let root = find(&mut parent, i);
+        let g = by_root.entry(root).or_insert(Group {
+            members: 0,
+            mass: 0.0,
+        });
+        g.members += 1;
+        g.mass += b.mass as f64;
+    }
+    let mut roots: Vec<(usize, Group)> = by_root
+        .into_iter()
+        .filter(|(_, g)| g.members >= min_members)
+        .collect();
+    roots.sort_by(|a, b| b.1.mass.total_cmp(&a.1.mass).then(a.0.cmp(&b.0)));
+
+    let index_of: HashMap<usize, usize> = roots
+        .iter()
+        .enumerate()
+        .map(|(k, (root, _))| (*root, k))
+        .collect();
+    let group_of = (0..data.len())
+        .map(|i| index_of.get(&find(&mut parent, i)).copied())
+        .collect();
+    (group_of, roots.into_iter().map(|(_, g)| g).collect())
+}
+
+pub fn update_groups(
+    bodies: Res<Bodies>,
+    mut fof: ResMut<FofGroups>,
+    mut log: ResMut<DiagnosticsLog>,
+) {
+    let step = bodies.step;
+    if !is_due(fof.last_step, step, fof.interval) {
+        return;
+    }
+    fof.last_step = Some(step);
+    let (group_of, groups) = find_groups(&bodies.data, fof.linking_length, fof.min_members);
+    fof.group_of = group_of;
+    fof.groups = groups;
+
+    let in_groups: usize = fof.groups.iter().map(|g| g.members).sum();
+    log.record(
+        "fof",
+        bodies.elapsed_time,
+        &["groups", "members", "largest_mass"],
+        &[
+            fof.groups.len() as f64,
+            in_groups as f64,
+            fof.groups.first().map_or(0.0, |g| g.mass * CODE.mass),
+        ],
+    );
+}
+
+pub fn update_group_text(
+    fof: Res<FofGroups>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<UiGroups>>,
+) {
+    if !fof.is_changed() && !lang.is_changed() {
+        return;
+    }
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    let mut out = fill(
+        tr("FoF groups (b = {} m): {}", "FoF 그룹 (b = {} m): {}").get(*lang),
+        &[
+            &format!("{:.1E}", CODE.length(fof.linking_length)),
+            &fof.groups.len(),
+        ],
+    );
+    for (k, g) in fof.groups.iter().take(LISTED_GROUPS).enumerate() {
+        out += "\n";
+        out += &fill(
+            tr("group {}: {} bodies, {} kg", "그룹 {}: 천체 {}개, {} kg").get(*lang),
+            &[&k, &g.members, &format!("{:.2E}", g.mass * CODE.mass)],
+        );
+    }
+    t.sections[0].value = out;
+}
@@ -0,0 +1,388 @@
+//! Pairwise interaction laws used by the direct solvers.
+//!
+//! Each law gives the radial acceleration one body feels from another and
+//! the matching pair potential, so forces and energies always agree. Bodies
+//! are snapshot rows `[x, y, mass, charge]`. The mesh solvers only know
+//! Newtonian gravity.
+//!
+//! The Lennard-Jones law turns the same integrator into a molecular-dynamics
+//! engine; its parameters are in reduced units (lengths in σ, energies in ε).
+//!
+//! MOND is not pairwise: its pairs act like gravity, and each body's total
+//! self-gravity is then boosted by the interpolation function (see
+//! [`mond_boost`]). The energy readout stays Newtonian under MOND.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::constants::PhysicsConstants;
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, fill, tr};
+use crate::physics::{PhysicsSettings, PhysicsTask, Solver};
+use crate::units::{CODE, SI};
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub enum ForceLaw {
+    /// Attractive G m / r^2
+    #[default]
+    Gravity,
+    /// Same magnitude with the sign flipped: every pair repels
+    AntiGravity,
+    /// Gravity plus electrostatics k q_i q_j / r^2 (like charges repel)
+    Coulomb,
+    /// Short-range 12-6 potential, no gravity
+    LennardJones(LjParams),
+    /// Modified Newtonian dynamics with acceleration scale a₀ (m/s²)
+    Mond { a0: f32 },
+    /// True 2D gravity: G m / (L r) with log potential (G m_i m_j / L) ln(r / L),
+    /// matching the 3D law at r = L (m)
+    Gravity2D { length: f32 },
+    /// Attractive G m / r^2 (L / r)^(n-2): an inverse-`exponent` law matching
+    /// gravity at r = L (m), for exploring non-inverse-square orbits
+    PowerLaw { exponent: f32, length: f32 },
+}
+
+/// Milgrom's a₀, for MOND switched on at runtime
+pub const MOND_A0: f32 = SI.acceleration(1.2E-10);
+/// Matching length of 2D gravity and power laws switched on at runtime
+pub const MATCHING_LENGTH: f32 = SI.length(1.0E14);
+/// Exponent change per key press
+const EXPONENT_STEP: f32 = 0.1;
+const EXPONENT_RANGE: (f32, f32) = (0.5, 4.0);
+
+/// Lennard-Jones parameters; σ and ε set the reduced length and energy units
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct LjParams {
+    /// Length unit σ, where the potential crosses zero (m)
+    pub sigma: f32,
+    /// Energy unit ε, the depth of the well (J)
+    pub epsilon: f32,
+    /// Pairs farther apart than this many σ don't interact
+    pub cutoff: f32,
+}
+
+impl LjParams {
+    /// Force magnitude along the pair (positive = repulsive) at separation r
+    fn force(&self, r: f64) -> f64 {
+        if r >= self.cutoff as f64 * self.sigma as f64 {
+            return 0.0;
+        }
+        let sr6 = (self.sigma as f64 / r).powi(6);
+        24.0 * self.epsilon as f64 / r * (2.0 * sr6 * sr6 - sr6)
+    }
+
+    /// Truncated and shifted potential, zero at the cutoff
+    fn potential(&self, r: f64) -> f64 {
+        let v = |r: f64| {
+            let sr6 = (self.sigma as f64 / r).powi(6);
+            4.0 * self.epsilon as f64 * (sr6 * sr6 - sr6)
+        };
+        let rc = self.cutoff as f64 * self.sigma as f64;
+        if r >= rc { 0.0 } else { v(r) - v(rc) }
+    }
+}
+
+impl ForceLaw {
+    fn next(self) -> Self {
+        match self {
+            ForceLaw::Gravity => ForceLaw::AntiGravity,
+            ForceLaw::AntiGravity => ForceLaw::Coulomb,
+            ForceLaw::Coulomb => ForceLaw::Mond { a0: MOND_A0 },
+            ForceLaw::Mond { .. } => ForceLaw::Gravity2D {
+                length: MATCHING_LENGTH,
+            },
+            ForceLaw::Gravity2D { .. } => ForceLaw::PowerLaw {
+                exponent: 2.0,
+                length: MATCHING_LENGTH,
+            },
+            ForceLaw::PowerLaw { .. } | ForceLaw::LennardJones(_) => ForceLaw::Gravity,
+        }
+    }
+
+    /// Acceleration of body i towards body j (negative = away) at
+    /// separation `r > 0`, softened by `eps` the way the law calls for: the
+    /// inverse-square laws through the softening kernel, 2D gravity and the
+    /// power law as sqrt(r² + ε²), and Lennard-Jones not at all, since its
+    /// core repels
+    pub fn radial_accel(
+        self,
+        r: f32,
+        eps: f32,
+        i: &[f32; 4],
+        j: &[f32; 4],
+        constants: &PhysicsConstants,
+    ) -> f32 {
+        let (r, eps) = (r as f64, eps as f64);
+        match self {
+            ForceLaw::LennardJones(lj) => (-lj.force(r) / i[2] as f64) as f32,
+            ForceLaw::Gravity2D { length } => {
+                // dU/dr / m_i of the log potential at sqrt(r² + ε²)
+                (constants.gravitation as f64 * j[2] as f64 * r
+                    / (length as f64 * (r * r + eps * eps))) as f32
+            }
+            ForceLaw::PowerLaw { exponent, length } => {
+                // dU/dr / m_i with U taken at s = sqrt(r² + ε²): G m L^(n-2) r / s^(n+1)
+                let s = (r * r + eps * eps).sqrt();
+                (constants.gravitation as f64 * j[2] as f64 * r / (s * s * s)
+                    * (length as f64 / s).powf(exponent as f64 - 2.0)) as f32
+            }
+            _ => {
+                let r2 = constants.softening_kernel.force_r2(r as f32, eps as f32);
+                (self.inverse_square_strength(i, j, constants) / r2 as f64) as f32
+            }
+        }
+    }
+
+    /// Pair potential energy at separation r, softened by `eps` as in
+    /// [`ForceLaw::radial_accel`]
+    pub fn pair_potential(
+        self,
+        r: f64,
+        eps: f64,
+        i: &[f32; 4],
+        j: &[f32; 4],
+        constants: &PhysicsConstants,
+    ) -> f64 {
+        match self {
+            ForceLaw::LennardJones(lj) => lj.potential(r),
+            ForceLaw::Gravity2D { length } => {
+                let length = length as f64;
+                let r = (r * r + eps * eps).sqrt();
+                constants.gravitation as f64 * i[2] as f64 * j[2] as f64 / length
+                    * (r / length).ln()
+            }
+            ForceLaw::PowerLaw { exponent, length } => {
+                let (n, length) = (exponent as f64, length as f64);
+                let r = (r * r + eps * eps).sqrt();
+                let gmm = constants.gravitation as f64 * i[2] as f64 * j[2] as f64;
+                if (n - 1.0).abs() < 1e-6 {
+                    gmm / length * (r / length).ln()
+                } else {
+                    // U = -G m_i m_j L^(n-2) r^(1-n) / (n-1)
+                    -gmm / (r * (n - 1.0)) * (length / r).powf(n - 2.0)
+                }
+            }
+            _ => {
+                let r = constants.softening_kernel.potential_r(r, eps);
+                -(i[2] as f64) * self.inverse_square_strength(i, j, constants) / r
+            }
+        }
+    }
+
+    /// S in a_i = S / r^2 towards j; also scales the Ewald image sum (zero
+    /// for laws without a 1/r tail). Evaluated in f64 since k q_i q_j
+    /// overflows f32 for stellar charges.
+    pub fn inverse_square_strength(
+        self,
+        i: &[f32; 4],
+        j: &[f32; 4],
+        constants: &PhysicsConstants,
+    ) -> f64 {
+        let g = constants.gravitation as f64;
+        let (mi, mj) = (i[2] as f64, j[2] as f64);
+        match self {
+            ForceLaw::Gravity | ForceLaw::Mond { .. } => g * mj,
+            ForceLaw::AntiGravity => -g * mj,
+            ForceLaw::Coulomb => g * mj - constants.coulomb as f64 * i[3] as f64 * j[3] as f64 / mi,
+            ForceLaw::LennardJones(_) | ForceLaw::Gravity2D { .. } | ForceLaw::PowerLaw { .. } => {
+                0.0
+            }
+        }
+    }
+}
+
+/// MOND "simple" interpolation ν(y) = 1/2 + sqrt(1/4 + 1/y), y = g_N / a₀:
+/// Newtonian for y ≫ 1, g = sqrt(g_N a₀) for y ≪ 1
+pub fn mond_nu(y: f64) -> f64 {
+    if y <= 0.0 {
+        return 1.0;
+    }
+    0.5 + (0.25 + 1.0 / y).sqrt()
+}
+
+/// Turn Newtonian self-gravity into MOND gravity body by body
+pub fn mond_boost(accel: &mut [[f32; 2]], a0: f32) {
+    for a in accel.iter_mut() {
+        let g = (a[0] as f64).hypot(a[1] as f64);
+        let nu = mond_nu(g / a0 as f64) as f32;
+        a[0] *= nu;
+        a[1] *= nu;
+    }
+}
+
+pub fn cycle_force_law(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut settings: ResMut<PhysicsSettings>,
+) {
+    if bindings.just_pressed(&keys, Action::CycleForceLaw) {
+        settings.force_law = settings.force_law.next();
+        if !settings.solver.is_direct() {
+            warn!("Force laws other than gravity are only applied by the direct solvers");
+        }
+        info!("Force law: {:?}", settings.force_law);
+    }
+}
+
+/// Exponent keys step the power-law exponent, switching to it if needed
+pub fn adjust_force_exponent(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut settings: ResMut<PhysicsSettings>,
+) {
+    let step = if bindings.just_pressed(&keys, Action::ExponentUp) {
+        EXPONENT_STEP
+    } else if bindings.just_pressed(&keys, Action::ExponentDown) {
+        -EXPONENT_STEP
+    } else {
+        return;
+    };
+    let (exponent, length) = match settings.force_law {
+        ForceLaw::PowerLaw { exponent, length } => (exponent, length),
+        _ => (2.0, MATCHING_LENGTH),
+    };
+    let exponent = (exponent + step).clamp(EXPONENT_RANGE.0, EXPONENT_RANGE.1);
+    settings.force_law = ForceLaw::PowerLaw { exponent, length };
+    info!("Force law: F ∝ 1/r^{exponent:.1}");
+}
+
+#[derive(Component)]
+pub struct ForceLawText;
+
+pub fn spawn_force_law_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 16.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            top: Val::Px(210.0),
+            ..Default::default()
+        }),
+        ForceLawText,
+    ));
+}
+
+pub fn update_force_law_text(
+    settings: Res<PhysicsSettings>,
+    task: Res<PhysicsTask>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<ForceLawText>>,
+) {
+    // The far-field error changes with every refresh
+    let far_field = settings.far_field.is_active() && settings.solver.is_direct();
+    if !settings.is_changed() && !lang.is_changed() && !far_field {
+        return;
+    }
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    let lang = *lang;
+    let law = match settings.force_law {
+        ForceLaw::Gravity => tr("gravity", "중력").get(lang).to_string(),
+        ForceLaw::AntiGravity => tr("anti-gravity", "반중력").get(lang).to_string(),
+        ForceLaw::Coulomb => tr("gravity + Coulomb", "중력 + 쿨롱").get(lang).to_string(),
+        ForceLaw::LennardJones(_) => tr("Lennard-Jones", "레너드-존스").get(lang).to_string(),
+        ForceLaw::Mond { a0 } => format!("MOND, a0 = {:.2E} m/s²", CODE.acceleration(a0)),
+        ForceLaw::Gravity2D { .. } => tr("2D gravity (1/r)", "2차원 중력 (1/r)")
+            .get(lang)
+            .to_string(),
+        ForceLaw::PowerLaw { exponent, .. } => format!(
+            "F ∝ 1/r^{exponent:.1}   ({})",
+            tr("[ / ] to adjust", "[ / ] 로 조절").get(lang)
+        ),
+    };
+    let mut solver = match settings.solver {
+        Solver::Direct => tr("direct", "직접"),
+        Solver::DirectChunked => tr("chunked direct", "분할 직접"),
+        Solver::ParticleMesh => tr("PM", "PM"),
+        Solver::P3M => tr("P³M", "P³M"),
+    }
+    .get(lang)
+    .to_string();
+    if settings.auto_solver {
+        solver = fill(tr("auto ({})", "자동 ({})").get(lang), &[&solver]);
+    }
+    t.sections[0].value = format!(
+        "{}: {law}\n{}: {solver}",
+        tr("force law", "힘 법칙").get(lang),
+        tr("solver", "힘 계산").get(lang)
+    );
+    if far_field {
+        let template = tr(
+            "far field every {} steps, error ≤ {}",
+            "원거리 힘 {}스텝마다, 오차 ≤ {}",
+        );
+        let error = task
+            .far_field_error()
+            .map_or("–".to_string(), |e| format!("{:.2}%", 100.0 * e));
+        t.sections[0].value += "\n";
+        t.sections[0].value += &fill(template.get(lang), &[&settings.far_field.interval, &error]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::softening::SofteningKernel;
+
+    /// m_i a_i towards j must be dU/dr, the negative of the outward force
+    fn assert_force_matches_potential(law: ForceLaw, kernel: SofteningKernel) {
+        let constants = PhysicsConstants {
+            gravitation: 1.0,
+            coulomb: 0.5,
+            softening: 0.5,
+            softening_kernel: kernel,
+            ..Default::default()
+        };
+        let eps = constants.softening as f64;
+        let (i, j) = ([0.0, 0.0, 2.0, 1.0], [0.0, 0.0, 3.0, -1.5]);
+        let potential = |r: f64| law.pair_potential(r, eps, &i, &j, &constants);
+        // Inside and outside the spline support of 1.4
+        for r in [0.3, 0.8, 1.2, 1.7, 2.5] {
+            let h = 1e-5;
+            let dudr = (potential(r + h) - potential(r - h)) / (2.0 * h);
+            let force =
+                i[2] as f64 * law.radial_accel(r as f32, eps as f32, &i, &j, &constants) as f64;
+            assert!(
+                (force - dudr).abs() <= 1e-4 * dudr.abs().max(1.0),
+                "{law:?}, {kernel:?}, r = {r}: force {force}, dU/dr {dudr}"
+            );
+        }
+    }
+
+    #[test]
+    fn softened_forces_are_potential_gradients() {
+        let laws = [
+            ForceLaw::Gravity,
+            ForceLaw::AntiGravity,
+            ForceLaw::Coulomb,
+            ForceLaw::Mond { a0: MOND_A0 },
+            ForceLaw::LennardJones(LjParams {
+                sigma: 1.0,
+                epsilon: 1.0,
+                cutoff: 3.0,
+            }),
+            ForceLaw::Gravity2D { length: 2.0 },
+            ForceLaw::PowerLaw {
+                exponent: 3.0,
+                length: 2.0,
+            },
+            ForceLaw::PowerLaw {
+                exponent: 1.0,
+                length: 2.0,
+            },
+        ];
+        for law in laws {
+            for kernel in [SofteningKernel::Plummer, SofteningKernel::Spline] {
+                assert_force_matches_potential(law, kernel);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Animated GIF of the last few seconds: a ring buffer of small, palettized
+//! frames captured a few times per second, written out on a hotkey. The
+//! encoder (LZW, one fixed global palette) is written here to avoid a
+//! dependency.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+
+use crate::keybindings::{Action, KeyBindings};
+
+const FPS: f32 = 10.0;
+/// Seconds kept in the ring buffer
+const SECONDS: f32 = 10.0;
+/// Frames wider than this are box-filtered down by an integer factor
+const MAX_WIDTH: u32 = 320;
+/// Palette: a 6×6×6 color cube, then a ramp of grays for the dark background
+const CUBE_LEVELS: u32 = 6;
+const GRAYS: u32 = 256 - CUBE_LEVELS * CUBE_LEVELS * CUBE_LEVELS;
+
+/// One downsampled frame as palette indices
+struct Frame {
+    width: u16,
+    height: u16,
+    indices: Vec<u8>,
+}
+
+fn palette() -> Vec<[u8; 3]> {
+    let level = |i: u32| (i * 255 / (CUBE_LEVELS - 1)) as u8;
+    let mut colors = Vec::with_capacity(256);
+    for r in 0..CUBE_LEVELS {
+        for g in 0..CUBE_LEVELS {
+            for b in 0..CUBE_LEVELS {
+                colors.push([level(r), level(g), level(b)]);
+            }
+        }
+    }
+    colors.extend((0..GRAYS).map(|i| [(i * 255 / (GRAYS - 1)) as u8; 3]));
+    colors
+}
+
+/// Nearest palette entry: grays for near-neutral pixels, else the cube
+fn quantize([r, g, b]: [u8; 3]) -> u8 {
+    let (lo, hi) = (r.min(g).min(b), r.max(g).max(b));
+    if hi - lo < 16 {
+        let v = (r as u32 + g as u32 + b as u32) / 3;
+        return (CUBE_LEVELS.pow(3) + (v * (GRAYS - 1) + 127) / 255) as u8;
+    }
+    let level = |c: u8| (c as u32 * (CUBE_LEVELS - 1) + 127) / 255;
+    (level(r) * CUBE_LEVELS * CUBE_LEVELS + level(g) * CUBE_LEVELS + level(b)) as u8
+}
+
+impl Frame {
+    /// Box-filter a captured frame down and map it onto the palette
+    fn from_image(image: &Image) -> Option<Self> {
+        let (r, b) = match image.texture_descriptor.format {
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => (2, 0),
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => (0, 2),
+            _ => return None,
+        };
+        let size = image.texture_descriptor.size;
+        let factor = size.width.div_ceil(MAX_WIDTH).max(1);
+        let (width, height) = (size.width / factor, size.height / factor);
+        let mut indices = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0u32; 3];
+                for dy in 0..factor {
+                    let row = ((y * factor + dy) * size.width) as usize;
+                    for dx in 0..factor {
+                        let p = 4 * (row + (x * factor + dx) as usize);
+                        let px = image.data.get(p..p + 4)?;
+                        sum[0] += px[r] as u32;
+                        sum[1] += px[1] as u32;
+                        sum[2] += px[b] as u32;
+                    }
+                }
+                let n = factor * factor;
+                indices.push(quantize(sum.map(|s| (s / n) as u8)));
+            }
+        }
+        Some(Self {
+            width: width as u16,
+            height: height as u16,
+            indices,
+        })
+    }
+}
+
+/// LSB-first bit packing into 255-byte sub-blocks
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.acc |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self, out: &mut Vec<u8>) {
+        if self.bits > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        for block in self.bytes.chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+}
+
+/// GIF LZW with 8-bit symbols, codes of 9 to 12 bits
+fn lzw(indices: &[u8], out: &mut Vec<u8>) {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    const MAX_CODES: u16 = 4096;
+    out.push(8);
+    let mut w = BitWriter {
+        bytes: Vec::new(),
+        acc: 0,
+        bits: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = END + 1;
+    let mut size = 9;
+    w.write(CLEAR, size);
+    let Some((&first, rest)) = indices.split_first() else {
+        w.write(END, size);
+        w.finish(out);
+        return;
+    };
+    let mut prefix = first as u16;
+    for &k in rest {
+        if let Some(&code) = table.get(&(prefix, k)) {
+            prefix = code;
+            continue;
+        }
+        w.write(prefix, size);
+        table.insert((prefix, k), next);
+        next += 1;
+        // The decoder adds its entries one code later, hence `>`
+        if next > 1 << size && size < 12 {
+            size += 1;
+        }
+        if next == MAX_CODES {
+            w.write(CLEAR, size);
+            table.clear();
+            next = END + 1;
+            size = 9;
+        }
+        prefix = k as u16;
+    }
+    w.write(prefix, size);
+    w.write(END, size);
+    w.finish(out);
+}
+
+/// A looping GIF89a of equally sized frames, `delay` in 1/100 s
+fn encode(frames: &[Frame], delay: u16) -> Vec<u8> {
+    let (width, height) = frames.first().map_or((1, 1), |f| (f.width, f.height));
+    let mut out = Vec::new();
+    out.extend_from_slice(b"GIF89a");
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    // Global color table of 2^(7+1) entries, 8 bits per primary
+    out.extend_from_slice(&[0xF7, 0, 0]);
+    out.extend(palette().into_iter().flatten());
+    // Loop forever
+    out.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+    for frame in frames
+        .iter()
+        .filter(|f| (f.width, f.height) == (width, height))
+    {
+        out.extend_from_slice(&[0x21, 0xF9, 0x04, 0x04]);
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.push(0x2C);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        out.push(0);
+        lzw(&frame.indices, &mut out);
+    }
+    out.push(0x3B);
+    out
+}
+
+#[derive(Resource, Default)]
+pub struct GifRecorder {
+    frames: VecDeque<Frame>,
+    /// Seconds since the last capture
+    since_capture: f32,
+}
+
+/// Capture a frame every 1/FPS seconds of wall-clock time
+pub fn capture_gif_frame(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut recorder: ResMut<GifRecorder>,
+) {
+    recorder.since_capture += time.delta_secs();
+    if recorder.since_capture < 1.0 / FPS {
+        return;
+    }
+    recorder.since_capture = 0.0;
+    commands
+        .spawn(Screenshot::primary_window())
+        .observe(store_frame);
+}
+
+fn store_frame(trigger: Trigger<ScreenshotCaptured>, mut recorder: ResMut<GifRecorder>) {
+    let Some(frame) = Frame::from_image(&trigger.event().0) else {
+        return;
+    };
+    recorder.frames.push_back(frame);
+    let excess = recorder
+        .frames
+        .len()
+        .saturating_sub((FPS * SECONDS) as usize);
+    recorder.frames.drain(..excess);
+}
+
+/// Write the buffered seconds to `gif_<unix time>.gif` in the background
+pub fn save_gif(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut recorder: ResMut<GifRecorder>,
+) {
+    if !bindings.just_pressed(&keys, Action::SaveGif) || recorder.frames.is_empty() {
+        return;
+    }
+    let frames: Vec<Frame> = recorder.frames.drain(..).collect();
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = PathBuf::from(format!("gif_{stamp}.gif"));
+    std::thread::spawn(
+        move || match fs::write(&path, encode(&frames, (100.0 / FPS) as u16)) {
+            Ok(()) => info!("Wrote {} frames to {}", frames.len(), path.display()),
+            Err(e) => error!("failed to write {}: {e}", path.display()),
+        },
+    );
+}
@@ -0,0 +1,404 @@
+//! Body density splatted into a texture by compute shaders, drawn behind the
+//! bodies as a colormapped heatmap or as isocontours. The CPU only copies
+//! positions out; binning and shading run on the GPU, so the view stays
+//! cheap at 100k bodies. Densities are body counts per texel, on a log scale
+//! relative to the densest texel in view.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_graph::{self, RenderGraph, RenderLabel};
+use bevy::render::render_resource::binding_types::{
+    storage_buffer_read_only_sized, storage_buffer_sized, texture_storage_2d, uniform_buffer,
+};
+use bevy::render::render_resource::{
+    BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, Buffer, BufferDescriptor,
+    BufferUsages, CachedComputePipelineId, ComputePassDescriptor, ComputePipelineDescriptor,
+    Extent3d, PipelineCache, RawBufferVec, ShaderStages, ShaderType, StorageTextureAccess,
+    TextureDimension, TextureFormat, TextureUsages, UniformBuffer,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
+use bevy::render::texture::GpuImage;
+use bevy::render::view::RenderLayers;
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::window::PrimaryWindow;
+
+use crate::colormap::Colormap;
+use crate::keybindings::{Action, KeyBindings};
+use crate::starfield::{self, BackgroundCamera};
+use crate::{Bodies, MainCamera};
+
+const SHADER_PATH: &str = "shaders/density_splat.wgsl";
+
+/// Texels per side of the texture, the finest resolution; the used corner
+/// is stretched over the main camera's view
+pub const SIZE: u32 = 256;
+/// Colormap entries handed to the shader
+const LUT_SIZE: usize = 256;
+const WORKGROUP_2D: u32 = 8;
+const WORKGROUP_1D: u32 = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DensityView {
+    #[default]
+    Off,
+    Heatmap,
+    Contours,
+}
+
+impl DensityView {
+    fn next(self) -> Self {
+        match self {
+            DensityView::Off => DensityView::Heatmap,
+            DensityView::Heatmap => DensityView::Contours,
+            DensityView::Contours => DensityView::Off,
+        }
+    }
+}
+
+/// Everything the render world needs for one frame's density pass
+#[derive(Resource, Clone, ExtractResource)]
+pub struct GpuDensity {
+    pub view: DensityView,
+    /// Texels per side actually binned, at most `SIZE`
+    pub resolution: u32,
+    image: Handle<Image>,
+    /// Body positions
+    positions: Vec<[f32; 2]>,
+    /// World position of the texture's lower-left corner
+    origin: Vec2,
+    texels_per_unit: Vec2,
+    lut: Vec<[f32; 4]>,
+}
+
+/// The on-screen quad showing the texture
+#[derive(Component)]
+pub struct DensityBackground;
+
+pub fn spawn_gpu_density(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING | TextureUsages::COPY_DST;
+    image.sampler = ImageSampler::linear();
+    let image = images.add(image);
+
+    commands.spawn((
+        Sprite::from_image(image.clone()),
+        // Between the stars and the trails
+        Transform::from_xyz(0.0, 0.0, 0.5),
+        Visibility::Hidden,
+        RenderLayers::layer(starfield::LAYER),
+        DensityBackground,
+    ));
+    commands.insert_resource(GpuDensity {
+        view: DensityView::Off,
+        resolution: SIZE,
+        image,
+        positions: Vec::new(),
+        origin: Vec2::ZERO,
+        texels_per_unit: Vec2::ZERO,
+        lut: Vec::new(),
+    });
+}
+
+pub fn cycle_density_view(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut density: ResMut<GpuDensity>,
+) {
+    if bindings.just_pressed(&keys, Action::CycleDensityView) {
+        density.view = density.view.next();
+        info!("Density view: {:?}", density.view);
+    }
+}
+
+/// Copy positions and the view rectangle out for the render world, and
+/// keep the quad covering the screen
+pub fn update_gpu_density(
+    mut density: ResMut<GpuDensity>,
+    bodies: Res<Bodies>,
+    colormap: Res<Colormap>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    main_q: Query<
+        (&Transform, &OrthographicProjection),
+        (With<MainCamera>, Without<BackgroundCamera>),
+    >,
+    bg_q: Query<(&Transform, &OrthographicProjection), With<BackgroundCamera>>,
+    mut quad_q: Query<
+        (&mut Sprite, &mut Transform, &mut Visibility),
+        (
+            With<DensityBackground>,
+            Without<MainCamera>,
+            Without<BackgroundCamera>,
+        ),
+    >,
+) {
+    let Ok((mut sprite, mut quad_tf, mut vis)) = quad_q.get_single_mut() else {
+        return;
+    };
+    if density.view == DensityView::Off {
+        vis.set_if_neq(Visibility::Hidden);
+        if !density.positions.is_empty() {
+            density.positions = Vec::new();
+        }
+        return;
+    }
+    let (Ok(window), Ok((main_tf, main_proj)), Ok((bg_tf, bg_proj))) =
+        (win_q.get_single(), main_q.get_single(), bg_q.get_single())
+    else {
+        return;
+    };
+    vis.set_if_neq(Visibility::Inherited);
+
+    let density = density.as_mut();
+    density.positions.clear();
+    density
+        .positions
+        .extend(bodies.data.iter().map(|b| [b.x, b.y]));
+    let area = main_proj.area;
+    density.origin = main_tf.translation.truncate() + area.min;
+    let resolution = density.resolution.clamp(1, SIZE);
+    density.texels_per_unit = Vec2::splat(resolution as f32) / area.size();
+    if density.lut.is_empty() || colormap.is_changed() {
+        density.lut = (0..LUT_SIZE)
+            .map(|i| {
+                let c = colormap
+                    .sample(i as f32 / (LUT_SIZE - 1) as f32)
+                    .to_linear();
+                [c.red, c.green, c.blue, 1.0]
+            })
+            .collect();
+    }
+
+    let screen = window.size();
+    sprite.custom_size = Some(screen);
+    sprite.rect = Some(Rect::new(0.0, 0.0, resolution as f32, resolution as f32));
+    *quad_tf = Transform::from_translation(bg_tf.translation.truncate().extend(0.5))
+        .with_scale(Vec3::splat(bg_proj.scale));
+}
+
+pub struct GpuDensityPlugin;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct DensityLabel;
+
+impl Plugin for GpuDensityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractResourcePlugin::<GpuDensity>::default());
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.init_resource::<DensityBuffers>().add_systems(
+            Render,
+            prepare_bind_group.in_set(RenderSet::PrepareBindGroups),
+        );
+        let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        graph.add_node(DensityLabel, DensityNode);
+        graph.add_node_edge(DensityLabel, bevy::render::graph::CameraDriverLabel);
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<DensityPipeline>();
+    }
+}
+
+#[derive(ShaderType, Clone, Copy, Default)]
+struct DensityParams {
+    origin: Vec2,
+    texels_per_unit: Vec2,
+    size: u32,
+    count: u32,
+    contours: u32,
+}
+
+#[derive(Resource)]
+struct DensityPipeline {
+    layout: BindGroupLayout,
+    /// SIZE² bins, then the largest bin
+    bins: Buffer,
+    clear: CachedComputePipelineId,
+    splat: CachedComputePipelineId,
+    shade: CachedComputePipelineId,
+}
+
+impl FromWorld for DensityPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout = device.create_bind_group_layout(
+            "density_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<DensityParams>(false),
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_sized(false, None),
+                    storage_buffer_read_only_sized(false, None),
+                    texture_storage_2d(TextureFormat::Rgba8Unorm, StorageTextureAccess::WriteOnly),
+                ),
+            ),
+        );
+        let bins = device.create_buffer(&BufferDescriptor {
+            label: Some("density_bins"),
+            size: (SIZE as u64 * SIZE as u64 + 1) * 4,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let shader = world.load_asset(SHADER_PATH);
+        let cache = world.resource::<PipelineCache>();
+        let queue = |entry: &'static str| {
+            cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(format!("density_{entry}").into()),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs: Vec::new(),
+                entry_point: entry.into(),
+                zero_initialize_workgroup_memory: false,
+            })
+        };
+        let (clear, splat, shade) = (queue("clear"), queue("splat"), queue("shade"));
+        Self {
+            layout,
+            bins,
+            clear,
+            splat,
+            shade,
+        }
+    }
+}
+
+/// Upload buffers, kept across frames so their allocations are reused
+#[derive(Resource)]
+struct DensityBuffers {
+    params: UniformBuffer<DensityParams>,
+    positions: RawBufferVec<[f32; 2]>,
+    lut: RawBufferVec<[f32; 4]>,
+}
+
+impl Default for DensityBuffers {
+    fn default() -> Self {
+        Self {
+            params: UniformBuffer::default(),
+            positions: RawBufferVec::new(BufferUsages::STORAGE),
+            lut: RawBufferVec::new(BufferUsages::STORAGE),
+        }
+    }
+}
+
+/// This frame's bindings, body count and resolution; absent while the view is off
+#[derive(Resource)]
+struct DensityBindGroup(BindGroup, u32, u32);
+
+fn prepare_bind_group(
+    mut commands: Commands,
+    density: Option<Res<GpuDensity>>,
+    pipeline: Res<DensityPipeline>,
+    mut buffers: ResMut<DensityBuffers>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    commands.remove_resource::<DensityBindGroup>();
+    let Some(density) = density.filter(|d| d.view != DensityView::Off) else {
+        return;
+    };
+    let Some(image) = gpu_images.get(&density.image) else {
+        return;
+    };
+
+    let buffers = buffers.as_mut();
+    buffers.positions.clear();
+    buffers.positions.extend(density.positions.iter().copied());
+    // A binding can't be empty
+    if buffers.positions.is_empty() {
+        buffers.positions.push([0.0; 2]);
+    }
+    buffers.positions.write_buffer(&device, &queue);
+    buffers.lut.clear();
+    buffers.lut.extend(density.lut.iter().copied());
+    if buffers.lut.is_empty() {
+        buffers.lut.push([0.0; 4]);
+    }
+    buffers.lut.write_buffer(&device, &queue);
+    let count = density.positions.len() as u32;
+    let resolution = density.resolution.clamp(1, SIZE);
+    buffers.params.set(DensityParams {
+        origin: density.origin,
+        texels_per_unit: density.texels_per_unit,
+        size: resolution,
+        count,
+        contours: (density.view == DensityView::Contours) as u32,
+    });
+    buffers.params.write_buffer(&device, &queue);
+
+    let (Some(params), Some(positions), Some(lut)) = (
+        buffers.params.binding(),
+        buffers.positions.binding(),
+        buffers.lut.binding(),
+    ) else {
+        return;
+    };
+    let bind_group = device.create_bind_group(
+        "density_bind_group",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((
+            params,
+            positions,
+            pipeline.bins.as_entire_binding(),
+            lut,
+            &image.texture_view,
+        )),
+    );
+    commands.insert_resource(DensityBindGroup(bind_group, count, resolution));
+}
+
+struct DensityNode;
+
+impl render_graph::Node for DensityNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(DensityBindGroup(bind_group, count, resolution)) =
+            world.get_resource::<DensityBindGroup>()
+        else {
+            return Ok(());
+        };
+        let pipeline = world.resource::<DensityPipeline>();
+        let cache = world.resource::<PipelineCache>();
+        let (Some(clear), Some(splat), Some(shade)) = (
+            cache.get_compute_pipeline(pipeline.clear),
+            cache.get_compute_pipeline(pipeline.splat),
+            cache.get_compute_pipeline(pipeline.shade),
+        ) else {
+            // Still compiling
+            return Ok(());
+        };
+
+        let texels = resolution.div_ceil(WORKGROUP_2D);
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor::default());
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_pipeline(clear);
+        pass.dispatch_workgroups(texels, texels, 1);
+        pass.set_pipeline(splat);
+        pass.dispatch_workgroups(count.div_ceil(WORKGROUP_1D).max(1), 1, 1);
+        pass.set_pipeline(shade);
+        pass.dispatch_workgroups(texels, texels, 1);
+        Ok(())
+    }
+}
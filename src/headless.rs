@@ -0,0 +1,202 @@
+//! Synchronous leapfrog for runs without a window: open boundaries, plain
+//! gravity and the direct solver, stepped in a loop by the batch modes.
+
+use rand::SeedableRng;
+
+use crate::constants::PhysicsConstants;
+use crate::force_law::ForceLaw;
+use crate::neighbors::CellGrid;
+use crate::physics::{self, Boundary};
+use crate::sim_rng::Xoshiro256PlusPlus;
+use crate::summation::CompensatedSum;
+use crate::{BodyState, MAX_MASS, MIN_MASS, ic};
+
+/// One Plummer-sphere run, in code units
+#[derive(Clone, Copy, Debug)]
+pub struct RunConfig {
+    pub bodies: usize,
+    pub dt: f32,
+    /// Softening length
+    pub softening: f32,
+    pub seed: u64,
+    /// Plummer scale radius
+    pub scale_radius: f32,
+}
+
+pub struct Simulation {
+    pub bodies: Vec<BodyState>,
+    pub constants: PhysicsConstants,
+    pub dt: f32,
+    pub step: u64,
+    /// Simulated time
+    pub time: f64,
+    /// Split each force pass across the compute pool, which the caller
+    /// must have initialized
+    pub chunked: bool,
+    snapshot: physics::Snapshot,
+    accel: Vec<[f32; 2]>,
+    grid: CellGrid,
+}
+
+impl Simulation {
+    pub fn new(config: &RunConfig, constants: &PhysicsConstants) -> Self {
+        let constants = PhysicsConstants {
+            softening: config.softening,
+            ..*constants
+        };
+        let mean_mass = 0.5 * (MAX_MASS + MIN_MASS);
+        let bodies = ic::plummer(
+            &mut Xoshiro256PlusPlus::seed_from_u64(config.seed),
+            config.bodies,
+            mean_mass * config.bodies as f32,
+            config.scale_radius,
+            (0.0, 0.0),
+            (0.0, 0.0),
+            constants.gravitation,
+        );
+        let mut sim = Self {
+            bodies,
+            constants,
+            dt: config.dt,
+            step: 0,
+            time: 0.0,
+            chunked: false,
+            snapshot: Vec::new(),
+            accel: Vec::new(),
+            grid: CellGrid::default(),
+        };
+        sim.update_accelerations();
+        sim
+    }
+
+    fn update_snapshot(&mut self) {
+        self.snapshot.clear();
+        self.snapshot
+            .extend(self.bodies.iter().map(|b| [b.x, b.y, b.mass, b.charge]));
+    }
+
+    fn update_accelerations(&mut self) {
+        self.update_snapshot();
+        let pass = if self.chunked {
+            physics::accelerations_chunked
+        } else {
+            physics::accelerations
+        };
+        pass(
+            &self.snapshot,
+            Boundary::Open,
+            ForceLaw::Gravity,
+            &self.constants,
+            &mut self.grid,
+            &mut self.accel,
+        );
+        for (b, a) in self.bodies.iter_mut().zip(&self.accel) {
+            (b.ax, b.ay) = (a[0], a[1]);
+        }
+    }
+
+    /// Add bodies between steps, with their accelerations
+    pub fn add_bodies(&mut self, new: impl IntoIterator<Item = BodyState>) {
+        self.bodies.extend(new);
+        self.update_accelerations();
+    }
+
+    /// Kick, drift, force pass, kick
+    pub fn step(&mut self) {
+        let dt_half = 0.5 * self.dt;
+        for b in self.bodies.iter_mut().filter(|b| !b.fixed) {
+            b.vx += b.ax * dt_half;
+            b.vy += b.ay * dt_half;
+            b.x += b.vx * self.dt;
+            b.y += b.vy * self.dt;
+        }
+        self.update_accelerations();
+        for b in self.bodies.iter_mut().filter(|b| !b.fixed) {
+            b.vx += b.ax * dt_half;
+            b.vy += b.ay * dt_half;
+        }
+        self.step += 1;
+        self.time += self.dt as f64;
+    }
+
+    pub fn kinetic_energy(&self) -> f64 {
+        self.bodies
+            .iter()
+            .map(|b| 0.5 * b.mass as f64 * (b.vx * b.vx + b.vy * b.vy) as f64)
+            .sum::<CompensatedSum>()
+            .value()
+    }
+
+    /// O(N^2); call only at output steps
+    pub fn potential_energy(&self) -> f64 {
+        // The snapshot is refreshed by every force pass, so it matches the bodies
+        physics::potential_energy(
+            &self.snapshot,
+            Boundary::Open,
+            ForceLaw::Gravity,
+            &self.constants,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_count::allocations;
+    use crate::pm::{self, PmConfig, PmGrids};
+    use crate::units::SI;
+
+    fn warmed_up(constants: &PhysicsConstants) -> Simulation {
+        let config = RunConfig {
+            bodies: 256,
+            dt: crate::D_TIME,
+            softening: 0.0,
+            seed: 1,
+            scale_radius: SI.length(5.0E13),
+        };
+        let mut sim = Simulation::new(&config, constants);
+        for _ in 0..10 {
+            sim.step();
+        }
+        sim
+    }
+
+    #[test]
+    fn direct_steps_do_not_allocate() {
+        let mut sim = warmed_up(&PhysicsConstants::default());
+        let n = allocations(|| (0..100).for_each(|_| sim.step()));
+        assert_eq!(n, 0, "{n} allocations in 100 steps");
+    }
+
+    #[test]
+    fn cutoff_grid_steps_do_not_allocate() {
+        let constants = PhysicsConstants {
+            cutoff_radius: SI.length(2.0E13),
+            ..Default::default()
+        };
+        let mut sim = warmed_up(&constants);
+        let n = allocations(|| (0..100).for_each(|_| sim.step()));
+        assert_eq!(n, 0, "{n} allocations in 100 steps");
+    }
+
+    #[test]
+    fn mesh_passes_reuse_their_grids() {
+        let sim = warmed_up(&PhysicsConstants::default());
+        let (config, mut grids, mut accel) = (PmConfig::default(), PmGrids::default(), Vec::new());
+        for short_range in [false, true] {
+            let mut pass = || {
+                pm::accelerations(
+                    &sim.snapshot,
+                    &config,
+                    short_range,
+                    &sim.constants,
+                    &mut grids,
+                    &mut accel,
+                )
+            };
+            pass();
+            let n = allocations(pass);
+            assert_eq!(n, 0, "{n} allocations in a repeated pass");
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! HUD layout: every overlay's root node is tagged with the `Overlay` it
+//! belongs to, and each overlay can be hidden or moved to a corner of the
+//! window. Overlays sharing a corner are stacked outward from it in the
+//! order of `Overlay::ALL`, using their laid-out heights, so moving one
+//! never leaves it on top of another. The layout is saved with the user
+//! settings.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Distance from the window edges (px)
+const MARGIN: f32 = 20.0;
+/// Space between overlays stacked in one corner (px)
+const GAP: f32 = 10.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Overlay {
+    /// Elapsed time, energies and real-time factor
+    Energy,
+    /// Core and Lagrangian radii
+    Structure,
+    /// Bound pairs, friends-of-friends groups and Lyapunov exponent
+    Diagnostics,
+    /// Selected body details
+    Selection,
+    /// Colormap legend and bound fraction
+    Legend,
+    ScaleBar,
+    SpeedHistogram,
+    RadialProfiles,
+}
+
+impl Overlay {
+    pub const ALL: [Overlay; 8] = [
+        Overlay::Energy,
+        Overlay::Structure,
+        Overlay::Diagnostics,
+        Overlay::Selection,
+        Overlay::Legend,
+        Overlay::ScaleBar,
+        Overlay::SpeedHistogram,
+        Overlay::RadialProfiles,
+    ];
+
+    /// Console name
+    pub fn name(self) -> &'static str {
+        match self {
+            Overlay::Energy => "energy",
+            Overlay::Structure => "structure",
+            Overlay::Diagnostics => "diagnostics",
+            Overlay::Selection => "selection",
+            Overlay::Legend => "legend",
+            Overlay::ScaleBar => "scale",
+            Overlay::SpeedHistogram => "histogram",
+            Overlay::RadialProfiles => "profiles",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|o| o.name() == name)
+    }
+
+    fn default_corner(self) -> Corner {
+        match self {
+            Overlay::Energy => Corner::TopLeft,
+            Overlay::Structure | Overlay::Legend | Overlay::SpeedHistogram => Corner::BottomLeft,
+            Overlay::Diagnostics | Overlay::RadialProfiles => Corner::TopRight,
+            Overlay::Selection | Overlay::ScaleBar => Corner::BottomRight,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// Console name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tl" => Some(Corner::TopLeft),
+            "tr" => Some(Corner::TopRight),
+            "bl" => Some(Corner::BottomLeft),
+            "br" => Some(Corner::BottomRight),
+            _ => None,
+        }
+    }
+
+    fn is_left(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::BottomLeft)
+    }
+
+    fn is_top(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::TopRight)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Slot {
+    pub visible: bool,
+    pub corner: Corner,
+}
+
+/// Placement of each overlay; missing entries use the defaults
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct HudLayout {
+    pub slots: BTreeMap<Overlay, Slot>,
+}
+
+impl HudLayout {
+    pub fn slot(&self, overlay: Overlay) -> Slot {
+        self.slots.get(&overlay).copied().unwrap_or(Slot {
+            visible: true,
+            corner: overlay.default_corner(),
+        })
+    }
+
+    pub fn slot_mut(&mut self, overlay: Overlay) -> &mut Slot {
+        let slot = self.slot(overlay);
+        self.slots.entry(overlay).or_insert(slot)
+    }
+}
+
+/// Root node of (part of) an overlay
+#[derive(Component, Clone, Copy)]
+pub struct HudItem(pub Overlay);
+
+/// Place every tagged overlay from the layout. Heights come from the last
+/// layout pass, so a resized overlay pushes its neighbours a frame later.
+pub fn apply_hud_layout(
+    layout: Res<HudLayout>,
+    mut items: Query<(
+        Entity,
+        &HudItem,
+        &ComputedNode,
+        &Visibility,
+        Option<&TargetCamera>,
+        &mut Style,
+    )>,
+) {
+    let mut order: Vec<(Overlay, Entity)> = items.iter().map(|(e, item, ..)| (item.0, e)).collect();
+    order.sort();
+    // Offset reached in each corner, per window (overlays may be routed to
+    // the analysis window's camera)
+    let mut offsets: Vec<((Corner, Option<Entity>), f32)> = Vec::new();
+    for (overlay, entity) in order {
+        let Ok((_, _, node, visibility, target, mut style)) = items.get_mut(entity) else {
+            continue;
+        };
+        let slot = layout.slot(overlay);
+        let mut placed = style.clone();
+        placed.display = if slot.visible {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if slot.visible && *visibility != Visibility::Hidden {
+            let key = (slot.corner, target.map(|t| t.0));
+            let offset = match offsets.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, offset)) => offset,
+                None => {
+                    offsets.push((key, MARGIN));
+                    &mut offsets.last_mut().unwrap().1
+                }
+            };
+            let side = Val::Px(MARGIN);
+            let along = Val::Px(*offset);
+            (placed.left, placed.right) = if slot.corner.is_left() {
+                (side, Val::Auto)
+            } else {
+                (Val::Auto, side)
+            };
+            (placed.top, placed.bottom) = if slot.corner.is_top() {
+                (along, Val::Auto)
+            } else {
+                (Val::Auto, along)
+            };
+            placed.position_type = PositionType::Absolute;
+            *offset += node.size().y * node.inverse_scale_factor() + GAP;
+        }
+        style.set_if_neq(placed);
+    }
+}
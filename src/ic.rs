@@ -0,0 +1,351 @@
+//! Initial-condition generators for bodies added at runtime. The ones that
+//! take only a body count and numeric parameters are also registered in
+//! `ic_registry` for the menu and the command line.
+
+use rand::{Rng, distributions::Standard};
+
+use crate::BodyState;
+
+/// Mass given to test particles: small enough that their pull is negligible
+pub const TRACER_MASS: f32 = 1.0;
+
+/// Plummer sphere (Aarseth, Hénon & Wielen 1974) laid into the plane:
+/// `n` equal masses summing to `total_mass`, scale radius `a`, centered at
+/// `center` and moving with `bulk_velocity`, in equilibrium for the
+/// gravitational constant `g`.
+pub fn plummer<R: Rng>(
+    rng: &mut R,
+    n: usize,
+    total_mass: f32,
+    a: f32,
+    center: (f32, f32),
+    bulk_velocity: (f32, f32),
+    g: f32,
+) -> Vec<BodyState> {
+    let m = total_mass / n.max(1) as f32;
+    let mut out = Vec::with_capacity(n);
+    while out.len() < n {
+        // Radius from the inverted cumulative mass profile; drop the far tail
+        let x: f32 = rng.sample(Standard);
+        let r = a / (x.max(1e-6).powf(-2.0 / 3.0) - 1.0).sqrt();
+        if !r.is_finite() || r > 10.0 * a {
+            continue;
+        }
+
+        // Speed as a fraction q of the local escape speed, g(q) = q²(1 - q²)^3.5
+        let q = loop {
+            let q: f32 = rng.sample(Standard);
+            let y: f32 = rng.sample::<f32, _>(Standard) * 0.1;
+            if y < q * q * (1.0 - q * q).powf(3.5) {
+                break q;
+            }
+        };
+        let v_escape = (2.0 * g * total_mass).sqrt() * (r * r + a * a).powf(-0.25);
+        let v = q * v_escape;
+
+        let (ps, pc) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+        let (vs, vc) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+        let mut b = BodyState::new();
+        b.mass = m;
+        b.x = center.0 + r * pc;
+        b.y = center.1 + r * ps;
+        b.vx = bulk_velocity.0 + v * vc;
+        b.vy = bulk_velocity.1 + v * vs;
+        b.x_prev = b.x;
+        b.y_prev = b.y;
+        out.push(b);
+    }
+    out
+}
+
+/// Uniform square of side `size` with speeds up to `max_speed` in random directions
+pub fn uniform<R: Rng>(
+    rng: &mut R,
+    n: usize,
+    mass: f32,
+    size: f32,
+    max_speed: f32,
+    center: (f32, f32),
+) -> Vec<BodyState> {
+    (0..n)
+        .map(|_| {
+            let mut b = BodyState::new();
+            b.mass = mass;
+            b.x = center.0 + (rng.sample::<f32, _>(Standard) - 0.5) * size;
+            b.y = center.1 + (rng.sample::<f32, _>(Standard) - 0.5) * size;
+            let v = max_speed * rng.sample::<f32, _>(Standard);
+            let (s, c) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+            b.vx = v * c;
+            b.vy = v * s;
+            b.x_prev = b.x;
+            b.y_prev = b.y;
+            b
+        })
+        .collect()
+}
+
+/// Square lattice of `n` equal masses with the given spacing, centered on the
+/// origin, moving in random directions at `speed`
+pub fn lattice<R: Rng>(
+    rng: &mut R,
+    n: usize,
+    spacing: f32,
+    mass: f32,
+    speed: f32,
+) -> Vec<BodyState> {
+    let side = (n as f32).sqrt().ceil().max(1.0) as usize;
+    let offset = 0.5 * (side - 1) as f32 * spacing;
+    (0..n)
+        .map(|k| {
+            let mut b = BodyState::new();
+            b.mass = mass;
+            b.x = (k % side) as f32 * spacing - offset;
+            b.y = (k / side) as f32 * spacing - offset;
+            let (s, c) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+            b.vx = speed * c;
+            b.vy = speed * s;
+            b.x_prev = b.x;
+            b.y_prev = b.y;
+            b
+        })
+        .collect()
+}
+
+/// Restricted three-body setup: a primary and a secondary on a circular
+/// orbit about their center of mass (at the origin), plus `n` massless
+/// tracers on circular orbits around the primary with radii drawn uniformly
+/// from `tracer_radii`. The primary comes first, then the secondary.
+pub fn restricted_three_body<R: Rng>(
+    rng: &mut R,
+    n: usize,
+    primary_mass: f32,
+    secondary_mass: f32,
+    separation: f32,
+    tracer_radii: (f32, f32),
+    g: f32,
+) -> Vec<BodyState> {
+    let total = primary_mass + secondary_mass;
+    let omega = (g * total / separation.powi(3)).sqrt();
+    let mut primary = BodyState::new();
+    primary.mass = primary_mass;
+    primary.x = -separation * secondary_mass / total;
+    primary.vy = omega * primary.x;
+    let mut secondary = BodyState::new();
+    secondary.mass = secondary_mass;
+    secondary.x = separation * primary_mass / total;
+    secondary.vy = omega * secondary.x;
+
+    let mut out = Vec::with_capacity(n + 2);
+    out.push(primary);
+    out.push(secondary);
+    for _ in 0..n {
+        let r = tracer_radii.0 + (tracer_radii.1 - tracer_radii.0) * rng.sample::<f32, _>(Standard);
+        let v = (g * primary_mass / r).sqrt();
+        let (s, c) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+        let mut b = BodyState::new();
+        b.mass = TRACER_MASS;
+        b.x = primary.x + r * c;
+        b.y = primary.y + r * s;
+        b.vx = primary.vx - v * s;
+        b.vy = primary.vy + v * c;
+        out.push(b);
+    }
+    for b in &mut out {
+        b.x_prev = b.x;
+        b.y_prev = b.y;
+    }
+    out
+}
+
+/// Cold exponential disk of `n` equal masses summing to `total_mass`, scale
+/// length `scale`, on circular orbits about the origin. Speeds balance the
+/// enclosed mass (spherical approximation), boosted by MOND when `mond_a0`
+/// is given.
+pub fn disk<R: Rng>(
+    rng: &mut R,
+    n: usize,
+    total_mass: f32,
+    scale: f32,
+    g: f32,
+    mond_a0: Option<f32>,
+) -> Vec<BodyState> {
+    let m = total_mass / n.max(1) as f32;
+    // Surface density ∝ exp(-r/scale) gives r ~ Gamma(2, scale); cut at 5 scales
+    let mut radii: Vec<f32> = Vec::with_capacity(n);
+    while radii.len() < n {
+        let u: f32 = rng.sample::<f32, _>(Standard) * rng.sample::<f32, _>(Standard);
+        let r = -scale * u.max(1e-12).ln();
+        if r <= 5.0 * scale {
+            radii.push(r);
+        }
+    }
+    radii.sort_by(f32::total_cmp);
+
+    radii
+        .iter()
+        .enumerate()
+        .map(|(k, &r)| {
+            let enclosed = m as f64 * k as f64;
+            let r_eff = r.max(0.1 * scale) as f64;
+            let mut accel = g as f64 * enclosed / (r_eff * r_eff);
+            if let Some(a0) = mond_a0 {
+                accel *= crate::force_law::mond_nu(accel / a0 as f64);
+            }
+            let v = (accel * r as f64).sqrt() as f32;
+            let (s, c) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+            let mut b = BodyState::new();
+            b.mass = m;
+            b.x = r * c;
+            b.y = r * s;
+            b.vx = -v * s;
+            b.vy = v * c;
+            b.x_prev = b.x;
+            b.y_prev = b.y;
+            b
+        })
+        .collect()
+}
+
+/// Density of a lowered isothermal (King 1966) model at dimensionless
+/// potential `w`, up to a constant factor
+fn king_density(w: f64) -> f64 {
+    if w <= 0.0 {
+        return 0.0;
+    }
+    w.exp() * crate::special::erf(w.sqrt())
+        - (4.0 * w / std::f64::consts::PI).sqrt() * (1.0 + 2.0 * w / 3.0)
+}
+
+/// King (1966) model laid into the plane: `n` equal masses summing to
+/// `total_mass`, central potential `w0` (in units of σ²) and core radius
+/// `r0`, in equilibrium for the gravitational constant `g`. The profile is
+/// integrated out to the tidal radius, where the potential reaches zero.
+pub fn king<R: Rng>(
+    rng: &mut R,
+    n: usize,
+    total_mass: f32,
+    w0: f32,
+    r0: f32,
+    g: f32,
+) -> Vec<BodyState> {
+    // W'' + 2W'/r = -9 ρ(W)/ρ(W0), with r in core radii; the state is
+    // (W, W', enclosed mass in units of 4π ρ(W0) r0³)
+    let w0 = w0.clamp(1.0, 12.0) as f64;
+    let rho0 = king_density(w0);
+    let deriv = |r: f64, s: [f64; 3]| {
+        let rho = king_density(s[0]) / rho0;
+        [s[1], -9.0 * rho - 2.0 * s[1] / r, rho * r * r]
+    };
+    let mut r = 1e-4;
+    let mut state = [w0, -3.0 * r, r.powi(3) / 3.0];
+    let mut profile = vec![(0.0, 0.0, w0)];
+    while state[0] > 0.0 && profile.len() < 1_000_000 {
+        let h = 0.01 * r.max(0.1);
+        let k1 = deriv(r, state);
+        let k2 = deriv(
+            r + 0.5 * h,
+            std::array::from_fn(|i| state[i] + 0.5 * h * k1[i]),
+        );
+        let k3 = deriv(
+            r + 0.5 * h,
+            std::array::from_fn(|i| state[i] + 0.5 * h * k2[i]),
+        );
+        let k4 = deriv(r + h, std::array::from_fn(|i| state[i] + h * k3[i]));
+        for (i, s) in state.iter_mut().enumerate() {
+            *s += h / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]);
+        }
+        r += h;
+        profile.push((r, state[2], state[0].max(0.0)));
+    }
+    let mass_total = state[2];
+    let sigma = (g as f64 * total_mass as f64 / (9.0 * r0 as f64 * mass_total)).sqrt();
+
+    let m = total_mass / n.max(1) as f32;
+    (0..n)
+        .map(|_| {
+            // Radius and potential from the inverted cumulative mass
+            let target = rng.sample::<f64, _>(Standard) * mass_total;
+            let k = profile
+                .partition_point(|p| p.1 < target)
+                .clamp(1, profile.len() - 1);
+            let (lo, hi) = (profile[k - 1], profile[k]);
+            let t = ((target - lo.1) / (hi.1 - lo.1).max(f64::MIN_POSITIVE)).clamp(0.0, 1.0);
+            let r = lo.0 + t * (hi.0 - lo.0);
+            let w = lo.2 + t * (hi.2 - lo.2);
+
+            // Speed in units of σ from f(s) ∝ s²(exp(W - s²/2) - 1), s < √(2W)
+            let s_max = (2.0 * w).sqrt();
+            let f = |s: f64| s * s * ((w - 0.5 * s * s).exp() - 1.0);
+            let peak = (1..=32)
+                .map(|i| f(s_max * i as f64 / 32.0))
+                .fold(0.0, f64::max)
+                * 1.2;
+            let s = loop {
+                let s = s_max * rng.sample::<f64, _>(Standard);
+                if peak <= 0.0 || rng.sample::<f64, _>(Standard) * peak < f(s) {
+                    break s;
+                }
+            };
+            let (r, v) = ((r * r0 as f64) as f32, (s * sigma) as f32);
+
+            let (ps, pc) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+            let (vs, vc) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+            let mut b = BodyState::new();
+            b.mass = m;
+            b.x = r * pc;
+            b.y = r * ps;
+            b.vx = v * vc;
+            b.vy = v * vs;
+            b.x_prev = b.x;
+            b.y_prev = b.y;
+            b
+        })
+        .collect()
+}
+
+/// Hernquist (1990) sphere laid into the plane: `n` equal masses summing to
+/// `total_mass`, scale radius `a`, cut at 20 scale radii. Speeds are drawn
+/// from a Maxwellian with the isotropic Jeans dispersion, below the local
+/// escape speed.
+pub fn hernquist<R: Rng>(rng: &mut R, n: usize, total_mass: f32, a: f32, g: f32) -> Vec<BodyState> {
+    let m = total_mass / n.max(1) as f32;
+    let gm = g as f64 * total_mass as f64;
+    let mut out = Vec::with_capacity(n);
+    while out.len() < n {
+        // M(<r) = M r²/(r + a)²
+        let x: f32 = rng.sample(Standard);
+        let r = a * x.sqrt() / (1.0 - x.sqrt());
+        if !r.is_finite() || r > 20.0 * a {
+            continue;
+        }
+
+        // Hernquist 1990, eq. 10
+        let s = (r / a) as f64;
+        let dispersion = gm / (12.0 * a as f64)
+            * (12.0 * s * (1.0 + s).powi(3) * ((1.0 + s) / s.max(1e-12)).ln()
+                - s / (1.0 + s) * (25.0 + 52.0 * s + 42.0 * s * s + 12.0 * s.powi(3)));
+        let sigma = dispersion.max(0.0).sqrt() as f32;
+        let v_escape = (2.0 * gm / (r + a) as f64).sqrt() as f32;
+        let v = loop {
+            let (x, y) = crate::stochastic::gaussian_pair(rng);
+            let (z, _) = crate::stochastic::gaussian_pair(rng);
+            let v = sigma * (x * x + y * y + z * z).sqrt();
+            if v < v_escape {
+                break v;
+            }
+        };
+
+        let (ps, pc) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+        let (vs, vc) = (std::f32::consts::TAU * rng.sample::<f32, _>(Standard)).sin_cos();
+        let mut b = BodyState::new();
+        b.mass = m;
+        b.x = r * pc;
+        b.y = r * ps;
+        b.vx = v * vc;
+        b.vy = v * vs;
+        b.x_prev = b.x;
+        b.y_prev = b.y;
+        out.push(b);
+    }
+    out
+}
@@ -0,0 +1,688 @@
+//! Initial-condition generators behind one interface: each has a registry
+//! key, a parameter schema with defaults and units, and generates bodies
+//! from a body count, the gravitational constant and the force law. The
+//! built-in scenarios are runs of these generators; each generator they
+//! don't cover gets a menu entry of its own. Every generator can be named
+//! in scenario files
+//! (`Generated(generator: "king", params: [("w0", 7.0)])`) and on the
+//! command line (`--ic king:w0=7`), and is listed by `ic-list`.
+
+use crate::force_law::ForceLaw;
+use crate::sim_rng::Xoshiro256PlusPlus;
+use crate::units::{CODE, SI, UnitScale};
+use crate::{BodyState, MAX_MASS, MIN_MASS, ic, init_bodies};
+
+const MEAN_MASS: f32 = 0.5 * (MAX_MASS + MIN_MASS);
+
+/// What a parameter measures, for converting scenario units
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamKind {
+    /// m
+    Length,
+    /// kg
+    Mass,
+    /// m/s
+    Velocity,
+    /// C/kg
+    ChargePerMass,
+    /// Dimensionless
+    Number,
+}
+
+impl ParamKind {
+    fn unit(self) -> &'static str {
+        match self {
+            ParamKind::Length => "m",
+            ParamKind::Mass => "kg",
+            ParamKind::Velocity => "m/s",
+            ParamKind::ChargePerMass => "C/kg",
+            ParamKind::Number => "",
+        }
+    }
+
+    /// `value` converted by `s`
+    pub fn convert(self, s: &UnitScale, value: f32) -> f32 {
+        match self {
+            ParamKind::Length => s.length(value),
+            ParamKind::Mass => s.mass(value),
+            ParamKind::Velocity => s.velocity(value),
+            ParamKind::ChargePerMass => s.per_mass(value),
+            ParamKind::Number => value,
+        }
+    }
+}
+
+/// One entry of a generator's parameter schema
+pub struct Param {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    /// In code units, like the overrides once their scenario is converted
+    pub default: f32,
+    pub description: &'static str,
+}
+
+/// Every parameter of a schema, at its default unless overridden
+#[derive(Clone, Debug)]
+pub struct ParamValues(Vec<(&'static str, f32)>);
+
+impl ParamValues {
+    /// Fails on names that aren't in the schema
+    pub fn new(schema: &'static [Param], overrides: &[(String, f32)]) -> Result<Self, String> {
+        let mut values: Vec<_> = schema.iter().map(|p| (p.name, p.default)).collect();
+        for (name, value) in overrides {
+            let Some(slot) = values.iter_mut().find(|(n, _)| n == name) else {
+                let known: Vec<_> = schema.iter().map(|p| p.name).collect();
+                return Err(format!(
+                    "unknown parameter '{name}' (expected one of: {})",
+                    known.join(", ")
+                ));
+            };
+            slot.1 = *value;
+        }
+        Ok(Self(values))
+    }
+
+    /// Fails if `name` isn't in the schema the values were built from
+    pub fn get(&self, name: &str) -> Result<f32, String> {
+        self.0
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| format!("parameter '{name}' isn't in the schema"))
+    }
+}
+
+/// A family of initial conditions
+pub trait IcGenerator: Sync {
+    /// Registry key, used by scenario files and `--ic`
+    fn name(&self) -> &'static str;
+    /// Menu entry
+    fn title(&self) -> &'static str;
+    /// One-line preview shown in the menu
+    fn description(&self) -> &'static str;
+    fn params(&self) -> &'static [Param];
+    /// Time step (s) for a run at the defaults
+    fn recommended_dt(&self) -> f32 {
+        1.0E07
+    }
+    /// `n` bodies in equilibrium (where the model has one) for the
+    /// gravitational constant `g` and the force law `law`
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        params: &ParamValues,
+        g: f32,
+        law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String>;
+}
+
+/// Every generator, in menu order
+pub static REGISTRY: &[&dyn IcGenerator] = &[
+    &RandomField,
+    &UniformBox,
+    &PlummerSphere,
+    &NeutralPlasma,
+    &SquareLattice,
+    &ExponentialDisk,
+    &KingModel,
+    &HernquistSphere,
+    &RestrictedThreeBody,
+];
+
+pub fn find(name: &str) -> Option<&'static dyn IcGenerator> {
+    REGISTRY.iter().copied().find(|g| g.name() == name)
+}
+
+/// Look up `name` and generate with its defaults overridden by `overrides`
+pub fn generate(
+    name: &str,
+    overrides: &[(String, f32)],
+    rng: &mut Xoshiro256PlusPlus,
+    n: usize,
+    g: f32,
+    law: ForceLaw,
+) -> Result<Vec<BodyState>, String> {
+    let generator = find(name).ok_or_else(|| format!("unknown generator '{name}'"))?;
+    let params = ParamValues::new(generator.params(), overrides)?;
+    generator.generate(rng, n, &params, g, law)
+}
+
+/// `name[:key=value,...]`, as given to `--ic`
+pub fn parse_spec(spec: &str) -> Result<(String, Vec<(String, f32)>), String> {
+    let (name, rest) = spec.split_once(':').unwrap_or((spec, ""));
+    let generator = find(name).ok_or_else(|| format!("unknown generator '{name}'"))?;
+    let mut overrides = Vec::new();
+    for pair in rest.split(',').filter(|p| !p.is_empty()) {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got '{pair}'"))?;
+        let value = value
+            .parse()
+            .map_err(|_| format!("'{value}' isn't a number"))?;
+        overrides.push((key.to_string(), value));
+    }
+    ParamValues::new(generator.params(), &overrides)?;
+    Ok((name.to_string(), overrides))
+}
+
+/// For `ic-list`: every generator with its parameter schema
+pub fn print_list() {
+    for generator in REGISTRY {
+        println!("{}  {}", generator.name(), generator.description());
+        for p in generator.params() {
+            println!(
+                "    {:<14}{:>10} {:<4} {}",
+                p.name,
+                p.kind.convert(&CODE, p.default),
+                p.kind.unit(),
+                p.description
+            );
+        }
+    }
+}
+
+const MASS: Param = Param {
+    name: "mass",
+    kind: ParamKind::Mass,
+    default: MEAN_MASS,
+    description: "mass of each body",
+};
+
+struct RandomField;
+
+impl IcGenerator for RandomField {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn title(&self) -> &'static str {
+        "Random field"
+    }
+
+    fn description(&self) -> &'static str {
+        "Stars scattered uniformly with random masses and velocities"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        &[]
+    }
+
+    fn recommended_dt(&self) -> f32 {
+        2.0E07
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        _p: &ParamValues,
+        _g: f32,
+        _law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        Ok(init_bodies(n, rng).data)
+    }
+}
+
+struct UniformBox;
+
+impl IcGenerator for UniformBox {
+    fn name(&self) -> &'static str {
+        "uniform"
+    }
+
+    fn title(&self) -> &'static str {
+        "Uniform box"
+    }
+
+    fn description(&self) -> &'static str {
+        "Equal masses scattered uniformly over a square, optionally with random speeds"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const {
+            &[
+                Param {
+                    name: "size",
+                    kind: ParamKind::Length,
+                    default: SI.length(1.0E15),
+                    description: "side of the square",
+                },
+                Param {
+                    name: "max_speed",
+                    kind: ParamKind::Velocity,
+                    default: 0.0,
+                    description: "speeds are uniform up to this, in random directions",
+                },
+                MASS,
+            ]
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        p: &ParamValues,
+        _g: f32,
+        _law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        Ok(ic::uniform(
+            rng,
+            n,
+            p.get("mass")?,
+            p.get("size")?,
+            p.get("max_speed")?,
+            (0.0, 0.0),
+        ))
+    }
+}
+
+struct PlummerSphere;
+
+impl IcGenerator for PlummerSphere {
+    fn name(&self) -> &'static str {
+        "plummer"
+    }
+
+    fn title(&self) -> &'static str {
+        "Plummer sphere"
+    }
+
+    fn description(&self) -> &'static str {
+        "Plummer (1911) cluster in virial equilibrium"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const {
+            &[
+                Param {
+                    name: "scale_radius",
+                    kind: ParamKind::Length,
+                    default: SI.length(5.0E13),
+                    description: "Plummer radius",
+                },
+                MASS,
+            ]
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        p: &ParamValues,
+        g: f32,
+        _law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        Ok(ic::plummer(
+            rng,
+            n,
+            p.get("mass")? * n as f32,
+            p.get("scale_radius")?,
+            (0.0, 0.0),
+            (0.0, 0.0),
+            g,
+        ))
+    }
+}
+
+struct NeutralPlasma;
+
+impl IcGenerator for NeutralPlasma {
+    fn name(&self) -> &'static str {
+        "plasma"
+    }
+
+    fn title(&self) -> &'static str {
+        "Neutral plasma"
+    }
+
+    fn description(&self) -> &'static str {
+        "Random field with charges of alternating sign, so the system is neutral"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const {
+            &[Param {
+                name: "charge_to_mass",
+                kind: ParamKind::ChargePerMass,
+                default: SI.per_mass(1.0E-09),
+                description: "charge of each body per mean body mass",
+            }]
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        p: &ParamValues,
+        _g: f32,
+        _law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        let q = p.get("charge_to_mass")? * MEAN_MASS;
+        let mut bodies = init_bodies(n, rng).data;
+        for (i, b) in bodies.iter_mut().enumerate() {
+            b.charge = if i % 2 == 0 { q } else { -q };
+        }
+        Ok(bodies)
+    }
+}
+
+struct SquareLattice;
+
+impl IcGenerator for SquareLattice {
+    fn name(&self) -> &'static str {
+        "lattice"
+    }
+
+    fn title(&self) -> &'static str {
+        "Square lattice"
+    }
+
+    fn description(&self) -> &'static str {
+        "Equal masses on a square lattice moving in random directions"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const {
+            &[
+                Param {
+                    name: "spacing",
+                    kind: ParamKind::Length,
+                    default: SI.length(2.24E13),
+                    description: "distance between neighbours",
+                },
+                Param {
+                    name: "mass",
+                    kind: ParamKind::Mass,
+                    default: SI.mass(4.5E29),
+                    description: "mass of each body",
+                },
+                Param {
+                    name: "speed",
+                    kind: ParamKind::Velocity,
+                    default: SI.velocity(1.0E04),
+                    description: "speed of each body",
+                },
+            ]
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        p: &ParamValues,
+        _g: f32,
+        _law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        Ok(ic::lattice(
+            rng,
+            n,
+            p.get("spacing")?,
+            p.get("mass")?,
+            p.get("speed")?,
+        ))
+    }
+}
+
+struct ExponentialDisk;
+
+impl IcGenerator for ExponentialDisk {
+    fn name(&self) -> &'static str {
+        "disk"
+    }
+
+    fn title(&self) -> &'static str {
+        "Exponential disk"
+    }
+
+    fn description(&self) -> &'static str {
+        "Cold exponential disk on circular orbits (MOND ones under MOND)"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const {
+            &[
+                Param {
+                    name: "scale_length",
+                    kind: ParamKind::Length,
+                    default: SI.length(8.0E13),
+                    description: "e-folding length of the surface density",
+                },
+                MASS,
+            ]
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        p: &ParamValues,
+        g: f32,
+        law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        let mond_a0 = match law {
+            ForceLaw::Mond { a0 } => Some(a0),
+            _ => None,
+        };
+        Ok(ic::disk(
+            rng,
+            n,
+            p.get("mass")? * n as f32,
+            p.get("scale_length")?,
+            g,
+            mond_a0,
+        ))
+    }
+}
+
+struct KingModel;
+
+impl IcGenerator for KingModel {
+    fn name(&self) -> &'static str {
+        "king"
+    }
+
+    fn title(&self) -> &'static str {
+        "King model"
+    }
+
+    fn description(&self) -> &'static str {
+        "King (1966) tidally truncated cluster; higher w0 means a denser core"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const {
+            &[
+                Param {
+                    name: "w0",
+                    kind: ParamKind::Number,
+                    default: 6.0,
+                    description: "central potential in units of the velocity dispersion squared (1-12)",
+                },
+                Param {
+                    name: "core_radius",
+                    kind: ParamKind::Length,
+                    default: SI.length(2.0E13),
+                    description: "King radius",
+                },
+                MASS,
+            ]
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        p: &ParamValues,
+        g: f32,
+        _law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        Ok(ic::king(
+            rng,
+            n,
+            p.get("mass")? * n as f32,
+            p.get("w0")?,
+            p.get("core_radius")?,
+            g,
+        ))
+    }
+}
+
+struct HernquistSphere;
+
+impl IcGenerator for HernquistSphere {
+    fn name(&self) -> &'static str {
+        "hernquist"
+    }
+
+    fn title(&self) -> &'static str {
+        "Hernquist sphere"
+    }
+
+    fn description(&self) -> &'static str {
+        "Hernquist (1990) cuspy bulge with isotropic Jeans velocities"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const {
+            &[
+                Param {
+                    name: "scale_radius",
+                    kind: ParamKind::Length,
+                    default: SI.length(5.0E13),
+                    description: "Hernquist scale radius",
+                },
+                MASS,
+            ]
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        p: &ParamValues,
+        g: f32,
+        _law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        Ok(ic::hernquist(
+            rng,
+            n,
+            p.get("mass")? * n as f32,
+            p.get("scale_radius")?,
+            g,
+        ))
+    }
+}
+
+struct RestrictedThreeBody;
+
+impl IcGenerator for RestrictedThreeBody {
+    fn name(&self) -> &'static str {
+        "restricted3"
+    }
+
+    fn title(&self) -> &'static str {
+        "Restricted three-body"
+    }
+
+    fn description(&self) -> &'static str {
+        "Two primaries on a circular orbit plus massless tracers around the first; the body count is the number of tracers"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const {
+            &[
+                Param {
+                    name: "primary_mass",
+                    kind: ParamKind::Mass,
+                    default: SI.mass(1.0E33),
+                    description: "mass of the star the tracers orbit",
+                },
+                Param {
+                    name: "secondary_mass",
+                    kind: ParamKind::Mass,
+                    default: SI.mass(1.0E31),
+                    description: "mass of the perturbing planet",
+                },
+                Param {
+                    name: "separation",
+                    kind: ParamKind::Length,
+                    default: SI.length(3.0E14),
+                    description: "distance between the primaries",
+                },
+                Param {
+                    name: "inner_radius",
+                    kind: ParamKind::Length,
+                    default: SI.length(8.0E13),
+                    description: "innermost tracer orbit",
+                },
+                Param {
+                    name: "outer_radius",
+                    kind: ParamKind::Length,
+                    default: SI.length(2.2E14),
+                    description: "outermost tracer orbit",
+                },
+            ]
+        }
+    }
+
+    fn recommended_dt(&self) -> f32 {
+        2.0E07
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Xoshiro256PlusPlus,
+        n: usize,
+        p: &ParamValues,
+        g: f32,
+        _law: ForceLaw,
+    ) -> Result<Vec<BodyState>, String> {
+        Ok(ic::restricted_three_body(
+            rng,
+            n,
+            p.get("primary_mass")?,
+            p.get("secondary_mass")?,
+            p.get("separation")?,
+            (p.get("inner_radius")?, p.get("outer_radius")?),
+            g,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    #[test]
+    fn every_generator_is_listed_once() {
+        let mut listed: Vec<_> = Scenario::builtin()
+            .into_iter()
+            .chain(Scenario::generators())
+            .filter_map(|s| s.initial.generator().map(str::to_string))
+            .collect();
+        for name in &listed {
+            assert!(find(name).is_some(), "'{name}' isn't registered");
+        }
+        listed.sort();
+        let mut registered: Vec<_> = REGISTRY.iter().map(|g| g.name().to_string()).collect();
+        registered.sort();
+        assert_eq!(listed, registered);
+    }
+
+    #[test]
+    fn unknown_parameters_are_errors() {
+        let params = ParamValues::new(PlummerSphere.params(), &[]).unwrap();
+        assert_eq!(params.get("scale_radius"), Ok(SI.length(5.0E13)));
+        assert!(params.get("core_radius").is_err());
+        assert!(ParamValues::new(PlummerSphere.params(), &[("w0".into(), 7.0)]).is_err());
+    }
+}
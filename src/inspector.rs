@@ -0,0 +1,48 @@
+//! Reflection registration for the simulation state and its parameters.
+//!
+//! Building with `--features inspector` adds a live world inspector
+//! (bevy-inspector-egui) on top, in which every registered resource can be
+//! browsed and edited while the simulation runs. Edits to `PhysicsSettings`
+//! and `PhysicsConstants` take effect at the next step, like the console.
+
+use bevy::prelude::*;
+
+use crate::accessibility::{Accessibility, Palette};
+use crate::clumps::ClumpConfig;
+use crate::constants::PhysicsConstants;
+use crate::far_field::FarFieldConfig;
+use crate::force_law::{ForceLaw, LjParams};
+use crate::locale::Language;
+use crate::physics::{Boundary, PhysicsSettings, Solver};
+use crate::pm::PmConfig;
+use crate::realtime::RealTimeFactor;
+use crate::softening::SofteningKernel;
+use crate::trails::Trails;
+use crate::{Bodies, BodyState};
+
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BodyState>()
+            .register_type::<Bodies>()
+            .register_type::<PhysicsSettings>()
+            .register_type::<Solver>()
+            .register_type::<Boundary>()
+            .register_type::<PmConfig>()
+            .register_type::<FarFieldConfig>()
+            .register_type::<ClumpConfig>()
+            .register_type::<ForceLaw>()
+            .register_type::<LjParams>()
+            .register_type::<PhysicsConstants>()
+            .register_type::<SofteningKernel>()
+            .register_type::<RealTimeFactor>()
+            .register_type::<Trails>()
+            .register_type::<Accessibility>()
+            .register_type::<Palette>()
+            .register_type::<Language>();
+
+        #[cfg(feature = "inspector")]
+        app.add_plugins(bevy_inspector_egui::quick::WorldInspectorPlugin::new());
+    }
+}
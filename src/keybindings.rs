@@ -0,0 +1,472 @@
+//! Central keybinding registry and the help overlay generated from it.
+//! Bindings can be remapped in `keybindings.ron` next to the user settings.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::locale::{Language, Tr, tr};
+use crate::settings::UserSettings;
+
+/// Everything that can be bound to a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    ToggleHelp,
+    ToggleConsole,
+    TogglePause,
+    BackToMenu,
+    MenuPrevious,
+    MenuNext,
+    MenuMoreBodies,
+    MenuFewerBodies,
+    MenuStart,
+    MenuTutorial,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    CycleSolver,
+    CycleBoundary,
+    CycleForceLaw,
+    ExponentDown,
+    ExponentUp,
+    ToggleKicks,
+    CycleColorMode,
+    CycleColormap,
+    CycleDensityView,
+    ToggleZoomView,
+    LinkBodies,
+    TogglePin,
+    ToggleAttractor,
+    PlaceEmitter,
+    ToggleAutoRealTime,
+    ToggleOrbitPath,
+    TogglePoincare,
+    ToggleLyapunov,
+    ToggleRegularization,
+    ReverseTime,
+    Rewind,
+    ToggleStarfield,
+    ToggleTrails,
+    ToggleGlow,
+    SaveGif,
+    TrailDecayDown,
+    TrailDecayUp,
+    CycleLanguage,
+    FollowCenterOfMass,
+    ToggleSpeedHistogram,
+    TogglePhaseView,
+    ToggleAnalysisWindow,
+}
+
+impl Action {
+    pub fn description(self) -> Tr {
+        match self {
+            Action::ToggleHelp => tr("show / hide this help", "이 도움말 보이기 / 숨기기"),
+            Action::ToggleConsole => tr("developer console", "개발자 콘솔"),
+            Action::TogglePause => tr("pause / resume", "일시정지 / 재개"),
+            Action::BackToMenu => tr("back to the scenario menu", "시나리오 메뉴로 돌아가기"),
+            Action::MenuPrevious => tr("menu: previous scenario", "메뉴: 이전 시나리오"),
+            Action::MenuNext => tr("menu: next scenario", "메뉴: 다음 시나리오"),
+            Action::MenuMoreBodies => tr("menu: more bodies", "메뉴: 천체 늘리기"),
+            Action::MenuFewerBodies => tr("menu: fewer bodies", "메뉴: 천체 줄이기"),
+            Action::MenuStart => tr("menu: start", "메뉴: 시작"),
+            Action::MenuTutorial => tr("menu: guided tutorial", "메뉴: 튜토리얼"),
+            Action::PanLeft => tr("pan left", "왼쪽으로 이동"),
+            Action::PanRight => tr("pan right", "오른쪽으로 이동"),
+            Action::PanUp => tr("pan up", "위로 이동"),
+            Action::PanDown => tr("pan down", "아래로 이동"),
+            Action::CycleSolver => tr(
+                "force solver: direct / chunked direct / PM / P³M / auto",
+                "힘 계산: 직접 / 분할 직접 / PM / P³M / 자동",
+            ),
+            Action::CycleBoundary => tr(
+                "boundary: open / periodic / periodic + Ewald",
+                "경계: 열림 / 주기 / 주기 + Ewald",
+            ),
+            Action::CycleForceLaw => tr(
+                "force law: gravity / anti-gravity / gravity + Coulomb / MOND / 2D gravity / power law",
+                "힘 법칙: 중력 / 반중력 / 중력 + 쿨롱 / MOND / 2차원 중력 / 거듭제곱 법칙",
+            ),
+            Action::ExponentDown => tr("power-law force exponent down", "거듭제곱 힘 지수 낮추기"),
+            Action::ExponentUp => tr("power-law force exponent up", "거듭제곱 힘 지수 높이기"),
+            Action::ToggleKicks => tr("stochastic kicks on / off", "무작위 충격 켜기 / 끄기"),
+            Action::CycleColorMode => tr(
+                "coloring: white / group / density / bound",
+                "색칠: 흰색 / 그룹 / 밀도 / 속박",
+            ),
+            Action::CycleColormap => tr(
+                "colormap: viridis / inferno / coolwarm",
+                "컬러맵: viridis / inferno / coolwarm",
+            ),
+            Action::CycleDensityView => tr(
+                "density background: off / heatmap / contours",
+                "밀도 배경: 끄기 / 히트맵 / 등고선",
+            ),
+            Action::ToggleZoomView => tr("zoom view on / off", "확대 보기 켜기 / 끄기"),
+            Action::LinkBodies => tr(
+                "spring link: selected body to the next one picked",
+                "스프링 연결: 선택한 천체와 다음에 고른 천체",
+            ),
+            Action::TogglePin => tr("pin / unpin the selected body", "선택한 천체 고정 / 해제"),
+            Action::ToggleAttractor => tr(
+                "mouse attractor mode (left: attract, right: repel)",
+                "마우스 인력 모드 (왼쪽: 끌기, 오른쪽: 밀기)",
+            ),
+            Action::PlaceEmitter => tr(
+                "place emitter at the cursor / switch it off",
+                "커서 위치에 방출기 놓기 / 끄기",
+            ),
+            Action::ToggleAutoRealTime => tr(
+                "auto real-time factor on / off",
+                "실시간 배율 자동 조절 켜기 / 끄기",
+            ),
+            Action::ToggleOrbitPath => tr(
+                "record / remove the selected body's orbit path",
+                "선택한 천체의 궤적 기록 / 지우기",
+            ),
+            Action::TogglePoincare => tr(
+                "Poincaré section panel on / off",
+                "푸앵카레 단면 패널 켜기 / 끄기",
+            ),
+            Action::ToggleLyapunov => tr(
+                "Lyapunov exponent of the selected body on / off",
+                "선택한 천체의 랴푸노프 지수 켜기 / 끄기",
+            ),
+            Action::ToggleRegularization => tr(
+                "analytic orbit for the tightest binary on / off",
+                "가장 가까운 쌍성의 해석적 궤도 켜기 / 끄기",
+            ),
+            Action::ReverseTime => tr(
+                "reverse time (negate every velocity)",
+                "시간 되돌리기 (모든 속도 반전)",
+            ),
+            Action::Rewind => tr(
+                "rewind: integrate backward (steps set with 'rewind' in the console)",
+                "되감기: 거꾸로 적분 (스텝 수는 콘솔의 'rewind'로 설정)",
+            ),
+            Action::ToggleStarfield => tr("starfield background on / off", "별 배경 켜기 / 끄기"),
+            Action::ToggleTrails => tr("long-exposure trails on / off", "장노출 궤적 켜기 / 끄기"),
+            Action::ToggleGlow => tr("additive glow on / off", "가산 발광 켜기 / 끄기"),
+            Action::SaveGif => tr(
+                "save the last 10 seconds as a GIF",
+                "최근 10초를 GIF로 저장",
+            ),
+            Action::TrailDecayDown => tr("shorter trails", "궤적 짧게"),
+            Action::TrailDecayUp => tr("longer trails", "궤적 길게"),
+            Action::CycleLanguage => tr("language: English / Korean", "언어: 영어 / 한국어"),
+            Action::FollowCenterOfMass => tr(
+                "camera follows the center of mass on / off",
+                "질량 중심 따라가기 켜기 / 끄기",
+            ),
+            Action::ToggleSpeedHistogram => tr(
+                "speed histogram on / off ('histogram' in the console to tune)",
+                "속력 히스토그램 켜기 / 끄기 (콘솔의 'histogram'으로 조정)",
+            ),
+            Action::TogglePhaseView => tr(
+                "phase-space view on / off ('phase x|y' in the console)",
+                "위상 공간 보기 켜기 / 끄기 (콘솔의 'phase x|y')",
+            ),
+            Action::ToggleAnalysisWindow => tr(
+                "plots and diagnostics in a separate window on / off",
+                "그래프와 진단을 별도 창에 표시 켜기 / 끄기",
+            ),
+        }
+    }
+}
+
+/// Mouse controls, listed in the help overlay after the keys
+const MOUSE_HELP: [(Tr, Tr); 3] = [
+    (tr("Wheel", "휠"), tr("zoom", "확대 / 축소")),
+    (
+        tr("Left click", "왼쪽 클릭"),
+        tr("select body", "천체 선택"),
+    ),
+    (
+        tr("Left / right hold", "왼쪽 / 오른쪽 누르기"),
+        tr(
+            "attractor mode: attract / repel",
+            "끌개 모드: 끌어당김 / 밀어냄",
+        ),
+    ),
+];
+
+/// Action → key table. An action may have several keys.
+#[derive(Resource)]
+pub struct KeyBindings {
+    pub bindings: Vec<(Action, KeyCode)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (Action::ToggleHelp, KeyCode::KeyH),
+                (Action::ToggleHelp, KeyCode::F1),
+                (Action::ToggleConsole, KeyCode::Backquote),
+                (Action::TogglePause, KeyCode::Space),
+                (Action::BackToMenu, KeyCode::Escape),
+                (Action::MenuPrevious, KeyCode::ArrowLeft),
+                (Action::MenuNext, KeyCode::ArrowRight),
+                (Action::MenuMoreBodies, KeyCode::ArrowUp),
+                (Action::MenuFewerBodies, KeyCode::ArrowDown),
+                (Action::MenuStart, KeyCode::Enter),
+                (Action::MenuTutorial, KeyCode::KeyT),
+                (Action::PanLeft, KeyCode::ArrowLeft),
+                (Action::PanRight, KeyCode::ArrowRight),
+                (Action::PanUp, KeyCode::ArrowUp),
+                (Action::PanDown, KeyCode::ArrowDown),
+                (Action::CycleSolver, KeyCode::KeyM),
+                (Action::CycleBoundary, KeyCode::KeyB),
+                (Action::CycleForceLaw, KeyCode::KeyG),
+                (Action::ExponentDown, KeyCode::BracketLeft),
+                (Action::ExponentUp, KeyCode::BracketRight),
+                (Action::ToggleKicks, KeyCode::KeyN),
+                (Action::CycleColorMode, KeyCode::KeyC),
+                (Action::CycleColormap, KeyCode::KeyV),
+                (Action::CycleDensityView, KeyCode::KeyD),
+                (Action::ToggleZoomView, KeyCode::KeyZ),
+                (Action::LinkBodies, KeyCode::KeyL),
+                (Action::TogglePin, KeyCode::KeyP),
+                (Action::ToggleAttractor, KeyCode::KeyA),
+                (Action::PlaceEmitter, KeyCode::KeyE),
+                (Action::ToggleAutoRealTime, KeyCode::KeyT),
+                (Action::ToggleOrbitPath, KeyCode::KeyO),
+                (Action::TogglePoincare, KeyCode::KeyS),
+                (Action::ToggleLyapunov, KeyCode::KeyY),
+                (Action::ToggleRegularization, KeyCode::KeyK),
+                (Action::ReverseTime, KeyCode::KeyU),
+                (Action::Rewind, KeyCode::KeyQ),
+                (Action::ToggleStarfield, KeyCode::KeyF),
+                (Action::ToggleTrails, KeyCode::KeyR),
+                (Action::ToggleGlow, KeyCode::KeyI),
+                (Action::SaveGif, KeyCode::F12),
+                (Action::TrailDecayDown, KeyCode::Minus),
+                (Action::TrailDecayUp, KeyCode::Equal),
+                (Action::CycleLanguage, KeyCode::F2),
+                (Action::FollowCenterOfMass, KeyCode::KeyX),
+                (Action::ToggleSpeedHistogram, KeyCode::KeyJ),
+                (Action::TogglePhaseView, KeyCode::KeyW),
+                (Action::ToggleAnalysisWindow, KeyCode::F3),
+            ],
+        }
+    }
+}
+
+/// Keys that can be named in the config file, by their help-overlay names
+#[rustfmt::skip]
+const NAMED_KEYS: [KeyCode; 72] = {
+    use KeyCode::*;
+    [
+        KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM,
+        KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+        Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+        Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        ArrowLeft, ArrowRight, ArrowUp, ArrowDown, Space, Enter, Escape, Tab, Backspace,
+        Backquote, Minus, Equal, BracketLeft, BracketRight,
+    ]
+};
+
+/// Keys named like the help overlay shows them ("M", "Left", "F12"), any case
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    NAMED_KEYS
+        .iter()
+        .copied()
+        .find(|&k| key_name(k).eq_ignore_ascii_case(name))
+}
+
+impl KeyBindings {
+    /// `keybindings.ron` beside the settings file
+    pub fn path() -> PathBuf {
+        UserSettings::path().with_file_name("keybindings.ron")
+    }
+
+    /// Defaults, with the actions listed in the config file remapped. A
+    /// missing file is created with the defaults, for editing.
+    pub fn load() -> Self {
+        let mut out = Self::default();
+        let path = Self::path();
+        let Ok(text) = fs::read_to_string(&path) else {
+            if let Err(e) = out.save() {
+                warn!("failed to write default keybindings: {e}");
+            }
+            return out;
+        };
+        match ron::from_str::<HashMap<Action, Vec<String>>>(&text) {
+            Ok(remap) => out.remap(&remap),
+            Err(e) => warn!("ignoring unreadable keybindings {}: {e}", path.display()),
+        }
+        out
+    }
+
+    /// Replace the keys of each listed action, keeping the table order
+    fn remap(&mut self, remap: &HashMap<Action, Vec<String>>) {
+        for (&action, names) in remap {
+            let keys: Vec<KeyCode> = names
+                .iter()
+                .filter_map(|name| {
+                    let key = parse_key(name);
+                    if key.is_none() {
+                        warn!("unknown key '{name}' for {action:?}");
+                    }
+                    key
+                })
+                .collect();
+            let at = self
+                .bindings
+                .iter()
+                .position(|(a, _)| *a == action)
+                .unwrap_or(self.bindings.len());
+            self.bindings.retain(|(a, _)| *a != action);
+            let at = at.min(self.bindings.len());
+            self.bindings
+                .splice(at..at, keys.into_iter().map(|k| (action, k)));
+        }
+    }
+
+    /// Write every action with its keys, in table order
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        }
+        let mut text = String::from(
+            "// Action: [keys], named as in the help overlay (H).\n\
+             // Actions left out keep their default keys.\n{\n",
+        );
+        for a in self.actions() {
+            let keys: Vec<String> = self
+                .keys(a)
+                .map(|k| format!("\"{}\"", key_name(k)))
+                .collect();
+            text += &format!("    {a:?}: [{}],\n", keys.join(", "));
+        }
+        text += "}\n";
+        fs::write(&path, text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Every action with its key names, as the config file lists them
+    pub fn table(&self) -> HashMap<Action, Vec<String>> {
+        self.actions()
+            .into_iter()
+            .map(|a| (a, self.keys(a).map(key_name).collect()))
+            .collect()
+    }
+
+    /// Defaults remapped by a table from [`Self::table`]
+    pub fn from_table(table: &HashMap<Action, Vec<String>>) -> Self {
+        let mut out = Self::default();
+        out.remap(table);
+        out
+    }
+
+    /// Bound actions, each once, in table order
+    fn actions(&self) -> Vec<Action> {
+        let mut actions: Vec<Action> = Vec::new();
+        for (a, _) in &self.bindings {
+            if !actions.contains(a) {
+                actions.push(*a);
+            }
+        }
+        actions
+    }
+
+    fn keys(&self, action: Action) -> impl Iterator<Item = KeyCode> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(a, _)| *a == action)
+            .map(|(_, k)| *k)
+    }
+
+    pub fn just_pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        self.keys(action).any(|k| input.just_pressed(k))
+    }
+
+    pub fn pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        self.keys(action).any(|k| input.pressed(k))
+    }
+
+    /// One line per action, keys joined with " / ", in table order
+    pub fn help_text(&self, lang: Language) -> String {
+        let mut out = tr("Controls", "조작법").get(lang).to_string() + "\n";
+        for a in self.actions() {
+            let keys: Vec<String> = self.keys(a).map(key_name).collect();
+            out += &format!("\n{:<14} {}", keys.join(" / "), a.description().get(lang));
+        }
+        out += "\n";
+        for (input, what) in MOUSE_HELP {
+            out += &format!("\n{:<14} {}", input.get(lang), what.get(lang));
+        }
+        out
+    }
+}
+
+/// Short display name of a key ("KeyM" → "M", "ArrowLeft" → "Left")
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Arrow"))
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+#[derive(Component)]
+pub struct HelpOverlay;
+
+#[derive(Component)]
+pub struct HelpText;
+
+pub fn spawn_help_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(25.0),
+                    top: Val::Percent(15.0),
+                    padding: UiRect::all(Val::Px(16.0)),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            HelpOverlay,
+        ))
+        .with_children(|overlay| {
+            overlay.spawn((TextBundle::from_section("", style), HelpText));
+        });
+}
+
+pub fn toggle_help(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    lang: Res<Language>,
+    mut overlay_q: Query<&mut Visibility, With<HelpOverlay>>,
+    mut text_q: Query<&mut Text, With<HelpText>>,
+) {
+    if bindings.is_changed() || lang.is_changed() {
+        if let Ok(mut t) = text_q.get_single_mut() {
+            t.sections[0].value = bindings.help_text(*lang);
+        }
+    }
+    if !bindings.just_pressed(&keys, Action::ToggleHelp) {
+        return;
+    }
+    for mut vis in overlay_q.iter_mut() {
+        *vis = match *vis {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
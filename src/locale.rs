@@ -0,0 +1,114 @@
+//! UI language. On-screen strings are [`Tr`] pairs kept next to the code that
+//! shows them; the current [`Language`] picks one, and can be switched at
+//! runtime. Korean needs a font with Hangul, looked up like the Latin one.
+
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::keybindings::{Action, KeyBindings};
+
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub enum Language {
+    #[default]
+    English,
+    Korean,
+}
+
+impl Language {
+    fn next(self) -> Self {
+        match self {
+            Language::English => Language::Korean,
+            Language::Korean => Language::English,
+        }
+    }
+
+    /// `en` or `ko`
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "en" => Some(Language::English),
+            "ko" => Some(Language::Korean),
+            _ => None,
+        }
+    }
+
+    /// Font with this language's glyphs, under `assets/`
+    pub fn font(self) -> &'static str {
+        match self {
+            Language::English => "fonts/FiraSans-Bold.ttf",
+            Language::Korean => "fonts/NanumGothic-Bold.ttf",
+        }
+    }
+}
+
+/// One UI string in every supported language. Templates mark their
+/// arguments with `{}`, filled in order by [`fill`].
+#[derive(Clone, Copy, Debug)]
+pub struct Tr {
+    pub en: &'static str,
+    pub ko: &'static str,
+}
+
+pub const fn tr(en: &'static str, ko: &'static str) -> Tr {
+    Tr { en, ko }
+}
+
+impl Tr {
+    pub fn get(self, lang: Language) -> &'static str {
+        match lang {
+            Language::English => self.en,
+            Language::Korean => self.ko,
+        }
+    }
+}
+
+/// Replace each `{}` in `template` with the next argument
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() {
+        out += first;
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out += &arg.to_string();
+        }
+        out += part;
+    }
+    out
+}
+
+pub fn cycle_language(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut lang: ResMut<Language>,
+) {
+    if bindings.just_pressed(&keys, Action::CycleLanguage) {
+        *lang = lang.next();
+        info!("Language: {:?}", *lang);
+    }
+}
+
+/// Give every text the current language's font: all of them when the
+/// language changes, otherwise just the newly spawned ones
+pub fn apply_language_font(
+    lang: Res<Language>,
+    asset_server: Res<AssetServer>,
+    mut q: Query<&mut Text>,
+) {
+    if *lang == Language::English && !lang.is_changed() {
+        return;
+    }
+    let font = asset_server.load(lang.font());
+    for mut t in q.iter_mut() {
+        if !lang.is_changed() && !t.is_added() {
+            continue;
+        }
+        for section in t.sections.iter_mut() {
+            section.style.font = font.clone();
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Largest-Lyapunov-exponent estimate for one body (Benettin et al. 1980):
+//! a reference copy and a slightly displaced shadow are integrated as test
+//! particles in the field of the other bodies, and their separation is
+//! renormalized every few steps while the logarithmic growth is summed.
+
+use bevy::prelude::*;
+
+use crate::constants::PhysicsConstants;
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, tr};
+use crate::physics::{PhysicsSettings, single_acceleration};
+use crate::selection::Selection;
+use crate::units::CODE;
+use crate::{Bodies, UiLyapunov};
+
+/// Initial shadow offset as a fraction of the body's distance from the origin;
+/// large enough to stay resolved by the f32 force snapshot
+const OFFSET_FRACTION: f64 = 1.0E-5;
+/// Steps between renormalizations
+const RENORM_STEPS: u32 = 10;
+/// Renormalizations between log lines
+const LOG_EVERY: u32 = 100;
+
+/// Test-particle state, in f64 so the small offset survives
+#[derive(Clone, Copy, Default)]
+struct Tracer {
+    x: f64,
+    y: f64,
+    vx: f64,
+    vy: f64,
+    ax: f64,
+    ay: f64,
+}
+
+#[derive(Resource, Default)]
+pub struct Lyapunov {
+    /// Body being followed, `None` when off
+    pub target: Option<usize>,
+    reference: Tracer,
+    shadow: Tracer,
+    /// Separation the shadow is reset to
+    d0: f64,
+    log_sum: f64,
+    /// Integrated time
+    time: f64,
+    since_renorm: u32,
+    renorms: u32,
+    last_step: u64,
+    last_time: f64,
+    /// Running estimate, per unit time
+    pub estimate: Option<f64>,
+}
+
+impl Lyapunov {
+    /// Start following body `i` from its current state, or stop
+    pub fn follow(&mut self, bodies: &Bodies, target: Option<usize>) {
+        *self = Self::default();
+        let Some(b) = target.and_then(|i| bodies.data.get(i)) else {
+            return;
+        };
+        let reference = Tracer {
+            x: b.x as f64,
+            y: b.y as f64,
+            vx: b.vx as f64,
+            vy: b.vy as f64,
+            ax: b.ax as f64,
+            ay: b.ay as f64,
+        };
+        self.d0 = OFFSET_FRACTION * reference.x.hypot(reference.y).max(1.0);
+        self.shadow = Tracer {
+            x: reference.x + self.d0,
+            ..reference
+        };
+        self.reference = reference;
+        self.target = target;
+        self.last_step = bodies.step;
+        self.last_time = bodies.elapsed_time as f64;
+    }
+}
+
+/// Lyapunov key starts / stops the estimate for the selected body
+pub fn toggle_lyapunov(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    selection: Res<Selection>,
+    bodies: Res<Bodies>,
+    mut lyapunov: ResMut<Lyapunov>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleLyapunov) {
+        return;
+    }
+    let target = if lyapunov.target.is_some() {
+        None
+    } else {
+        selection.0
+    };
+    lyapunov.follow(&bodies, target);
+}
+
+/// Advance both copies by the time the simulation moved since the last
+/// update (kick-drift-kick in the current field), then renormalize
+pub fn update_lyapunov(
+    bodies: Res<Bodies>,
+    settings: Res<PhysicsSettings>,
+    constants: Res<PhysicsConstants>,
+    mut lyapunov: ResMut<Lyapunov>,
+) {
+    let Some(i) = lyapunov.target else {
+        return;
+    };
+    if bodies.step == lyapunov.last_step || i >= bodies.data.len() {
+        return;
+    }
+    let _span = info_span!("lyapunov").entered();
+    let now = bodies.elapsed_time as f64;
+    let dt = now - lyapunov.last_time;
+    lyapunov.last_step = bodies.step;
+    lyapunov.last_time = now;
+
+    let mut snap: Vec<[f32; 4]> = bodies
+        .data
+        .iter()
+        .map(|b| [b.x, b.y, b.mass, b.charge])
+        .collect();
+    let mut advance = |t: &mut Tracer| {
+        t.vx += 0.5 * dt * t.ax;
+        t.vy += 0.5 * dt * t.ay;
+        t.x += dt * t.vx;
+        t.y += dt * t.vy;
+        // The tracer stands in for body i, which is left out of the sum
+        snap[i][0] = t.x as f32;
+        snap[i][1] = t.y as f32;
+        let a = single_acceleration(&snap, i, settings.boundary, settings.force_law, &constants);
+        t.ax = a[0] as f64;
+        t.ay = a[1] as f64;
+        t.vx += 0.5 * dt * t.ax;
+        t.vy += 0.5 * dt * t.ay;
+    };
+    let Lyapunov {
+        reference, shadow, ..
+    } = &mut *lyapunov;
+    advance(reference);
+    advance(shadow);
+
+    let ly = &mut *lyapunov;
+    ly.time += dt;
+    ly.since_renorm += 1;
+    if ly.since_renorm < RENORM_STEPS {
+        return;
+    }
+    ly.since_renorm = 0;
+    let (dx, dy) = (ly.shadow.x - ly.reference.x, ly.shadow.y - ly.reference.y);
+    let d = dx.hypot(dy);
+    if d == 0.0 || !d.is_finite() {
+        return;
+    }
+    ly.log_sum += (d / ly.d0).ln();
+    // Pull the shadow back along the separation, scaling the velocity offset alike
+    let k = ly.d0 / d;
+    ly.shadow.x = ly.reference.x + k * dx;
+    ly.shadow.y = ly.reference.y + k * dy;
+    ly.shadow.vx = ly.reference.vx + k * (ly.shadow.vx - ly.reference.vx);
+    ly.shadow.vy = ly.reference.vy + k * (ly.shadow.vy - ly.reference.vy);
+    let estimate = ly.log_sum / ly.time;
+    ly.estimate = Some(estimate);
+    ly.renorms += 1;
+    if ly.renorms % LOG_EVERY == 0 {
+        info!(
+            "Lyapunov exponent of body {i}: {:.3E} 1/s after {:.2E} s",
+            estimate / CODE.time,
+            ly.time * CODE.time
+        );
+    }
+}
+
+pub fn update_lyapunov_text(
+    lyapunov: Res<Lyapunov>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<UiLyapunov>>,
+) {
+    if !lyapunov.is_changed() && !lang.is_changed() {
+        return;
+    }
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    let lang = *lang;
+    let name = tr("Lyapunov", "랴푸노프").get(lang);
+    let unit = tr("year", "년").get(lang);
+    t.sections[0].value = match (lyapunov.target, lyapunov.estimate) {
+        (None, _) => String::new(),
+        (Some(i), None) => format!("{name} #{i}: {}", tr("measuring…", "측정 중…").get(lang)),
+        (Some(i), Some(l)) => {
+            let year = 3.154E7 / CODE.time;
+            let e_fold = if l > 0.0 {
+                format!("{:.2E} {unit}", 1.0 / l / year)
+            } else {
+                "∞".to_string()
+            };
+            format!(
+                "{name} #{i}: λ = {:.3E} /{unit}   {} {e_fold}",
+                l * year,
+                tr("e-folding", "e배 시간").get(lang)
+            )
+        }
+    };
+}
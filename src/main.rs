@@ -1,45 +1,140 @@
 use bevy::core_pipeline::core_2d::Camera2dBundle;
 use bevy::prelude::*;
-use bevy::sprite::SpriteBundle;
-use bevy::window::PrimaryWindow;
-use rand::{distributions::Standard, rngs::StdRng, Rng, SeedableRng};
+use bevy::sprite::Material2dPlugin;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use bevy::window::{ExitCondition, PrimaryWindow, WindowResolution};
+use rand::{Rng, distributions::Standard};
+
+use crate::hud::{HudItem, Overlay};
+use crate::locale::tr;
+use crate::sim_rng::Xoshiro256PlusPlus;
+use crate::units::{CODE, SI};
+
+mod accessibility;
+#[cfg(test)]
+mod alloc_count;
+mod analysis_window;
+mod attractor;
+mod batch;
+mod bench;
+mod binaries;
+mod body_history;
+mod boundness;
+mod camera;
+mod center_of_mass;
+mod cli;
+mod clumps;
+mod collisions;
+mod coloring;
+mod colormap;
+mod compare;
+mod console;
+mod constants;
+mod core_collapse;
+mod correlation;
+mod density;
+mod determinism;
+mod diagnostics;
+mod emitter;
+mod ensemble;
+mod ewald;
+mod external;
+mod far_field;
+mod fft;
+mod fof;
+mod force_law;
+mod gif;
+mod gpu_density;
+mod headless;
+mod hud;
+mod ic;
+mod ic_registry;
+mod inspector;
+mod keybindings;
+mod locale;
+mod lyapunov;
+mod mass_evolution;
+mod menu;
+mod metrics;
+mod momentum;
+mod morton;
+mod neighbors;
+mod npz;
+mod orbit_path;
+mod parquet;
+mod pause;
+mod phase_view;
+mod physics;
+mod plot_output;
+mod pm;
+mod poincare;
+mod point_sprites;
+mod quality;
+mod radial_profile;
+mod realtime;
+mod regularization;
+mod remote;
+mod replay;
+mod reversal;
+mod rotating_frame;
+mod scale_bar;
+mod scenario;
+mod selection;
+mod settings;
+mod sim_rng;
+mod slingshot;
+mod snapshot;
+mod softening;
+mod special;
+mod speed_histogram;
+mod springs;
+mod starfield;
+mod stochastic;
+mod structure;
+mod summation;
+mod thermostat;
+mod timing;
+mod trails;
+mod tutorial;
+mod units;
+mod video;
+mod zoom_view;
 
 const NUM_BODIES: usize = 1000;
 const ASPECT_RATIO: f32 = 5.0;
 
-const MAX_X: f32 = 5.0E14;
-const MIN_X: f32 = -5.0E14;
-const MAX_Y: f32 = 5.0E14;
-const MIN_Y: f32 = -5.0E14;
+// Everything below is in code units (see `units`), written as SI values
+const MAX_X: f32 = SI.length(5.0E14);
+const MIN_X: f32 = SI.length(-5.0E14);
+const MAX_Y: f32 = SI.length(5.0E14);
+const MIN_Y: f32 = SI.length(-5.0E14);
 
-const MAX_MASS: f32 = 9.0E29;
-const MIN_MASS: f32 = 1.0E15;
+const MAX_MASS: f32 = SI.mass(9.0E29);
+const MIN_MASS: f32 = SI.mass(1.0E15);
 
-const MAX_V: f32 = 9.0E03;
-const MIN_V: f32 = 1.0E03;
+const MAX_V: f32 = SI.velocity(9.0E03);
+const MIN_V: f32 = SI.velocity(1.0E03);
 
-const GRAVITATION: f32 = 6.67E-11; // G
-const D_TIME: f32 = 2.0E07; // dt (s)
-const D_TIME_HALF: f32 = 1.0E07; // dt/2
-const A_RIGHT_YEAR: f32 = 9.46E15; // 1 light year (m)
+const GRAVITATION: f32 = 1.0; // G
+const COULOMB: f32 = 1.0; // Coulomb constant k
+const D_TIME: f32 = SI.time(2.0E07); // default dt (2e7 s)
+const A_RIGHT_YEAR: f32 = SI.length(9.46E15); // 1 light year
+const PHYSICS_HZ: f64 = 30.0; // fixed physics steps per wall-clock second
+const NPZ_INTERVAL: u64 = 100; // default steps between --npz snapshots
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Reflect)]
 struct BodyState {
     mass: f32,
+    charge: f32, // zero unless an electrostatic scenario assigns one
+    fixed: bool, // pinned: exerts forces but is never kicked or drifted
     x: f32,
     y: f32,
     vx: f32,
     vy: f32,
     ax: f32,
     ay: f32,
-    vx_half: f32,
-    vy_half: f32,
-    x_new: f32,
-    y_new: f32,
-    vx_new: f32,
-    vy_new: f32,
-    ax_new: f32,
-    ay_new: f32,
+    x_prev: f32, // position before the last physics step (render interpolation)
+    y_prev: f32,
     disp_x: f32, // screen/world mapped
     disp_y: f32,
 }
@@ -47,39 +142,58 @@ impl BodyState {
     fn new() -> Self {
         Self {
             mass: 0.0,
+            charge: 0.0,
+            fixed: false,
             x: 0.0,
             y: 0.0,
             vx: 0.0,
             vy: 0.0,
             ax: 0.0,
             ay: 0.0,
-            vx_half: 0.0,
-            vy_half: 0.0,
-            x_new: 0.0,
-            y_new: 0.0,
-            vx_new: 0.0,
-            vy_new: 0.0,
-            ax_new: 0.0,
-            ay_new: 0.0,
+            x_prev: 0.0,
+            y_prev: 0.0,
             disp_x: 0.0,
             disp_y: 0.0,
         }
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
 struct Bodies {
+    /// Last completed state; everything outside the integrator reads this
     data: Vec<BodyState>,
+    /// Write buffer for the step in flight (v^{n+1/2}, x^{n+1}), swapped
+    /// with `data` once the step completes
+    next: Vec<BodyState>,
     elapsed_time: f32,
+    step: u64,
     kinetic_energy: f64,
     potential_energy: f64,
 }
 
-#[derive(Component)]
-struct BodyVisual {
-    index: usize,
+/// Screen-space culling and LOD for the body points
+#[derive(Resource)]
+struct ViewCulling {
+    /// Extra border around the viewport (fraction of its half-size) before a point is dropped
+    margin: f32,
+    /// Bodies farther than this many viewport half-sizes away skip interpolation
+    lod_distance: f32,
+}
+
+impl Default for ViewCulling {
+    fn default() -> Self {
+        Self {
+            margin: 0.05,
+            lod_distance: 3.0,
+        }
+    }
 }
 
+/// The primary 2D camera (user zoom/pan, UI)
+#[derive(Component)]
+struct MainCamera;
+
 #[derive(Component)]
 struct UiElapsed;
 
@@ -89,54 +203,617 @@ struct UiKe;
 #[derive(Component)]
 struct UiPe;
 
+#[derive(Component)]
+struct UiStructure;
+
+#[derive(Component)]
+struct UiBinaries;
+
+#[derive(Component)]
+struct UiGroups;
+
+#[derive(Component)]
+struct UiSelection;
+
+#[derive(Component)]
+struct UiRealTime;
+
+#[derive(Component)]
+struct UiLyapunov;
+
 fn main() {
+    let args = cli::CliArgs::parse();
+    if let Some((a, b)) = &args.compare {
+        if let Err(e) = compare::run(a, b) {
+            eprintln!("compare failed: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some((spec, dir)) = &args.batch {
+        if let Err(e) = batch::run(spec, dir) {
+            eprintln!("batch failed: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(sizes) = &args.bench_direct {
+        bench::run(sizes);
+        return;
+    }
+    if let Some((addr, bodies, seed)) = &args.serve {
+        // No app, so no `LogPlugin` to print the server's log
+        let _ = bevy::utils::tracing::subscriber::set_global_default(console_logger());
+        if let Err(e) = remote::serve(addr, *bodies, *seed) {
+            eprintln!("serve failed: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.ic_list {
+        ic_registry::print_list();
+        return;
+    }
+    if let Some((steps, seed)) = args.check_determinism {
+        if !determinism::run(steps, seed) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some((spec, dir)) = &args.ensemble {
+        if let Err(e) = ensemble::run(spec, dir) {
+            eprintln!("ensemble failed: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let replay = args.replay.as_ref().map(|path| {
+        replay::Recording::load(path).unwrap_or_else(|e| {
+            eprintln!("failed to load recording: {e}");
+            std::process::exit(1);
+        })
+    });
+    if replay.is_some() && (args.record.is_some() || args.video.is_some()) {
+        eprintln!("--replay can't be combined with --record or --video");
+        std::process::exit(2);
+    }
+    // Recordings name their scenario by file, which an --ic setup doesn't have
+    if args.ic.is_some() && (args.record.is_some() || replay.is_some()) {
+        eprintln!("--ic can't be combined with --record or --replay");
+        std::process::exit(2);
+    }
+    let ic_spec = args.ic.as_ref().map(|spec| {
+        ic_registry::parse_spec(spec).unwrap_or_else(|e| {
+            eprintln!("--ic: {e}");
+            std::process::exit(2);
+        })
+    });
+    // A replay starts from the recorded setup, not the current files
+    let scenario_path = match &replay {
+        Some(r) => r.scenario.clone(),
+        None => args.scenario.clone(),
+    };
+    // Read before the app (and its `LogPlugin`) exists, so log to a
+    // console logger of their own
+    let (user_settings, bindings) =
+        bevy::utils::tracing::subscriber::with_default(console_logger(), || match &replay {
+            Some(r) => (
+                r.settings.clone(),
+                keybindings::KeyBindings::from_table(&r.keybindings),
+            ),
+            None => (
+                settings::UserSettings::load(),
+                keybindings::KeyBindings::load(),
+            ),
+        });
+    let physics_constants = match &replay {
+        Some(r) => r.constants,
+        None if args.record.is_some() => constants::PhysicsConstants::read_asset_file(),
+        None => constants::PhysicsConstants::default(),
+    };
+    let seed = match &replay {
+        Some(r) => r.seed,
+        None => args.seed.unwrap_or_else(rand::random),
+    };
+    println!("Random seed: {seed} (rerun with --seed {seed})");
+    let mut scenarios = scenario::Scenario::builtin();
+    scenarios.extend(scenario::Scenario::generators());
+    scenarios.extend(scenario::Scenario::scan_dir(std::path::Path::new(
+        scenario::SCENARIO_DIR,
+    )));
+    let mut preselected = user_settings
+        .last_scenario
+        .as_ref()
+        .and_then(|name| scenarios.iter().position(|s| &s.name == name))
+        .unwrap_or(0);
+    if let Some(path) = &scenario_path {
+        scenarios.push(scenario::Scenario::load(path).unwrap_or_else(|e| {
+            eprintln!("failed to load scenario: {e}");
+            std::process::exit(1);
+        }));
+        preselected = scenarios.len() - 1;
+    }
+    if let Some((name, params)) = ic_spec {
+        let generator = ic_registry::find(&name).expect("checked by parse_spec");
+        scenarios.push(scenario::Scenario::generated(generator, params));
+        preselected = scenarios.len() - 1;
+    }
+    let diagnostics_log = match &args.diagnostics {
+        Some(path) => diagnostics::DiagnosticsLog::create(path).unwrap_or_else(|e| {
+            eprintln!("failed to create diagnostics log {}: {e}", path.display());
+            std::process::exit(1);
+        }),
+        None => diagnostics::DiagnosticsLog::default(),
+    };
+    let plot_output = match &args.plot_output {
+        Some(dir) => plot_output::PlotOutput::create(dir).unwrap_or_else(|e| {
+            eprintln!("failed to create plot output in {}: {e}", dir.display());
+            std::process::exit(1);
+        }),
+        None => plot_output::PlotOutput::default(),
+    };
+    let npz_export = match &args.npz {
+        Some(dir) => {
+            let interval = args.npz_interval.unwrap_or(NPZ_INTERVAL);
+            npz::NpzExport::new(dir.clone(), interval).unwrap_or_else(|e| {
+                eprintln!("failed to create npz output in {}: {e}", dir.display());
+                std::process::exit(1);
+            })
+        }
+        None => npz::NpzExport::default(),
+    };
+    let parquet_output = match &args.parquet {
+        Some(dir) => parquet::ParquetOutput::create(dir).unwrap_or_else(|e| {
+            eprintln!("failed to create parquet output in {}: {e}", dir.display());
+            std::process::exit(1);
+        }),
+        None => parquet::ParquetOutput::default(),
+    };
+    let video_export = match &args.video {
+        Some(path) => video::VideoExport::new(
+            path.clone(),
+            args.video_fps.unwrap_or(video::DEFAULT_FPS),
+            args.video_duration,
+        ),
+        None => video::VideoExport::default(),
+    };
+    // Recording frames are captured at exactly the requested size
+    let resolution = match (&replay, args.video_size) {
+        (Some(r), _) => r.window.into(),
+        (None, Some((w, h))) => {
+            WindowResolution::new(w as f32, h as f32).with_scale_factor_override(1.0)
+        }
+        (None, None) => (800., 800.).into(),
+    };
+    let input_log = match (replay, &args.record) {
+        (Some(recording), _) => replay::InputLog::Replaying { recording, next: 0 },
+        (None, Some(path)) => replay::InputLog::Recording {
+            path: path.clone(),
+            recording: replay::Recording {
+                version: replay::FORMAT_VERSION,
+                seed,
+                window: (resolution.width(), resolution.height()),
+                scenario: scenario_path,
+                settings: user_settings.clone(),
+                keybindings: bindings.table(),
+                constants: physics_constants,
+                frames: Vec::new(),
+            },
+        },
+        (None, None) => replay::InputLog::Off,
+    };
+    let lockstep = !matches!(input_log, replay::InputLog::Off);
+    let time_strategy = match &input_log {
+        replay::InputLog::Replaying { recording, .. } => recording.first_frame_time(),
+        _ => video_export.time_strategy(),
+    };
+
     App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "(LeapFrog) Star motion by universal gravitation".to_string(),
-                resolution: (800., 800.).into(),
-                ..Default::default()
-            }),
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "(LeapFrog) Star motion by universal gravitation".to_string(),
+                        resolution,
+                        // Cursor positions are replayed relative to the window size
+                        resizable: !video_export.is_recording() && !lockstep,
+                        ..Default::default()
+                    }),
+                    // The analysis window alone doesn't keep the app running
+                    exit_condition: ExitCondition::OnPrimaryClosed,
+                    ..Default::default()
+                })
+                .set(bevy::log::LogPlugin {
+                    custom_layer: timing::span_layer,
+                    ..Default::default()
+                }),
+        )
+        .add_plugins(Material2dPlugin::<point_sprites::PointMaterial>::default())
+        .add_plugins(Material2dPlugin::<trails::TrailMaterial>::default())
+        .add_plugins(gpu_density::GpuDensityPlugin)
+        .add_plugins(inspector::InspectorPlugin)
+        .add_plugins(timing::TimingPlugin)
+        .add_plugins(body_history::BodyHistoryPlugin)
+        .add_plugins(metrics::MetricsPlugin {
+            addr: args.metrics.clone(),
+        })
+        .add_plugins(remote::RemotePlugin {
+            connect: args.connect.clone(),
+        })
+        .init_state::<menu::AppState>()
+        .insert_resource(menu::MenuChoice::new(scenarios, preselected))
+        // Placeholders until the menu starts a run
+        .init_resource::<Bodies>()
+        .insert_resource(stochastic::StochasticKicks::new(None, 0))
+        .init_resource::<mass_evolution::MassEvolution>()
+        .init_resource::<thermostat::Thermostat>()
+        .init_resource::<scenario::Scenario>()
+        .insert_resource(diagnostics_log)
+        .insert_resource(plot_output)
+        .insert_resource(npz_export)
+        .insert_resource(parquet_output)
+        .insert_resource(time_strategy)
+        .insert_resource(video_export)
+        .init_resource::<gif::GifRecorder>()
+        .init_resource::<structure::StructureDiagnostics>()
+        .init_resource::<binaries::BinaryScan>()
+        .init_resource::<fof::FofGroups>()
+        .insert_resource(user_settings.color_mode)
+        .insert_resource(user_settings.colormap)
+        .insert_resource(user_settings.language)
+        .insert_resource(user_settings.accessibility)
+        .insert_resource(user_settings.hud.clone())
+        .init_resource::<density::DensityField>()
+        .init_resource::<selection::Selection>()
+        .init_resource::<springs::Springs>()
+        .init_resource::<attractor::MouseAttractor>()
+        .init_resource::<emitter::Emitter>()
+        .init_resource::<slingshot::SlingshotGame>()
+        .init_resource::<tutorial::Tutorial>()
+        .init_resource::<orbit_path::OrbitPaths>()
+        .init_resource::<poincare::PoincareSection>()
+        .init_resource::<lyapunov::Lyapunov>()
+        .init_resource::<momentum::MomentumCorrection>()
+        .init_resource::<regularization::Regularization>()
+        .init_resource::<reversal::TimeReversal>()
+        .init_resource::<reversal::Rewind>()
+        .init_resource::<morton::MortonOrder>()
+        .init_resource::<neighbors::SpatialIndex>()
+        .init_resource::<collisions::Collisions>()
+        .init_resource::<center_of_mass::CenterOfMass>()
+        .init_resource::<rotating_frame::RotatingFrame>()
+        .init_resource::<speed_histogram::SpeedHistogram>()
+        .init_resource::<radial_profile::RadialProfiles>()
+        .init_resource::<phase_view::PhaseView>()
+        .init_resource::<correlation::Correlation>()
+        .init_resource::<boundness::Boundness>()
+        .init_resource::<core_collapse::CoreCollapseMonitor>()
+        .init_resource::<analysis_window::AnalysisWindow>()
+        .init_resource::<quality::QualityGovernor>()
+        .add_event::<collisions::Collision>()
+        .add_event::<core_collapse::CoreCollapse>()
+        .add_event::<morton::BodiesReordered>()
+        .add_event::<physics::StepFinished>()
+        .insert_resource(zoom_view::ZoomView {
+            enabled: user_settings.zoom_view,
             ..Default::default()
-        }))
-        .insert_resource(init_bodies())
-        .add_systems(Startup, setup)
-        .add_systems(Update, (leapfrog_step, update_visuals, update_ui_texts))
+        })
+        .insert_resource(user_settings)
+        .insert_resource(bindings)
+        .insert_resource(sim_rng::SimRng::new(seed))
+        .insert_resource(input_log)
+        .init_resource::<console::Console>()
+        .init_resource::<physics::PendingBodies>()
+        .init_resource::<coloring::BodyColors>()
+        .init_resource::<ViewCulling>()
+        .init_resource::<realtime::RealTimeFactor>()
+        .insert_resource(Time::<Fixed>::from_hz(PHYSICS_HZ))
+        .add_systems(
+            Startup,
+            (
+                setup,
+                colormap::spawn_legend,
+                zoom_view::spawn_zoom_camera,
+                point_sprites::spawn_body_points,
+                starfield::spawn_starfield,
+                trails::spawn_trails,
+                gpu_density::spawn_gpu_density,
+                keybindings::spawn_help_overlay,
+                console::spawn_console,
+                slingshot::spawn_game_text,
+                tutorial::spawn_tutorial_text,
+                poincare::spawn_poincare_panel,
+                force_law::spawn_force_law_text,
+                reversal::spawn_reversal_text,
+                scale_bar::spawn_scale_bar,
+                speed_histogram::spawn_speed_histogram,
+                radial_profile::spawn_profile_panel,
+                phase_view::spawn_phase_view,
+                boundness::spawn_bound_text,
+                core_collapse::spawn_collapse_alert,
+            ),
+        )
+        .add_systems(PostStartup, settings::apply_camera_zoom)
+        .add_systems(
+            Last,
+            (
+                settings::save_on_exit.run_if(not(replay::replaying)),
+                replay::save_recording,
+                parquet::finish_on_exit,
+                video::finish_on_exit,
+                video::capture_frame.run_if(in_state(menu::AppState::Running)),
+                gif::capture_gif_frame.run_if(not(in_state(menu::AppState::MainMenu))),
+            ),
+        )
+        .insert_resource(if lockstep {
+            physics::PhysicsTask::lockstep()
+        } else {
+            physics::PhysicsTask::default()
+        })
+        .init_resource::<physics::PhysicsSettings>()
+        .insert_resource(physics_constants)
+        .init_asset::<constants::PhysicsConstants>()
+        .init_asset_loader::<constants::PhysicsConstantsLoader>()
+        .add_systems(
+            Startup,
+            constants::load_physics_constants.run_if(not(replay::lockstep)),
+        )
+        .add_systems(
+            Update,
+            (
+                constants::poll_physics_constants,
+                constants::apply_physics_constants,
+            )
+                .chain()
+                .run_if(resource_exists::<constants::ConstantsWatch>),
+        )
+        .add_systems(
+            PreUpdate,
+            (
+                replay::replay_inputs.before(bevy::input::InputSystem),
+                replay::record_inputs.after(bevy::input::InputSystem),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                locale::cycle_language,
+                locale::apply_language_font,
+                accessibility::apply_high_contrast,
+            )
+                .chain(),
+        )
+        .add_systems(
+            FixedUpdate,
+            (physics::leapfrog_step, reversal::apply_rewind)
+                .chain()
+                .run_if(in_state(menu::AppState::Running))
+                .run_if(not(remote::viewing)),
+        )
+        .add_systems(
+            OnEnter(menu::AppState::MainMenu),
+            (
+                menu::spawn_menu,
+                tutorial::stop_tutorial,
+                point_sprites::clear_points,
+                trails::clear_trails,
+            ),
+        )
+        .add_systems(OnExit(menu::AppState::MainMenu), menu::despawn_menu)
+        .add_systems(OnEnter(menu::AppState::Paused), pause::enter_idle)
+        .add_systems(OnExit(menu::AppState::Paused), pause::leave_idle)
+        .add_systems(
+            Update,
+            (
+                menu::menu_input,
+                tutorial::start_tutorial,
+                menu::update_menu_text,
+            )
+                .chain()
+                .run_if(in_state(menu::AppState::MainMenu)),
+        )
+        .add_systems(
+            Update,
+            (
+                timing::begin_phases,
+                // Indices first, so nothing below sees a stale body order
+                (
+                    morton::remap_body_indices,
+                    neighbors::update_spatial_index,
+                    collisions::detect_collisions,
+                    collisions::report_selected_collisions,
+                )
+                    .chain(),
+                // Input (the console goes first so it can swallow keystrokes)
+                console::console_input,
+                console::run_console_commands,
+                (
+                    camera::camera_controls,
+                    center_of_mass::toggle_follow,
+                    speed_histogram::toggle_speed_histogram,
+                    phase_view::toggle_phase_view,
+                    analysis_window::toggle_analysis_window,
+                    physics::cycle_solver,
+                    physics::auto_select_solver.after(physics::cycle_solver),
+                    force_law::cycle_force_law,
+                    force_law::adjust_force_exponent,
+                    stochastic::toggle_kicks,
+                    coloring::cycle_color_mode,
+                    colormap::cycle_colormap,
+                    gpu_density::cycle_density_view,
+                    point_sprites::toggle_glow,
+                    selection::pick_body
+                        .run_if(attractor::attractor_disabled)
+                        .run_if(slingshot::game_inactive),
+                    slingshot::launch_probe,
+                    springs::link_selected.after(selection::pick_body),
+                ),
+                (
+                    attractor::toggle_attractor,
+                    attractor::update_attractor,
+                    emitter::place_emitter,
+                    selection::toggle_pin,
+                    orbit_path::toggle_orbit_path,
+                    poincare::toggle_poincare_panel,
+                    lyapunov::toggle_lyapunov,
+                    regularization::toggle_regularization,
+                    reversal::toggle_time_reversal,
+                    reversal::rewind_key,
+                    starfield::toggle_starfield,
+                    trails::toggle_trails,
+                    gif::save_gif,
+                    zoom_view::toggle_zoom_view,
+                    realtime::toggle_auto_real_time,
+                    keybindings::toggle_help,
+                    menu::toggle_pause,
+                    menu::return_to_menu,
+                ),
+                timing::end_phase("input"),
+                // Analysis
+                (
+                    structure::update_structure,
+                    core_collapse::detect_core_collapse.after(structure::update_structure),
+                    binaries::scan_binaries,
+                    fof::update_groups,
+                    density::update_density,
+                    boundness::update_boundness,
+                    center_of_mass::update_center_of_mass,
+                    speed_histogram::update_speed_histogram,
+                    radial_profile::update_radial_profiles,
+                    correlation::update_correlation,
+                    // Rate controllers
+                    (realtime::update_real_time_factor, quality::govern_quality),
+                    // Both draw from the shared generator, so their order
+                    // decides which draws each one gets
+                    emitter::emit_bodies
+                        .run_if(in_state(menu::AppState::Running))
+                        .before(tutorial::advance_tutorial),
+                    slingshot::update_game,
+                    tutorial::advance_tutorial,
+                    orbit_path::record_orbit_paths,
+                    poincare::record_crossings,
+                    lyapunov::update_lyapunov,
+                    plot_output::record_plot_data.after(structure::update_structure),
+                    npz::export_npz,
+                    parquet::record_parquet,
+                )
+                    .run_if(pause::needs_refresh),
+                timing::end_phase("analysis"),
+                // Visuals
+                (
+                    coloring::apply_colors,
+                    update_visuals.run_if(pause::needs_refresh),
+                    center_of_mass::follow_center_of_mass,
+                    rotating_frame::rotate_view,
+                    starfield::follow_main_camera,
+                    trails::update_trails,
+                    gpu_density::update_gpu_density.run_if(pause::needs_refresh),
+                    external::draw_external,
+                    binaries::draw_binaries,
+                    selection::draw_selection,
+                    springs::draw_springs,
+                    attractor::draw_attractor,
+                    emitter::draw_emitter,
+                    center_of_mass::draw_center_of_mass,
+                    slingshot::draw_game,
+                    orbit_path::draw_orbit_paths,
+                    zoom_view::update_zoom_view,
+                    phase_view::update_phase_view.run_if(pause::needs_refresh),
+                )
+                    .chain(),
+                timing::end_phase("visuals"),
+                // UI
+                (
+                    update_ui_texts,
+                    structure::update_structure_text,
+                    binaries::update_binary_text,
+                    fof::update_group_text,
+                    selection::update_selection_text,
+                    console::update_console_panel,
+                    colormap::update_legend,
+                    realtime::update_real_time_text,
+                    slingshot::update_game_text,
+                    tutorial::update_tutorial_text,
+                    poincare::update_poincare_panel,
+                    lyapunov::update_lyapunov_text,
+                    force_law::update_force_law_text,
+                    reversal::update_reversal_text,
+                    scale_bar::update_scale_bar,
+                    speed_histogram::update_speed_histogram_panel,
+                    radial_profile::update_profile_panel,
+                    boundness::update_bound_text,
+                    core_collapse::show_collapse_alert,
+                    // Placement, once the overlays know their window
+                    (
+                        analysis_window::route_analysis_overlays,
+                        hud::apply_hud_layout,
+                    )
+                        .chain(),
+                ),
+                timing::end_phase("ui"),
+            )
+                .chain()
+                .run_if(not(in_state(menu::AppState::MainMenu))),
+        )
         .run();
 }
 
-fn init_bodies() -> Bodies {
-    let mut rng = StdRng::from_entropy();
-    let mut data = vec![BodyState::new(); NUM_BODIES];
+/// Logger for whatever runs outside the app
+fn console_logger() -> impl bevy::utils::tracing::Subscriber + Send + Sync {
+    bevy::log::tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .finish()
+}
 
-    for i in 0..NUM_BODIES {
-        let r: f32 = rng.sample(Standard);
-        data[i].mass = r * (MAX_MASS - MIN_MASS) + MIN_MASS;
+/// Bodies drawn from one jumped stream of the generator. Fixed, so the
+/// draws don't depend on how many threads share the work.
+const INIT_CHUNK: usize = 4096;
 
-        let r: f32 = rng.sample(Standard);
-        data[i].x = r * (MAX_X - MIN_X) + MIN_X;
+fn init_bodies(count: usize, rng: &mut Xoshiro256PlusPlus) -> Bodies {
+    let mut data = vec![BodyState::new(); count];
+    let streams: Vec<Xoshiro256PlusPlus> = (0..count.div_ceil(INIT_CHUNK))
+        .map(|_| rng.split())
+        .collect();
 
-        let r: f32 = rng.sample(Standard);
-        data[i].y = r * (MAX_Y - MIN_Y) + MIN_Y;
+    ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+        for (chunk, mut rng) in data.chunks_mut(INIT_CHUNK).zip(streams) {
+            scope.spawn(async move {
+                for b in chunk.iter_mut() {
+                    let r: f32 = rng.sample(Standard);
+                    b.mass = r * (MAX_MASS - MIN_MASS) + MIN_MASS;
 
-        let mut r: f32 = rng.sample(Standard);
-        data[i].vx = r * (MAX_V - MIN_V) + MIN_V;
-        let flip: f32 = rng.sample(Standard);
-        if flip < 0.5 {
-            data[i].vx = -data[i].vx;
-        }
+                    let r: f32 = rng.sample(Standard);
+                    b.x = r * (MAX_X - MIN_X) + MIN_X;
 
-        r = rng.sample(Standard);
-        data[i].vy = r * (MAX_V - MIN_V) + MIN_V;
-        let flip: f32 = rng.sample(Standard);
-        if flip < 0.5 {
-            data[i].vy = -data[i].vy;
+                    let r: f32 = rng.sample(Standard);
+                    b.y = r * (MAX_Y - MIN_Y) + MIN_Y;
+
+                    let mut r: f32 = rng.sample(Standard);
+                    b.vx = r * (MAX_V - MIN_V) + MIN_V;
+                    let flip: f32 = rng.sample(Standard);
+                    if flip < 0.5 {
+                        b.vx = -b.vx;
+                    }
+
+                    r = rng.sample(Standard);
+                    b.vy = r * (MAX_V - MIN_V) + MIN_V;
+                    let flip: f32 = rng.sample(Standard);
+                    if flip < 0.5 {
+                        b.vy = -b.vy;
+                    }
+
+                    b.x_prev = b.x;
+                    b.y_prev = b.y;
+                }
+            });
         }
-    }
+    });
 
     Bodies {
         data,
+        next: Vec::new(),
         elapsed_time: 0.0,
+        step: 0,
         kinetic_energy: 0.0,
         potential_energy: 0.0,
     }
@@ -144,24 +821,18 @@ fn init_bodies() -> Bodies {
 
 fn setup(mut commands: Commands, bodies: Res<Bodies>, asset_server: Res<AssetServer>) {
     // Camera
-    commands.spawn(Camera2dBundle::default());
-
-    // Tiny white sprites as particles
-    for i in 0..NUM_BODIES {
-        commands.spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    color: Color::WHITE,
-                    custom_size: Some(Vec2::splat(2.0)),
-                    ..Default::default()
-                },
-                transform: Transform::from_translation(Vec3::new(0., 0., 0.)),
+    // The starfield camera clears the frame underneath this one
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                clear_color: ClearColorConfig::None,
                 ..Default::default()
             },
-            BodyVisual { index: i },
-            Name::new(format!("Body {i}")),
-        ));
-    }
+            ..Default::default()
+        },
+        MainCamera,
+        IsDefaultUiCamera,
+    ));
 
     // UI Text
     let font = asset_server.load("fonts/FiraSans-Bold.ttf");
@@ -181,6 +852,7 @@ fn setup(mut commands: Commands, bodies: Res<Bodies>, asset_server: Res<AssetSer
                 ..Default::default()
             }),
         UiElapsed,
+        HudItem(Overlay::Energy),
     ));
 
     commands.spawn((
@@ -193,10 +865,11 @@ fn setup(mut commands: Commands, bodies: Res<Bodies>, asset_server: Res<AssetSer
                 ..Default::default()
             }),
         UiKe,
+        HudItem(Overlay::Energy),
     ));
 
     commands.spawn((
-        TextBundle::from_section("sum of potential energy: 0.00E+00 J", style)
+        TextBundle::from_section("sum of potential energy: 0.00E+00 J", style.clone())
             .with_text_justify(JustifyText::Left)
             .with_style(Style {
                 position_type: PositionType::Absolute,
@@ -205,150 +878,247 @@ fn setup(mut commands: Commands, bodies: Res<Bodies>, asset_server: Res<AssetSer
                 ..Default::default()
             }),
         UiPe,
+        HudItem(Overlay::Energy),
     ));
 
-    info!("Initialized {} bodies", bodies.data.len());
-}
-
-/// Single-frame Leapfrog: Kick (v^{n+1/2}), Drift (x^{n+1}), Accel, Kick (v^{n+1})
-fn leapfrog_step(mut bodies: ResMut<Bodies>) {
-    let n = bodies.data.len();
-
-    // Kick: v^{n+1/2} = v^n + a^n * dt/2
-    for b in bodies.data.iter_mut() {
-        b.vx_half = b.vx + b.ax * D_TIME_HALF;
-        b.vy_half = b.vy + b.ay * D_TIME_HALF;
-    }
+    commands.spawn((
+        TextBundle::from_section(
+            "real-time factor: 0.00E+00",
+            TextStyle {
+                font_size: 16.0,
+                ..style.clone()
+            },
+        )
+        .with_text_justify(JustifyText::Left)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            top: Val::Px(110.0),
+            ..Default::default()
+        }),
+        UiRealTime,
+        HudItem(Overlay::Energy),
+    ));
 
-    // Drift: x^{n+1} = x^n + v^{n+1/2} * dt
-    for b in bodies.data.iter_mut() {
-        b.x_new = b.x + b.vx_half * D_TIME;
-        b.y_new = b.y + b.vy_half * D_TIME;
-    }
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                ..style.clone()
+            },
+        )
+        .with_text_justify(JustifyText::Left)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            bottom: Val::Px(20.0),
+            ..Default::default()
+        }),
+        UiStructure,
+        HudItem(Overlay::Structure),
+    ));
 
-    // Compute a^{n+1} at the drifted positions (O(N^2))
-    for i in 0..n {
-        bodies.data[i].ax_new = 0.0;
-        bodies.data[i].ay_new = 0.0;
-    }
-    for i in 0..n {
-        for j in 0..n {
-            if i == j {
-                continue;
-            }
-            let dx = bodies.data[j].x_new - bodies.data[i].x_new;
-            let dy = bodies.data[j].y_new - bodies.data[i].y_new;
-            let r2 = dx * dx + dy * dy;
-
-            // Ignore very far interactions (>= 1 ly), like your Macroquad version
-            let r = r2.sqrt();
-            if r > A_RIGHT_YEAR {
-                continue;
-            }
-
-            // Softening (optional) could go here to avoid singularities; omitted to match original.
-            let a_mag = GRAVITATION * bodies.data[j].mass / r2;
-            let ax = a_mag * dx / r;
-            let ay = a_mag * dy / r;
-            bodies.data[i].ax_new += ax;
-            bodies.data[i].ay_new += ay;
-        }
-    }
+    commands.spawn((
+        TextBundle::from_section(
+            "bound pairs: 0",
+            TextStyle {
+                font_size: 16.0,
+                ..style.clone()
+            },
+        )
+        .with_text_justify(JustifyText::Left)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            top: Val::Px(20.0),
+            ..Default::default()
+        }),
+        UiBinaries,
+        HudItem(Overlay::Diagnostics),
+    ));
 
-    // Kick: v^{n+1} = v^{n+1/2} + a^{n+1} * dt/2
-    for b in bodies.data.iter_mut() {
-        b.vx_new = b.vx_half + b.ax_new * D_TIME_HALF;
-        b.vy_new = b.vy_half + b.ay_new * D_TIME_HALF;
-    }
+    commands.spawn((
+        TextBundle::from_section(
+            "FoF groups: 0",
+            TextStyle {
+                font_size: 16.0,
+                ..style.clone()
+            },
+        )
+        .with_text_justify(JustifyText::Left)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            top: Val::Px(150.0),
+            ..Default::default()
+        }),
+        UiGroups,
+        HudItem(Overlay::Diagnostics),
+    ));
 
-    // Advance state (k+1 → k)
-    for b in bodies.data.iter_mut() {
-        b.x = b.x_new;
-        b.y = b.y_new;
-        b.vx = b.vx_new;
-        b.vy = b.vy_new;
-        b.ax = b.ax_new;
-        b.ay = b.ay_new;
-    }
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                ..style.clone()
+            },
+        )
+        .with_text_justify(JustifyText::Left)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            top: Val::Px(180.0),
+            ..Default::default()
+        }),
+        UiLyapunov,
+        HudItem(Overlay::Diagnostics),
+    ));
 
-    // Energies
-    // KE = 1/2 m v^2
-    let mut ke_sum: f64 = 0.0;
-    for b in bodies.data.iter() {
-        let v2 = (b.vx * b.vx + b.vy * b.vy) as f64;
-        ke_sum += 0.5 * b.mass as f64 * v2;
-    }
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                ..style
+            },
+        )
+        .with_text_justify(JustifyText::Left)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            bottom: Val::Px(20.0),
+            ..Default::default()
+        }),
+        UiSelection,
+        HudItem(Overlay::Selection),
+    ));
 
-    // PE = -G \sum_{i<j} m_i m_j / r_ij  (one pass with i<j to avoid double counting)
-    let mut pe_sum: f64 = 0.0;
-    for i in 0..n {
-        for j in (i + 1)..n {
-            let dx = (bodies.data[j].x - bodies.data[i].x) as f64;
-            let dy = (bodies.data[j].y - bodies.data[i].y) as f64;
-            let r = (dx * dx + dy * dy).sqrt();
-            if r == 0.0 {
-                continue;
-            }
-            pe_sum +=
-                -1.0 * GRAVITATION as f64 * bodies.data[i].mass as f64 * bodies.data[j].mass as f64
-                    / r;
-        }
-    }
+    info!("Initialized {} bodies", bodies.data.len());
+}
 
-    bodies.kinetic_energy = ke_sum;
-    bodies.potential_energy = pe_sum;
-    bodies.elapsed_time += D_TIME;
+/// Space (code units) → world units scale factor for the current window
+fn world_scale(window: &Window) -> Vec2 {
+    Vec2::new(
+        window.width() / 2.0 / MAX_X / ASPECT_RATIO,
+        window.height() / 2.0 / MAX_Y / ASPECT_RATIO,
+    )
 }
 
+/// Interpolate the display positions and write every body into the point mesh
 fn update_visuals(
-    mut q: Query<(&BodyVisual, &mut Transform)>,
     mut bodies: ResMut<Bodies>,
+    colors: Res<coloring::BodyColors>,
+    culling: Res<ViewCulling>,
+    fixed_time: Res<Time<Fixed>>,
     win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    zoom_q: Query<(&Camera, &Transform, &OrthographicProjection), With<zoom_view::ZoomCamera>>,
+    points_q: Query<&Mesh2d, With<point_sprites::BodyPoints>>,
+    mut meshes: ResMut<Assets<Mesh>>,
 ) {
+    let _span = info_span!("update_visuals", bodies = bodies.data.len()).entered();
     let Ok(window) = win_q.get_single() else {
         return;
     };
+    let Ok((cam_tf, proj)) = cam_q.get_single() else {
+        return;
+    };
+    let Some(mesh) = points_q
+        .get_single()
+        .ok()
+        .and_then(|h| meshes.get_mut(&h.0))
+    else {
+        return;
+    };
     // Convert space coords → world coords (similar to Macroquad screen mapping)
-    let disp_x_conv = window.width() / 2.0 / MAX_X / ASPECT_RATIO;
-    let disp_y_conv = window.height() / 2.0 / MAX_Y / ASPECT_RATIO;
+    let Vec2 {
+        x: disp_x_conv,
+        y: disp_y_conv,
+    } = world_scale(window);
     let half_x = window.width() / 2.0;
     let half_y = window.height() / 2.0;
 
-    // Fill disp_x/disp_y fields and move visuals
-    for (bv, mut tf) in q.iter_mut() {
-        let b = &mut bodies.data[bv.index];
-        b.disp_x = b.x * disp_x_conv + half_x;
-        b.disp_y = b.y * disp_y_conv + half_y;
-        tf.translation.x = b.disp_x - half_x; // center at (0,0) in world
-        tf.translation.y = b.disp_y - half_y;
-        tf.translation.z = 0.0;
-    }
+    // How far the frame is between the last and the next physics step (0..1)
+    let alpha = fixed_time.overstep_fraction();
+
+    // Visible world rectangle of the camera, widened by the largest point
+    // radius so a point straddling the edge is still drawn
+    let cam = cam_tf.translation.truncate();
+    let view_half = Vec2::new(half_x, half_y) * proj.scale;
+    let pad = mass_evolution::MAX_SPRITE_SIZE * proj.scale.max(1.0);
+    let cull_half = view_half * (1.0 + culling.margin) + pad;
+    let lod_half = view_half * culling.lod_distance;
+    // The zoom inset can look away from the main view, at the selected body
+    let inset = zoom_q
+        .get_single()
+        .ok()
+        .filter(|(camera, ..)| camera.is_active)
+        .map(|(_, tf, proj)| (tf.translation.truncate(), proj.area.half_size() + pad));
+    let inside = |p: Vec2, (center, half): (Vec2, Vec2)| {
+        let offset = (p - center).abs();
+        offset.x <= half.x && offset.y <= half.y
+    };
+
+    // Fill disp_x/disp_y fields for every body (other views read them) and
+    // emit points only for the visible ones
+    let points = bodies.data.iter_mut().enumerate().filter_map(|(i, b)| {
+        // LOD: far off-screen bodies skip interpolation
+        let now = Vec2::new(b.x * disp_x_conv, b.y * disp_y_conv);
+        let pos = if inside(now, (cam, lod_half)) {
+            let x = b.x_prev + (b.x - b.x_prev) * alpha;
+            let y = b.y_prev + (b.y - b.y_prev) * alpha;
+            Vec2::new(x * disp_x_conv, y * disp_y_conv)
+        } else {
+            now
+        };
+        b.disp_x = pos.x + half_x;
+        b.disp_y = pos.y + half_y;
+
+        let visible = inside(pos, (cam, cull_half)) || inset.is_some_and(|v| inside(pos, v));
+        visible.then(|| point_sprites::Point {
+            position: pos, // center at (0,0) in world
+            size: mass_evolution::sprite_size(b.mass),
+            color: colors.0.get(i).copied().unwrap_or([1.0; 4]),
+        })
+    });
+    point_sprites::write_points(mesh, points);
 }
 
 fn update_ui_texts(
     bodies: Res<Bodies>,
+    lang: Res<locale::Language>,
     mut q_elapsed: Query<&mut Text, With<UiElapsed>>,
     mut q_ke: Query<&mut Text, With<UiKe>>,
     mut q_pe: Query<&mut Text, With<UiPe>>,
 ) {
-    if !bodies.is_changed() {
+    if !bodies.is_changed() && !lang.is_changed() {
         return;
     }
+    let lang = *lang;
 
-    let elapsed_year = bodies.elapsed_time / 3.154E7; // seconds → years
+    let elapsed_year = CODE.time(bodies.elapsed_time) / 3.154E7; // seconds → years
     if let Ok(mut t) = q_elapsed.get_single_mut() {
-        t.sections[0].value = format!("elapsed_year:      {:.2E} year", elapsed_year);
+        t.sections[0].value = format!(
+            "{}:      {:.2E} {}",
+            tr("elapsed_year", "경과 시간").get(lang),
+            elapsed_year,
+            tr("year", "년").get(lang)
+        );
     }
     if let Ok(mut t) = q_ke.get_single_mut() {
         t.sections[0].value = format!(
-            "sum of kinetic energy:      {:.2E} J",
-            bodies.kinetic_energy
+            "{}:      {:.2E} J",
+            tr("sum of kinetic energy", "운동 에너지 합").get(lang),
+            CODE.energy_f64(bodies.kinetic_energy)
         );
     }
     if let Ok(mut t) = q_pe.get_single_mut() {
         t.sections[0].value = format!(
-            "sum of potential energy:      {:.2E} J",
-            bodies.potential_energy
+            "{}:      {:.2E} J",
+            tr("sum of potential energy", "위치 에너지 합").get(lang),
+            CODE.energy_f64(bodies.potential_energy)
         );
     }
 }
@@ -0,0 +1,79 @@
+//! Per-body mass evolution: secular mass loss and transfer inside tight binaries.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::binaries::find_bound_pairs;
+use crate::{BodyState, MAX_MASS};
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct MassEvolutionConfig {
+    /// e-folding time of secular mass loss (s); 0 disables it
+    pub loss_timescale: f32,
+    /// Bodies never drop below this mass (kg)
+    pub min_mass: f32,
+    /// Fraction of the donor's mass moved to its companion per second
+    pub transfer_rate: f32,
+    /// Bound pairs closer than this exchange mass (m)
+    pub transfer_separation: f32,
+}
+
+impl Default for MassEvolutionConfig {
+    fn default() -> Self {
+        Self {
+            loss_timescale: 0.0,
+            min_mass: 1.0E15,
+            transfer_rate: 0.0,
+            transfer_separation: 5.0E12,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct MassEvolution(pub Option<MassEvolutionConfig>);
+
+impl MassEvolution {
+    /// Evolve masses over one step of length dt (applied after the velocity update)
+    pub fn apply(&self, data: &mut [BodyState], dt: f32, g: f32) {
+        let Some(cfg) = self.0 else {
+            return;
+        };
+
+        if cfg.loss_timescale > 0.0 {
+            let keep = (-dt / cfg.loss_timescale).exp();
+            for b in data.iter_mut() {
+                b.mass = (b.mass * keep).max(cfg.min_mass);
+            }
+        }
+
+        if cfg.transfer_rate > 0.0 {
+            // The lighter member of each bound pair donates to the heavier one
+            for pair in find_bound_pairs(data, cfg.transfer_separation, g) {
+                let (donor, accretor) = (pair.i, pair.j);
+                let dm = (data[donor].mass * cfg.transfer_rate * dt)
+                    .min(data[donor].mass - cfg.min_mass)
+                    .max(0.0);
+                if dm == 0.0 {
+                    continue;
+                }
+                // Transferred mass carries the donor's velocity, so momentum is conserved
+                let (vx_d, vy_d) = (data[donor].vx, data[donor].vy);
+                let a = &mut data[accretor];
+                let m_new = a.mass + dm;
+                a.vx = (a.mass * a.vx + dm * vx_d) / m_new;
+                a.vy = (a.mass * a.vy + dm * vy_d) / m_new;
+                a.mass = m_new;
+                data[donor].mass -= dm;
+            }
+        }
+    }
+}
+
+/// Largest sprite edge length (px)
+pub const MAX_SPRITE_SIZE: f32 = 8.0;
+
+/// Sprite edge length (px) for a body mass; ~2 px for a typical star
+pub fn sprite_size(mass: f32) -> f32 {
+    (2.0 * (mass / (0.5 * MAX_MASS)).cbrt()).clamp(1.0, MAX_SPRITE_SIZE)
+}
@@ -0,0 +1,291 @@
+//! Application states and the main menu shown before a run starts.
+
+use bevy::prelude::*;
+
+use rand::Rng;
+
+use crate::constants::PhysicsConstants;
+use crate::force_law::ForceLaw;
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, tr};
+use crate::lyapunov::Lyapunov;
+use crate::mass_evolution::MassEvolution;
+use crate::orbit_path::OrbitPaths;
+use crate::physics::{PendingBodies, PhysicsSettings, PhysicsTask};
+use crate::poincare::PoincareSection;
+use crate::reversal::{Rewind, TimeReversal};
+use crate::scenario::{InitialConditions, Scenario, World};
+use crate::selection::Selection;
+use crate::sim_rng::{SimRng, Xoshiro256PlusPlus};
+use crate::slingshot::SlingshotGame;
+use crate::springs::Springs;
+use crate::stochastic::StochasticKicks;
+use crate::thermostat::Thermostat;
+use crate::units::CODE;
+use crate::{Bodies, BodyState, NUM_BODIES, ic_registry};
+
+const MIN_BODY_COUNT: usize = 10;
+pub const MAX_BODY_COUNT: usize = 100_000;
+
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    Running,
+    Paused,
+}
+
+/// What the menu will launch
+#[derive(Resource)]
+pub struct MenuChoice {
+    pub scenarios: Vec<Scenario>,
+    pub selected: usize,
+    pub body_count: usize,
+}
+
+impl MenuChoice {
+    pub fn new(scenarios: Vec<Scenario>, selected: usize) -> Self {
+        let mut choice = Self {
+            scenarios,
+            selected,
+            body_count: NUM_BODIES,
+        };
+        choice.select(selected);
+        choice
+    }
+
+    /// Select a scenario and take over its recommended body count
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        if let Some(n) = self.scenarios.get(index).and_then(|s| s.bodies) {
+            self.body_count = n.clamp(MIN_BODY_COUNT, MAX_BODY_COUNT);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MenuRoot;
+
+#[derive(Component)]
+pub struct MenuText;
+
+pub fn spawn_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 22.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MenuRoot,
+        ))
+        .with_children(|root| {
+            root.spawn((
+                TextBundle::from_section("", style).with_text_justify(JustifyText::Center),
+                MenuText,
+            ));
+        });
+}
+
+pub fn despawn_menu(mut commands: Commands, q: Query<Entity, With<MenuRoot>>) {
+    for e in q.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+pub fn menu_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut choice: ResMut<MenuChoice>,
+    constants: Res<PhysicsConstants>,
+    mut rng: ResMut<SimRng>,
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let n = choice.scenarios.len().max(1);
+    if bindings.just_pressed(&keys, Action::MenuPrevious) {
+        let i = (choice.selected + n - 1) % n;
+        choice.select(i);
+    }
+    if bindings.just_pressed(&keys, Action::MenuNext) {
+        let i = (choice.selected + 1) % n;
+        choice.select(i);
+    }
+    if bindings.just_pressed(&keys, Action::MenuMoreBodies) {
+        choice.body_count = (choice.body_count * 2).min(MAX_BODY_COUNT);
+    }
+    if bindings.just_pressed(&keys, Action::MenuFewerBodies) {
+        choice.body_count = (choice.body_count / 2).max(MIN_BODY_COUNT);
+    }
+    if bindings.just_pressed(&keys, Action::MenuStart) {
+        let scenario = choice
+            .scenarios
+            .get(choice.selected)
+            .cloned()
+            .unwrap_or_default();
+        start_run(
+            &mut commands,
+            scenario,
+            choice.body_count,
+            &constants,
+            &mut rng,
+        );
+        next_state.set(AppState::Running);
+    }
+}
+
+/// Fresh bodies and per-run resources for the chosen scenario
+pub fn start_run(
+    commands: &mut Commands,
+    scenario: Scenario,
+    body_count: usize,
+    constants: &PhysicsConstants,
+    rng: &mut Xoshiro256PlusPlus,
+) {
+    info!("Starting '{}' with {body_count} bodies", scenario.name);
+    let (force_law, thermostat) = match scenario.world {
+        World::Gravitational => (scenario.force_law, None),
+        World::LennardJones { params, thermostat } => (ForceLaw::LennardJones(params), thermostat),
+    };
+    let mut bodies = match &scenario.initial {
+        InitialConditions::Empty => Bodies::default(),
+        InitialConditions::Explicit(specs) => Bodies {
+            data: specs
+                .iter()
+                .map(|s| {
+                    let mut b = BodyState::new();
+                    b.mass = s.mass;
+                    (b.x, b.y) = s.position;
+                    (b.vx, b.vy) = s.velocity;
+                    b.x_prev = b.x;
+                    b.y_prev = b.y;
+                    b
+                })
+                .collect(),
+            ..Default::default()
+        },
+        InitialConditions::Generated { generator, params } => Bodies {
+            data: ic_registry::generate(
+                generator,
+                params,
+                rng,
+                body_count,
+                constants.gravitation,
+                force_law,
+            )
+            .unwrap_or_else(|e| {
+                error!("{e}");
+                Vec::new()
+            }),
+            ..Default::default()
+        },
+    };
+    if let Some(game) = &scenario.slingshot {
+        bodies.data.extend(game.planet_bodies());
+    }
+    commands.insert_resource(bodies);
+    commands.insert_resource(PhysicsSettings {
+        dt: scenario.dt.unwrap_or(constants.dt),
+        force_law,
+        ..Default::default()
+    });
+    commands.insert_resource(StochasticKicks::new(scenario.stochastic, rng.r#gen()));
+    commands.insert_resource(MassEvolution(scenario.mass_evolution));
+    commands.insert_resource(Thermostat(thermostat));
+    commands.insert_resource(SlingshotGame::new(scenario.slingshot.clone()));
+    commands.insert_resource(PoincareSection::new(scenario.poincare));
+    commands.insert_resource(scenario);
+    commands.insert_resource(PhysicsTask::default());
+    commands.insert_resource(PendingBodies::default());
+    commands.insert_resource(TimeReversal::default());
+    commands.insert_resource(Rewind::default());
+    commands.insert_resource(Selection::default());
+    commands.insert_resource(Springs::default());
+    commands.insert_resource(OrbitPaths::default());
+    commands.insert_resource(Lyapunov::default());
+}
+
+pub fn update_menu_text(
+    choice: Res<MenuChoice>,
+    constants: Res<PhysicsConstants>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<MenuText>>,
+) {
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    if !choice.is_changed() && !constants.is_changed() && !lang.is_changed() && !t.is_added() {
+        return;
+    }
+    let lang = *lang;
+    let mut out = tr(
+        "(LeapFrog) Star motion by universal gravitation",
+        "(LeapFrog) 만유인력에 의한 별의 운동",
+    )
+    .get(lang)
+    .to_string()
+        + "\n\n";
+    for (i, s) in choice.scenarios.iter().enumerate() {
+        let marker = if i == choice.selected { ">" } else { " " };
+        out += &format!("{marker} {}\n", s.name);
+    }
+    if let Some(s) = choice.scenarios.get(choice.selected) {
+        out += &format!(
+            "\n{}\n{}: {}   dt: {:.2E} s ({})\n",
+            s.description,
+            tr("Bodies", "천체 수").get(lang),
+            choice.body_count,
+            CODE.time(s.dt.unwrap_or(constants.dt)),
+            tr("recommended", "권장").get(lang)
+        );
+    }
+    out += "\n";
+    out += tr(
+        "Left/Right: scenario   Up/Down: body count   Enter: start   T: tutorial",
+        "좌/우: 시나리오   위/아래: 천체 수   Enter: 시작   T: 튜토리얼",
+    )
+    .get(lang);
+    t.sections[0].value = out;
+}
+
+/// Space pauses / resumes the physics
+pub fn toggle_pause(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !bindings.just_pressed(&keys, Action::TogglePause) {
+        return;
+    }
+    match state.get() {
+        AppState::Running => next_state.set(AppState::Paused),
+        AppState::Paused => next_state.set(AppState::Running),
+        AppState::MainMenu => {}
+    }
+}
+
+/// Esc ends the run: drop the bodies and go back to the menu
+pub fn return_to_menu(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !bindings.just_pressed(&keys, Action::BackToMenu) {
+        return;
+    }
+    commands.insert_resource(Bodies::default());
+    commands.insert_resource(PhysicsTask::default());
+    next_state.set(AppState::MainMenu);
+}
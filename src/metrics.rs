@@ -0,0 +1,179 @@
+//! Prometheus endpoint for long unattended runs. Built with
+//! `--features metrics`, `--metrics <addr>` (e.g. `0.0.0.0:9184`) serves
+//! `GET /metrics` in the Prometheus text format from a background thread:
+//! steps per second, body count, relative energy drift since the first step
+//! of the current run, and frame time. Values are refreshed once a second.
+
+use bevy::prelude::*;
+
+pub struct MetricsPlugin {
+    /// Address to listen on, `None` for no endpoint
+    pub addr: Option<String>,
+}
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(addr) = &self.addr else {
+            return;
+        };
+        #[cfg(feature = "metrics")]
+        match server::Metrics::serve(addr) {
+            Ok(metrics) => {
+                info!("Serving metrics on http://{addr}/metrics");
+                app.insert_resource(metrics)
+                    .add_systems(Update, server::update_metrics);
+            }
+            Err(e) => error!("failed to serve metrics on {addr}: {e}"),
+        }
+        #[cfg(not(feature = "metrics"))]
+        {
+            let _ = app;
+            warn!("ignoring --metrics {addr}: built without the metrics feature");
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+mod server {
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use bevy::prelude::*;
+
+    use crate::Bodies;
+
+    /// Seconds of wall-clock time averaged into each published sample
+    const PUBLISH_INTERVAL: f32 = 1.0;
+
+    #[derive(Clone, Copy, Default)]
+    struct Sample {
+        steps_per_second: f64,
+        bodies: usize,
+        energy_drift: f64,
+        frame_time: f64,
+    }
+
+    #[derive(Resource)]
+    pub struct Metrics {
+        /// Latest sample, read by the server thread
+        shared: Arc<Mutex<Sample>>,
+        initial_energy: Option<f64>,
+        last_step: Option<u64>,
+        /// Steps, frames and seconds since the last sample
+        window: (u64, u32, f32),
+    }
+
+    impl Metrics {
+        /// Bind `addr` and answer scrapes on a background thread
+        pub fn serve(addr: &str) -> io::Result<Self> {
+            let listener = TcpListener::bind(addr)?;
+            let shared = Arc::new(Mutex::new(Sample::default()));
+            let sample = shared.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Err(e) = respond(stream, &sample) {
+                        warn!("metrics request failed: {e}");
+                    }
+                }
+            });
+            Ok(Self {
+                shared,
+                initial_energy: None,
+                last_step: None,
+                window: (0, 0, 0.0),
+            })
+        }
+    }
+
+    pub fn update_metrics(
+        time: Res<Time<Real>>,
+        bodies: Res<Bodies>,
+        mut metrics: ResMut<Metrics>,
+    ) {
+        let step = bodies.step;
+        // A step count going back means a new run
+        let advanced = match metrics.last_step {
+            Some(last) if step >= last => step - last,
+            _ => {
+                metrics.initial_energy = None;
+                0
+            }
+        };
+        metrics.last_step = Some(step);
+        let total = bodies.kinetic_energy + bodies.potential_energy;
+        // Energies exist once the first step of a run has finished
+        if step > 0 && metrics.initial_energy.is_none() {
+            metrics.initial_energy = Some(total);
+        }
+
+        let (steps, frames, seconds) = &mut metrics.window;
+        *steps += advanced;
+        *frames += 1;
+        *seconds += time.delta_secs();
+        if *seconds < PUBLISH_INTERVAL {
+            return;
+        }
+        let (steps, frames, seconds) = std::mem::take(&mut metrics.window);
+        let drift = match metrics.initial_energy {
+            Some(e0) if e0 != 0.0 => (total - e0) / e0.abs(),
+            _ => 0.0,
+        };
+        *metrics.shared.lock().unwrap() = Sample {
+            steps_per_second: steps as f64 / seconds as f64,
+            bodies: bodies.data.len(),
+            energy_drift: drift,
+            frame_time: seconds as f64 / frames.max(1) as f64,
+        };
+    }
+
+    fn respond(mut stream: TcpStream, sample: &Mutex<Sample>) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut request = String::new();
+        BufReader::new(&stream).read_line(&mut request)?;
+        let path = request.split_whitespace().nth(1).unwrap_or("");
+        let (status, body) = if path == "/metrics" {
+            ("200 OK", render(&sample.lock().unwrap()))
+        } else {
+            ("404 Not Found", "not found\n".to_string())
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\n\
+             Content-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    /// Prometheus text exposition, one gauge per value
+    fn render(s: &Sample) -> String {
+        let gauges = [
+            (
+                "nbody_steps_per_second",
+                "Physics steps completed per second of wall-clock time",
+                s.steps_per_second,
+            ),
+            ("nbody_bodies", "Bodies simulated", s.bodies as f64),
+            (
+                "nbody_energy_drift",
+                "Relative change of the total energy since the first step of the run",
+                s.energy_drift,
+            ),
+            (
+                "nbody_frame_time_seconds",
+                "Mean frame time over the last sample",
+                s.frame_time,
+            ),
+        ];
+        gauges
+            .iter()
+            .map(|(name, help, value)| {
+                format!("# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n")
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,56 @@
+//! Optional removal of the net momentum that cutoff asymmetry and rounding
+//! slowly feed into the system, so the view stays centered on long runs.
+
+use bevy::prelude::*;
+
+use crate::BodyState;
+use crate::summation::CompensatedSum;
+use crate::units::CODE;
+
+#[derive(Resource, Default)]
+pub struct MomentumCorrection {
+    /// Correct every this many steps, `None` for off
+    pub interval: Option<u64>,
+    /// Magnitude of all momentum removed so far
+    pub removed: f64,
+}
+
+impl MomentumCorrection {
+    /// Subtract the center-of-mass velocity from the free bodies when due.
+    /// Pinned bodies carry no momentum, so the free ones absorb the whole
+    /// correction.
+    pub fn apply(&mut self, data: &mut [BodyState], step: u64) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        if step % interval.max(1) != 0 {
+            return;
+        }
+        let mut px = CompensatedSum::default();
+        let mut py = CompensatedSum::default();
+        let mut mass = CompensatedSum::default();
+        for b in data.iter().filter(|b| !b.fixed) {
+            px += b.mass as f64 * b.vx as f64;
+            py += b.mass as f64 * b.vy as f64;
+            mass += b.mass as f64;
+        }
+        let (px, py, m) = (px.value(), py.value(), mass.value());
+        if m <= 0.0 {
+            return;
+        }
+        let (dvx, dvy) = (px / m, py / m);
+        for b in data.iter_mut().filter(|b| !b.fixed) {
+            b.vx -= dvx as f32;
+            b.vy -= dvy as f32;
+        }
+        let p = px.hypot(py);
+        self.removed += p;
+        let velocity = CODE.length / CODE.time;
+        info!(
+            "Removed net momentum {:.3E} kg m/s (Δv = {:.3E} m/s), {:.3E} in total",
+            p * CODE.mass * velocity,
+            dvx.hypot(dvy) * velocity,
+            self.removed * CODE.mass * velocity
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Periodic reordering of the bodies along a Morton (Z-order) curve, so
+//! bodies close in space sit close in memory for the pair sums. Everything
+//! that refers to bodies by index is remapped with the permutation.
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::BodyState;
+use crate::binaries::BinaryScan;
+use crate::body_history::BodyHistory;
+use crate::boundness::Boundness;
+use crate::coloring::BodyColors;
+use crate::density::DensityField;
+use crate::fof::FofGroups;
+use crate::lyapunov::Lyapunov;
+use crate::orbit_path::OrbitPaths;
+use crate::poincare::PoincareSection;
+use crate::regularization::Regularization;
+use crate::selection::Selection;
+use crate::slingshot::SlingshotGame;
+use crate::springs::Springs;
+use crate::stochastic::StochasticKicks;
+
+/// Steps between reorderings by default
+const DEFAULT_INTERVAL: u64 = 200;
+
+#[derive(Resource)]
+pub struct MortonOrder {
+    /// Reorder every this many steps, `None` for off
+    pub interval: Option<u64>,
+    /// (key, index) of every body, sorted
+    keys: Vec<(u32, usize)>,
+    /// The bodies in the new order, swapped with the old ones
+    sorted: Vec<BodyState>,
+    /// Permutation of the last reordering, shared with its event and
+    /// reused once the event is gone
+    new_index: Arc<Vec<usize>>,
+}
+
+impl Default for MortonOrder {
+    fn default() -> Self {
+        Self {
+            interval: Some(DEFAULT_INTERVAL),
+            keys: Vec::new(),
+            sorted: Vec::new(),
+            new_index: Arc::default(),
+        }
+    }
+}
+
+/// The bodies were reordered: body `old` is now at `new_index[old]`
+#[derive(Event)]
+pub struct BodiesReordered {
+    pub new_index: Arc<Vec<usize>>,
+}
+
+impl BodiesReordered {
+    pub fn remap(&self, old: usize) -> usize {
+        self.new_index.get(old).copied().unwrap_or(old)
+    }
+
+    /// Move per-body values into the new order
+    pub fn permute<T: Clone>(&self, values: &mut Vec<T>) {
+        if values.len() != self.new_index.len() {
+            return;
+        }
+        let old = values.clone();
+        for (i, v) in old.into_iter().enumerate() {
+            values[self.new_index[i]] = v;
+        }
+    }
+}
+
+impl MortonOrder {
+    /// Sort `data` along the Z curve when due, returning the permutation
+    pub fn apply(&mut self, data: &mut Vec<BodyState>, step: u64) -> Option<BodiesReordered> {
+        let interval = self.interval?;
+        if data.len() < 2 || step % interval.max(1) != 0 {
+            return None;
+        }
+        let _span = info_span!("morton_sort", bodies = data.len()).entered();
+        let (mut min, mut max) = (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN));
+        for b in data.iter() {
+            min = min.min(Vec2::new(b.x, b.y));
+            max = max.max(Vec2::new(b.x, b.y));
+        }
+        let scale = u16::MAX as f32 / (max - min).max(Vec2::splat(f32::MIN_POSITIVE));
+        let key = |b: &BodyState| {
+            let q = ((Vec2::new(b.x, b.y) - min) * scale).min(Vec2::splat(u16::MAX as f32));
+            spread_bits(q.x as u16) | (spread_bits(q.y as u16) << 1)
+        };
+
+        // Ties keep their order, as the index is part of the key
+        self.keys.clear();
+        self.keys
+            .extend(data.iter().enumerate().map(|(i, b)| (key(b), i)));
+        self.keys.sort_unstable();
+        if self.keys.iter().enumerate().all(|(k, &(_, i))| k == i) {
+            return None;
+        }
+        // Copies only while an event still holds the last permutation
+        let new_index = Arc::make_mut(&mut self.new_index);
+        new_index.clear();
+        new_index.resize(data.len(), 0);
+        for (new, &(_, old)) in self.keys.iter().enumerate() {
+            new_index[old] = new;
+        }
+        self.sorted.clear();
+        self.sorted.extend(self.keys.iter().map(|&(_, i)| data[i]));
+        std::mem::swap(data, &mut self.sorted);
+        Some(BodiesReordered {
+            new_index: Arc::clone(&self.new_index),
+        })
+    }
+}
+
+/// Interleave the bits of `v` with zeros: b15…b0 → 0b15…0b0
+fn spread_bits(v: u16) -> u32 {
+    let mut x = v as u32;
+    x = (x | (x << 8)) & 0x00FF_00FF;
+    x = (x | (x << 4)) & 0x0F0F_0F0F;
+    x = (x | (x << 2)) & 0x3333_3333;
+    (x | (x << 1)) & 0x5555_5555
+}
+
+/// Carry every stored body index over to the new order
+pub fn remap_body_indices(
+    mut events: EventReader<BodiesReordered>,
+    mut selection: ResMut<Selection>,
+    mut springs: ResMut<Springs>,
+    mut paths: ResMut<OrbitPaths>,
+    mut lyapunov: ResMut<Lyapunov>,
+    mut regularization: ResMut<Regularization>,
+    mut game: ResMut<SlingshotGame>,
+    mut scan: ResMut<BinaryScan>,
+    mut fof: ResMut<FofGroups>,
+    mut density: ResMut<DensityField>,
+    mut section: ResMut<PoincareSection>,
+    mut boundness: ResMut<Boundness>,
+    mut history: ResMut<BodyHistory>,
+    mut kicks: ResMut<StochasticKicks>,
+    mut colors: ResMut<BodyColors>,
+) {
+    for ev in events.read() {
+        selection.0 = selection.0.map(|i| ev.remap(i));
+        springs.remap(ev);
+        paths.remap(ev);
+        lyapunov.target = lyapunov.target.map(|i| ev.remap(i));
+        regularization.current = regularization
+            .current
+            .map(|(i, j)| (ev.remap(i), ev.remap(j)));
+        game.remap(ev);
+        for p in scan.pairs.iter_mut() {
+            (p.i, p.j) = (ev.remap(p.i), ev.remap(p.j));
+        }
+        ev.permute(&mut fof.group_of);
+        ev.permute(&mut density.log_density);
+        ev.permute(&mut boundness.bound);
+        ev.permute(&mut colors.0);
+        kicks.remap(ev);
+        history.body = history.body.map(|i| ev.remap(i));
+        section.remap(ev);
+    }
+}
@@ -0,0 +1,197 @@
+//! Uniform hash grid that restricts the direct sum to pairs within the
+//! interaction cutoff, so a short cutoff actually saves work. The grid is
+//! rebuilt in place each step, reusing its storage.
+//!
+//! The same grid, binned once per step with a cell size set by the body
+//! count, backs the `SpatialIndex` resource that the systems looking for
+//! bodies near a point (selection, collisions, density) query instead of
+//! scanning every body.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::Bodies;
+use crate::morton::BodiesReordered;
+
+/// Cost of visiting one cell, in units of one pair evaluation
+const CELL_VISIT_COST: f32 = 2.0;
+/// Largest number of cells per cutoff length tried by the auto-tuning
+const MAX_SUBDIVISION: i64 = 4;
+/// Average number of bodies per cell the spatial index aims for
+const BODIES_PER_CELL: f32 = 4.0;
+
+#[derive(Default)]
+pub struct CellGrid {
+    cell: f32,
+    /// Neighbor cells searched in each direction
+    reach: i64,
+    /// Body indices grouped by cell
+    order: Vec<usize>,
+    /// Range of `order` holding each occupied cell
+    cells: HashMap<(i64, i64), (usize, usize)>,
+    /// (cell, body) pairs, sorted to group `order`
+    keyed: Vec<((i64, i64), usize)>,
+}
+
+impl CellGrid {
+    /// Bin the snapshot into cells of cutoff / k, with k tuned from the mean
+    /// density. `false` (and the grid unusable) when the cutoff covers the
+    /// whole distribution anyway.
+    pub fn rebuild(&mut self, snap: &[[f32; 4]], cutoff: f32) -> bool {
+        if snap.len() < 2 || !(cutoff > 0.0 && cutoff.is_finite()) {
+            return false;
+        }
+        let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+        for p in snap {
+            for k in 0..2 {
+                min[k] = min[k].min(p[k]);
+                max[k] = max[k].max(p[k]);
+            }
+        }
+        let (w, h) = (max[0] - min[0], max[1] - min[1]);
+        if cutoff >= w.max(h) {
+            return false;
+        }
+
+        // Bodies inside one cutoff-sized square, on average
+        let per_square = snap.len() as f32 * cutoff * cutoff / (w.max(cutoff) * h.max(cutoff));
+        // Finer cells test fewer pairs ((2k+1)/k)² but visit more cells (2k+1)²
+        let cost = |k: i64| {
+            let side = (2 * k + 1) as f32;
+            per_square * (side / k as f32).powi(2) + CELL_VISIT_COST * side * side
+        };
+        let reach = (1..=MAX_SUBDIVISION)
+            .min_by(|&a, &b| cost(a).total_cmp(&cost(b)))
+            .unwrap_or(1);
+
+        self.bin(
+            snap.iter().map(|p| (p[0], p[1])),
+            cutoff / reach as f32,
+            reach,
+        );
+        true
+    }
+
+    /// Bin the snapshot into cells of a fixed size, for `neighbors` out to
+    /// one cell
+    pub fn rebuild_fixed(&mut self, snap: &[[f32; 4]], cell: f32) {
+        self.bin(snap.iter().map(|p| (p[0], p[1])), cell, 1);
+    }
+
+    /// Bin points into cells of a fixed size, for `candidates`
+    pub fn rebuild_with_cell(&mut self, points: &[[f32; 2]], cell: f32) {
+        self.bin(points.iter().map(|p| (p[0], p[1])), cell, 1);
+    }
+
+    fn bin(&mut self, points: impl Iterator<Item = (f32, f32)>, cell: f32, reach: i64) {
+        self.reach = reach;
+        self.cell = cell;
+        self.keyed.clear();
+        self.keyed.extend(
+            points
+                .enumerate()
+                .map(|(i, (x, y))| (cell_of(x, y, cell), i)),
+        );
+        self.keyed.sort_unstable();
+        self.order.clear();
+        self.cells.clear();
+        // At most one cell per point, so later rebuilds never grow the map
+        self.cells.reserve(self.keyed.len());
+        for (k, &(key, i)) in self.keyed.iter().enumerate() {
+            self.order.push(i);
+            self.cells.entry(key).or_insert((k, k)).1 = k + 1;
+        }
+    }
+
+    /// Every body in the cells around `p` (including `p` itself, if it is one)
+    pub fn neighbors(&self, p: &[f32; 4]) -> impl Iterator<Item = usize> + '_ {
+        let (cx, cy) = cell_of(p[0], p[1], self.cell);
+        let reach = self.reach;
+        (cy - reach..=cy + reach)
+            .flat_map(move |ny| (cx - reach..=cx + reach).map(move |nx| (nx, ny)))
+            .filter_map(|key| self.cells.get(&key))
+            .flat_map(|&(start, end)| &self.order[start..end])
+            .copied()
+    }
+
+    /// Every body in the cells the square of half-width `r` around (x, y)
+    /// touches. A square covering more cells than are occupied reads them
+    /// all instead.
+    pub fn candidates(&self, x: f32, y: f32, r: f32) -> impl Iterator<Item = usize> + '_ {
+        let lo = cell_of(x - r, y - r, self.cell);
+        let hi = cell_of(x + r, y + r, self.cell);
+        let visits = (hi.0 - lo.0 + 1).saturating_mul(hi.1 - lo.1 + 1);
+        let wide = self.order.is_empty() || visits > self.cells.len() as i64;
+        let (lo, hi) = if wide { ((0, 0), (-1, -1)) } else { (lo, hi) };
+        let all: &[usize] = if wide { &self.order } else { &[] };
+        (lo.1..=hi.1)
+            .flat_map(move |ny| (lo.0..=hi.0).map(move |nx| (nx, ny)))
+            .filter_map(|key| self.cells.get(&key))
+            .flat_map(|&(start, end)| &self.order[start..end])
+            .chain(all)
+            .copied()
+    }
+}
+
+fn cell_of(x: f32, y: f32, cell: f32) -> (i64, i64) {
+    ((x / cell).floor() as i64, (y / cell).floor() as i64)
+}
+
+/// Positions of the current step binned on a grid, for radius queries
+#[derive(Resource, Default)]
+pub struct SpatialIndex {
+    grid: CellGrid,
+    positions: Vec<[f32; 2]>,
+    /// Step and body count the index was built for
+    built: Option<(u64, usize)>,
+}
+
+impl SpatialIndex {
+    /// Indices of the bodies within `r` of `pos` (sim coordinates, m)
+    pub fn neighbors_within(&self, pos: Vec2, r: f32) -> impl Iterator<Item = usize> + '_ {
+        let r2 = r * r;
+        self.grid
+            .candidates(pos.x, pos.y, r)
+            .filter(move |&i| Vec2::from(self.positions[i]).distance_squared(pos) <= r2)
+    }
+
+    /// The body closest to `pos` within `r`
+    pub fn nearest_within(&self, pos: Vec2, r: f32) -> Option<usize> {
+        self.neighbors_within(pos, r).min_by(|&a, &b| {
+            let da = Vec2::from(self.positions[a]).distance_squared(pos);
+            da.total_cmp(&Vec2::from(self.positions[b]).distance_squared(pos))
+        })
+    }
+
+    /// Side of a grid cell, about `BODIES_PER_CELL` bodies across
+    pub fn cell_size(&self) -> f32 {
+        self.grid.cell
+    }
+}
+
+/// Rebin the bodies after each step, body count change or reorder
+pub fn update_spatial_index(
+    bodies: Res<Bodies>,
+    mut reordered: EventReader<BodiesReordered>,
+    mut index: ResMut<SpatialIndex>,
+) {
+    let key = (bodies.step, bodies.data.len());
+    if reordered.read().count() == 0 && index.built == Some(key) {
+        return;
+    }
+    index.built = Some(key);
+    let SpatialIndex {
+        grid, positions, ..
+    } = &mut *index;
+    positions.clear();
+    positions.extend(bodies.data.iter().map(|b| [b.x, b.y]));
+    let (mut min, mut max) = (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN));
+    for &p in positions.iter() {
+        min = min.min(p.into());
+        max = max.max(p.into());
+    }
+    let extent = (max - min).max_element().max(1.0);
+    let cells_per_side = (positions.len() as f32 / BODIES_PER_CELL).sqrt().max(1.0);
+    grid.rebuild_with_cell(positions, extent / cells_per_side);
+}
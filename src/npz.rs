@@ -0,0 +1,314 @@
+//! NumPy `.npz` snapshot writer: an uncompressed zip of `.npy` arrays, so
+//! Python users load a snapshot with one call.
+//!
+//! ```text
+//! s = np.load("snapshot_000100.npz")
+//! s["x"], s["v"]    # (n, 2) positions (m) and velocities (m/s)
+//! s["mass"], s["charge"], s["fixed"], s["time"], s["step"]   # SI
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::Bodies;
+use crate::diagnostics::is_due;
+use crate::units::CODE;
+
+/// One array: dtype descriptor, shape and little-endian bytes
+struct NpyArray {
+    descr: &'static str,
+    shape: Vec<usize>,
+    bytes: Vec<u8>,
+}
+
+impl NpyArray {
+    fn f32(shape: Vec<usize>, values: impl Iterator<Item = f32>) -> Self {
+        Self {
+            descr: "<f4",
+            shape,
+            bytes: values.flat_map(f32::to_le_bytes).collect(),
+        }
+    }
+
+    /// `.npy` version 1.0 file contents
+    fn encode(&self) -> Vec<u8> {
+        let shape = match self.shape.as_slice() {
+            [n] => format!("({n},)"),
+            dims => format!(
+                "({})",
+                dims.iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}",
+            self.descr
+        );
+        // Magic (6) + version (2) + length (2) + header, padded to 64 bytes
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut out = Vec::with_capacity(10 + header.len() + self.bytes.len());
+        out.extend_from_slice(b"\x93NUMPY\x01\x00");
+        out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        out.extend_from_slice(header.as_bytes());
+        out.extend_from_slice(&self.bytes);
+        out
+    }
+}
+
+/// CRC-32 (IEEE) as required by zip entries
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Store `files` uncompressed in a zip archive
+fn write_zip(w: &mut impl Write, files: &[(String, Vec<u8>)]) -> io::Result<()> {
+    let mut central = Vec::new();
+    let mut offset = 0u32;
+    for (name, data) in files {
+        let crc = crc32(data);
+        let size = data.len() as u32;
+        // Fields shared by the local and central headers: version, flags,
+        // method (stored), time, date (1980-01-01), crc, sizes, name length
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0x21u16.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+
+        w.write_all(&0x0403_4b50u32.to_le_bytes())?;
+        w.write_all(&common)?;
+        w.write_all(&0u16.to_le_bytes())?; // extra length
+        w.write_all(name.as_bytes())?;
+        w.write_all(data)?;
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // made by
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&[0; 12]); // extra, comment, disk, attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+
+        offset += 30 + name.len() as u32 + size;
+    }
+    w.write_all(&central)?;
+    w.write_all(&0x0605_4b50u32.to_le_bytes())?;
+    w.write_all(&[0; 4])?; // disk numbers
+    w.write_all(&(files.len() as u16).to_le_bytes())?;
+    w.write_all(&(files.len() as u16).to_le_bytes())?;
+    w.write_all(&(central.len() as u32).to_le_bytes())?;
+    w.write_all(&offset.to_le_bytes())?;
+    w.write_all(&0u16.to_le_bytes())?; // comment length
+    Ok(())
+}
+
+/// Write the current bodies as an `.npz` archive
+pub fn save(bodies: &Bodies, path: &Path) -> Result<(), String> {
+    let n = bodies.data.len();
+    let data = &bodies.data;
+    let arrays = [
+        (
+            "x",
+            NpyArray::f32(
+                vec![n, 2],
+                data.iter()
+                    .flat_map(|b| [CODE.length(b.x), CODE.length(b.y)]),
+            ),
+        ),
+        (
+            "v",
+            NpyArray::f32(
+                vec![n, 2],
+                data.iter()
+                    .flat_map(|b| [CODE.velocity(b.vx), CODE.velocity(b.vy)]),
+            ),
+        ),
+        (
+            "mass",
+            NpyArray::f32(vec![n], data.iter().map(|b| CODE.mass(b.mass))),
+        ),
+        (
+            "charge",
+            NpyArray::f32(vec![n], data.iter().map(|b| CODE.charge(b.charge))),
+        ),
+        (
+            "fixed",
+            NpyArray {
+                descr: "|b1",
+                shape: vec![n],
+                bytes: data.iter().map(|b| b.fixed as u8).collect(),
+            },
+        ),
+        (
+            "time",
+            NpyArray {
+                descr: "<f8",
+                shape: Vec::new(),
+                bytes: (bodies.elapsed_time as f64 * CODE.time)
+                    .to_le_bytes()
+                    .to_vec(),
+            },
+        ),
+        (
+            "step",
+            NpyArray {
+                descr: "<u8",
+                shape: Vec::new(),
+                bytes: bodies.step.to_le_bytes().to_vec(),
+            },
+        ),
+    ];
+    let files: Vec<(String, Vec<u8>)> = arrays
+        .iter()
+        .map(|(name, a)| (format!("{name}.npy"), a.encode()))
+        .collect();
+    let result = File::create(path).and_then(|f| {
+        let mut w = BufWriter::new(f);
+        write_zip(&mut w, &files)?;
+        w.flush()
+    });
+    result.map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// `--npz <dir>`: a snapshot every `interval` steps
+#[derive(Resource, Default)]
+pub struct NpzExport {
+    pub dir: Option<PathBuf>,
+    pub interval: u64,
+    last_step: Option<u64>,
+}
+
+impl NpzExport {
+    pub fn new(dir: PathBuf, interval: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: Some(dir),
+            interval,
+            last_step: None,
+        })
+    }
+}
+
+pub fn export_npz(bodies: Res<Bodies>, mut export: ResMut<NpzExport>) {
+    let Some(dir) = export.dir.clone() else {
+        return;
+    };
+    if bodies.data.is_empty() || !is_due(export.last_step, bodies.step, export.interval) {
+        return;
+    }
+    export.last_step = Some(bodies.step);
+    let path = dir.join(format!("snapshot_{:06}.npz", bodies.step));
+    if let Err(e) = save(&bodies, &path) {
+        error!("npz export failed, disabling: {e}");
+        export.dir = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(b: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([b[at], b[at + 1]])
+    }
+
+    fn u32_at(b: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(b[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn zip_headers_point_at_the_stored_files() {
+        let files = vec![
+            ("a.npy".to_string(), b"first".to_vec()),
+            ("bb.npy".to_string(), vec![7; 300]),
+        ];
+        let mut zip = Vec::new();
+        write_zip(&mut zip, &files).unwrap();
+
+        // End of central directory: the last 22 bytes without a comment
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(&zip, end), 0x0605_4b50);
+        assert_eq!(u16_at(&zip, end + 8), 2);
+        assert_eq!(u16_at(&zip, end + 10), 2);
+        let central_size = u32_at(&zip, end + 12) as usize;
+        let central_offset = u32_at(&zip, end + 16) as usize;
+        assert_eq!(central_offset + central_size, end);
+
+        let mut at = central_offset;
+        for (name, data) in &files {
+            assert_eq!(u32_at(&zip, at), 0x0201_4b50);
+            assert_eq!(u16_at(&zip, at + 10), 0, "stored");
+            let crc = u32_at(&zip, at + 16);
+            assert_eq!(crc, crc32(data));
+            assert_eq!(u32_at(&zip, at + 20) as usize, data.len());
+            assert_eq!(u32_at(&zip, at + 24) as usize, data.len());
+            let name_len = u16_at(&zip, at + 28) as usize;
+            assert_eq!(u16_at(&zip, at + 30), 0);
+            assert_eq!(u16_at(&zip, at + 32), 0);
+            let local = u32_at(&zip, at + 42) as usize;
+            assert_eq!(&zip[at + 46..at + 46 + name_len], name.as_bytes());
+            at += 46 + name_len;
+
+            assert_eq!(u32_at(&zip, local), 0x0403_4b50);
+            assert_eq!(u16_at(&zip, local + 8), 0, "stored");
+            assert_eq!(u32_at(&zip, local + 14), crc);
+            assert_eq!(u32_at(&zip, local + 18) as usize, data.len());
+            assert_eq!(u16_at(&zip, local + 26) as usize, name.len());
+            let extra = u16_at(&zip, local + 28) as usize;
+            let start = local + 30 + name.len() + extra;
+            assert_eq!(&zip[local + 30..local + 30 + name.len()], name.as_bytes());
+            assert_eq!(&zip[start..start + data.len()], &data[..]);
+        }
+        assert_eq!(at, end);
+    }
+
+    #[test]
+    fn npy_header_is_padded_and_describes_the_array() {
+        for (shape, text) in [
+            (vec![3, 2], "(3, 2)"),
+            (vec![5], "(5,)"),
+            (Vec::new(), "()"),
+        ] {
+            let count = shape.iter().product::<usize>();
+            let array = NpyArray::f32(shape, (0..count).map(|i| i as f32));
+            let npy = array.encode();
+            assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+            let header_len = u16_at(&npy, 8) as usize;
+            let data_start = 10 + header_len;
+            assert_eq!(data_start % 64, 0, "data isn't 64-byte aligned");
+            let header = std::str::from_utf8(&npy[10..data_start]).unwrap();
+            assert!(header.ends_with('\n'));
+            assert_eq!(
+                header.trim_end(),
+                format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {text}, }}")
+            );
+            assert_eq!(&npy[data_start..], &array.bytes[..]);
+            assert_eq!(npy.len() - data_start, 4 * count);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Full recorded orbits of chosen bodies, drawn as persistent polylines and
+//! exportable to CSV or SVG.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::keybindings::{Action, KeyBindings};
+use crate::morton::BodiesReordered;
+use crate::selection::Selection;
+use crate::units::CODE;
+use crate::{Bodies, world_scale};
+
+/// Beyond this many points a path is thinned to every other point
+const MAX_POINTS: usize = 20_000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PathPoint {
+    /// Simulation time
+    pub time: f64,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Default)]
+pub struct OrbitPath {
+    pub points: Vec<PathPoint>,
+    /// Record one point every `stride` steps; doubles whenever the path is thinned
+    stride: u64,
+}
+
+/// Recorded paths by body index
+#[derive(Resource, Default)]
+pub struct OrbitPaths {
+    pub paths: BTreeMap<usize, OrbitPath>,
+    last_step: u64,
+}
+
+impl OrbitPaths {
+    pub fn remap(&mut self, ev: &BodiesReordered) {
+        self.paths = std::mem::take(&mut self.paths)
+            .into_iter()
+            .map(|(i, path)| (ev.remap(i), path))
+            .collect();
+    }
+
+    pub fn toggle(&mut self, i: usize) -> bool {
+        if self.paths.remove(&i).is_some() {
+            return false;
+        }
+        self.paths.insert(
+            i,
+            OrbitPath {
+                points: Vec::new(),
+                stride: 1,
+            },
+        );
+        true
+    }
+
+    /// Write every path as CSV or SVG, chosen by the file extension
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => self.to_csv(),
+            Some("svg") => self.to_svg(),
+            _ => return Err("export needs a .csv or .svg file".into()),
+        };
+        fs::write(path, text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Times in seconds, positions in meters
+    fn to_csv(&self) -> String {
+        let mut out = String::from("body,time,x,y\n");
+        for (i, path) in &self.paths {
+            for p in &path.points {
+                let _ = writeln!(
+                    out,
+                    "{i},{:e},{:e},{:e}",
+                    p.time * CODE.time,
+                    CODE.length(p.x),
+                    CODE.length(p.y)
+                );
+            }
+        }
+        out
+    }
+
+    fn to_svg(&self) -> String {
+        let points = || self.paths.values().flat_map(|p| &p.points);
+        let (mut min, mut max) = (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN));
+        for p in points() {
+            min = min.min(Vec2::new(p.x, p.y));
+            max = max.max(Vec2::new(p.x, p.y));
+        }
+        if min.x > max.x {
+            (min, max) = (Vec2::ZERO, Vec2::ONE);
+        }
+        // Fit the longer side to 1000 user units, y pointing up
+        let scale = 1000.0 / (max - min).max_element().max(f32::MIN_POSITIVE);
+        let size = (max - min) * scale;
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-10 -10 {:.1} {:.1}\">\n\
+             <rect x=\"-10\" y=\"-10\" width=\"100%\" height=\"100%\" fill=\"black\"/>\n",
+            size.x + 20.0,
+            size.y + 20.0
+        );
+        for (k, (i, path)) in self.paths.iter().enumerate() {
+            let mut coords = String::new();
+            for p in &path.points {
+                let _ = write!(
+                    coords,
+                    "{:.1},{:.1} ",
+                    (p.x - min.x) * scale,
+                    (max.y - p.y) * scale
+                );
+            }
+            let _ = writeln!(
+                out,
+                "<polyline id=\"body{i}\" fill=\"none\" stroke=\"hsl({:.0},80%,60%)\" points=\"{}\"/>",
+                path_hue(k),
+                coords.trim_end()
+            );
+        }
+        out += "</svg>\n";
+        out
+    }
+}
+
+/// Distinct hue per path (golden-angle steps)
+fn path_hue(k: usize) -> f32 {
+    (k as f32 * 137.5) % 360.0
+}
+
+/// Path key starts / stops recording the selected body
+pub fn toggle_orbit_path(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    selection: Res<Selection>,
+    mut paths: ResMut<OrbitPaths>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleOrbitPath) {
+        return;
+    }
+    let Some(i) = selection.0 else {
+        return;
+    };
+    let on = paths.toggle(i);
+    info!(
+        "Orbit path of body {i} {}",
+        if on { "recording" } else { "removed" }
+    );
+}
+
+/// Append the current positions once per physics step
+pub fn record_orbit_paths(bodies: Res<Bodies>, mut paths: ResMut<OrbitPaths>) {
+    if paths.paths.is_empty() || bodies.step == paths.last_step {
+        return;
+    }
+    paths.last_step = bodies.step;
+    let step = bodies.step;
+    let time = bodies.elapsed_time as f64;
+    for (&i, path) in paths.paths.iter_mut() {
+        let Some(b) = bodies.data.get(i) else {
+            continue;
+        };
+        if step % path.stride != 0 {
+            continue;
+        }
+        path.points.push(PathPoint {
+            time,
+            x: b.x,
+            y: b.y,
+        });
+        if path.points.len() > MAX_POINTS {
+            let mut k = 0;
+            path.points.retain(|_| {
+                k += 1;
+                k % 2 == 1
+            });
+            path.stride *= 2;
+        }
+    }
+}
+
+pub fn draw_orbit_paths(
+    mut gizmos: Gizmos,
+    paths: Res<OrbitPaths>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(window) = win_q.get_single() else {
+        return;
+    };
+    let scale = world_scale(window);
+    for (k, path) in paths.paths.values().enumerate() {
+        gizmos.linestrip_2d(
+            path.points.iter().map(|p| Vec2::new(p.x, p.y) * scale),
+            Color::hsl(path_hue(k), 0.8, 0.6),
+        );
+    }
+}
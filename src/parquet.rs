@@ -0,0 +1,691 @@
+//! `--parquet <dir>`: per-step diagnostics and periodic body tables as
+//! Parquet files for polars/pandas/duckdb.
+//!
+//! `diagnostics.parquet` has one row per physics step; `bodies.parquet` has
+//! one row group per snapshot, tagged with its step. Columns are plain
+//! encoded and uncompressed, and every value is in SI. The footer is rewritten after every row group,
+//! so a file is readable up to its last row group even if the app dies.
+//!
+//! ```text
+//! pl.read_parquet("bodies.parquet").filter(pl.col("step") == 1000)
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::diagnostics::is_due;
+use crate::physics::StepFinished;
+use crate::units::CODE;
+use crate::{Bodies, BodyState};
+
+/// Diagnostics rows buffered per row group
+const DIAGNOSTICS_ROW_GROUP: usize = 4096;
+/// Steps between body tables
+const SNAPSHOT_INTERVAL: u64 = 1000;
+
+const MAGIC: &[u8] = b"PAR1";
+
+/// Thrift compact protocol type ids
+const T_I32: u8 = 5;
+const T_I64: u8 = 6;
+const T_BINARY: u8 = 8;
+const T_LIST: u8 = 9;
+const T_STRUCT: u8 = 12;
+
+/// Encoder for the Thrift compact protocol that Parquet metadata uses
+#[derive(Default)]
+struct Thrift {
+    out: Vec<u8>,
+    last_field: Vec<i16>,
+}
+
+impl Thrift {
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.out.push(v as u8);
+    }
+
+    fn zigzag(&mut self, v: i64) {
+        self.varint(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, ty: u8) {
+        let last = self.last_field.last_mut().expect("field outside a struct");
+        let delta = id - std::mem::replace(last, id);
+        if (1..=15).contains(&delta) {
+            self.out.push((delta as u8) << 4 | ty);
+        } else {
+            self.out.push(ty);
+            self.zigzag(id as i64);
+        }
+    }
+
+    fn begin(&mut self) {
+        self.last_field.push(0);
+    }
+
+    fn end(&mut self) {
+        self.out.push(0);
+        self.last_field.pop();
+    }
+
+    fn i32(&mut self, id: i16, v: i32) {
+        self.field(id, T_I32);
+        self.zigzag(v as i64);
+    }
+
+    fn i64(&mut self, id: i16, v: i64) {
+        self.field(id, T_I64);
+        self.zigzag(v);
+    }
+
+    fn string(&mut self, id: i16, s: &str) {
+        self.field(id, T_BINARY);
+        self.varint(s.len() as u64);
+        self.out.extend_from_slice(s.as_bytes());
+    }
+
+    fn list(&mut self, id: i16, ty: u8, len: usize) {
+        self.field(id, T_LIST);
+        if len < 15 {
+            self.out.push((len as u8) << 4 | ty);
+        } else {
+            self.out.push(0xF0 | ty);
+            self.varint(len as u64);
+        }
+    }
+
+    /// Begin a struct field; close with `end`
+    fn struct_field(&mut self, id: i16) {
+        self.field(id, T_STRUCT);
+        self.begin();
+    }
+}
+
+/// A column's values; all columns are required (non-nullable)
+pub enum Column {
+    Bool(Vec<bool>),
+    I64(Vec<i64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+impl Column {
+    fn len(&self) -> usize {
+        match self {
+            Column::Bool(v) => v.len(),
+            Column::I64(v) => v.len(),
+            Column::F32(v) => v.len(),
+            Column::F64(v) => v.len(),
+        }
+    }
+
+    /// Parquet physical type
+    fn physical_type(&self) -> i32 {
+        match self {
+            Column::Bool(_) => 0,
+            Column::I64(_) => 2,
+            Column::F32(_) => 4,
+            Column::F64(_) => 5,
+        }
+    }
+
+    /// PLAIN encoding: little-endian values, booleans bit-packed LSB first
+    fn plain(&self) -> Vec<u8> {
+        match self {
+            Column::Bool(v) => v
+                .chunks(8)
+                .map(|bits| {
+                    bits.iter()
+                        .enumerate()
+                        .fold(0u8, |byte, (i, &b)| byte | (b as u8) << i)
+                })
+                .collect(),
+            Column::I64(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            Column::F32(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            Column::F64(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        }
+    }
+}
+
+/// Metadata of a written column chunk, kept for the footer
+struct ChunkMeta {
+    physical_type: i32,
+    num_values: i64,
+    offset: i64,
+    size: i64,
+}
+
+struct RowGroupMeta {
+    num_rows: i64,
+    columns: Vec<ChunkMeta>,
+}
+
+/// Streaming Parquet writer: a flat schema, one data page per column chunk.
+/// Each row group overwrites the previous footer with one that covers it.
+pub struct ParquetWriter<W: Write + Seek> {
+    out: W,
+    names: Vec<&'static str>,
+    physical_types: Option<Vec<i32>>,
+    offset: i64,
+    row_groups: Vec<RowGroupMeta>,
+}
+
+impl<W: Write + Seek> ParquetWriter<W> {
+    pub fn new(mut out: W, names: &[&'static str]) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        let mut writer = Self {
+            out,
+            names: names.to_vec(),
+            physical_types: None,
+            offset: MAGIC.len() as i64,
+            row_groups: Vec::new(),
+        };
+        writer.write_footer()?;
+        Ok(writer)
+    }
+
+    /// Append one row group; columns follow the names given to `new`
+    pub fn write_row_group(&mut self, columns: &[Column]) -> io::Result<()> {
+        assert_eq!(columns.len(), self.names.len(), "column count");
+        let types: Vec<i32> = columns.iter().map(Column::physical_type).collect();
+        assert_eq!(
+            *self.physical_types.get_or_insert_with(|| types.clone()),
+            types,
+            "column types changed between row groups"
+        );
+        let num_rows = columns.first().map_or(0, Column::len);
+        let mut chunks = Vec::with_capacity(columns.len());
+        for column in columns {
+            assert_eq!(column.len(), num_rows, "ragged columns");
+            let data = column.plain();
+            let mut header = Thrift::default();
+            header.begin();
+            header.i32(1, 0); // DATA_PAGE
+            header.i32(2, data.len() as i32);
+            header.i32(3, data.len() as i32);
+            header.struct_field(5);
+            header.i32(1, num_rows as i32);
+            header.i32(2, 0); // PLAIN
+            header.i32(3, 3); // RLE definition levels (none: required)
+            header.i32(4, 3); // RLE repetition levels (none: flat)
+            header.end();
+            header.end();
+            self.out.write_all(&header.out)?;
+            self.out.write_all(&data)?;
+            let size = (header.out.len() + data.len()) as i64;
+            chunks.push(ChunkMeta {
+                physical_type: column.physical_type(),
+                num_values: num_rows as i64,
+                offset: self.offset,
+                size,
+            });
+            self.offset += size;
+        }
+        self.row_groups.push(RowGroupMeta {
+            num_rows: num_rows as i64,
+            columns: chunks,
+        });
+        self.write_footer()
+    }
+
+    /// Flush and hand back the output; the footer is already in place
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Write the metadata for every row group so far, then step back so the
+    /// next row group overwrites it. The new footer lists one more row group
+    /// than the old one, so nothing stale is left past its end.
+    fn write_footer(&mut self) -> io::Result<()> {
+        let mut t = Thrift::default();
+        t.begin();
+        t.i32(1, 1); // version
+        t.list(2, T_STRUCT, self.names.len() + 1);
+        t.begin();
+        t.string(4, "schema");
+        t.i32(5, self.names.len() as i32);
+        t.end();
+        let types = self.physical_types.clone().unwrap_or_default();
+        for (i, name) in self.names.iter().enumerate() {
+            t.begin();
+            // Without row groups the types are unknown; any type reads as empty
+            t.i32(1, types.get(i).copied().unwrap_or(5));
+            t.i32(3, 0); // REQUIRED
+            t.string(4, name);
+            t.end();
+        }
+        let num_rows = self.row_groups.iter().map(|g| g.num_rows).sum();
+        t.i64(3, num_rows);
+        t.list(4, T_STRUCT, self.row_groups.len());
+        for group in &self.row_groups {
+            t.begin();
+            t.list(1, T_STRUCT, group.columns.len());
+            for (chunk, name) in group.columns.iter().zip(&self.names) {
+                t.begin();
+                t.i64(2, chunk.offset);
+                t.struct_field(3);
+                t.i32(1, chunk.physical_type);
+                t.list(2, T_I32, 1);
+                t.zigzag(0); // PLAIN
+                t.list(3, T_BINARY, 1);
+                t.varint(name.len() as u64);
+                t.out.extend_from_slice(name.as_bytes());
+                t.i32(4, 0); // UNCOMPRESSED
+                t.i64(5, chunk.num_values);
+                t.i64(6, chunk.size);
+                t.i64(7, chunk.size);
+                t.i64(9, chunk.offset);
+                t.end();
+                t.end();
+            }
+            t.i64(2, group.columns.iter().map(|c| c.size).sum());
+            t.i64(3, group.num_rows);
+            t.end();
+        }
+        t.string(6, "bevy_nbody_leapfrog");
+        t.end();
+        self.out.write_all(&t.out)?;
+        self.out.write_all(&(t.out.len() as u32).to_le_bytes())?;
+        self.out.write_all(MAGIC)?;
+        self.out.flush()?;
+        self.out.seek(SeekFrom::Start(self.offset as u64))?;
+        Ok(())
+    }
+}
+
+const DIAGNOSTICS_COLUMNS: &[&str] = &["step", "time", "kinetic", "potential", "total"];
+const BODY_COLUMNS: &[&str] = &[
+    "step", "id", "x", "y", "vx", "vy", "mass", "charge", "fixed",
+];
+
+/// Per-step diagnostics waiting for the next row group
+#[derive(Default)]
+struct DiagnosticsRows {
+    step: Vec<i64>,
+    time: Vec<f64>,
+    kinetic: Vec<f64>,
+    potential: Vec<f64>,
+}
+
+impl DiagnosticsRows {
+    fn take_columns(&mut self) -> Vec<Column> {
+        let rows = std::mem::take(self);
+        let total = rows
+            .kinetic
+            .iter()
+            .zip(&rows.potential)
+            .map(|(k, p)| k + p)
+            .collect();
+        vec![
+            Column::I64(rows.step),
+            Column::F64(rows.time),
+            Column::F64(rows.kinetic),
+            Column::F64(rows.potential),
+            Column::F64(total),
+        ]
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ParquetOutput {
+    diagnostics: Option<ParquetWriter<BufWriter<File>>>,
+    bodies: Option<ParquetWriter<BufWriter<File>>>,
+    rows: DiagnosticsRows,
+    last_snapshot_step: Option<u64>,
+}
+
+impl ParquetOutput {
+    pub fn create(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let diagnostics = BufWriter::new(File::create(dir.join("diagnostics.parquet"))?);
+        let bodies = BufWriter::new(File::create(dir.join("bodies.parquet"))?);
+        Ok(Self {
+            diagnostics: Some(ParquetWriter::new(diagnostics, DIAGNOSTICS_COLUMNS)?),
+            bodies: Some(ParquetWriter::new(bodies, BODY_COLUMNS)?),
+            ..Default::default()
+        })
+    }
+
+    fn flush_diagnostics(&mut self) {
+        if self.rows.step.is_empty() {
+            return;
+        }
+        let columns = self.rows.take_columns();
+        if let Some(w) = self.diagnostics.as_mut() {
+            if let Err(e) = w.write_row_group(&columns) {
+                error!("parquet diagnostics write failed, disabling: {e}");
+                self.diagnostics = None;
+            }
+        }
+    }
+}
+
+fn body_table(bodies: &Bodies) -> Vec<Column> {
+    let data = &bodies.data;
+    let f32s = |f: fn(&BodyState) -> f32| Column::F32(data.iter().map(f).collect());
+    vec![
+        Column::I64(vec![bodies.step as i64; data.len()]),
+        Column::I64((0..data.len() as i64).collect()),
+        f32s(|b| CODE.length(b.x)),
+        f32s(|b| CODE.length(b.y)),
+        f32s(|b| CODE.velocity(b.vx)),
+        f32s(|b| CODE.velocity(b.vy)),
+        f32s(|b| CODE.mass(b.mass)),
+        f32s(|b| CODE.charge(b.charge)),
+        Column::Bool(data.iter().map(|b| b.fixed).collect()),
+    ]
+}
+
+/// One diagnostics row per completed step, read from the integrator's
+/// events so steps between two runs of this system aren't lost
+pub fn record_parquet(
+    bodies: Res<Bodies>,
+    mut steps: EventReader<StepFinished>,
+    mut output: ResMut<ParquetOutput>,
+) {
+    if output.diagnostics.is_none() && output.bodies.is_none() {
+        return;
+    }
+    let output = &mut *output;
+    for ev in steps.read() {
+        output.rows.step.push(ev.step as i64);
+        output.rows.time.push(ev.time as f64 * CODE.time);
+        output.rows.kinetic.push(CODE.energy_f64(ev.kinetic_energy));
+        output
+            .rows
+            .potential
+            .push(CODE.energy_f64(ev.potential_energy));
+        if output.rows.step.len() >= DIAGNOSTICS_ROW_GROUP {
+            output.flush_diagnostics();
+        }
+    }
+    let step = bodies.step;
+    if !bodies.data.is_empty() && is_due(output.last_snapshot_step, step, SNAPSHOT_INTERVAL) {
+        output.last_snapshot_step = Some(step);
+        if let Some(w) = output.bodies.as_mut() {
+            if let Err(e) = w.write_row_group(&body_table(&bodies)) {
+                error!("parquet body table write failed, disabling: {e}");
+                output.bodies = None;
+            }
+        }
+    }
+}
+
+/// Write the buffered rows as a last row group when the app closes
+pub fn finish_on_exit(mut exit: EventReader<AppExit>, mut output: ResMut<ParquetOutput>) {
+    if exit.read().next().is_none() {
+        return;
+    }
+    output.flush_diagnostics();
+    for writer in [output.diagnostics.take(), output.bodies.take()]
+        .into_iter()
+        .flatten()
+    {
+        if let Err(e) = writer.finish() {
+            error!("failed to finish parquet file: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// A decoded Thrift compact value; integers of every width are `Int`
+    #[derive(Debug)]
+    enum Value {
+        Int(i64),
+        Binary(Vec<u8>),
+        List(Vec<Value>),
+        Struct(Vec<(i16, Value)>),
+    }
+
+    impl Value {
+        fn field(&self, id: i16) -> &Value {
+            let Value::Struct(fields) = self else {
+                panic!("not a struct: {self:?}");
+            };
+            &fields
+                .iter()
+                .find(|(i, _)| *i == id)
+                .expect("missing field")
+                .1
+        }
+
+        fn int(&self, id: i16) -> i64 {
+            match self.field(id) {
+                Value::Int(v) => *v,
+                v => panic!("not an integer: {v:?}"),
+            }
+        }
+
+        fn string(&self, id: i16) -> String {
+            match self.field(id) {
+                Value::Binary(b) => String::from_utf8(b.clone()).unwrap(),
+                v => panic!("not a string: {v:?}"),
+            }
+        }
+
+        fn list(&self, id: i16) -> &[Value] {
+            match self.field(id) {
+                Value::List(items) => items,
+                v => panic!("not a list: {v:?}"),
+            }
+        }
+    }
+
+    struct Reader<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl Reader<'_> {
+        fn byte(&mut self) -> u8 {
+            self.pos += 1;
+            self.bytes[self.pos - 1]
+        }
+
+        fn varint(&mut self) -> u64 {
+            let mut v = 0;
+            for shift in (0..64).step_by(7) {
+                let b = self.byte();
+                v |= ((b & 0x7F) as u64) << shift;
+                if b < 0x80 {
+                    break;
+                }
+            }
+            v
+        }
+
+        fn zigzag(&mut self) -> i64 {
+            let v = self.varint();
+            (v >> 1) as i64 ^ -((v & 1) as i64)
+        }
+
+        fn value(&mut self, ty: u8) -> Value {
+            match ty {
+                T_I32 | T_I64 => Value::Int(self.zigzag()),
+                T_BINARY => {
+                    let len = self.varint() as usize;
+                    self.pos += len;
+                    Value::Binary(self.bytes[self.pos - len..self.pos].to_vec())
+                }
+                T_LIST => {
+                    let header = self.byte();
+                    let mut len = (header >> 4) as usize;
+                    if len == 15 {
+                        len = self.varint() as usize;
+                    }
+                    Value::List((0..len).map(|_| self.value(header & 0x0F)).collect())
+                }
+                T_STRUCT => self.structure(),
+                _ => panic!("unexpected type {ty}"),
+            }
+        }
+
+        fn structure(&mut self) -> Value {
+            let mut fields = Vec::new();
+            let mut last = 0;
+            loop {
+                let header = self.byte();
+                if header == 0 {
+                    return Value::Struct(fields);
+                }
+                let delta = (header >> 4) as i16;
+                last = if delta == 0 {
+                    self.zigzag() as i16
+                } else {
+                    last + delta
+                };
+                fields.push((last, self.value(header & 0x0F)));
+            }
+        }
+    }
+
+    fn footer(file: &[u8]) -> Value {
+        assert_eq!(&file[..4], MAGIC);
+        assert_eq!(&file[file.len() - 4..], MAGIC);
+        let len_at = file.len() - 8;
+        let len = u32::from_le_bytes(file[len_at..len_at + 4].try_into().unwrap()) as usize;
+        let mut r = Reader {
+            bytes: &file[len_at - len..len_at],
+            pos: 0,
+        };
+        let meta = r.structure();
+        assert_eq!(r.pos, len, "footer length");
+        meta
+    }
+
+    /// Every column of every row group, as raw PLAIN bytes, checked against
+    /// the page headers and the chunk metadata
+    fn pages(file: &[u8], meta: &Value) -> Vec<Vec<Vec<u8>>> {
+        meta.list(4)
+            .iter()
+            .map(|group| {
+                let rows = group.int(3);
+                group
+                    .list(1)
+                    .iter()
+                    .map(|chunk| {
+                        let offset = chunk.int(2) as usize;
+                        let chunk_meta = chunk.field(3);
+                        assert_eq!(chunk_meta.int(9) as usize, offset);
+                        assert_eq!(chunk_meta.int(5), rows);
+                        let mut r = Reader {
+                            bytes: file,
+                            pos: offset,
+                        };
+                        let header = r.structure();
+                        assert_eq!(header.int(1), 0, "data page");
+                        let size = header.int(3) as usize;
+                        assert_eq!(header.int(2) as usize, size);
+                        assert_eq!(header.field(5).int(1), rows);
+                        assert_eq!(r.pos + size - offset, chunk_meta.int(7) as usize);
+                        file[r.pos..r.pos + size].to_vec()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn f64s(bytes: &[u8]) -> Vec<f64> {
+        bytes
+            .chunks(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn row_groups_round_trip() {
+        let names = ["step", "energy", "flag", "x"];
+        let groups = [
+            (
+                vec![1i64, 2, 3],
+                vec![0.5, -1.0, 2.5],
+                vec![true, false, true],
+                vec![1.0f32, 2.0, 3.0],
+            ),
+            (
+                (4..24).collect(),
+                (4..24).map(|i| i as f64 * 0.25).collect(),
+                (4..24).map(|i| i % 3 == 0).collect(),
+                (4..24).map(|i| -(i as f32)).collect(),
+            ),
+        ];
+        let mut w = ParquetWriter::new(Cursor::new(Vec::new()), &names).unwrap();
+        for (step, energy, flag, x) in &groups {
+            w.write_row_group(&[
+                Column::I64(step.clone()),
+                Column::F64(energy.clone()),
+                Column::Bool(flag.clone()),
+                Column::F32(x.clone()),
+            ])
+            .unwrap();
+        }
+        let file = w.finish().unwrap().into_inner();
+        let meta = footer(&file);
+        assert_eq!(meta.int(3), 23);
+        let schema = meta.list(2);
+        assert_eq!(schema[0].int(5), names.len() as i64);
+        let columns: Vec<_> = schema[1..]
+            .iter()
+            .map(|e| (e.string(4), e.int(1)))
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("step".into(), 2),
+                ("energy".into(), 5),
+                ("flag".into(), 0),
+                ("x".into(), 4)
+            ]
+        );
+        let pages = pages(&file, &meta);
+        assert_eq!(pages.len(), groups.len());
+        for (page, (step, energy, flag, x)) in pages.iter().zip(&groups) {
+            let steps: Vec<_> = page[0]
+                .chunks(8)
+                .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+            assert_eq!(&steps, step);
+            assert_eq!(&f64s(&page[1]), energy);
+            let flags: Vec<_> = (0..flag.len())
+                .map(|i| page[2][i / 8] >> (i % 8) & 1 == 1)
+                .collect();
+            assert_eq!(&flags, flag);
+            let xs: Vec<_> = page[3]
+                .chunks(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+            assert_eq!(&xs, x);
+        }
+    }
+
+    #[test]
+    fn unfinished_files_are_readable() {
+        let mut w = ParquetWriter::new(Cursor::new(Vec::new()), &["t"]).unwrap();
+        assert_eq!(footer(w.out.get_ref()).int(3), 0);
+        for rows in 1..4 {
+            w.write_row_group(&[Column::F64(vec![rows as f64; rows])])
+                .unwrap();
+            let file = w.out.get_ref();
+            let meta = footer(file);
+            assert_eq!(meta.list(4).len(), rows);
+            assert_eq!(meta.int(3) as usize, rows * (rows + 1) / 2);
+            assert_eq!(
+                f64s(&pages(file, &meta)[rows - 1][0]),
+                vec![rows as f64; rows]
+            );
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Idling while paused. The physics already stops with the `Running` state;
+//! on top of that the window switches to reactive updates, so a frame is
+//! only run on input or a window event, and the per-body work of each
+//! frame (analysis, the point mesh, the heatmap copy, the phase view) is
+//! skipped unless the view or the bodies actually changed. Gizmo overlays
+//! are cheap and keep being drawn, so they don't vanish from the frames
+//! that do run.
+
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy::winit::WinitSettings;
+
+use crate::coloring::BodyColors;
+use crate::menu::AppState;
+use crate::video::VideoExport;
+use crate::{Bodies, MainCamera};
+
+/// What the per-body work depends on, besides input
+#[derive(Clone, Copy, PartialEq)]
+pub struct ViewState {
+    step: u64,
+    bodies: usize,
+    camera: Transform,
+    zoom: f32,
+    window: Vec2,
+}
+
+/// Run condition: always while running, and while paused only when the
+/// step, the body count, the camera, the window or the colors changed, or
+/// there was input this frame
+pub fn needs_refresh(
+    state: Res<State<AppState>>,
+    bodies: Res<Bodies>,
+    colors: Res<BodyColors>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut wheel: EventReader<MouseWheel>,
+    cam_q: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    mut last: Local<Option<ViewState>>,
+) -> bool {
+    let scrolled = wheel.read().count() > 0;
+    let (camera, zoom) = cam_q
+        .get_single()
+        .map_or((Transform::IDENTITY, 1.0), |(tf, proj)| (*tf, proj.scale));
+    let view = ViewState {
+        step: bodies.step,
+        bodies: bodies.data.len(),
+        camera,
+        zoom,
+        window: win_q.get_single().map_or(Vec2::ZERO, |w| w.size()),
+    };
+    let moved = last.replace(view) != Some(view);
+    *state.get() != AppState::Paused
+        || moved
+        || scrolled
+        || colors.is_changed()
+        || keys.get_pressed().next().is_some()
+        || keys.get_just_released().next().is_some()
+        || mouse.get_pressed().next().is_some()
+}
+
+/// Only wake on input and window events while paused, unless every frame
+/// is being recorded
+pub fn enter_idle(mut winit: ResMut<WinitSettings>, video: Res<VideoExport>) {
+    if !video.is_recording() {
+        *winit = WinitSettings::desktop_app();
+    }
+}
+
+pub fn leave_idle(mut winit: ResMut<WinitSettings>) {
+    *winit = WinitSettings::game();
+}
@@ -0,0 +1,221 @@
+//! Phase-space view: a corner viewport plotting every body at (x, vx) (or
+//! (y, vy)) relative to the center of mass, drawn with the same point mesh
+//! and material as the main view on a render layer of its own.
+
+use bevy::prelude::*;
+use bevy::render::camera::{ClearColorConfig, Viewport};
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use bevy::window::PrimaryWindow;
+
+use crate::coloring::BodyColors;
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, fill, tr};
+use crate::point_sprites::{self, Point, PointMaterial};
+use crate::units::CODE;
+use crate::{Bodies, BodyState, mass_evolution, world_scale};
+
+/// Render layer of the phase-space points and camera
+pub const LAYER: usize = 3;
+/// View size as a fraction of the window's shorter side
+const VIEW_FRACTION: f32 = 0.3;
+const VIEW_MARGIN_PX: f32 = 20.0;
+/// Half-width of the plotted square (world units of the phase camera)
+const EXTENT: f32 = 100.0;
+/// Each axis spans this many rms values either side of the center of mass
+const RANGE_RMS: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhaseAxis {
+    X,
+    Y,
+}
+
+#[derive(Resource)]
+pub struct PhaseView {
+    pub enabled: bool,
+    pub axis: PhaseAxis,
+    /// Position and velocity at the plot edge (m, m/s)
+    range: (f32, f32),
+}
+
+impl Default for PhaseView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            axis: PhaseAxis::X,
+            range: (0.0, 0.0),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PhaseCamera;
+
+#[derive(Component)]
+pub struct PhasePoints;
+
+#[derive(Component)]
+pub struct PhaseText;
+
+pub fn spawn_phase_view(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PointMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: 2,
+                is_active: false,
+                clear_color: ClearColorConfig::Custom(Color::srgb(0.03, 0.05, 0.04)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        RenderLayers::layer(LAYER),
+        PhaseCamera,
+    ));
+    commands.spawn((
+        Mesh2d(meshes.add(point_sprites::new_mesh(std::iter::empty()))),
+        MeshMaterial2d(materials.add(PointMaterial::default())),
+        Transform::default(),
+        NoFrustumCulling,
+        RenderLayers::layer(LAYER),
+        PhasePoints,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 14.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(VIEW_MARGIN_PX),
+            ..Default::default()
+        }),
+        Visibility::Hidden,
+        PhaseText,
+    ));
+}
+
+pub fn toggle_phase_view(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut view: ResMut<PhaseView>,
+) {
+    if bindings.just_pressed(&keys, Action::TogglePhaseView) {
+        view.enabled = !view.enabled;
+    }
+}
+
+/// Place the viewport (bottom left, mirroring the zoom view) and refill the
+/// phase-space points from the interpolated positions
+pub fn update_phase_view(
+    mut view: ResMut<PhaseView>,
+    bodies: Res<Bodies>,
+    colors: Res<BodyColors>,
+    lang: Res<Language>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    mut cam_q: Query<(&mut Camera, &mut OrthographicProjection), With<PhaseCamera>>,
+    points_q: Query<&Mesh2d, With<PhasePoints>>,
+    mut text_q: Query<(&mut Text, &mut Style, &mut Visibility), With<PhaseText>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let (Ok(window), Ok((mut cam, mut proj))) = (win_q.get_single(), cam_q.get_single_mut()) else {
+        return;
+    };
+    cam.is_active = view.enabled;
+    if let Ok((_, _, mut vis)) = text_q.get_single_mut() {
+        *vis = if view.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if !view.enabled {
+        return;
+    }
+
+    let sf = window.scale_factor();
+    let side = window.width().min(window.height()) * VIEW_FRACTION;
+    let pos = Vec2::new(
+        VIEW_MARGIN_PX,
+        window.height() - side - 4.0 * VIEW_MARGIN_PX,
+    );
+    cam.viewport = Some(Viewport {
+        physical_position: (pos * sf).as_uvec2(),
+        physical_size: UVec2::splat((side * sf) as u32).max(UVec2::ONE),
+        ..Default::default()
+    });
+    proj.scale = 2.0 * EXTENT / side.max(1.0);
+
+    // Positions from the interpolated display ones, like the main view
+    let half = Vec2::new(window.width(), window.height()) / 2.0;
+    let scale = world_scale(window);
+    let axis = view.axis;
+    let sample = |b: &BodyState| match axis {
+        PhaseAxis::X => ((b.disp_x - half.x) / scale.x, b.vx),
+        PhaseAxis::Y => ((b.disp_y - half.y) / scale.y, b.vy),
+    };
+    let mass: f64 = bodies.data.iter().map(|b| b.mass as f64).sum();
+    if mass <= 0.0 {
+        return;
+    }
+    let (mut q_sum, mut p_sum) = (0.0f64, 0.0f64);
+    for b in &bodies.data {
+        let (q, p) = sample(b);
+        q_sum += b.mass as f64 * q as f64;
+        p_sum += b.mass as f64 * p as f64;
+    }
+    let center = Vec2::new((q_sum / mass) as f32, (p_sum / mass) as f32);
+    let n = bodies.data.len() as f32;
+    let spread = bodies
+        .data
+        .iter()
+        .map(|b| (Vec2::from(sample(b)) - center).powf(2.0) / n)
+        .sum::<Vec2>();
+    let range = (RANGE_RMS * spread.x.sqrt(), RANGE_RMS * spread.y.sqrt());
+    let extent = Vec2::new(range.0, range.1).max(Vec2::splat(f32::MIN_POSITIVE));
+
+    if let Some(mesh) = points_q
+        .get_single()
+        .ok()
+        .and_then(|h| meshes.get_mut(&h.0))
+    {
+        let points = bodies.data.iter().enumerate().map(|(i, b)| Point {
+            position: (Vec2::from(sample(b)) - center) / extent * EXTENT,
+            size: mass_evolution::sprite_size(b.mass),
+            color: colors.0.get(i).copied().unwrap_or([1.0; 4]),
+        });
+        point_sprites::write_points(mesh, points);
+    }
+
+    if view.range != range || lang.is_changed() {
+        view.range = range;
+        if let Ok((mut t, mut style, _)) = text_q.get_single_mut() {
+            let (q, p) = match view.axis {
+                PhaseAxis::X => ("x", "vx"),
+                PhaseAxis::Y => ("y", "vy"),
+            };
+            t.sections[0].value = fill(
+                tr(
+                    "phase space {} – {}: ±{} m, ±{} km/s",
+                    "위상 공간 {} – {}: ±{} m, ±{} km/s",
+                )
+                .get(*lang),
+                &[
+                    &q,
+                    &p,
+                    &format!("{:.2E}", CODE.length(range.0)),
+                    &format!("{:.2}", CODE.velocity(range.1) / 1.0E3),
+                ],
+            );
+            style.top = Val::Px(pos.y - 20.0);
+        }
+    }
+}
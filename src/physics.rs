@@ -0,0 +1,1095 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bevy::core::FrameCount;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::tasks::ComputeTaskPool;
+
+use crate::attractor::MouseAttractor;
+use crate::binaries::BinaryScan;
+use crate::clumps::{ClumpConfig, ClumpStep};
+use crate::constants::PhysicsConstants;
+use crate::ewald;
+use crate::external::{self, ExternalField};
+use crate::far_field::{FarFieldCache, FarFieldConfig};
+use crate::fof::FofGroups;
+use crate::force_law::{self, ForceLaw};
+use crate::keybindings::{Action, KeyBindings};
+use crate::mass_evolution::MassEvolution;
+use crate::momentum::MomentumCorrection;
+use crate::morton::{BodiesReordered, MortonOrder};
+use crate::neighbors::CellGrid;
+use crate::pm::{self, PmConfig};
+use crate::regularization::{Regularization, RegularizedPair};
+use crate::scenario::Scenario;
+use crate::softening;
+use crate::springs::{self, Spring, Springs};
+use crate::stochastic::StochasticKicks;
+use crate::summation::CompensatedSum;
+use crate::thermostat::Thermostat;
+use crate::{Bodies, BodyState, D_TIME, MAX_X, MIN_X};
+
+/// Side length of the periodic box (same extent as the initial distribution)
+pub const BOX_SIZE: f32 = MAX_X - MIN_X;
+/// Automatic solver choice: from this many bodies the direct sum is split
+/// across threads...
+const AUTO_CHUNKED_BODIES: usize = 256;
+/// ...and from this many the mesh takes over, where it applies
+const AUTO_MESH_BODIES: usize = 8192;
+
+/// Force solver used for a^{n+1}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum Solver {
+    /// Exact O(N^2) pair sum
+    #[default]
+    Direct,
+    /// Exact pair sum split into chunks of bodies across the compute task pool
+    DirectChunked,
+    /// FFT particle-mesh, smoothed on the mesh scale
+    ParticleMesh,
+    /// Particle-mesh plus direct short-range correction
+    P3M,
+}
+
+impl Solver {
+    /// Exact pair sums, the only solvers that honor the boundary and force law
+    pub fn is_direct(self) -> bool {
+        matches!(self, Solver::Direct | Solver::DirectChunked)
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Solver::Direct => Solver::DirectChunked,
+            Solver::DirectChunked => Solver::ParticleMesh,
+            Solver::ParticleMesh => Solver::P3M,
+            Solver::P3M => Solver::Direct,
+        }
+    }
+
+    /// Cheapest solver for `bodies` that still honors the boundary and the
+    /// force law: the plain direct sum while threads don't pay off, the
+    /// chunked one up to a few thousand bodies, then P³M, which is only
+    /// used for open-boundary gravity
+    pub fn auto(bodies: usize, boundary: Boundary, law: ForceLaw) -> Self {
+        if bodies < AUTO_CHUNKED_BODIES {
+            Solver::Direct
+        } else if bodies < AUTO_MESH_BODIES || boundary.is_periodic() || law != ForceLaw::Gravity {
+            Solver::DirectChunked
+        } else {
+            Solver::P3M
+        }
+    }
+}
+
+/// Boundary treatment of the simulation box
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum Boundary {
+    /// Isolated system, no box
+    #[default]
+    Open,
+    /// Positions wrap, forces use the nearest image only
+    Periodic,
+    /// Positions wrap, forces include all images via the Ewald correction table
+    PeriodicEwald,
+}
+
+impl Boundary {
+    fn next(self) -> Self {
+        match self {
+            Boundary::Open => Boundary::Periodic,
+            Boundary::Periodic => Boundary::PeriodicEwald,
+            Boundary::PeriodicEwald => Boundary::Open,
+        }
+    }
+
+    pub fn is_periodic(self) -> bool {
+        self != Boundary::Open
+    }
+}
+
+/// Default of [`PhysicsSettings::frame_budget`] (s)
+const DEFAULT_FRAME_BUDGET: f32 = 0.008;
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct PhysicsSettings {
+    /// Timestep; a change takes effect at the next step
+    pub dt: f32,
+    pub solver: Solver,
+    /// `solver` follows the body count (see [`Solver::auto`])
+    pub auto_solver: bool,
+    pub pm: PmConfig,
+    pub boundary: Boundary,
+    pub force_law: ForceLaw,
+    /// Far-field refresh interval of the direct solvers
+    pub far_field: FarFieldConfig,
+    /// Subcycling of friends-of-friends clumps
+    pub clumps: ClumpConfig,
+    /// Potential energy is summed every this many steps; the readout keeps
+    /// the last sum in between
+    pub energy_interval: u64,
+    /// Main-thread physics time allowed per rendered frame (s), `None` for
+    /// no limit. It caps only the work around the force pass, which runs on
+    /// the force worker anyway. Ignored in lockstep, where every pass is
+    /// awaited in full.
+    pub frame_budget: Option<f32>,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            dt: D_TIME,
+            solver: Solver::default(),
+            auto_solver: false,
+            pm: PmConfig::default(),
+            boundary: Boundary::default(),
+            force_law: ForceLaw::default(),
+            far_field: FarFieldConfig::default(),
+            clumps: ClumpConfig::default(),
+            energy_interval: 1,
+            frame_budget: Some(DEFAULT_FRAME_BUDGET),
+        }
+    }
+}
+
+/// Bodies waiting to be added or to replace existing ones. They join between
+/// steps, never while a force pass is in flight, so every body always has a
+/// consistent half-step state.
+#[derive(Resource, Default)]
+pub struct PendingBodies {
+    pub added: Vec<BodyState>,
+    /// (index, new state) pairs overwriting bodies in place, e.g. recycled escapers
+    pub replaced: Vec<(usize, BodyState)>,
+    /// Negate every velocity, reversing the direction of time
+    pub reverse: bool,
+}
+
+/// Snapshot handed to the force worker: [x, y, mass, charge]
+pub type Snapshot = Vec<[f32; 4]>;
+
+/// Storage moved into the force job and handed back with its result, so a
+/// steady-state step reuses the previous step's allocations
+#[derive(Default)]
+pub struct ForceBuffers {
+    pub snapshot: Snapshot,
+    pub accel: Vec<[f32; 2]>,
+    grid: CellGrid,
+    fields: Vec<ExternalField>,
+    links: Vec<Spring>,
+    far_field: FarFieldCache,
+    /// Snapshot with each clump merged into one composite body
+    reduced: Snapshot,
+    reduced_accel: Vec<[f32; 2]>,
+    /// Entry of `reduced` each body is part of
+    slot: Vec<usize>,
+    /// Meshes of the particle-mesh solvers
+    mesh: pm::PmGrids,
+}
+
+/// Output of one background force pass, evaluated at the drifted positions x^{n+1}
+pub struct ForceResult {
+    /// Timestep the pass was started with
+    pub dt: f32,
+    /// Force law the pass was started with
+    pub law: ForceLaw,
+    /// `accel` holds the new accelerations
+    pub buffers: ForceBuffers,
+    /// `None` when the energy wasn't due this step
+    pub potential_energy: Option<f64>,
+    /// Pair whose relative orbit is advanced analytically this step
+    pub regularized: Option<RegularizedPair>,
+    /// Clumps whose internal motion is subcycled this step
+    pub clumps: ClumpStep,
+}
+
+/// Everything one force pass reads, handed to the force worker
+struct ForceJob {
+    buffers: ForceBuffers,
+    clumps: ClumpStep,
+    regularized: Option<RegularizedPair>,
+    solver: Solver,
+    pm: PmConfig,
+    boundary: Boundary,
+    law: ForceLaw,
+    far_field: FarFieldConfig,
+    constants: PhysicsConstants,
+    step: u64,
+    dt: f32,
+    /// Simulated time at the drifted positions
+    time: f32,
+    energy_due: bool,
+}
+
+/// Job and result slots shared with the force worker
+#[derive(Default)]
+struct Handoff {
+    job: Option<ForceJob>,
+    result: Option<thread::Result<ForceResult>>,
+    /// The worker exits once it sees this
+    closed: bool,
+}
+
+/// Thread running the force passes one after another. It lives as long as
+/// its [`PhysicsTask`], so starting a pass only hands the job over instead
+/// of spawning a task for it.
+struct ForceWorker {
+    shared: Arc<(Mutex<Handoff>, Condvar)>,
+}
+
+impl ForceWorker {
+    fn spawn() -> Self {
+        let shared: Arc<(Mutex<Handoff>, Condvar)> = Arc::default();
+        let worker = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("force worker".into())
+            .spawn(move || {
+                let (handoff, wake) = &*worker;
+                let mut slots = handoff.lock().unwrap();
+                loop {
+                    if slots.closed {
+                        return;
+                    }
+                    let Some(job) = slots.job.take() else {
+                        slots = wake.wait(slots).unwrap();
+                        continue;
+                    };
+                    drop(slots);
+                    // A panic is handed over like a result and resumed by the caller
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| job.run()));
+                    slots = handoff.lock().unwrap();
+                    slots.result = Some(result);
+                    wake.notify_all();
+                }
+            })
+            .expect("failed to spawn the force worker");
+        Self { shared }
+    }
+
+    fn start(&self, job: ForceJob) {
+        let (handoff, wake) = &*self.shared;
+        handoff.lock().unwrap().job = Some(job);
+        wake.notify_all();
+    }
+
+    /// Result of the pass started last, waiting for it if `wait`
+    fn finished(&self, wait: bool) -> Option<ForceResult> {
+        let (handoff, wake) = &*self.shared;
+        let mut slots = handoff.lock().unwrap();
+        while wait && slots.result.is_none() {
+            slots = wake.wait(slots).unwrap();
+        }
+        let result = slots.result.take()?;
+        drop(slots);
+        Some(result.unwrap_or_else(|payload| panic::resume_unwind(payload)))
+    }
+}
+
+impl Drop for ForceWorker {
+    fn drop(&mut self) {
+        let (handoff, wake) = &*self.shared;
+        if let Ok(mut slots) = handoff.lock() {
+            slots.closed = true;
+        }
+        wake.notify_all();
+    }
+}
+
+/// In-flight force computation. While it runs, the render loop keeps
+/// showing the last completed state.
+#[derive(Resource, Default)]
+pub struct PhysicsTask {
+    /// Started on the first step
+    worker: Option<ForceWorker>,
+    running: bool,
+    /// Buffers of the last finished pass, reused by the next one
+    spare: ForceBuffers,
+    /// Clumps of the last finished pass, refilled for the next one
+    spare_clumps: ClumpStep,
+    far_field_error: Option<f32>,
+    /// Members and ends of the clumps of the last pass started, see
+    /// [`ClumpStep::layout`]
+    clump_members: Vec<usize>,
+    clump_ends: Vec<usize>,
+    /// Wait for each pass at the next step instead of skipping steps until
+    /// it is done, so the steps per frame don't depend on thread timing
+    lockstep: bool,
+    /// Main-thread time spent stepping in frame `frame`
+    spent: Duration,
+    frame: u32,
+}
+
+impl PhysicsTask {
+    pub fn lockstep() -> Self {
+        Self {
+            lockstep: true,
+            ..Default::default()
+        }
+    }
+
+    /// A force pass is in flight
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Far-field drift measured at the last refresh, relative to the total
+    /// acceleration of the worst body
+    pub fn far_field_error(&self) -> Option<f32> {
+        self.far_field_error
+    }
+}
+
+/// Bodies that get a treatment of their own instead of the plain leapfrog
+#[derive(SystemParam)]
+pub struct CloseEncounters<'w> {
+    regularization: ResMut<'w, Regularization>,
+    scan: Res<'w, BinaryScan>,
+    fof: Res<'w, FofGroups>,
+}
+
+/// Sent from the integrator each time a step completes, with the state it
+/// left in `Bodies`
+#[derive(Event, Clone, Copy, Debug)]
+pub struct StepFinished {
+    pub step: u64,
+    pub time: f32,
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+}
+
+/// What the integrator tells the rest of the app
+#[derive(SystemParam)]
+pub struct StepEvents<'w> {
+    reordered: EventWriter<'w, BodiesReordered>,
+    finished: EventWriter<'w, StepFinished>,
+}
+
+/// Leapfrog split across frames:
+/// Kick (v^{n+1/2}) + Drift (x^{n+1}) → hand the force pass to the worker → (later frame) Kick (v^{n+1})
+///
+/// The force pass runs on the force worker, so however long it takes it is
+/// spread over as many frames as it needs and the step is only completed
+/// once the whole pass is in. The pass itself isn't split up: the frame
+/// budget only caps what stays on the main thread (the kicks, the drift,
+/// the reordering and the snapshot). Once a frame has spent it, the fixed
+/// steps still due in that frame do nothing, and the simulation runs slower
+/// instead of the frame rate dropping. The first step of a frame always
+/// runs.
+///
+/// In lockstep (recording and replay) none of this applies: each step
+/// waits for its whole pass on the main thread and the budget is ignored,
+/// since which frame a pass finished in would otherwise depend on timing.
+pub fn leapfrog_step(
+    mut bodies: ResMut<Bodies>,
+    mut task: ResMut<PhysicsTask>,
+    settings: Res<PhysicsSettings>,
+    scenario: Res<Scenario>,
+    mut kicks: ResMut<StochasticKicks>,
+    mass_evolution: Res<MassEvolution>,
+    mut pending: ResMut<PendingBodies>,
+    thermostat: Res<Thermostat>,
+    springs: Res<Springs>,
+    attractor: Res<MouseAttractor>,
+    constants: Res<PhysicsConstants>,
+    mut momentum: ResMut<MomentumCorrection>,
+    mut encounters: CloseEncounters,
+    mut morton: ResMut<MortonOrder>,
+    mut events: StepEvents,
+    frame: Res<FrameCount>,
+) {
+    let lockstep = task.lockstep;
+    let started = Instant::now();
+    if task.frame != frame.0 {
+        task.frame = frame.0;
+        task.spent = Duration::ZERO;
+    }
+    let over_budget = settings
+        .frame_budget
+        .is_some_and(|budget| task.spent.as_secs_f32() >= budget);
+    if over_budget && !lockstep {
+        return;
+    }
+    if task.running {
+        let worker = task.worker.as_ref().expect("a pass is running");
+        let Some(mut result) = worker.finished(lockstep) else {
+            return; // still computing, keep displaying the previous state
+        };
+        task.running = false;
+        finish_step(
+            &mut bodies,
+            &mut result,
+            &mut kicks,
+            &mass_evolution,
+            &thermostat,
+            &mut momentum,
+            &constants,
+        );
+        events.finished.send(StepFinished {
+            step: bodies.step,
+            time: bodies.elapsed_time,
+            kinetic_energy: bodies.kinetic_energy,
+            potential_energy: bodies.potential_energy,
+        });
+        task.far_field_error = result.buffers.far_field.error;
+        task.spare = result.buffers;
+        task.spare_clumps = result.clumps;
+    }
+    // Cached neighbor lists refer to bodies by index and to the old forces
+    let mut invalidate = !pending.replaced.is_empty()
+        || !pending.added.is_empty()
+        || settings.is_changed()
+        || constants.is_changed();
+    for (i, b) in pending.replaced.drain(..) {
+        if let Some(slot) = bodies.data.get_mut(i) {
+            *slot = b;
+        }
+    }
+    if !pending.added.is_empty() {
+        bodies.data.append(&mut pending.added);
+    }
+    // v^n and a^n are both on the step boundary, so this is an exact reversal
+    if std::mem::take(&mut pending.reverse) {
+        for b in bodies.data.iter_mut() {
+            b.vx = -b.vx;
+            b.vy = -b.vy;
+        }
+    }
+
+    let dt = settings.dt;
+    let dt_half = 0.5 * dt;
+    let periodic = settings.boundary.is_periodic();
+    // Kepler orbits only describe plain gravity between point masses in open space
+    let CloseEncounters {
+        regularization,
+        scan,
+        fof,
+    } = &mut encounters;
+    let mut regularized = if settings.force_law == ForceLaw::Gravity && !periodic {
+        regularization.choose(&bodies.data, scan, dt, constants.gravitation)
+    } else {
+        None
+    };
+    // Composite masses need a center of mass that doesn't wrap, and MOND
+    // isn't a sum of pair forces
+    let pair = regularized.map(|p| [p.i, p.j]);
+    let exclude = pair.as_ref().map_or(&[][..], |p| &p[..]);
+    let mut clumps = std::mem::take(&mut task.spare_clumps);
+    if !periodic && !matches!(settings.force_law, ForceLaw::Mond { .. }) {
+        clumps.choose(&bodies.data, fof, &settings.clumps, exclude);
+    } else {
+        clumps.clear();
+    }
+    if clumps.len() != task.clump_ends.len() {
+        info!(
+            "Subcycling {} clump(s), {} substeps each",
+            clumps.len(),
+            settings.clumps.substeps
+        );
+    }
+    // Sort only now: the pair above was found by index in the last binary scan
+    let step = bodies.step;
+    if let Some(ev) = morton.apply(&mut bodies.data, step) {
+        if let Some(pair) = regularized.as_mut() {
+            (pair.i, pair.j) = (ev.remap(pair.i), ev.remap(pair.j));
+        }
+        clumps.remap(|i| ev.remap(i));
+        events.reordered.send(ev);
+        invalidate = true;
+    }
+    // The reduced snapshot the far field is cached for changes with the clumps
+    let (members, ends) = clumps.layout();
+    if members != task.clump_members || ends != task.clump_ends {
+        task.clump_members.clear();
+        task.clump_members.extend_from_slice(members);
+        task.clump_ends.clear();
+        task.clump_ends.extend_from_slice(ends);
+        invalidate = true;
+    }
+
+    // Write the half-advanced state into the back buffer; `data` stays readable
+    let kick_drift_span = info_span!("kick_drift").entered();
+    let Bodies { data, next, .. } = &mut *bodies;
+    next.clear();
+    next.extend(data.iter().map(|b| {
+        let mut n = *b;
+        if b.fixed {
+            return n;
+        }
+        // Kick: v^{n+1/2} = v^n + a^n * dt/2
+        n.vx = b.vx + b.ax * dt_half;
+        n.vy = b.vy + b.ay * dt_half;
+        // Drift: x^{n+1} = x^n + v^{n+1/2} * dt
+        n.x = b.x + n.vx * dt;
+        n.y = b.y + n.vy * dt;
+        if periodic {
+            n.x = wrap(n.x);
+            n.y = wrap(n.y);
+        }
+        n
+    }));
+
+    drop(kick_drift_span);
+
+    // Compute a^{n+1} (and PE) at the drifted positions off the main thread
+    let mut buffers = std::mem::take(&mut task.spare);
+    if invalidate {
+        buffers.far_field.invalidate();
+    }
+    buffers.snapshot.clear();
+    buffers
+        .snapshot
+        .extend(next.iter().map(|b| [b.x, b.y, b.mass, b.charge]));
+    buffers.fields.clear();
+    buffers.fields.extend_from_slice(&scenario.external);
+    buffers.fields.extend(attractor.field());
+    buffers.links.clear();
+    buffers.links.extend_from_slice(&springs.links);
+    let job = ForceJob {
+        buffers,
+        clumps,
+        regularized,
+        solver: settings.solver,
+        pm: settings.pm,
+        boundary: settings.boundary,
+        law: settings.force_law,
+        far_field: settings.far_field,
+        constants: *constants,
+        step,
+        dt,
+        time: bodies.elapsed_time + dt,
+        energy_due: (step + 1) % settings.energy_interval.max(1) == 0,
+    };
+    task.worker
+        .get_or_insert_with(ForceWorker::spawn)
+        .start(job);
+    task.running = true;
+    task.spent += started.elapsed();
+}
+
+impl ForceJob {
+    fn run(mut self) -> ForceResult {
+        let Self {
+            solver,
+            boundary,
+            law,
+            constants,
+            step,
+            ..
+        } = self;
+        let ForceBuffers {
+            snapshot,
+            accel,
+            grid,
+            fields,
+            links,
+            far_field,
+            reduced,
+            reduced_accel,
+            slot,
+            mesh,
+        } = &mut self.buffers;
+        let clumps = &self.clumps;
+        let force_span = info_span!("force", ?solver, bodies = snapshot.len()).entered();
+        // Clumps enter the global sum as single composite masses
+        let (snap, out) = if clumps.is_empty() {
+            (&*snapshot, &mut *accel)
+        } else {
+            clumps.reduce(snapshot, reduced, slot);
+            (&*reduced, &mut *reduced_accel)
+        };
+        match solver {
+            Solver::Direct | Solver::DirectChunked if self.far_field.is_active() => {
+                let kernel = PairKernel::new(snap, boundary, law, &constants);
+                let chunked = solver == Solver::DirectChunked;
+                far_field.accelerations(&kernel, &self.far_field, step, chunked, out);
+            }
+            Solver::Direct => accelerations(snap, boundary, law, &constants, grid, out),
+            Solver::DirectChunked => {
+                accelerations_chunked(snap, boundary, law, &constants, grid, out)
+            }
+            Solver::ParticleMesh => pm::accelerations(snap, &self.pm, false, &constants, mesh, out),
+            Solver::P3M => pm::accelerations(snap, &self.pm, true, &constants, mesh, out),
+        }
+        if !clumps.is_empty() {
+            ClumpStep::expand(slot, reduced_accel, accel);
+        }
+        if let ForceLaw::Mond { a0 } = law {
+            force_law::mond_boost(accel, a0);
+        }
+        external::add_accelerations(fields, self.time, snapshot, accel, constants.gravitation);
+        springs::add_accelerations(links, snapshot, boundary, accel);
+        drop(force_span);
+        let potential_energy = self.energy_due.then(|| {
+            let _span = info_span!("potential_energy").entered();
+            potential_energy(snapshot, boundary, law, &constants)
+                + springs::potential_energy(links, snapshot, boundary)
+        });
+        ForceResult {
+            dt: self.dt,
+            law,
+            buffers: self.buffers,
+            potential_energy,
+            regularized: self.regularized,
+            clumps: self.clumps,
+        }
+    }
+}
+
+/// Cycle Direct → chunked Direct → PM → P³M → auto and Open → Periodic → Periodic + Ewald
+pub fn cycle_solver(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut settings: ResMut<PhysicsSettings>,
+) {
+    if bindings.just_pressed(&keys, Action::CycleSolver) {
+        if settings.auto_solver {
+            settings.auto_solver = false;
+            settings.solver = Solver::Direct;
+        } else if settings.solver == Solver::P3M {
+            settings.auto_solver = true;
+        } else {
+            settings.solver = settings.solver.next();
+        }
+        if settings.auto_solver {
+            info!("Force solver: auto");
+        } else {
+            info!("Force solver: {:?}", settings.solver);
+        }
+    }
+    if bindings.just_pressed(&keys, Action::CycleBoundary) {
+        settings.boundary = settings.boundary.next();
+        if settings.boundary.is_periodic() && !settings.solver.is_direct() {
+            warn!("Periodic forces are only applied by the direct solver");
+        }
+        info!("Boundary: {:?}", settings.boundary);
+    }
+}
+
+/// Re-pick the automatic solver whenever the body count, boundary or force
+/// law changes
+pub fn auto_select_solver(
+    bodies: Res<Bodies>,
+    pending: Res<PendingBodies>,
+    mut settings: ResMut<PhysicsSettings>,
+) {
+    if !settings.auto_solver {
+        return;
+    }
+    let n = bodies.data.len() + pending.added.len();
+    let solver = Solver::auto(n, settings.boundary, settings.force_law);
+    if solver != settings.solver {
+        settings.solver = solver;
+        info!("Auto solver: {solver:?} for {n} bodies");
+    }
+}
+
+/// Wrap a coordinate into [MIN_X, MAX_X)
+fn wrap(x: f32) -> f32 {
+    (x - MIN_X).rem_euclid(BOX_SIZE) + MIN_X
+}
+
+/// Nearest-image separation
+pub fn min_image(d: f32) -> f32 {
+    d - BOX_SIZE * (d / BOX_SIZE).round()
+}
+
+fn finish_step(
+    bodies: &mut Bodies,
+    result: &mut ForceResult,
+    kicks: &mut StochasticKicks,
+    mass_evolution: &MassEvolution,
+    thermostat: &Thermostat,
+    momentum: &mut MomentumCorrection,
+    constants: &PhysicsConstants,
+) {
+    let dt = result.dt;
+    let dt_half = 0.5 * dt;
+    let accel = &mut result.buffers.accel;
+    // Noise doesn't run backward; a rewind (dt < 0) still adds it
+    kicks.apply(accel, dt.abs());
+
+    let kick_span = info_span!("kick").entered();
+    let Bodies { data, next, .. } = &mut *bodies;
+    for ((n, b), a) in next.iter_mut().zip(data.iter()).zip(accel.iter()) {
+        // Kick: v^{n+1} = v^{n+1/2} + a^{n+1} * dt/2
+        n.ax = a[0];
+        n.ay = a[1];
+        if n.fixed {
+            n.x_prev = n.x;
+            n.y_prev = n.y;
+            continue;
+        }
+        n.vx += n.ax * dt_half;
+        n.vy += n.ay * dt_half;
+
+        // A body that wrapped around the box should not be interpolated across it
+        let wrapped = (n.x - b.x).abs() > BOX_SIZE / 2.0 || (n.y - b.y).abs() > BOX_SIZE / 2.0;
+        n.x_prev = if wrapped { n.x } else { b.x };
+        n.y_prev = if wrapped { n.y } else { b.y };
+    }
+
+    // Swap buffers: the finished step becomes the readable state (n+1 → n)
+    std::mem::swap(data, next);
+    drop(kick_span);
+
+    // Masses change between steps; the next force pass picks them up
+    if let Some(pair) = result.regularized {
+        pair.apply(&mut bodies.data, dt, constants.gravitation);
+    }
+    result
+        .clumps
+        .apply(&mut bodies.data, dt, result.law, constants);
+    mass_evolution.apply(&mut bodies.data, dt, constants.gravitation);
+    thermostat.apply(&mut bodies.data, dt.abs(), result.law);
+    momentum.apply(&mut bodies.data, bodies.step + 1);
+
+    // KE = 1/2 m v^2
+    let _energy_span = info_span!("kinetic_energy").entered();
+    let mut ke_sum = CompensatedSum::default();
+    for b in bodies.data.iter() {
+        let v2 = (b.vx * b.vx + b.vy * b.vy) as f64;
+        ke_sum += 0.5 * b.mass as f64 * v2;
+    }
+
+    bodies.kinetic_energy = ke_sum.value();
+    if let Some(pe) = result.potential_energy {
+        bodies.potential_energy = pe;
+    }
+    bodies.elapsed_time += dt;
+    bodies.step += 1;
+}
+
+/// Bodies per tile of the blocked direct sum: two tiles of snapshot rows
+/// (2 KiB) stay in L1 while every pair between them is evaluated
+const TILE: usize = 64;
+
+/// Direct O(N^2) accelerations for every body in the snapshot, written to
+/// `accel`; `grid` is scratch space for the cutoff
+pub fn accelerations(
+    snap: &[[f32; 4]],
+    boundary: Boundary,
+    law: ForceLaw,
+    constants: &PhysicsConstants,
+    grid: &mut CellGrid,
+    accel: &mut Vec<[f32; 2]>,
+) {
+    let kernel = PairKernel::new(snap, boundary, law, constants);
+    let grid = cutoff_grid(snap, boundary, constants, grid);
+    accel.clear();
+    accel.resize(snap.len(), [0.0; 2]);
+    kernel.block(0, accel, grid);
+}
+
+/// Same sum as [`accelerations`], with the bodies split into one chunk per
+/// compute-pool thread. Every chunk reads the shared immutable snapshot and
+/// writes only its own slice of the output.
+pub fn accelerations_chunked(
+    snap: &[[f32; 4]],
+    boundary: Boundary,
+    law: ForceLaw,
+    constants: &PhysicsConstants,
+    grid: &mut CellGrid,
+    accel: &mut Vec<[f32; 2]>,
+) {
+    let n = snap.len();
+    accel.clear();
+    accel.resize(n, [0.0; 2]);
+    if n == 0 {
+        return;
+    }
+    let kernel = PairKernel::new(snap, boundary, law, constants);
+    let grid = cutoff_grid(snap, boundary, constants, grid);
+    let pool = ComputeTaskPool::get();
+    // Whole tiles per chunk, so only the last tile of the last chunk is partial
+    let chunk = n.div_ceil(pool.thread_num().max(1)).next_multiple_of(TILE);
+    pool.scope(|scope| {
+        for (c, out) in accel.chunks_mut(chunk).enumerate() {
+            scope.spawn(async move { kernel.block(c * chunk, out, grid) });
+        }
+    });
+}
+
+/// The direct sum one body at a time, without tiles; kept as the baseline
+/// for `bench-direct`
+pub fn accelerations_untiled(
+    snap: &[[f32; 4]],
+    boundary: Boundary,
+    law: ForceLaw,
+    constants: &PhysicsConstants,
+) -> Vec<[f32; 2]> {
+    let kernel = PairKernel::new(snap, boundary, law, constants);
+    (0..snap.len()).map(|i| kernel.body(i, None)).collect()
+}
+
+/// Direct-sum acceleration of body `i` alone, for test particles integrated
+/// outside the solver
+pub fn single_acceleration(
+    snap: &[[f32; 4]],
+    i: usize,
+    boundary: Boundary,
+    law: ForceLaw,
+    constants: &PhysicsConstants,
+) -> [f32; 2] {
+    PairKernel::new(snap, boundary, law, constants).body(i, None)
+}
+
+/// Neighbor grid for the cutoff, which only applies to open boundaries
+fn cutoff_grid<'a>(
+    snap: &[[f32; 4]],
+    boundary: Boundary,
+    constants: &PhysicsConstants,
+    grid: &'a mut CellGrid,
+) -> Option<&'a CellGrid> {
+    if boundary.is_periodic() {
+        return None;
+    }
+    let _span = info_span!("neighbor_grid").entered();
+    grid.rebuild(snap, constants.cutoff_radius)
+        .then_some(&*grid)
+}
+
+fn ewald_table(boundary: Boundary) -> Option<&'static ewald::EwaldTable> {
+    (boundary == Boundary::PeriodicEwald).then(|| ewald::table(BOX_SIZE as f64))
+}
+
+/// Everything a pair interaction reads besides the two indices
+#[derive(Clone, Copy)]
+pub struct PairKernel<'a> {
+    snap: &'a [[f32; 4]],
+    boundary: Boundary,
+    law: ForceLaw,
+    constants: &'a PhysicsConstants,
+    table: Option<&'a ewald::EwaldTable>,
+}
+
+impl<'a> PairKernel<'a> {
+    pub fn new(
+        snap: &'a [[f32; 4]],
+        boundary: Boundary,
+        law: ForceLaw,
+        constants: &'a PhysicsConstants,
+    ) -> Self {
+        Self {
+            snap,
+            boundary,
+            law,
+            constants,
+            table: ewald_table(boundary),
+        }
+    }
+
+    pub fn body_count(&self) -> usize {
+        self.snap.len()
+    }
+
+    /// Separation from body `i` to body `j`, nearest image if periodic
+    pub fn separation(&self, i: usize, j: usize) -> (f32, f32) {
+        let dx = self.snap[j][0] - self.snap[i][0];
+        let dy = self.snap[j][1] - self.snap[i][1];
+        if self.boundary.is_periodic() {
+            (min_image(dx), min_image(dy))
+        } else {
+            (dx, dy)
+        }
+    }
+
+    /// Add the acceleration of body `i` due to body `j` to `a`
+    pub fn add(&self, a: &mut [f32; 2], i: usize, j: usize) {
+        let (snap, constants) = (self.snap, self.constants);
+        if i == j {
+            return;
+        }
+        let (dx, dy) = self.separation(i, j);
+        if let Some(table) = self.table {
+            let c = table.correction(dx as f64, dy as f64);
+            let s = self
+                .law
+                .inverse_square_strength(&snap[i], &snap[j], constants);
+            a[0] += (s * c[0]) as f32;
+            a[1] += (s * c[1]) as f32;
+        }
+        // Ignore very far interactions (>= 1 ly by default), like your Macroquad version
+        let r = (dx * dx + dy * dy).sqrt();
+        if r == 0.0 || (r > constants.cutoff_radius && !self.boundary.is_periodic()) {
+            return;
+        }
+
+        // Softened: e.g. Plummer a = S d / (r^2 + eps^2)^{3/2}
+        let eps = softening::pair_length(constants, snap[i][2], snap[j][2]);
+        let a_mag = self.law.radial_accel(r, eps, &snap[i], &snap[j], constants);
+        a[0] += a_mag * dx / r;
+        a[1] += a_mag * dy / r;
+    }
+
+    /// Acceleration of body `i` from every other body in the snapshot, or
+    /// only from the grid cells around it when a cutoff grid is given
+    pub fn body(&self, i: usize, grid: Option<&CellGrid>) -> [f32; 2] {
+        let mut a = [0.0f32; 2];
+        match grid {
+            Some(grid) => grid
+                .neighbors(&self.snap[i])
+                .for_each(|j| self.add(&mut a, i, j)),
+            None => (0..self.snap.len()).for_each(|j| self.add(&mut a, i, j)),
+        }
+        a
+    }
+
+    /// Accelerations of bodies `first..first + out.len()`. Without a grid
+    /// the pairs are visited tile by tile; each body still sums its sources
+    /// in index order, so the result matches the untiled loop exactly.
+    fn block(&self, first: usize, out: &mut [[f32; 2]], grid: Option<&CellGrid>) {
+        if grid.is_some() {
+            for (k, a) in out.iter_mut().enumerate() {
+                *a = self.body(first + k, grid);
+            }
+            return;
+        }
+        let n = self.snap.len();
+        for (t, tile) in out.chunks_mut(TILE).enumerate() {
+            tile.fill([0.0; 2]);
+            let i0 = first + t * TILE;
+            for j0 in (0..n).step_by(TILE) {
+                for (k, a) in tile.iter_mut().enumerate() {
+                    for j in j0..(j0 + TILE).min(n) {
+                        self.add(a, i0 + k, j);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// PE = -G \sum_{i<j} m_i m_j / r_ij  (one pass with i<j to avoid double counting),
+/// with r_ij softened the same way as the direct forces and the pair term
+/// taken from the active force law
+///
+/// With Ewald the pair energy uses the periodic potential; the constant
+/// self-image term is left out since it doesn't change over a run.
+pub fn potential_energy(
+    snap: &[[f32; 4]],
+    boundary: Boundary,
+    law: ForceLaw,
+    constants: &PhysicsConstants,
+) -> f64 {
+    let n = snap.len();
+    let mut pe_sum = CompensatedSum::default();
+    let table = ewald_table(boundary);
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let mut dx = snap[j][0] - snap[i][0];
+            let mut dy = snap[j][1] - snap[i][1];
+            if boundary.is_periodic() {
+                dx = min_image(dx);
+                dy = min_image(dy);
+            }
+            let (dx, dy) = (dx as f64, dy as f64);
+            if let Some(table) = table {
+                let s = law.inverse_square_strength(&snap[i], &snap[j], constants);
+                pe_sum += -(snap[i][2] as f64) * s * table.correction(dx, dy)[2];
+            }
+            // Coincident bodies exert no force on each other either
+            let r = (dx * dx + dy * dy).sqrt();
+            if r == 0.0 {
+                continue;
+            }
+            let eps = softening::pair_length(constants, snap[i][2], snap[j][2]) as f64;
+            pe_sum += law.pair_potential(r, eps, &snap[i], &snap[j], constants);
+        }
+    }
+    pe_sum.value()
+}
+
+/// `leapfrog_step` on a world of its own, in lockstep as `--record` and
+/// `--replay` run it, for tests of the interactive integrator
+#[cfg(test)]
+pub(crate) struct LockstepRun {
+    pub world: World,
+    system: bevy::ecs::system::BoxedSystem,
+}
+
+#[cfg(test)]
+impl LockstepRun {
+    pub fn new(data: Vec<BodyState>, constants: PhysicsConstants) -> Self {
+        use bevy::ecs::event::Events;
+
+        let mut world = World::new();
+        world.insert_resource(Bodies {
+            data,
+            ..Default::default()
+        });
+        world.insert_resource(constants);
+        world.insert_resource(PhysicsTask::lockstep());
+        world.insert_resource(StochasticKicks::new(None, 1));
+        world.init_resource::<MortonOrder>();
+        world.init_resource::<PhysicsSettings>();
+        world.init_resource::<Scenario>();
+        world.init_resource::<MassEvolution>();
+        world.init_resource::<PendingBodies>();
+        world.init_resource::<Thermostat>();
+        world.init_resource::<Springs>();
+        world.init_resource::<MouseAttractor>();
+        world.init_resource::<MomentumCorrection>();
+        world.init_resource::<Regularization>();
+        world.init_resource::<BinaryScan>();
+        world.init_resource::<FofGroups>();
+        world.init_resource::<Events<BodiesReordered>>();
+        world.init_resource::<Events<StepFinished>>();
+        world.init_resource::<FrameCount>();
+        let mut system: bevy::ecs::system::BoxedSystem =
+            Box::new(IntoSystem::into_system(leapfrog_step));
+        system.initialize(&mut world);
+        Self { world, system }
+    }
+
+    /// Run one frame; returns the number of Morton reorders it made
+    pub fn frame(&mut self) -> usize {
+        use bevy::ecs::event::Events;
+
+        self.system.run((), &mut self.world);
+        let mut events = self.world.resource_mut::<Events<BodiesReordered>>();
+        let reorders = events.iter_current_update_events().count();
+        events.update();
+        self.world.resource_mut::<Events<StepFinished>>().update();
+        self.world.resource_mut::<FrameCount>().0 += 1;
+        reorders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::alloc_count::allocations;
+    use crate::sim_rng::Xoshiro256PlusPlus;
+    use crate::units::SI;
+    use crate::{MAX_MASS, MIN_MASS, ic};
+
+    #[test]
+    fn interactive_steps_do_not_allocate() {
+        let constants = PhysicsConstants::default();
+        let n = 256;
+        let data = ic::plummer(
+            &mut Xoshiro256PlusPlus::seed_from_u64(1),
+            n,
+            0.5 * (MAX_MASS + MIN_MASS) * n as f32,
+            SI.length(5.0E13),
+            (0.0, 0.0),
+            (0.0, 0.0),
+            constants.gravitation,
+        );
+        let mut run = LockstepRun::new(data, constants);
+        // Often enough that reorders land in the measured steps
+        run.world.resource_mut::<MortonOrder>().interval = Some(5);
+        let mut reorders = 0;
+        for _ in 0..20 {
+            reorders += run.frame();
+        }
+        let allocated = allocations(|| (0..100).for_each(|_| reorders += run.frame()));
+        assert_eq!(allocated, 0, "{allocated} allocations in 100 steps");
+        assert!(reorders > 4, "only {reorders} Morton reorders");
+        assert!(run.world.resource::<Bodies>().step >= 119);
+    }
+}
@@ -0,0 +1,152 @@
+//! `--plot-output <dir>`: whitespace-separated data files that gnuplot,
+//! numpy.loadtxt or pandas read directly, plus a gnuplot script turning them
+//! into energy and Lagrangian-radius plots.
+//!
+//! ```text
+//! cd <dir> && gnuplot plot.gp   # energy.png, energy_drift.png, lagrangian.png
+//! ```
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::Bodies;
+use crate::diagnostics::is_due;
+use crate::structure::StructureDiagnostics;
+use crate::units::CODE;
+
+/// Energy is written every this many steps
+const ENERGY_INTERVAL: u64 = 10;
+const YEAR: f64 = 3.154E7;
+
+const GNUPLOT_SCRIPT: &str = r#"# Generated by bevy_nbody_leapfrog; run with `gnuplot plot.gp` in this folder
+set terminal pngcairo size 1000,700 enhanced font ",12"
+set grid
+set key top left
+set xlabel "time (year)"
+
+set output "energy.png"
+set title "Energy"
+set ylabel "energy (J)"
+plot "energy.dat" using 1:2 with lines title "kinetic", \
+     "" using 1:3 with lines title "potential", \
+     "" using 1:4 with lines lw 2 title "total"
+
+set output "energy_drift.png"
+set title "Relative energy drift"
+set ylabel "(E - E_0) / |E_0|"
+plot "energy.dat" using 1:5 with lines notitle
+
+set output "lagrangian.png"
+set title "Lagrangian radii"
+set ylabel "radius (m)"
+set logscale y
+plot "lagrangian.dat" using 1:2 with lines title "10%", \
+     "" using 1:3 with lines title "50%", \
+     "" using 1:4 with lines title "90%", \
+     "" using 1:5 with lines dt 2 title "core"
+"#;
+
+#[derive(Resource, Default)]
+pub struct PlotOutput {
+    energy: Option<BufWriter<File>>,
+    lagrangian: Option<BufWriter<File>>,
+    /// Total energy at the start of the current run
+    initial_energy: Option<f64>,
+    last_energy_step: Option<u64>,
+    last_structure_step: Option<u64>,
+}
+
+impl PlotOutput {
+    pub fn create(dir: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("plot.gp"), GNUPLOT_SCRIPT)?;
+        let mut energy = BufWriter::new(File::create(dir.join("energy.dat"))?);
+        writeln!(
+            energy,
+            "# time_year kinetic_J potential_J total_J relative_drift"
+        )?;
+        let mut lagrangian = BufWriter::new(File::create(dir.join("lagrangian.dat"))?);
+        writeln!(lagrangian, "# time_year r10_m r50_m r90_m r_core_m")?;
+        Ok(Self {
+            energy: Some(energy),
+            lagrangian: Some(lagrangian),
+            ..Default::default()
+        })
+    }
+}
+
+/// Write one line, closing the file on error. A step count that went
+/// backwards starts a new run, separated by a blank line so plots don't
+/// join the runs.
+fn write_row(
+    file: &mut Option<BufWriter<File>>,
+    last_step: &mut Option<u64>,
+    step: u64,
+    values: &[f64],
+) {
+    let Some(w) = file.as_mut() else {
+        return;
+    };
+    let restarted = last_step.is_some_and(|last| step < last);
+    *last_step = Some(step);
+    let row: Vec<String> = values.iter().map(|v| format!("{v:.6e}")).collect();
+    let mut result = Ok(());
+    if restarted {
+        result = writeln!(w);
+    }
+    if let Err(e) = result.and_then(|_| writeln!(w, "{}", row.join(" ")).and_then(|_| w.flush())) {
+        error!("plot output write failed, disabling: {e}");
+        *file = None;
+    }
+}
+
+pub fn record_plot_data(
+    bodies: Res<Bodies>,
+    structure: Res<StructureDiagnostics>,
+    mut plot: ResMut<PlotOutput>,
+) {
+    if plot.energy.is_none() && plot.lagrangian.is_none() {
+        return;
+    }
+    let plot = &mut *plot;
+    let step = bodies.step;
+    let time = bodies.elapsed_time as f64 * CODE.time / YEAR;
+
+    // Energies exist once the first step of a run has finished
+    if step > 0 && is_due(plot.last_energy_step, step, ENERGY_INTERVAL) {
+        let total = bodies.kinetic_energy + bodies.potential_energy;
+        if plot.last_energy_step.is_none_or(|last| step < last) {
+            plot.initial_energy = Some(total);
+        }
+        let e0 = plot.initial_energy.unwrap_or(total);
+        let drift = if e0 != 0.0 {
+            (total - e0) / e0.abs()
+        } else {
+            0.0
+        };
+        let values = [
+            time,
+            CODE.energy_f64(bodies.kinetic_energy),
+            CODE.energy_f64(bodies.potential_energy),
+            CODE.energy_f64(total),
+            drift,
+        ];
+        write_row(&mut plot.energy, &mut plot.last_energy_step, step, &values);
+    }
+
+    if let (Some(s), Some(structure_step)) = (structure.latest, structure.last_step) {
+        if plot.last_structure_step != Some(structure_step) {
+            let r = s.lagrangian_radii.map(|r| CODE.length(r) as f64);
+            let values = [time, r[0], r[1], r[2], CODE.length(s.core_radius) as f64];
+            write_row(
+                &mut plot.lagrangian,
+                &mut plot.last_structure_step,
+                structure_step,
+                &values,
+            );
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Particle-mesh (PM) and particle-particle/particle-mesh (P³M) gravity.
+//!
+//! The mesh part deposits mass with cloud-in-cell (CIC) weights, convolves it
+//! with an isolated (zero-padded) Green's function via FFT, differentiates the
+//! potential on the grid and interpolates the accelerations back with the same
+//! CIC weights. The Green's function is the long-range half of an erf/erfc
+//! split of the 1/r potential, so P³M only has to add the short-range erfc part
+//! for near neighbours found through a cell list.
+
+use std::f64::consts::PI;
+
+use bevy::reflect::Reflect;
+
+use crate::constants::PhysicsConstants;
+use crate::fft::{Complex, fft_2d};
+use crate::neighbors::CellGrid;
+use crate::special::{erf, erfc};
+
+#[derive(Clone, Copy, Debug, Reflect)]
+pub struct PmConfig {
+    /// Mesh cells per side (power of two); the FFT grid is twice this for zero padding
+    pub grid: usize,
+    /// Force split scale r_s in units of the mesh cell size
+    pub split_cells: f64,
+}
+
+impl Default for PmConfig {
+    fn default() -> Self {
+        Self {
+            grid: 128,
+            split_cells: 1.25,
+        }
+    }
+}
+
+/// Meshes and cell lists kept between passes, so a steady-state pass
+/// doesn't allocate
+#[derive(Default)]
+pub struct PmGrids {
+    rho: Vec<Complex>,
+    green: Vec<Complex>,
+    /// Column scratch of the 2D FFT
+    col: Vec<Complex>,
+    /// Cells of the short-range correction
+    cells: CellGrid,
+}
+
+/// Short-range pairs are cut where erfc(r / 2r_s) has decayed to ~1e-3
+const SHORT_RANGE_CUT: f64 = 4.5;
+
+/// Accelerations for every body in the snapshot ([x, y, mass]) into `accel`.
+/// With `short_range` the direct near-neighbour correction (P³M) is added.
+pub fn accelerations(
+    snap: &[[f32; 4]],
+    cfg: &PmConfig,
+    short_range: bool,
+    constants: &PhysicsConstants,
+    grids: &mut PmGrids,
+    accel: &mut Vec<[f32; 2]>,
+) {
+    let g = constants.gravitation as f64;
+    let n = snap.len();
+    accel.clear();
+    accel.resize(n, [0.0; 2]);
+    if n == 0 {
+        return;
+    }
+    let ng = cfg.grid.max(8).next_power_of_two();
+
+    // Square mesh covering all bodies with two spare cells on each side for
+    // the CIC stencil and the gradient
+    let (mut min_x, mut min_y) = (f64::MAX, f64::MAX);
+    let (mut max_x, mut max_y) = (f64::MIN, f64::MIN);
+    for p in snap {
+        min_x = min_x.min(p[0] as f64);
+        min_y = min_y.min(p[1] as f64);
+        max_x = max_x.max(p[0] as f64);
+        max_y = max_y.max(p[1] as f64);
+    }
+    let span = (max_x - min_x).max(max_y - min_y).max(1.0);
+    let h = span / (ng - 5) as f64;
+    let origin = (min_x - 2.0 * h, min_y - 2.0 * h);
+    let rs = cfg.split_cells * h;
+
+    // Zero-padded FFT grid
+    let m = 2 * ng;
+    let PmGrids {
+        rho,
+        green,
+        col,
+        cells,
+    } = grids;
+    rho.clear();
+    rho.resize(m * m, Complex::default());
+    for p in snap {
+        for (ix, iy, w) in cic_weights(p, origin, h) {
+            rho[iy * m + ix].re += w * p[2] as f64;
+        }
+    }
+
+    // Long-range Green's function, sampled at wrapped distances so the
+    // circular convolution equals the isolated one inside the mesh
+    green.resize(m * m, Complex::default());
+    for gy in 0..m {
+        let dy = gy.min(m - gy) as f64 * h;
+        for gx in 0..m {
+            let dx = gx.min(m - gx) as f64 * h;
+            let r = (dx * dx + dy * dy).sqrt();
+            green[gy * m + gx] = Complex::new(-g * long_range_kernel(r, rs), 0.0);
+        }
+    }
+
+    fft_2d(rho, m, false, col);
+    fft_2d(green, m, false, col);
+    for (a, g) in rho.iter_mut().zip(green.iter()) {
+        *a = *a * *g;
+    }
+    fft_2d(rho, m, true, col);
+    let phi = |ix: usize, iy: usize| rho[iy * m + ix].re;
+
+    // a = -grad(phi) by central differences, interpolated back with CIC
+    for (i, p) in snap.iter().enumerate() {
+        let (mut ax, mut ay) = (0.0, 0.0);
+        for (ix, iy, w) in cic_weights(p, origin, h) {
+            ax -= w * (phi(ix + 1, iy) - phi(ix - 1, iy)) / (2.0 * h);
+            ay -= w * (phi(ix, iy + 1) - phi(ix, iy - 1)) / (2.0 * h);
+        }
+        accel[i] = [ax as f32, ay as f32];
+    }
+
+    if short_range {
+        add_short_range(snap, rs, g, cells, accel);
+    }
+}
+
+/// The four CIC nodes (and weights) around a body
+fn cic_weights(p: &[f32; 4], origin: (f64, f64), h: f64) -> [(usize, usize, f64); 4] {
+    let u = (p[0] as f64 - origin.0) / h;
+    let v = (p[1] as f64 - origin.1) / h;
+    let (i, j) = (u.floor(), v.floor());
+    let (fx, fy) = (u - i, v - j);
+    let (i, j) = (i as usize, j as usize);
+    [
+        (i, j, (1.0 - fx) * (1.0 - fy)),
+        (i + 1, j, fx * (1.0 - fy)),
+        (i, j + 1, (1.0 - fx) * fy),
+        (i + 1, j + 1, fx * fy),
+    ]
+}
+
+/// erf(r / 2r_s) / r, finite at r = 0
+fn long_range_kernel(r: f64, rs: f64) -> f64 {
+    if r < 1e-12 * rs {
+        1.0 / (rs * PI.sqrt())
+    } else {
+        erf(r / (2.0 * rs)) / r
+    }
+}
+
+/// Direct erfc-weighted forces for pairs closer than SHORT_RANGE_CUT * r_s
+fn add_short_range(
+    snap: &[[f32; 4]],
+    rs: f64,
+    g: f64,
+    cells: &mut CellGrid,
+    accel: &mut [[f32; 2]],
+) {
+    let cut = SHORT_RANGE_CUT * rs;
+    cells.rebuild_fixed(snap, cut as f32);
+
+    for (i, p) in snap.iter().enumerate() {
+        let (mut ax, mut ay) = (0.0f64, 0.0f64);
+        for j in cells.neighbors(p) {
+            if i == j {
+                continue;
+            }
+            let dx = (snap[j][0] - p[0]) as f64;
+            let dy = (snap[j][1] - p[1]) as f64;
+            let r2 = dx * dx + dy * dy;
+            let r = r2.sqrt();
+            if r == 0.0 || r > cut {
+                continue;
+            }
+            let x = r / (2.0 * rs);
+            let split = erfc(x) + 2.0 * x / PI.sqrt() * (-x * x).exp();
+            let a_mag = g * snap[j][2] as f64 / r2 * split;
+            ax += a_mag * dx / r;
+            ay += a_mag * dy / r;
+        }
+        accel[i][0] += ax as f32;
+        accel[i][1] += ay as f32;
+    }
+}
@@ -0,0 +1,289 @@
+//! Surface-of-section tool: records where tracer particles cross a chosen
+//! line (in one direction) and plots the crossings in phase space.
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use serde::Deserialize;
+
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, fill, tr};
+use crate::morton::BodiesReordered;
+use crate::units::CODE;
+use crate::{Bodies, BodyState};
+
+/// Plot size in pixels
+const PANEL_PX: u32 = 256;
+/// Bodies lighter than this fraction of the heaviest one count as tracers
+const TRACER_FRACTION: f32 = 1.0E-9;
+const BACKGROUND: [u8; 4] = [0, 0, 0, 200];
+const DOT: [u8; 4] = [120, 220, 255, 255];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// The line `axis = value` (relative to the heaviest body), crossed in the
+/// positive direction. Crossings of `y = c` are plotted as (x, vx) and
+/// crossings of `x = c` as (y, vy).
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Surface {
+    pub axis: Axis,
+    pub value: f32,
+}
+
+impl Surface {
+    /// Signed distance to the surface and the plotted (q, p) of a state
+    /// relative to `origin`
+    fn project(&self, b: &BodyState, origin: &BodyState) -> (f32, Vec2) {
+        let (x, y) = (b.x - origin.x, b.y - origin.y);
+        let (vx, vy) = (b.vx - origin.vx, b.vy - origin.vy);
+        match self.axis {
+            Axis::Y => (y - self.value, Vec2::new(x, vx)),
+            Axis::X => (x - self.value, Vec2::new(y, vy)),
+        }
+    }
+
+    fn labels(&self) -> (&'static str, &'static str) {
+        match self.axis {
+            Axis::Y => ("x", "vx"),
+            Axis::X => ("y", "vy"),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct PoincareSection {
+    pub surface: Option<Surface>,
+    pub panel: bool,
+    /// Crossings in (q, p)
+    pub points: Vec<Vec2>,
+    /// Signed distance and (q, p) of every body at the previous step
+    prev: Vec<Option<(f32, Vec2)>>,
+    last_step: u64,
+    /// Plot range the image was drawn with, and how many points it shows
+    drawn_range: Option<Rect>,
+    drawn: usize,
+}
+
+impl PoincareSection {
+    pub fn new(surface: Option<Surface>) -> Self {
+        Self {
+            surface,
+            panel: surface.is_some(),
+            ..Default::default()
+        }
+    }
+
+    /// Switch to another surface (or none), dropping the recorded crossings
+    pub fn set_surface(&mut self, surface: Option<Surface>) {
+        *self = Self {
+            panel: surface.is_some() || self.panel,
+            ..Self::new(surface)
+        };
+    }
+
+    pub fn remap(&mut self, ev: &BodiesReordered) {
+        ev.permute(&mut self.prev);
+    }
+
+    /// Bounding box of the crossings, padded by 5%
+    fn range(&self) -> Option<Rect> {
+        let first = *self.points.first()?;
+        let mut r = Rect::from_corners(first, first);
+        for &p in &self.points {
+            r = r.union_point(p);
+        }
+        let pad = (r.size() * 0.05).max(Vec2::splat(f32::MIN_POSITIVE));
+        Some(Rect::from_corners(r.min - pad, r.max + pad))
+    }
+}
+
+/// Section key shows / hides the panel
+pub fn toggle_poincare_panel(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut section: ResMut<PoincareSection>,
+) {
+    if bindings.just_pressed(&keys, Action::TogglePoincare) {
+        section.panel = !section.panel;
+    }
+}
+
+/// Look for tracers that crossed the surface during the last step
+pub fn record_crossings(bodies: Res<Bodies>, mut section: ResMut<PoincareSection>) {
+    let Some(surface) = section.surface else {
+        return;
+    };
+    if bodies.step == section.last_step {
+        return;
+    }
+    section.last_step = bodies.step;
+    let Some(origin) = bodies.data.iter().max_by(|a, b| a.mass.total_cmp(&b.mass)) else {
+        return;
+    };
+    let threshold = TRACER_FRACTION * origin.mass;
+
+    let PoincareSection { prev, points, .. } = &mut *section;
+    prev.resize(bodies.data.len(), None);
+    for (b, prev) in bodies.data.iter().zip(prev.iter_mut()) {
+        if b.mass > threshold {
+            *prev = None;
+            continue;
+        }
+        let (d, qp) = surface.project(b, origin);
+        if let Some((d0, qp0)) = *prev {
+            if d0 < 0.0 && d >= 0.0 {
+                // Interpolate to where the straight segment meets the surface
+                points.push(qp0.lerp(qp, d0 / (d0 - d)));
+            }
+        }
+        *prev = Some((d, qp));
+    }
+}
+
+#[derive(Component)]
+pub struct PoincarePanel;
+
+#[derive(Component)]
+pub struct PoincareText;
+
+pub fn spawn_poincare_panel(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    asset_server: Res<AssetServer>,
+) {
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: PANEL_PX,
+            height: PANEL_PX,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &BACKGROUND,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.0),
+                    top: Val::Px(150.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..Default::default()
+                },
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            PoincarePanel,
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                PoincareText,
+            ));
+            panel.spawn(ImageBundle {
+                style: Style {
+                    width: Val::Px(PANEL_PX as f32),
+                    height: Val::Px(PANEL_PX as f32),
+                    ..Default::default()
+                },
+                image: UiImage::new(image),
+                ..Default::default()
+            });
+        });
+}
+
+/// Plot the new crossings; the whole image is redrawn when the range grows
+pub fn update_poincare_panel(
+    mut section: ResMut<PoincareSection>,
+    mut images: ResMut<Assets<Image>>,
+    mut panel_q: Query<&mut Visibility, With<PoincarePanel>>,
+    image_q: Query<&UiImage>,
+    mut text_q: Query<&mut Text, With<PoincareText>>,
+    lang: Res<Language>,
+) {
+    if !section.is_changed() && !lang.is_changed() {
+        return;
+    }
+    // Bookkeeping below must not retrigger this system next frame
+    let section = section.bypass_change_detection();
+    for mut vis in panel_q.iter_mut() {
+        *vis = if section.panel {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    let Some(image) = image_q.iter().find_map(|ui| images.get_mut(&ui.texture)) else {
+        return;
+    };
+
+    let range = section.range();
+    if range != section.drawn_range {
+        for px in image.data.chunks_exact_mut(4) {
+            px.copy_from_slice(&BACKGROUND);
+        }
+        section.drawn = 0;
+        section.drawn_range = range;
+    }
+    if let Some(range) = range {
+        let size = PANEL_PX as f32;
+        for p in &section.points[section.drawn..] {
+            let t = (*p - range.min) / range.size();
+            let (col, row) = ((t.x * size) as u32, ((1.0 - t.y) * size) as u32);
+            if col < PANEL_PX && row < PANEL_PX {
+                let k = 4 * (row * PANEL_PX + col) as usize;
+                image.data[k..k + 4].copy_from_slice(&DOT);
+            }
+        }
+    }
+    section.drawn = section.points.len();
+
+    if let Ok(mut t) = text_q.get_single_mut() {
+        let lang = *lang;
+        t.sections[0].value = match (section.surface, range) {
+            (None, _) => tr(
+                "Poincaré section off (console: section <x|y> <value>)",
+                "푸앵카레 단면 꺼짐 (콘솔: section <x|y> <값>)",
+            )
+            .get(lang)
+            .to_string(),
+            (Some(s), None) => {
+                let axis = if s.axis == Axis::Y { "y" } else { "x" };
+                fill(
+                    tr(
+                        "Poincaré section {} = {} m: no crossings yet",
+                        "푸앵카레 단면 {} = {} m: 아직 교차 없음",
+                    )
+                    .get(lang),
+                    &[&axis, &format!("{:.2E}", CODE.length(s.value))],
+                )
+            }
+            (Some(s), Some(r)) => {
+                let (q, p) = s.labels();
+                format!(
+                    "{} {}   {q}: {:.2E} … {:.2E} m   {p}: {:.2E} … {:.2E} m/s",
+                    section.points.len(),
+                    tr("crossings", "회 교차").get(lang),
+                    CODE.length(r.min.x),
+                    CODE.length(r.max.x),
+                    CODE.velocity(r.min.y),
+                    CODE.velocity(r.max.y)
+                )
+            }
+        };
+    }
+}
@@ -0,0 +1,250 @@
+//! Bodies drawn as a single mesh of round, soft-edged points instead of one
+//! sprite entity each. Point size follows mass and, partly, the camera zoom;
+//! colors come from [`BodyColors`](crate::coloring::BodyColors).
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::mesh::{
+    Indices, MeshVertexAttribute, MeshVertexBufferLayoutRef, PrimitiveTopology,
+    VertexAttributeValues,
+};
+use bevy::render::render_resource::{
+    AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, RenderPipelineDescriptor,
+    ShaderRef, ShaderType, SpecializedMeshPipelineError, VertexFormat,
+};
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use bevy::sprite::{AlphaMode2d, Material2d, Material2dKey};
+
+use crate::keybindings::{Action, KeyBindings};
+
+const SHADER_PATH: &str = "shaders/point_sprite.wgsl";
+
+/// Point diameter (px at the default zoom), one value per vertex
+pub const ATTRIBUTE_POINT_SIZE: MeshVertexAttribute =
+    MeshVertexAttribute::new("PointSize", 0x6e62_6f64, VertexFormat::Float32);
+
+/// Body brightness in the additive glow mode, low enough that a few
+/// overlapping bodies brighten before saturating
+const GLOW_INTENSITY: f32 = 0.35;
+
+/// Quad corners, in the order the indices below expect
+const CORNERS: [[f32; 2]; 4] = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+
+#[derive(ShaderType, Debug, Clone, Copy)]
+pub struct PointParams {
+    /// 0: size fixed in world units (like sprites), 1: fixed on screen
+    pub attenuation: f32,
+    /// Width of the faded rim, as a fraction of the radius
+    pub softness: f32,
+    /// Multiplies every point's alpha
+    pub intensity: f32,
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(PointMaterialKey)]
+pub struct PointMaterial {
+    #[uniform(0)]
+    pub params: PointParams,
+    /// Add overlapping points instead of blending them, so dense regions
+    /// glow like a cluster's surface brightness
+    pub additive: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PointMaterialKey {
+    additive: bool,
+}
+
+impl From<&PointMaterial> for PointMaterialKey {
+    fn from(material: &PointMaterial) -> Self {
+        Self {
+            additive: material.additive,
+        }
+    }
+}
+
+impl Default for PointMaterial {
+    fn default() -> Self {
+        Self {
+            params: PointParams {
+                attenuation: 0.5,
+                softness: 0.5,
+                intensity: 1.0,
+            },
+            additive: false,
+        }
+    }
+}
+
+impl Material2d for PointMaterial {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(1),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(2),
+            ATTRIBUTE_POINT_SIZE.at_shader_location(3),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        if key.bind_group_data.additive {
+            let target = descriptor
+                .fragment
+                .as_mut()
+                .and_then(|f| f.targets.first_mut())
+                .and_then(|t| t.as_mut());
+            if let Some(target) = target {
+                target.blend = Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::SrcAlpha,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent::OVER,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The entity holding the point mesh
+#[derive(Component)]
+pub struct BodyPoints;
+
+/// One body's point: world position, diameter (px) and linear RGBA color
+pub struct Point {
+    pub position: Vec2,
+    pub size: f32,
+    pub color: [f32; 4],
+}
+
+/// A point mesh holding `points`
+pub fn new_mesh(points: impl Iterator<Item = Point>) -> Mesh {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    write_points(&mut mesh, points);
+    mesh
+}
+
+pub fn spawn_body_points(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PointMaterial>>,
+) {
+    commands.spawn((
+        Mesh2d(meshes.add(new_mesh(std::iter::empty()))),
+        MeshMaterial2d(materials.add(PointMaterial::default())),
+        Transform::default(),
+        // The mesh changes every frame, so its bounds would go stale
+        NoFrustumCulling,
+        // Also drawn into the trail image
+        RenderLayers::from_layers(&[0, crate::trails::LAYER]),
+        BodyPoints,
+    ));
+}
+
+/// Take an attribute's buffer out of the mesh to refill it, so its
+/// allocation is reused; empty if missing
+fn take<T>(
+    mesh: &mut Mesh,
+    id: MeshVertexAttribute,
+    unwrap: fn(VertexAttributeValues) -> Option<Vec<T>>,
+) -> Vec<T> {
+    let mut values = mesh
+        .remove_attribute(id)
+        .and_then(unwrap)
+        .unwrap_or_default();
+    values.clear();
+    values
+}
+
+/// Replace the mesh contents with one quad per point
+pub fn write_points(mesh: &mut Mesh, points: impl Iterator<Item = Point>) {
+    use VertexAttributeValues as V;
+    let mut positions = take(mesh, Mesh::ATTRIBUTE_POSITION, |v| match v {
+        V::Float32x3(v) => Some(v),
+        _ => None,
+    });
+    let mut sizes = take(mesh, ATTRIBUTE_POINT_SIZE, |v| match v {
+        V::Float32(v) => Some(v),
+        _ => None,
+    });
+    let mut colors = take(mesh, Mesh::ATTRIBUTE_COLOR, |v| match v {
+        V::Float32x4(v) => Some(v),
+        _ => None,
+    });
+    for p in points {
+        positions.extend([[p.position.x, p.position.y, 0.0]; 4]);
+        sizes.extend([p.size; 4]);
+        colors.extend([p.color; 4]);
+    }
+    // An empty vertex buffer can't be uploaded; draw one invisible point instead
+    if positions.is_empty() {
+        positions.extend([[0.0; 3]; 4]);
+        sizes.extend([0.0; 4]);
+        colors.extend([[0.0; 4]; 4]);
+    }
+    let vertices = positions.len();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(ATTRIBUTE_POINT_SIZE, sizes);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+
+    // Corners and indices only depend on the point count
+    if mesh.attribute(Mesh::ATTRIBUTE_UV_0).map(|a| a.len()) != Some(vertices) {
+        let corners: Vec<[f32; 2]> = CORNERS.iter().copied().cycle().take(vertices).collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, corners);
+        let indices = (0..(vertices / 4) as u32)
+            .flat_map(|q| [0, 1, 2, 0, 2, 3].map(|k| 4 * q + k))
+            .collect();
+        mesh.insert_indices(Indices::U32(indices));
+    }
+}
+
+/// Switch the bodies between normal and additive blending
+pub fn toggle_glow(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    q: Query<&MeshMaterial2d<PointMaterial>, With<BodyPoints>>,
+    mut materials: ResMut<Assets<PointMaterial>>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleGlow) {
+        return;
+    }
+    for handle in q.iter() {
+        if let Some(material) = materials.get_mut(&handle.0) {
+            material.additive = !material.additive;
+            material.params.intensity = if material.additive {
+                GLOW_INTENSITY
+            } else {
+                1.0
+            };
+            info!("Additive glow: {}", material.additive);
+        }
+    }
+}
+
+/// The menu shows no bodies
+pub fn clear_points(mut meshes: ResMut<Assets<Mesh>>, q: Query<&Mesh2d, With<BodyPoints>>) {
+    for handle in q.iter() {
+        if let Some(mesh) = meshes.get_mut(&handle.0) {
+            write_points(mesh, std::iter::empty());
+        }
+    }
+}
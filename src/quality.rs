@@ -0,0 +1,143 @@
+//! Adaptive quality: a governor that watches the frame time and, when it
+//! stays above the target frame period, degrades the work nobody needs at
+//! full rate. Each level doubles the potential-energy and diagnostics
+//! intervals, halves the trail length and the heatmap resolution; levels
+//! are given back one at a time while there is headroom. Leaving the last
+//! degraded level restores the settings exactly as they were, so changes
+//! made to these knobs while degraded are overwritten.
+
+use bevy::prelude::*;
+
+use crate::binaries::BinaryScan;
+use crate::fof::FofGroups;
+use crate::gpu_density::{self, GpuDensity};
+use crate::physics::PhysicsSettings;
+use crate::structure::StructureDiagnostics;
+use crate::trails::{self, Trails};
+
+/// Wall-clock seconds between decisions
+const EVALUATE_INTERVAL: f64 = 1.0;
+/// Smoothing of the frame time, per frame
+const SMOOTHING: f32 = 0.1;
+/// Degrade above this share of the target frame period...
+const OVERLOAD: f32 = 1.1;
+/// ...and restore below this one
+const HEADROOM: f32 = 0.7;
+const MAX_LEVEL: u32 = 4;
+/// Coarsest heatmap the governor goes down to (texels per side)
+const MIN_HEATMAP: u32 = 32;
+
+/// The knobs as they were before the first degraded level
+#[derive(Clone, Copy, Debug)]
+struct Baseline {
+    energy_interval: u64,
+    trail_decay: f32,
+    heatmap: u32,
+    structure_interval: u64,
+    binaries_interval: u64,
+    groups_interval: u64,
+}
+
+#[derive(Resource)]
+pub struct QualityGovernor {
+    /// Frame rate to hold, `None` when the governor is off
+    pub target_fps: Option<f32>,
+    /// 0 is full quality
+    pub level: u32,
+    /// Smoothed frame time (s)
+    pub frame_time: f32,
+    baseline: Option<Baseline>,
+    last_decision: f64,
+}
+
+impl Default for QualityGovernor {
+    fn default() -> Self {
+        Self {
+            target_fps: None,
+            level: 0,
+            frame_time: 0.0,
+            baseline: None,
+            last_decision: 0.0,
+        }
+    }
+}
+
+impl QualityGovernor {
+    /// Turn the governor on with a target, or off with `None`; switching it
+    /// off returns to full quality at the next frame
+    pub fn set_target(&mut self, target_fps: Option<f32>) {
+        self.target_fps = target_fps;
+        if target_fps.is_none() {
+            self.level = 0;
+        }
+    }
+}
+
+pub fn govern_quality(
+    time: Res<Time<Real>>,
+    mut governor: ResMut<QualityGovernor>,
+    mut settings: ResMut<PhysicsSettings>,
+    mut trails: ResMut<Trails>,
+    mut heatmap: ResMut<GpuDensity>,
+    mut structure: ResMut<StructureDiagnostics>,
+    mut binaries: ResMut<BinaryScan>,
+    mut groups: ResMut<FofGroups>,
+) {
+    let governor = governor.as_mut();
+    let dt = time.delta_secs();
+    governor.frame_time += SMOOTHING * (dt - governor.frame_time);
+
+    let level = governor.level;
+    if let Some(fps) = governor.target_fps {
+        let now = time.elapsed_secs_f64();
+        if now - governor.last_decision < EVALUATE_INTERVAL {
+            return;
+        }
+        governor.last_decision = now;
+        let load = governor.frame_time * fps;
+        if load > OVERLOAD && level < MAX_LEVEL {
+            governor.level += 1;
+        } else if load < HEADROOM && level > 0 {
+            governor.level -= 1;
+        }
+    }
+    if governor.level == level && (level > 0 || governor.baseline.is_none()) {
+        return;
+    }
+
+    let baseline = *governor.baseline.get_or_insert(Baseline {
+        energy_interval: settings.energy_interval,
+        trail_decay: trails.decay,
+        heatmap: heatmap.resolution,
+        structure_interval: structure.interval,
+        binaries_interval: binaries.interval,
+        groups_interval: groups.interval,
+    });
+    let level = governor.level;
+    let factor = 1u64 << level;
+    settings.energy_interval = baseline.energy_interval * factor;
+    structure.interval = baseline.structure_interval * factor;
+    binaries.interval = baseline.binaries_interval * factor;
+    groups.interval = baseline.groups_interval * factor;
+    // Brightness left after n frames is decay^n: squaring halves the length
+    trails.decay = baseline
+        .trail_decay
+        .powi(factor as i32)
+        .max(trails::DECAY_RANGE.0)
+        .min(baseline.trail_decay);
+    heatmap.resolution = (baseline.heatmap >> level)
+        .max(MIN_HEATMAP)
+        .min(baseline.heatmap)
+        .min(gpu_density::SIZE);
+    if level == 0 {
+        governor.baseline = None;
+        info!("Quality restored");
+    } else {
+        info!(
+            "Quality level {level}: frame {:.1} ms, energy every {} steps, heatmap {}²",
+            governor.frame_time * 1e3,
+            settings.energy_interval,
+            heatmap.resolution
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Azimuthally averaged profiles around the center of mass: surface density
+//! and velocity dispersion in equal-width annuli out to twice the half-mass
+//! radius, recomputed every few steps, plotted in a panel and exportable.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::diagnostics::is_due;
+use crate::hud::{HudItem, Overlay};
+use crate::locale::{Language, fill, tr};
+use crate::units::CODE;
+use crate::{Bodies, BodyState};
+
+const ANNULI: usize = 24;
+/// Outer edge of the last annulus, in half-mass radii
+const EXTENT: f32 = 2.0;
+const DEFAULT_INTERVAL: u64 = 30;
+const PLOT_WIDTH: u32 = 240;
+const PLOT_HEIGHT: u32 = 120;
+const BACKGROUND: [u8; 4] = [0, 0, 0, 200];
+const DENSITY_COLOR: [u8; 4] = [120, 180, 255, 255];
+const DISPERSION_COLOR: [u8; 4] = [255, 160, 60, 255];
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Annulus {
+    /// Inner and outer radius
+    pub inner: f32,
+    pub outer: f32,
+    pub bodies: usize,
+    pub surface_density: f64,
+    /// One-dimensional velocity dispersion, the mean of the radial and
+    /// tangential ones; `None` with fewer than two bodies
+    pub dispersion: Option<f64>,
+}
+
+#[derive(Resource)]
+pub struct RadialProfiles {
+    pub panel: bool,
+    /// Recompute every this many steps
+    pub interval: u64,
+    last_step: Option<u64>,
+    pub annuli: Vec<Annulus>,
+}
+
+impl Default for RadialProfiles {
+    fn default() -> Self {
+        Self {
+            panel: false,
+            interval: DEFAULT_INTERVAL,
+            last_step: None,
+            annuli: Vec::new(),
+        }
+    }
+}
+
+/// Mass-weighted mean and variance
+#[derive(Default)]
+struct Moments {
+    mass: f64,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl Moments {
+    fn add(&mut self, m: f64, v: f64) {
+        self.mass += m;
+        self.sum += m * v;
+        self.sum_sq += m * v * v;
+    }
+
+    fn variance(&self) -> f64 {
+        let mean = self.sum / self.mass;
+        (self.sum_sq / self.mass - mean * mean).max(0.0)
+    }
+}
+
+pub fn compute(data: &[BodyState]) -> Vec<Annulus> {
+    let mass: f64 = data.iter().map(|b| b.mass as f64).sum();
+    if data.len() < 2 || mass <= 0.0 {
+        return Vec::new();
+    }
+    let weighted = |f: fn(&BodyState) -> f32| {
+        (data
+            .iter()
+            .map(|b| b.mass as f64 * f(b) as f64)
+            .sum::<f64>()
+            / mass) as f32
+    };
+    let center = Vec2::new(weighted(|b| b.x), weighted(|b| b.y));
+    let drift = Vec2::new(weighted(|b| b.vx), weighted(|b| b.vy));
+
+    let mut by_radius: Vec<(f32, f32)> = data
+        .iter()
+        .map(|b| ((Vec2::new(b.x, b.y) - center).length(), b.mass))
+        .collect();
+    by_radius.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut enclosed = 0.0;
+    let half_mass_radius = by_radius
+        .iter()
+        .find(|&&(_, m)| {
+            enclosed += m as f64;
+            enclosed >= 0.5 * mass
+        })
+        .map_or(0.0, |&(r, _)| r);
+    let width = EXTENT * half_mass_radius / ANNULI as f32;
+    if !(width > 0.0) {
+        return Vec::new();
+    }
+
+    let mut sums: Vec<(f64, usize, Moments, Moments)> =
+        (0..ANNULI).map(|_| Default::default()).collect();
+    for b in data {
+        let offset = Vec2::new(b.x, b.y) - center;
+        let k = (offset.length() / width) as usize;
+        let Some((m, n, radial, tangential)) = sums.get_mut(k) else {
+            continue;
+        };
+        let dir = offset.normalize_or_zero();
+        let v = Vec2::new(b.vx, b.vy) - drift;
+        *m += b.mass as f64;
+        *n += 1;
+        radial.add(b.mass as f64, v.dot(dir) as f64);
+        tangential.add(b.mass as f64, v.perp_dot(dir) as f64);
+    }
+    sums.into_iter()
+        .enumerate()
+        .map(|(k, (m, n, radial, tangential))| {
+            let (inner, outer) = (k as f32 * width, (k + 1) as f32 * width);
+            let area = std::f64::consts::PI * ((outer as f64).powi(2) - (inner as f64).powi(2));
+            Annulus {
+                inner,
+                outer,
+                bodies: n,
+                surface_density: m / area,
+                dispersion: (n >= 2 && m > 0.0)
+                    .then(|| (0.5 * (radial.variance() + tangential.variance())).sqrt()),
+            }
+        })
+        .collect()
+}
+
+impl RadialProfiles {
+    /// Write the annuli as CSV, in SI
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        if self.annuli.is_empty() {
+            return Err("no profiles yet (console: profiles on)".into());
+        }
+        let mut out = String::from("inner_m,outer_m,bodies,surface_density_kg_m2,dispersion_m_s\n");
+        for a in &self.annuli {
+            let dispersion = a.dispersion.map_or(String::new(), |s| {
+                format!("{:e}", s * CODE.length / CODE.time)
+            });
+            let _ = writeln!(
+                out,
+                "{:e},{:e},{},{:e},{dispersion}",
+                CODE.length(a.inner),
+                CODE.length(a.outer),
+                a.bodies,
+                CODE.surface_density(a.surface_density)
+            );
+        }
+        fs::write(path, out).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+/// Recompute every `interval` steps while the panel is shown
+pub fn update_radial_profiles(bodies: Res<Bodies>, mut profiles: ResMut<RadialProfiles>) {
+    if !profiles.panel || !is_due(profiles.last_step, bodies.step, profiles.interval) {
+        return;
+    }
+    profiles.last_step = Some(bodies.step);
+    profiles.annuli = compute(&bodies.data);
+}
+
+#[derive(Component)]
+pub struct ProfilePanel;
+
+#[derive(Component)]
+pub struct ProfilePlot;
+
+#[derive(Component)]
+pub struct ProfileText;
+
+pub fn spawn_profile_panel(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    asset_server: Res<AssetServer>,
+) {
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: PLOT_WIDTH,
+            height: PLOT_HEIGHT,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &BACKGROUND,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(20.0),
+                    top: Val::Px(330.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..Default::default()
+                },
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            ProfilePanel,
+            HudItem(Overlay::RadialProfiles),
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                ProfileText,
+            ));
+            panel.spawn((
+                ImageBundle {
+                    style: Style {
+                        width: Val::Px(PLOT_WIDTH as f32),
+                        height: Val::Px(PLOT_HEIGHT as f32),
+                        ..Default::default()
+                    },
+                    image: UiImage::new(image),
+                    ..Default::default()
+                },
+                ProfilePlot,
+            ));
+        });
+}
+
+/// Step plot of each profile, each scaled to its own range: log surface
+/// density in blue, dispersion from zero in orange
+pub fn update_profile_panel(
+    profiles: Res<RadialProfiles>,
+    lang: Res<Language>,
+    mut images: ResMut<Assets<Image>>,
+    mut panel_q: Query<&mut Visibility, With<ProfilePanel>>,
+    plot_q: Query<&UiImage, With<ProfilePlot>>,
+    mut text_q: Query<&mut Text, With<ProfileText>>,
+) {
+    if !profiles.is_changed() && !lang.is_changed() {
+        return;
+    }
+    for mut vis in panel_q.iter_mut() {
+        *vis = if profiles.panel {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if !profiles.panel {
+        return;
+    }
+    let Some(image) = plot_q.iter().find_map(|ui| images.get_mut(&ui.texture)) else {
+        return;
+    };
+    for px in image.data.chunks_exact_mut(4) {
+        px.copy_from_slice(&BACKGROUND);
+    }
+
+    let log_density: Vec<Option<f64>> = profiles
+        .annuli
+        .iter()
+        .map(|a| (a.surface_density > 0.0).then(|| CODE.surface_density(a.surface_density).log10()))
+        .collect();
+    let (lo, hi) = log_density
+        .iter()
+        .flatten()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let top_dispersion = profiles
+        .annuli
+        .iter()
+        .filter_map(|a| a.dispersion)
+        .fold(0.0, f64::max);
+    let columns = PLOT_WIDTH as usize / ANNULI.max(1);
+    let mut plot = |k: usize, t: f64, color: &[u8; 4]| {
+        let row = ((1.0 - t.clamp(0.0, 1.0)) * (PLOT_HEIGHT - 2) as f64) as usize;
+        for dy in 0..2 {
+            for col in k * columns..(k + 1) * columns {
+                let i = 4 * ((row + dy) * PLOT_WIDTH as usize + col);
+                image.data[i..i + 4].copy_from_slice(color);
+            }
+        }
+    };
+    for (k, a) in profiles.annuli.iter().enumerate() {
+        if let Some(v) = log_density[k] {
+            plot(k, (v - lo) / (hi - lo).max(f64::EPSILON), &DENSITY_COLOR);
+        }
+        if let Some(s) = a.dispersion.filter(|_| top_dispersion > 0.0) {
+            plot(k, s / top_dispersion, &DISPERSION_COLOR);
+        }
+    }
+
+    if let Ok(mut t) = text_q.get_single_mut() {
+        let lang = *lang;
+        t.sections[0].value = match profiles.annuli.last() {
+            None => tr("radial profiles: waiting", "반경 분포: 대기 중")
+                .get(lang)
+                .to_string(),
+            Some(outer) => fill(
+                tr(
+                    "radial profiles to {} m\nblue: log Σ {} … {} kg/m²\norange: σ up to {} km/s",
+                    "반경 분포 ({} m까지)\n파란색: log Σ {} … {} kg/m²\n주황색: σ 최대 {} km/s",
+                )
+                .get(lang),
+                &[
+                    &format!("{:.2E}", CODE.length(outer.outer)),
+                    &format!("{lo:.2}"),
+                    &format!("{hi:.2}"),
+                    &format!("{:.2}", top_dispersion * CODE.length / CODE.time / 1.0E3),
+                ],
+            ),
+        };
+    }
+}
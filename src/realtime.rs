@@ -0,0 +1,135 @@
+//! Real-time factor (simulated seconds per wall-clock second) and an auto
+//! mode that retunes the physics step rate to reach a target factor.
+//!
+//! dt is never touched: the auto mode only changes how many fixed physics
+//! steps run per second, so accuracy stays the same at any speed.
+
+use bevy::prelude::*;
+
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, tr};
+use crate::physics::PhysicsSettings;
+use crate::units::CODE;
+use crate::{Bodies, PHYSICS_HZ, UiRealTime};
+
+/// Wall-clock seconds between measurements
+const SAMPLE_INTERVAL: f64 = 0.5;
+/// Range the auto mode may move the fixed step rate in (steps per second)
+const MIN_STEP_HZ: f64 = 1.0;
+const MAX_STEP_HZ: f64 = 240.0;
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct RealTimeFactor {
+    /// Last measured simulated / wall-clock time ratio
+    pub measured: f64,
+    /// Retune the step rate towards `target`
+    pub auto: bool,
+    pub target: f64,
+    last_wall: f64,
+    last_sim: f64,
+}
+
+impl Default for RealTimeFactor {
+    fn default() -> Self {
+        Self {
+            measured: 0.0,
+            auto: false,
+            target: 1.0E8,
+            last_wall: 0.0,
+            last_sim: 0.0,
+        }
+    }
+}
+
+impl RealTimeFactor {
+    /// Turn the auto mode on with a new target, or off with `None`
+    pub fn set_target(&mut self, target: Option<f64>) {
+        match target {
+            Some(t) => {
+                self.target = t;
+                self.auto = true;
+            }
+            None => self.auto = false,
+        }
+    }
+}
+
+/// Sample the factor and, in auto mode, set the fixed step rate to
+/// target / dt, clamped to what the frame loop can reasonably deliver
+pub fn update_real_time_factor(
+    real: Res<Time<Real>>,
+    bodies: Res<Bodies>,
+    settings: Res<PhysicsSettings>,
+    mut rtf: ResMut<RealTimeFactor>,
+    mut fixed: ResMut<Time<Fixed>>,
+) {
+    let wall = real.elapsed_secs_f64();
+    let sim = bodies.elapsed_time as f64 * CODE.time;
+    if sim < rtf.last_sim {
+        // A new run started
+        rtf.last_sim = sim;
+    }
+    let span = wall - rtf.last_wall;
+    if span < SAMPLE_INTERVAL {
+        return;
+    }
+    rtf.measured = (sim - rtf.last_sim) / span;
+    rtf.last_wall = wall;
+    rtf.last_sim = sim;
+
+    let hz = if rtf.auto {
+        let dt = settings.dt.abs() as f64 * CODE.time;
+        (rtf.target / dt).clamp(MIN_STEP_HZ, MAX_STEP_HZ)
+    } else {
+        PHYSICS_HZ
+    };
+    if (fixed.timestep().as_secs_f64() * hz - 1.0).abs() > 1e-6 {
+        fixed.set_timestep_hz(hz);
+    }
+}
+
+pub fn toggle_auto_real_time(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut rtf: ResMut<RealTimeFactor>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleAutoRealTime) {
+        rtf.auto = !rtf.auto;
+        info!(
+            "Auto real-time factor: {} (target {:.2E})",
+            rtf.auto, rtf.target
+        );
+    }
+}
+
+pub fn update_real_time_text(
+    rtf: Res<RealTimeFactor>,
+    fixed: Res<Time<Fixed>>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<UiRealTime>>,
+) {
+    if !rtf.is_changed() && !lang.is_changed() {
+        return;
+    }
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    let lang = *lang;
+    let mode = if rtf.auto {
+        format!(
+            "{} {:.2E}",
+            tr("auto, target", "자동, 목표").get(lang),
+            rtf.target
+        )
+    } else {
+        tr("fixed", "고정").get(lang).to_string()
+    };
+    t.sections[0].value = format!(
+        "{}: {:.2E}  ({mode}, {:.0} {})",
+        tr("real-time factor", "실시간 배율").get(lang),
+        rtf.measured,
+        1.0 / fixed.timestep().as_secs_f64(),
+        tr("steps/s", "스텝/초").get(lang)
+    );
+}
@@ -0,0 +1,177 @@
+//! Two-body regularization of the tightest bound pair: its relative orbit is
+//! advanced analytically on the Kepler ellipse instead of by leapfrog, so a
+//! hard binary neither needs a tiny global timestep nor pumps energy into
+//! the run. The pair's center of mass still follows the leapfrog step, which
+//! carries the pull of everyone else; the tidal stretch across the pair is
+//! neglected.
+
+use bevy::prelude::*;
+
+use crate::BodyState;
+use crate::binaries::{BinaryScan, two_body_energy};
+use crate::keybindings::{Action, KeyBindings};
+
+/// Pairs whose period spans fewer steps than this are regularized
+const DEFAULT_MIN_PERIOD_STEPS: f64 = 100.0;
+
+#[derive(Resource)]
+pub struct Regularization {
+    pub enabled: bool,
+    pub min_period_steps: f64,
+    /// Pair treated during the last step
+    pub current: Option<(usize, usize)>,
+}
+
+impl Default for Regularization {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_period_steps: DEFAULT_MIN_PERIOD_STEPS,
+            current: None,
+        }
+    }
+}
+
+/// Both members as they were at the start of the step
+#[derive(Clone, Copy, Debug)]
+pub struct RegularizedPair {
+    pub i: usize,
+    pub j: usize,
+    a: BodyState,
+    b: BodyState,
+}
+
+impl Regularization {
+    /// The tightest pair of the latest binary scan, if it is still bound and
+    /// too tight for a timestep of `dt`
+    pub fn choose(
+        &mut self,
+        data: &[BodyState],
+        scan: &BinaryScan,
+        dt: f32,
+        g: f32,
+    ) -> Option<RegularizedPair> {
+        let pair = self
+            .enabled
+            .then(|| scan.pairs.first())
+            .flatten()
+            .and_then(|p| Some((p.i, p.j, data.get(p.i)?, data.get(p.j)?)))
+            .filter(|(_, _, a, b)| !a.fixed && !b.fixed)
+            .filter(|(_, _, a, b)| {
+                let energy = two_body_energy(a, b, g);
+                let mu = g as f64 * (a.mass as f64 + b.mass as f64);
+                let reduced = a.mass as f64 * b.mass as f64 / (a.mass as f64 + b.mass as f64);
+                // E = -μ m_red / 2a, T = 2π sqrt(a³/μ)
+                let semi_major_axis = -mu * reduced / (2.0 * energy);
+                let period = std::f64::consts::TAU * (semi_major_axis.powi(3) / mu).sqrt();
+                energy < 0.0 && period < self.min_period_steps * dt as f64
+            })
+            .map(|(i, j, a, b)| RegularizedPair { i, j, a: *a, b: *b });
+
+        let current = pair.map(|p| (p.i, p.j));
+        if current != self.current {
+            match current {
+                Some((i, j)) => info!("Regularizing the bound pair #{i} – #{j}"),
+                None => info!("No pair regularized"),
+            }
+            self.current = current;
+        }
+        pair
+    }
+}
+
+impl RegularizedPair {
+    /// Replace the leapfrog relative motion of the pair in `data` (already
+    /// advanced by `dt`) with the Kepler solution from the start of the step
+    pub fn apply(&self, data: &mut [BodyState], dt: f32, g: f32) {
+        let (ma, mb) = (self.a.mass as f64, self.b.mass as f64);
+        let total = ma + mb;
+        let rel = |a: &BodyState, b: &BodyState| {
+            [
+                (b.x - a.x) as f64,
+                (b.y - a.y) as f64,
+                (b.vx - a.vx) as f64,
+                (b.vy - a.vy) as f64,
+            ]
+        };
+        let Some([x, y, vx, vy]) = kepler_step(rel(&self.a, &self.b), g as f64 * total, dt as f64)
+        else {
+            return;
+        };
+
+        let (Some(a), Some(b)) = (data.get(self.i), data.get(self.j)) else {
+            return;
+        };
+        let com = [
+            (ma * a.x as f64 + mb * b.x as f64) / total,
+            (ma * a.y as f64 + mb * b.y as f64) / total,
+            (ma * a.vx as f64 + mb * b.vx as f64) / total,
+            (ma * a.vy as f64 + mb * b.vy as f64) / total,
+        ];
+        for (k, share) in [(self.i, -mb / total), (self.j, ma / total)] {
+            let body = &mut data[k];
+            body.x = (com[0] + share * x) as f32;
+            body.y = (com[1] + share * y) as f32;
+            body.vx = (com[2] + share * vx) as f32;
+            body.vy = (com[3] + share * vy) as f32;
+        }
+    }
+}
+
+/// Advance a bound relative orbit [x, y, vx, vy] by `dt` with Lagrange's f
+/// and g functions in the eccentric-anomaly change; `None` if unbound
+fn kepler_step(state: [f64; 4], mu: f64, dt: f64) -> Option<[f64; 4]> {
+    let [x, y, vx, vy] = state;
+    let r0 = x.hypot(y);
+    let energy = 0.5 * (vx * vx + vy * vy) - mu / r0;
+    if r0 == 0.0 || energy >= 0.0 {
+        return None;
+    }
+    let a = -mu / (2.0 * energy);
+    let n = (mu / a.powi(3)).sqrt();
+    let sigma = (x * vx + y * vy) / mu.sqrt();
+
+    // Kepler's equation for the change ΔE of eccentric anomaly:
+    // n dt = ΔE - (1 - r0/a) sin ΔE + σ0/√a (1 - cos ΔE)
+    let mean = n * dt;
+    let (c1, c2) = (1.0 - r0 / a, sigma / a.sqrt());
+    let mut de = mean;
+    for _ in 0..50 {
+        let (s, c) = de.sin_cos();
+        let f = de - c1 * s + c2 * (1.0 - c) - mean;
+        let fp = 1.0 - c1 * c + c2 * s;
+        let delta = f / fp;
+        de -= delta;
+        if delta.abs() < 1e-12 {
+            break;
+        }
+    }
+
+    let (s, c) = de.sin_cos();
+    let r = a + (r0 - a) * c + sigma * a.sqrt() * s;
+    let f = 1.0 - a / r0 * (1.0 - c);
+    let g = dt + (s - de) / n;
+    let f_dot = -(mu * a).sqrt() / (r * r0) * s;
+    let g_dot = 1.0 - a / r * (1.0 - c);
+    Some([
+        f * x + g * vx,
+        f * y + g * vy,
+        f_dot * x + g_dot * vx,
+        f_dot * y + g_dot * vy,
+    ])
+}
+
+/// Regularization key switches the analytic pair treatment on / off
+pub fn toggle_regularization(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut reg: ResMut<Regularization>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleRegularization) {
+        reg.enabled = !reg.enabled;
+        info!(
+            "Close-pair regularization {}",
+            if reg.enabled { "on" } else { "off" }
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Remote live viewing: `serve <addr> [bodies] [seed]` runs a Plummer sphere
+//! headlessly and streams its positions over TCP to every connected viewer;
+//! `--connect <addr>` starts the app as a viewer that shows the stream
+//! instead of simulating anything itself.
+//!
+//! Viewers can also add bodies: dragging with the left button and letting
+//! go flings a body from where the drag started, against the drag. The
+//! viewer only sends the request; the server checks it, adds the body
+//! between two steps and every viewer sees it in the next broadcast, so
+//! several people can play in one shared simulation.
+//!
+//! Messages are a kind byte and a little-endian u32 payload length. A new
+//! viewer first gets the masses, then one positions message per broadcast;
+//! the masses are sent again to everyone whenever bodies were added.
+//! Positions are quantized to 16 bits per axis within the bounding box of
+//! the bodies, which halves the stream at a resolution of 1/65535 of the
+//! box, far below a pixel. Both ends are this program, so every value on
+//! the wire is in code units.
+
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use bevy::window::PrimaryWindow;
+
+use crate::constants::PhysicsConstants;
+use crate::headless::{RunConfig, Simulation};
+use crate::menu::AppState;
+use crate::units::SI;
+use crate::{Bodies, BodyState, MAX_MASS, MIN_MASS, MainCamera, world_scale};
+
+const MASSES: u8 = 0;
+const POSITIONS: u8 = 1;
+/// Viewer to server: add a body
+const SPAWN: u8 = 2;
+/// Broadcasts per second of wall-clock time
+const SEND_HZ: f64 = 30.0;
+/// A viewer that can't take a message within this long is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_BODIES: usize = 1000;
+const QUANT: f32 = u16::MAX as f32;
+/// A flung body covers the length of the drag in this long
+const FLING_TIME: f32 = SI.time(5.0E10);
+/// Requests beyond this many bodies are ignored
+const MAX_BODIES: usize = 20_000;
+
+/// Largest payload of each message kind; longer headers are rejected before
+/// anything is allocated
+fn max_payload(kind: u8) -> Option<usize> {
+    match kind {
+        MASSES => Some(4 + 4 * MAX_BODIES),
+        POSITIONS => Some(52 + 4 * MAX_BODIES),
+        SPAWN => Some(20),
+        _ => None,
+    }
+}
+
+/// Run the server until killed
+pub fn serve(addr: &str, bodies: Option<usize>, seed: Option<u64>) -> io::Result<()> {
+    let config = RunConfig {
+        bodies: bodies.unwrap_or(DEFAULT_BODIES).min(MAX_BODIES),
+        dt: crate::D_TIME,
+        softening: 0.0,
+        seed: seed.unwrap_or(0),
+        scale_radius: SI.length(5.0E13),
+    };
+    let mut sim = Simulation::new(&config, &PhysicsConstants::read_asset_file());
+    let listener = TcpListener::bind(addr)?;
+    info!("Serving {} bodies on {addr}", config.bodies);
+
+    // Viewers accepted since the last broadcast, still waiting for the masses
+    let joined: Arc<Mutex<Vec<TcpStream>>> = Arc::default();
+    // Bodies requested by any viewer since the last step
+    let requested: Arc<Mutex<Vec<BodyState>>> = Arc::default();
+    let (accepted, inbox) = (joined.clone(), requested.clone());
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_nodelay(true);
+            let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+            if let Ok(peer) = stream.peer_addr() {
+                info!("viewer connected from {peer}");
+            }
+            if let Ok(reader) = stream.try_clone() {
+                let inbox = inbox.clone();
+                thread::spawn(move || read_requests(reader, &inbox));
+            }
+            accepted.lock().unwrap().push(stream);
+        }
+    });
+
+    let mut viewers: Vec<BufWriter<TcpStream>> = Vec::new();
+    let mut masses_changed = false;
+    let interval = Duration::from_secs_f64(1.0 / SEND_HZ);
+    let mut next_send = Instant::now();
+    loop {
+        let new_bodies = std::mem::take(&mut *requested.lock().unwrap());
+        if !new_bodies.is_empty() {
+            let room = MAX_BODIES.saturating_sub(sim.bodies.len());
+            sim.add_bodies(new_bodies.into_iter().take(room));
+            masses_changed = true;
+        }
+        sim.step();
+        if Instant::now() < next_send {
+            continue;
+        }
+        next_send += interval;
+        let new: Vec<TcpStream> = std::mem::take(&mut *joined.lock().unwrap());
+        if viewers.is_empty() && new.is_empty() {
+            continue;
+        }
+        let masses = encode_masses(&sim.bodies);
+        if std::mem::take(&mut masses_changed) {
+            viewers.retain_mut(|w| write_message(w, MASSES, &masses).is_ok());
+        }
+        for stream in new {
+            let mut w = BufWriter::new(stream);
+            if write_message(&mut w, MASSES, &masses).is_ok() {
+                viewers.push(w);
+            }
+        }
+        let positions = encode_positions(&sim);
+        viewers.retain_mut(|w| match write_message(w, POSITIONS, &positions) {
+            Ok(()) => true,
+            Err(e) => {
+                info!("viewer dropped: {e}");
+                false
+            }
+        });
+    }
+}
+
+/// Queue the bodies one viewer asks for until it disconnects or sends
+/// something malformed, which drops it
+fn read_requests(stream: TcpStream, inbox: &Mutex<Vec<BodyState>>) {
+    let mut r = BufReader::new(stream);
+    while let Ok(message) = read_message(&mut r) {
+        let Message::Spawn([x, y, vx, vy, mass]) = message else {
+            continue;
+        };
+        if ![x, y, vx, vy, mass].iter().all(|v| v.is_finite()) {
+            continue;
+        }
+        let mut b = BodyState::new();
+        b.mass = mass.clamp(MIN_MASS, MAX_MASS);
+        (b.x, b.y, b.vx, b.vy) = (x, y, vx, vy);
+        (b.x_prev, b.y_prev) = (x, y);
+        inbox.lock().unwrap().push(b);
+    }
+    // The writer half goes too, at the next broadcast
+    let _ = r.get_ref().shutdown(Shutdown::Both);
+}
+
+fn write_message(w: &mut impl Write, kind: u8, payload: &[u8]) -> io::Result<()> {
+    w.write_all(&[kind])?;
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(payload)?;
+    w.flush()
+}
+
+/// Body count, then one f32 mass per body
+fn encode_masses(bodies: &[BodyState]) -> Vec<u8> {
+    let mut out = (bodies.len() as u32).to_le_bytes().to_vec();
+    for b in bodies {
+        out.extend(b.mass.to_le_bytes());
+    }
+    out
+}
+
+/// Step, simulated time, kinetic and potential energy, bounding box, then
+/// the quantized [x, y] of each body
+fn encode_positions(sim: &Simulation) -> Vec<u8> {
+    let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+    for b in &sim.bodies {
+        min = [min[0].min(b.x), min[1].min(b.y)];
+        max = [max[0].max(b.x), max[1].max(b.y)];
+    }
+    let span = [(max[0] - min[0]).max(1.0), (max[1] - min[1]).max(1.0)];
+    let mut out = Vec::with_capacity(56 + 4 * sim.bodies.len());
+    out.extend(sim.step.to_le_bytes());
+    out.extend(sim.time.to_le_bytes());
+    out.extend(sim.kinetic_energy().to_le_bytes());
+    out.extend(sim.potential_energy().to_le_bytes());
+    out.extend((sim.bodies.len() as u32).to_le_bytes());
+    for v in [min[0], min[1], span[0], span[1]] {
+        out.extend(v.to_le_bytes());
+    }
+    for b in &sim.bodies {
+        for (k, p) in [b.x, b.y].into_iter().enumerate() {
+            let q = ((p - min[k]) / span[k] * QUANT).round() as u16;
+            out.extend(q.to_le_bytes());
+        }
+    }
+    out
+}
+
+/// One broadcast, decoded
+struct RemoteFrame {
+    step: u64,
+    time: f64,
+    kinetic_energy: f64,
+    potential_energy: f64,
+    positions: Vec<[f32; 2]>,
+}
+
+enum Message {
+    Masses(Vec<f32>),
+    Positions(RemoteFrame),
+    /// [x, y, vx, vy, mass] of a body to add
+    Spawn([f32; 5]),
+}
+
+fn read_message(r: &mut impl Read) -> io::Result<Message> {
+    let mut header = [0u8; 5];
+    r.read_exact(&mut header)?;
+    let len = u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize;
+    match max_payload(header[0]) {
+        Some(max) if len <= max => {}
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("message of {len} bytes is too long"),
+            ));
+        }
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown message kind {}", header[0]),
+            ));
+        }
+    }
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    let mut p = Payload(&payload);
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, "truncated message");
+    match header[0] {
+        MASSES => {
+            let n = p.u32().ok_or_else(bad)? as usize;
+            let masses = (0..n).map(|_| p.f32()).collect::<Option<_>>();
+            Ok(Message::Masses(masses.ok_or_else(bad)?))
+        }
+        POSITIONS => {
+            let frame = (|| {
+                let (step, time) = (p.u64()?, p.f64()?);
+                let (kinetic_energy, potential_energy) = (p.f64()?, p.f64()?);
+                let n = p.u32()? as usize;
+                let (min_x, min_y, span_x, span_y) = (p.f32()?, p.f32()?, p.f32()?, p.f32()?);
+                let positions = (0..n)
+                    .map(|_| {
+                        let (qx, qy) = (p.u16()? as f32, p.u16()? as f32);
+                        Some([min_x + qx / QUANT * span_x, min_y + qy / QUANT * span_y])
+                    })
+                    .collect::<Option<_>>()?;
+                Some(RemoteFrame {
+                    step,
+                    time,
+                    kinetic_energy,
+                    potential_energy,
+                    positions,
+                })
+            })();
+            Ok(Message::Positions(frame.ok_or_else(bad)?))
+        }
+        SPAWN => {
+            let body = [p.f32(), p.f32(), p.f32(), p.f32(), p.f32()];
+            let body = body
+                .into_iter()
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(bad)?;
+            Ok(Message::Spawn(body.try_into().unwrap()))
+        }
+        _ => unreachable!("rejected by max_payload"),
+    }
+}
+
+/// Little-endian reader over a message payload
+struct Payload<'a>(&'a [u8]);
+
+impl Payload<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.0.split_first_chunk::<N>()?;
+        self.0 = rest;
+        Some(*head)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.take().map(f32::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.take().map(f64::from_le_bytes)
+    }
+}
+
+/// Viewer side, switched on by `connect`
+pub struct RemotePlugin {
+    pub connect: Option<String>,
+}
+
+impl Plugin for RemotePlugin {
+    fn build(&self, app: &mut App) {
+        let Some(addr) = &self.connect else {
+            return;
+        };
+        let viewer = RemoteViewer::connect(addr).unwrap_or_else(|e| {
+            error!("failed to connect to {addr}: {e}");
+            std::process::exit(1);
+        });
+        app.insert_resource(viewer)
+            .add_systems(Startup, enter_viewer)
+            .add_systems(PreUpdate, apply_remote_frames)
+            .add_systems(Update, fling_body);
+    }
+}
+
+/// Messages decoded by a reader thread, and the stream requests go out on
+#[derive(Resource)]
+pub struct RemoteViewer {
+    messages: Mutex<Receiver<Message>>,
+    stream: TcpStream,
+    masses: Vec<f32>,
+    /// Simulation coordinates where the current fling drag started
+    drag_start: Option<Vec2>,
+}
+
+impl RemoteViewer {
+    fn connect(addr: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut r = BufReader::new(reader);
+            loop {
+                match read_message(&mut r) {
+                    Ok(m) => {
+                        if tx.send(m).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("remote stream ended: {e}");
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            messages: Mutex::new(rx),
+            stream,
+            masses: Vec::new(),
+            drag_start: None,
+        })
+    }
+}
+
+/// The viewer simulates nothing itself
+pub fn viewing(viewer: Option<Res<RemoteViewer>>) -> bool {
+    viewer.is_some()
+}
+
+/// Skip the menu: the bodies come from the server
+fn enter_viewer(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::Running);
+}
+
+/// Show the latest positions received
+fn apply_remote_frames(mut viewer: ResMut<RemoteViewer>, mut bodies: ResMut<Bodies>) {
+    let viewer = &mut *viewer;
+    let mut latest = None;
+    for message in viewer.messages.get_mut().unwrap().try_iter() {
+        match message {
+            Message::Masses(m) => viewer.masses = m,
+            Message::Positions(frame) => latest = Some(frame),
+            Message::Spawn(_) => {}
+        }
+    }
+    let Some(frame) = latest else {
+        return;
+    };
+    let bodies = &mut *bodies;
+    bodies.data.resize(frame.positions.len(), BodyState::new());
+    for (i, (b, p)) in bodies.data.iter_mut().zip(&frame.positions).enumerate() {
+        b.mass = viewer.masses.get(i).copied().unwrap_or(b.mass);
+        (b.x_prev, b.y_prev) = (b.x, b.y);
+        (b.x, b.y) = (p[0], p[1]);
+    }
+    bodies.step = frame.step;
+    bodies.elapsed_time = frame.time as f32;
+    bodies.kinetic_energy = frame.kinetic_energy;
+    bodies.potential_energy = frame.potential_energy;
+}
+
+/// Drag with the left button and release to ask the server for a body,
+/// flung from the start of the drag against it
+fn fling_body(
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut viewer: ResMut<RemoteViewer>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let (Ok(window), Ok((camera, cam_tf))) = (win_q.get_single(), cam_q.get_single()) else {
+        return;
+    };
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|c| camera.viewport_to_world_2d(cam_tf, c).ok())
+    else {
+        return;
+    };
+    let position = cursor / world_scale(window);
+    if buttons.just_pressed(MouseButton::Left) {
+        viewer.drag_start = Some(position);
+    }
+    if !buttons.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(start) = viewer.drag_start.take() else {
+        return;
+    };
+    let v = (start - position) / FLING_TIME;
+    let mass = 0.5 * (MAX_MASS + MIN_MASS);
+    let mut payload = Vec::with_capacity(20);
+    for value in [start.x, start.y, v.x, v.y, mass] {
+        payload.extend(value.to_le_bytes());
+    }
+    if let Err(e) = write_message(&mut &viewer.stream, SPAWN, &payload) {
+        warn!("failed to send the body to the server: {e}");
+    }
+}
@@ -0,0 +1,341 @@
+//! Input recording and deterministic replay. `--record <file.ron>` saves the
+//! random seed, everything that shapes a run besides the inputs (window
+//! size, scenario file, preferences, key bindings, physics constants) and
+//! every keyboard and mouse event, each with the frame it arrived in and
+//! that frame's wall-clock length. `--replay <file.ron>` starts from the
+//! same setup and feeds the events back in the same frames, on a clock
+//! advanced by the recorded lengths, so every fixed physics step happens
+//! in the same frame as before.
+//!
+//! Both modes run the physics in lockstep: each force pass is awaited by the
+//! next fixed step instead of being polled, since how many passes finish
+//! per frame would otherwise depend on thread timing. The physics constants
+//! are read once at startup rather than watched, as a reload lands in
+//! whichever frame the asset server gets to it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput, NativeKey, NativeKeyCode};
+use bevy::input::mouse::{MouseButtonInput, MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::PhysicsConstants;
+use crate::keybindings::{Action, key_name, parse_key};
+use crate::settings::UserSettings;
+
+/// Bumped whenever a recording would replay differently, e.g. when the
+/// random generator changes. Version 1 is the first with Xoshiro256++;
+/// version 2 runs in code units and stores the constants in them.
+pub const FORMAT_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Recording {
+    /// [`FORMAT_VERSION`] of the build that recorded it; older files have
+    /// none and read as 0
+    #[serde(default)]
+    pub version: u32,
+    pub seed: u64,
+    /// Logical window size, which the cursor positions are relative to
+    pub window: (f32, f32),
+    /// `--scenario` file the run was started with
+    pub scenario: Option<PathBuf>,
+    pub settings: UserSettings,
+    pub keybindings: HashMap<Action, Vec<String>>,
+    /// In code units, exactly as the run used them
+    pub constants: PhysicsConstants,
+    pub frames: Vec<RecordedFrame>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RecordedFrame {
+    /// Wall-clock length of the frame (ns)
+    pub delta_ns: u64,
+    /// Logical cursor position, `None` outside the window
+    pub cursor: Option<(f32, f32)>,
+    pub events: Vec<InputEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum InputEvent {
+    /// `code` is named as in the key bindings, `None` for keys outside them
+    Key {
+        code: Option<String>,
+        logical: LogicalKey,
+        pressed: bool,
+    },
+    Mouse {
+        button: Button,
+        pressed: bool,
+    },
+    Wheel {
+        x: f32,
+        y: f32,
+        pixels: bool,
+    },
+    Motion {
+        dx: f32,
+        dy: f32,
+    },
+}
+
+/// The part of the logical key the console reads
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum LogicalKey {
+    Character(String),
+    Space,
+    Backspace,
+    Enter,
+    Escape,
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let recording: Self =
+            ron::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+        if recording.version != FORMAT_VERSION {
+            return Err(format!(
+                "{}: recorded with format version {}, this build replays version \
+                 {FORMAT_VERSION}; record the session again",
+                path.display(),
+                recording.version
+            ));
+        }
+        Ok(recording)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Clock strategy for the first frame of a replay
+    pub fn first_frame_time(&self) -> TimeUpdateStrategy {
+        frame_time(self.frames.first())
+    }
+}
+
+fn frame_time(frame: Option<&RecordedFrame>) -> TimeUpdateStrategy {
+    match frame {
+        Some(f) => TimeUpdateStrategy::ManualDuration(Duration::from_nanos(f.delta_ns)),
+        None => TimeUpdateStrategy::Automatic,
+    }
+}
+
+#[derive(Resource, Default)]
+pub enum InputLog {
+    #[default]
+    Off,
+    Recording {
+        path: PathBuf,
+        recording: Recording,
+    },
+    Replaying {
+        recording: Recording,
+        /// Frame fed in next
+        next: usize,
+    },
+}
+
+/// Recording or replaying
+pub fn lockstep(log: Res<InputLog>) -> bool {
+    !matches!(*log, InputLog::Off)
+}
+
+pub fn replaying(log: Res<InputLog>) -> bool {
+    matches!(*log, InputLog::Replaying { .. })
+}
+
+/// Append this frame's length, cursor and input events to the recording
+pub fn record_inputs(
+    time: Res<Time<Real>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    mut keys: EventReader<KeyboardInput>,
+    mut buttons: EventReader<MouseButtonInput>,
+    mut wheel: EventReader<MouseWheel>,
+    mut motion: EventReader<MouseMotion>,
+    mut log: ResMut<InputLog>,
+) {
+    let InputLog::Recording { recording, .. } = &mut *log else {
+        return;
+    };
+    let mut events = Vec::new();
+    events.extend(keys.read().map(|ev| InputEvent::Key {
+        code: parse_key(&key_name(ev.key_code)).map(key_name),
+        logical: match &ev.logical_key {
+            Key::Character(s) => LogicalKey::Character(s.to_string()),
+            Key::Space => LogicalKey::Space,
+            Key::Backspace => LogicalKey::Backspace,
+            Key::Enter => LogicalKey::Enter,
+            Key::Escape => LogicalKey::Escape,
+            _ => LogicalKey::Other,
+        },
+        pressed: ev.state == ButtonState::Pressed,
+    }));
+    events.extend(buttons.read().map(|ev| InputEvent::Mouse {
+        button: match ev.button {
+            MouseButton::Left => Button::Left,
+            MouseButton::Right => Button::Right,
+            MouseButton::Middle => Button::Middle,
+            MouseButton::Back => Button::Back,
+            MouseButton::Forward => Button::Forward,
+            MouseButton::Other(n) => Button::Other(n),
+        },
+        pressed: ev.state == ButtonState::Pressed,
+    }));
+    events.extend(wheel.read().map(|ev| InputEvent::Wheel {
+        x: ev.x,
+        y: ev.y,
+        pixels: ev.unit == MouseScrollUnit::Pixel,
+    }));
+    events.extend(motion.read().map(|ev| InputEvent::Motion {
+        dx: ev.delta.x,
+        dy: ev.delta.y,
+    }));
+    recording.frames.push(RecordedFrame {
+        delta_ns: time.delta().as_nanos() as u64,
+        cursor: window_q
+            .get_single()
+            .ok()
+            .and_then(|w| w.cursor_position())
+            .map(|p| (p.x, p.y)),
+        events,
+    });
+}
+
+/// Replace this frame's real input with the recorded one, and set the
+/// clock for the next frame. Runs before the input systems read the events.
+pub fn replay_inputs(
+    mut window_q: Query<(Entity, &mut Window), With<PrimaryWindow>>,
+    mut keys: ResMut<Events<KeyboardInput>>,
+    mut buttons: ResMut<Events<MouseButtonInput>>,
+    mut wheel: ResMut<Events<MouseWheel>>,
+    mut motion: ResMut<Events<MouseMotion>>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut log: ResMut<InputLog>,
+) {
+    let InputLog::Replaying { recording, next } = &mut *log else {
+        return;
+    };
+    let Ok((window_entity, mut window)) = window_q.get_single_mut() else {
+        return;
+    };
+    let Some(frame) = recording.frames.get(*next) else {
+        return;
+    };
+    keys.clear();
+    buttons.clear();
+    wheel.clear();
+    motion.clear();
+    window.set_cursor_position(frame.cursor.map(|(x, y)| Vec2::new(x, y)));
+    for ev in &frame.events {
+        match ev {
+            InputEvent::Key {
+                code,
+                logical,
+                pressed,
+            } => {
+                keys.send(KeyboardInput {
+                    key_code: code
+                        .as_deref()
+                        .and_then(parse_key)
+                        .unwrap_or(KeyCode::Unidentified(NativeKeyCode::Unidentified)),
+                    logical_key: match logical {
+                        LogicalKey::Character(s) => Key::Character(s.as_str().into()),
+                        LogicalKey::Space => Key::Space,
+                        LogicalKey::Backspace => Key::Backspace,
+                        LogicalKey::Enter => Key::Enter,
+                        LogicalKey::Escape => Key::Escape,
+                        LogicalKey::Other => Key::Unidentified(NativeKey::Unidentified),
+                    },
+                    state: button_state(*pressed),
+                    repeat: false,
+                    window: window_entity,
+                });
+            }
+            InputEvent::Mouse { button, pressed } => {
+                buttons.send(MouseButtonInput {
+                    button: match *button {
+                        Button::Left => MouseButton::Left,
+                        Button::Right => MouseButton::Right,
+                        Button::Middle => MouseButton::Middle,
+                        Button::Back => MouseButton::Back,
+                        Button::Forward => MouseButton::Forward,
+                        Button::Other(n) => MouseButton::Other(n),
+                    },
+                    state: button_state(*pressed),
+                    window: window_entity,
+                });
+            }
+            InputEvent::Wheel { x, y, pixels } => {
+                wheel.send(MouseWheel {
+                    unit: if *pixels {
+                        MouseScrollUnit::Pixel
+                    } else {
+                        MouseScrollUnit::Line
+                    },
+                    x: *x,
+                    y: *y,
+                    window: window_entity,
+                });
+            }
+            InputEvent::Motion { dx, dy } => {
+                motion.send(MouseMotion {
+                    delta: Vec2::new(*dx, *dy),
+                });
+            }
+        }
+    }
+
+    *next += 1;
+    *strategy = frame_time(recording.frames.get(*next));
+    if *next == recording.frames.len() {
+        info!("Replay finished after {} frames", recording.frames.len());
+        *log = InputLog::Off;
+    }
+}
+
+fn button_state(pressed: bool) -> ButtonState {
+    if pressed {
+        ButtonState::Pressed
+    } else {
+        ButtonState::Released
+    }
+}
+
+/// Write the recording when the app closes
+pub fn save_recording(mut exit: EventReader<AppExit>, log: Res<InputLog>) {
+    if exit.read().next().is_none() {
+        return;
+    }
+    let InputLog::Recording { path, recording } = &*log else {
+        return;
+    };
+    match recording.save(path) {
+        Ok(()) => info!(
+            "Saved {} frames of input to {}",
+            recording.frames.len(),
+            path.display()
+        ),
+        Err(e) => error!("failed to save the recording: {e}"),
+    }
+}
@@ -0,0 +1,172 @@
+//! Time reversal: negating every velocity makes the leapfrog retrace its
+//! steps, since kick-drift-kick is symmetric in time. Run forward, reverse,
+//! run the same number of steps and the bodies return to where they
+//! started, up to the round-off collected on the way (and whatever the
+//! stochastic kicks or the thermostat added, which don't reverse).
+//!
+//! Rewind uses the same symmetry the other way round: the velocities stay
+//! as they are and the step runs with -dt for a set number of steps, so
+//! the clock runs back too. It starts from whatever the current state is,
+//! with no snapshot needed.
+
+use bevy::prelude::*;
+
+use crate::Bodies;
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, fill, tr};
+use crate::physics::{PendingBodies, PhysicsSettings, PhysicsTask};
+
+/// Steps integrated backward per rewind by default
+const DEFAULT_REWIND_STEPS: u64 = 100;
+
+#[derive(Resource, Default)]
+pub struct TimeReversal {
+    /// Velocities are negated relative to the initial conditions
+    pub reversed: bool,
+}
+
+/// Reverse key negates the velocities before the next step
+pub fn toggle_time_reversal(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut reversal: ResMut<TimeReversal>,
+    mut pending: ResMut<PendingBodies>,
+) {
+    if bindings.just_pressed(&keys, Action::ReverseTime) {
+        reversal.reversed = !reversal.reversed;
+        // Pressed twice before a step, the two reversals cancel
+        pending.reverse = !pending.reverse;
+        info!(
+            "Time runs {}",
+            if reversal.reversed {
+                "backward"
+            } else {
+                "forward"
+            }
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct Rewind {
+    /// Steps integrated backward per request
+    pub steps: u64,
+    requested: bool,
+    /// Forward dt, and the step count at which it is restored
+    active: Option<(f32, u64)>,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self {
+            steps: DEFAULT_REWIND_STEPS,
+            requested: false,
+            active: None,
+        }
+    }
+}
+
+impl Rewind {
+    /// Rewind by `steps` more steps, starting at the next step boundary
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Steps still to be integrated backward
+    pub fn remaining(&self, step: u64) -> Option<u64> {
+        self.active.map(|(_, until)| until.saturating_sub(step))
+    }
+}
+
+/// Rewind key integrates backward for the configured number of steps
+pub fn rewind_key(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut rewind: ResMut<Rewind>,
+) {
+    if bindings.just_pressed(&keys, Action::Rewind) {
+        rewind.request();
+    }
+}
+
+/// Runs right after the leapfrog step, which has just started a force pass
+/// with the current dt. Flipping dt here makes exactly the passes started
+/// from the next one on run backward; the pass already in flight finishes
+/// forward and is not counted.
+pub fn apply_rewind(
+    bodies: Res<Bodies>,
+    task: Res<PhysicsTask>,
+    mut settings: ResMut<PhysicsSettings>,
+    mut rewind: ResMut<Rewind>,
+) {
+    let step = bodies.step;
+    if rewind.requested && task.is_running() {
+        rewind.requested = false;
+        let steps = rewind.steps;
+        match rewind.active.as_mut() {
+            Some((_, until)) => *until += steps,
+            None => {
+                rewind.active = Some((settings.dt, step + steps));
+                settings.dt = -settings.dt.abs();
+                info!("Rewinding {steps} steps");
+            }
+        }
+    }
+    // The last backward pass was started when the count reached `until`
+    if let Some((dt, until)) = rewind.active {
+        if step >= until {
+            settings.dt = dt;
+            rewind.active = None;
+            info!("Rewind finished");
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ReversalText;
+
+pub fn spawn_reversal_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 20.0,
+                color: Color::srgb(1.0, 0.6, 0.3),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(40.0),
+            top: Val::Px(20.0),
+            ..Default::default()
+        }),
+        ReversalText,
+    ));
+}
+
+pub fn update_reversal_text(
+    reversal: Res<TimeReversal>,
+    rewind: Res<Rewind>,
+    bodies: Res<Bodies>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<ReversalText>>,
+) {
+    let remaining = rewind.remaining(bodies.step);
+    if !reversal.is_changed() && !rewind.is_changed() && !lang.is_changed() && remaining.is_none() {
+        return;
+    }
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    t.sections[0].value = if let Some(n) = remaining {
+        let template = tr("<< rewinding, {} steps left", "<< 되감는 중, {}스텝 남음");
+        fill(template.get(*lang), &[&n])
+    } else if reversal.reversed {
+        tr("<< time reversed", "<< 시간 역행 중")
+            .get(*lang)
+            .to_string()
+    } else {
+        String::new()
+    };
+}
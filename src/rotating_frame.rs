@@ -0,0 +1,79 @@
+//! Rotating reference frame view. The main camera turns with the frame, so
+//! bodies corotating at its angular velocity (Lagrange clouds, spiral
+//! patterns) appear stationary. The view turns about the screen center;
+//! combine with the center-of-mass follow mode to turn about the system.
+
+use bevy::prelude::*;
+
+use crate::binaries::BinaryScan;
+use crate::constants::PhysicsConstants;
+use crate::physics::PhysicsSettings;
+use crate::{Bodies, MainCamera};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameRate {
+    /// Fixed angular velocity (rad per unit time, counter-clockwise)
+    Fixed(f32),
+    /// The orbital rate of the tightest detected binary, updated as it changes
+    TightestBinary,
+}
+
+#[derive(Resource, Default)]
+pub struct RotatingFrame {
+    /// `None` for the inertial view
+    pub rate: Option<FrameRate>,
+    /// Frame angle (rad)
+    angle: f32,
+    /// Simulated time the angle was last advanced to
+    last_time: Option<f32>,
+}
+
+impl RotatingFrame {
+    pub fn set_rate(&mut self, rate: Option<FrameRate>) {
+        self.rate = rate;
+        self.angle = 0.0;
+        self.last_time = None;
+    }
+}
+
+/// Mean motion of the tightest binary, signed by its sense of rotation
+fn binary_rate(scan: &BinaryScan, bodies: &Bodies, g: f32) -> Option<f32> {
+    let pair = scan.pairs.first()?;
+    let (a, b) = (bodies.data.get(pair.i)?, bodies.data.get(pair.j)?);
+    let total = (a.mass + b.mass) as f64;
+    let n = (g as f64 * total / pair.semi_major_axis.powi(3)).sqrt();
+    let spin = (b.x - a.x) * (b.vy - a.vy) - (b.y - a.y) * (b.vx - a.vx);
+    Some((n as f32).copysign(spin)).filter(|w| w.is_finite())
+}
+
+/// Advance the frame angle to the displayed (interpolated) time and turn
+/// the camera by it
+pub fn rotate_view(
+    mut frame: ResMut<RotatingFrame>,
+    bodies: Res<Bodies>,
+    scan: Res<BinaryScan>,
+    constants: Res<PhysicsConstants>,
+    settings: Res<PhysicsSettings>,
+    fixed_time: Res<Time<Fixed>>,
+    mut cam_q: Query<&mut Transform, With<MainCamera>>,
+) {
+    let Ok(mut tf) = cam_q.get_single_mut() else {
+        return;
+    };
+    let Some(rate) = frame.rate else {
+        if tf.rotation != Quat::IDENTITY {
+            tf.rotation = Quat::IDENTITY;
+        }
+        return;
+    };
+    let omega = match rate {
+        FrameRate::Fixed(w) => Some(w),
+        FrameRate::TightestBinary => binary_rate(&scan, &bodies, constants.gravitation),
+    };
+    let t = bodies.elapsed_time - (1.0 - fixed_time.overstep_fraction()) * settings.dt;
+    if let (Some(w), Some(last)) = (omega, frame.last_time) {
+        frame.angle = (frame.angle + w * (t - last)) % std::f32::consts::TAU;
+    }
+    frame.last_time = Some(t);
+    tf.rotation = Quat::from_rotation_z(frame.angle);
+}
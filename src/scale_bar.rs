@@ -0,0 +1,110 @@
+//! Scale bar (bottom right unless moved in the HUD layout): a round
+//! physical length in the largest fitting unit (m, km, AU or light-years),
+//! resized with the zoom.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::hud::{HudItem, Overlay};
+use crate::locale::{Language, fill, tr};
+use crate::units::CODE;
+use crate::{A_RIGHT_YEAR, MainCamera, world_scale};
+
+/// Longest the bar may get (screen px)
+const MAX_BAR_PX: f32 = 150.0;
+/// Astronomical unit (m)
+const AU: f64 = 1.496E11;
+
+#[derive(Component)]
+pub struct ScaleBar;
+
+#[derive(Component)]
+pub struct ScaleBarText;
+
+pub fn spawn_scale_bar(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 14.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            HudItem(Overlay::ScaleBar),
+        ))
+        .with_children(|column| {
+            column.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(MAX_BAR_PX),
+                        height: Val::Px(3.0),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::WHITE),
+                    ..Default::default()
+                },
+                ScaleBar,
+            ));
+            column.spawn((TextBundle::from_section("", style), ScaleBarText));
+        });
+}
+
+/// Largest 1, 2 or 5 × 10^k not above `limit`, with the decimals it needs
+fn round_length(limit: f64) -> (f64, usize) {
+    let k = limit.log10().floor();
+    let decade = 10f64.powf(k);
+    let mantissa = [5.0, 2.0, 1.0]
+        .into_iter()
+        .find(|&m| m * decade <= limit)
+        .unwrap_or(1.0);
+    (mantissa * decade, (-k).max(0.0) as usize)
+}
+
+/// Refit the bar whenever the zoom, the window or the language changes
+pub fn update_scale_bar(
+    lang: Res<Language>,
+    win_q: Query<Ref<Window>, With<PrimaryWindow>>,
+    cam_q: Query<Ref<OrthographicProjection>, With<MainCamera>>,
+    mut bar_q: Query<&mut Style, With<ScaleBar>>,
+    mut text_q: Query<&mut Text, With<ScaleBarText>>,
+) {
+    let (Ok(window), Ok(proj)) = (win_q.get_single(), cam_q.get_single()) else {
+        return;
+    };
+    if !window.is_changed() && !proj.is_changed() && !lang.is_changed() {
+        return;
+    }
+    // Meters per screen pixel along x
+    let per_px = proj.scale as f64 / world_scale(&window).x as f64 * CODE.length;
+    let longest = MAX_BAR_PX as f64 * per_px;
+    if !(longest.is_finite() && longest > 0.0) {
+        return;
+    }
+    let light_year = A_RIGHT_YEAR as f64 * CODE.length;
+    let (unit, template) = if longest >= 0.05 * light_year {
+        (light_year, tr("{} light-years", "{} 광년"))
+    } else if longest >= 0.01 * AU {
+        (AU, tr("{} AU", "{} AU"))
+    } else if longest >= 1.0E3 {
+        (1.0E3, tr("{} km", "{} km"))
+    } else {
+        (1.0, tr("{} m", "{} m"))
+    };
+    let (length, decimals) = round_length(longest / unit);
+    if let Ok(mut style) = bar_q.get_single_mut() {
+        style.width = Val::Px((length * unit / per_px) as f32);
+    }
+    if let Ok(mut t) = text_q.get_single_mut() {
+        let value = format!("{length:.decimals$}");
+        t.sections[0].value = fill(template.get(*lang), &[&value]);
+    }
+}
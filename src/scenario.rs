@@ -0,0 +1,412 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::external::ExternalField;
+use crate::force_law::{ForceLaw, LjParams};
+use crate::ic_registry::{self, IcGenerator};
+use crate::mass_evolution::MassEvolutionConfig;
+use crate::poincare::{Axis, Surface};
+use crate::slingshot::{Planet, Ring, SlingshotConfig};
+use crate::stochastic::StochasticConfig;
+use crate::thermostat::ThermostatConfig;
+use crate::units::{UnitScale, UnitSystem};
+
+/// Folder scanned for user scenarios at startup
+pub const SCENARIO_DIR: &str = "assets/scenarios";
+
+/// One body given explicitly in a scenario
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct BodySpec {
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+    pub mass: f32,
+}
+
+/// How the bodies of a run are generated
+#[derive(Deserialize, Debug, Clone)]
+pub enum InitialConditions {
+    /// A registered generator (see `ic_registry`) by name, with some of its
+    /// parameters overridden
+    Generated {
+        generator: String,
+        #[serde(default)]
+        params: Vec<(String, f32)>,
+    },
+    /// No bodies of its own; a game layout supplies them
+    Empty,
+    /// Exactly these bodies (the body count is ignored)
+    Explicit(Vec<BodySpec>),
+}
+
+impl Default for InitialConditions {
+    /// The original setup: a uniform square with random masses and speeds
+    fn default() -> Self {
+        InitialConditions::generated("random", &[])
+    }
+}
+
+impl InitialConditions {
+    /// A registered generator with the given overrides
+    pub fn generated(generator: &str, params: &[(&str, f32)]) -> Self {
+        InitialConditions::Generated {
+            generator: generator.into(),
+            params: params.iter().map(|&(k, v)| (k.into(), v)).collect(),
+        }
+    }
+
+    /// Registry key of the generator, if any
+    pub fn generator(&self) -> Option<&str> {
+        match self {
+            InitialConditions::Generated { generator, .. } => Some(generator),
+            _ => None,
+        }
+    }
+
+    fn convert(&mut self, s: &UnitScale) {
+        match self {
+            InitialConditions::Empty => {}
+            InitialConditions::Explicit(specs) => {
+                for b in specs {
+                    b.position = s.point(b.position);
+                    b.velocity = s.velocity2(b.velocity);
+                    b.mass = s.mass(b.mass);
+                }
+            }
+            InitialConditions::Generated { generator, params } => {
+                // Unknown names are reported when the run starts
+                let Some(generator) = ic_registry::find(generator) else {
+                    return;
+                };
+                for (name, value) in params {
+                    if let Some(p) = generator.params().iter().find(|p| p.name == name) {
+                        *value = p.kind.convert(s, *value);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn convert_lj(params: &mut LjParams, s: &UnitScale) {
+    params.sigma = s.length(params.sigma);
+    params.epsilon = s.energy(params.epsilon);
+}
+
+/// What kind of system a scenario simulates
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub enum World {
+    /// Self-gravitating bodies using the scenario's `force_law`
+    #[default]
+    Gravitational,
+    /// Molecular dynamics: Lennard-Jones pairs, optionally thermostatted
+    LennardJones {
+        params: LjParams,
+        thermostat: Option<ThermostatConfig>,
+    },
+}
+
+/// Scenario description loaded from a RON file
+#[derive(Resource, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Scenario {
+    pub name: String,
+    /// One-line preview shown in the menu
+    pub description: String,
+    /// Units of every dimensional value below; converted to code units
+    /// before the scenario is used
+    pub units: UnitSystem,
+    pub initial: InitialConditions,
+    /// Recommended body count
+    pub bodies: Option<usize>,
+    /// Recommended timestep
+    pub dt: Option<f32>,
+    pub world: World,
+    /// Pair interaction the run starts with (gravitational worlds)
+    pub force_law: ForceLaw,
+    /// Time-dependent external forcing applied on top of self-gravity
+    pub external: Vec<ExternalField>,
+    /// Random kicks applied every step, if present
+    pub stochastic: Option<StochasticConfig>,
+    /// Mass loss / binary mass transfer, if present
+    pub mass_evolution: Option<MassEvolutionConfig>,
+    /// Turns the run into the slingshot challenge
+    pub slingshot: Option<SlingshotConfig>,
+    /// Surface of section recorded from the start, if present
+    pub poincare: Option<Surface>,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let mut scenario: Scenario =
+            ron::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+        if scenario.name.is_empty() {
+            scenario.name = path
+                .file_stem()
+                .map_or("unnamed".into(), |s| s.to_string_lossy().into_owned());
+        }
+        Ok(scenario.in_code_units())
+    }
+
+    /// Every dimensional value rewritten from the scenario's units into
+    /// code units; scenarios already in code units are returned unchanged
+    pub fn in_code_units(mut self) -> Self {
+        if self.units == UnitSystem::Code {
+            return self;
+        }
+        let s = self.units.scale();
+        self.units = UnitSystem::Code;
+        self.initial.convert(&s);
+        if let Some(dt) = &mut self.dt {
+            *dt = s.time(*dt);
+        }
+        if let World::LennardJones { params, thermostat } = &mut self.world {
+            convert_lj(params, &s);
+            if let Some(t) = thermostat {
+                t.relaxation_time = s.time(t.relaxation_time);
+            }
+        }
+        match &mut self.force_law {
+            ForceLaw::LennardJones(params) => convert_lj(params, &s),
+            ForceLaw::Mond { a0 } => *a0 = s.acceleration(*a0),
+            ForceLaw::Gravity2D { length } | ForceLaw::PowerLaw { length, .. } => {
+                *length = s.length(*length)
+            }
+            ForceLaw::Gravity | ForceLaw::AntiGravity | ForceLaw::Coulomb => {}
+        }
+        for field in &mut self.external {
+            match field {
+                ExternalField::Perturber {
+                    mass,
+                    start,
+                    velocity,
+                    softening,
+                } => {
+                    *mass = s.mass(*mass);
+                    *start = s.point(*start);
+                    *velocity = s.velocity2(*velocity);
+                    *softening = s.length(*softening);
+                }
+                ExternalField::RotatingBar {
+                    mass,
+                    half_length,
+                    pattern_speed,
+                    center,
+                    softening,
+                } => {
+                    *mass = s.mass(*mass);
+                    *half_length = s.length(*half_length);
+                    *pattern_speed = s.rate(*pattern_speed);
+                    *center = s.point(*center);
+                    *softening = s.length(*softening);
+                }
+            }
+        }
+        if let Some(k) = &mut self.stochastic {
+            k.amplitude = s.acceleration(k.amplitude);
+            k.correlation_time = s.time(k.correlation_time);
+        }
+        if let Some(m) = &mut self.mass_evolution {
+            m.loss_timescale = s.time(m.loss_timescale);
+            m.min_mass = s.mass(m.min_mass);
+            m.transfer_rate = s.rate(m.transfer_rate);
+            m.transfer_separation = s.length(m.transfer_separation);
+        }
+        if let Some(game) = &mut self.slingshot {
+            for p in &mut game.planets {
+                p.position = s.point(p.position);
+                p.mass = s.mass(p.mass);
+                p.radius = s.length(p.radius);
+            }
+            for r in &mut game.rings {
+                r.center = s.point(r.center);
+                r.radius = s.length(r.radius);
+            }
+            game.launch_x = s.length(game.launch_x);
+            game.speed_per_px = s.velocity(game.speed_per_px);
+            game.probe_mass = s.mass(game.probe_mass);
+            game.max_time = s.time(game.max_time);
+        }
+        if let Some(surface) = &mut self.poincare {
+            surface.value = s.length(surface.value);
+        }
+        self
+    }
+
+    /// Scenarios compiled into the binary, written in SI
+    pub fn builtin() -> Vec<Scenario> {
+        [
+            Scenario {
+                name: "Random field".into(),
+                description: "Stars scattered uniformly with random masses and velocities".into(),
+                initial: InitialConditions::generated("random", &[]),
+                bodies: Some(1000),
+                dt: Some(2.0E07),
+                ..Default::default()
+            },
+            Scenario {
+                name: "Plummer cluster".into(),
+                description: "Equal-mass Plummer sphere, a bound cluster near equilibrium".into(),
+                initial: InitialConditions::generated("plummer", &[]),
+                bodies: Some(1000),
+                dt: Some(1.0E07),
+                ..Default::default()
+            },
+            Scenario {
+                name: "Neutral plasma".into(),
+                description: "Equal numbers of positive and negative charges; watch opposite charges pair up and screen each other".into(),
+                initial: InitialConditions::generated("plasma", &[]),
+                bodies: Some(1000),
+                dt: Some(1.0E07),
+                force_law: ForceLaw::Coulomb,
+                ..Default::default()
+            },
+            Scenario {
+                name: "Lennard-Jones liquid".into(),
+                description: "Molecular dynamics on a lattice that melts into a liquid held at T* = 0.5".into(),
+                initial: InitialConditions::generated("lattice", &[]),
+                bodies: Some(400),
+                dt: Some(1.0E07),
+                world: World::LennardJones {
+                    params: LjParams {
+                        sigma: 2.0E13,
+                        epsilon: 4.5E37,
+                        cutoff: 2.5,
+                    },
+                    thermostat: Some(ThermostatConfig {
+                        temperature: 0.5,
+                        relaxation_time: 1.0E09,
+                    }),
+                },
+                ..Default::default()
+            },
+            Scenario {
+                name: "MOND disk".into(),
+                description: "Rotating disk in equilibrium under MOND; press G to cycle to Newtonian gravity and watch it fly apart".into(),
+                initial: InitialConditions::generated("disk", &[]),
+                bodies: Some(1000),
+                dt: Some(2.0E07),
+                // Scaled up from Milgrom's 1.2e-10 so the outer disk is in the MOND regime
+                force_law: ForceLaw::Mond { a0: 3.0E-07 },
+                ..Default::default()
+            },
+            Scenario {
+                name: "Restricted three-body".into(),
+                description: "Massless tracers around a star perturbed by a heavy planet, with a Poincaré section of their crossings".into(),
+                initial: InitialConditions::generated("restricted3", &[]),
+                bodies: Some(300),
+                dt: Some(2.0E07),
+                poincare: Some(Surface {
+                    axis: Axis::Y,
+                    value: 0.0,
+                }),
+                ..Default::default()
+            },
+            Scenario {
+                name: "Slingshot challenge".into(),
+                description: "Launch a probe from the left edge and gravity-assist it through every ring".into(),
+                initial: InitialConditions::Empty,
+                bodies: Some(0),
+                dt: Some(5.0E07),
+                slingshot: Some(SlingshotConfig {
+                    planets: vec![
+                        Planet {
+                            position: (-2.0E14, 1.5E14),
+                            mass: 2.0E31,
+                            radius: 8.0E12,
+                        },
+                        Planet {
+                            position: (2.0E14, -1.5E14),
+                            mass: 2.0E31,
+                            radius: 8.0E12,
+                        },
+                    ],
+                    rings: vec![
+                        Ring {
+                            center: (-2.0E14, 0.6E14),
+                            radius: 3.0E13,
+                        },
+                        Ring {
+                            center: (2.0E14, -0.6E14),
+                            radius: 3.0E13,
+                        },
+                        Ring {
+                            center: (4.5E14, 0.0),
+                            radius: 4.0E13,
+                        },
+                    ],
+                    launch_x: -4.8E14,
+                    speed_per_px: 150.0,
+                    probe_mass: 1.0E20,
+                    max_time: 1.0E11,
+                }),
+                ..Default::default()
+            },
+        ]
+        .into_iter()
+        .map(Scenario::in_code_units)
+        .collect()
+    }
+
+    /// A run of a registered generator; the overrides go into the name so
+    /// each variant has its own menu entry
+    pub fn generated(generator: &dyn IcGenerator, params: Vec<(String, f32)>) -> Scenario {
+        let mut name = generator.title().to_string();
+        if !params.is_empty() {
+            let list: Vec<_> = params.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            name += &format!(" ({})", list.join(", "));
+        }
+        Scenario {
+            name,
+            description: generator.description().into(),
+            initial: InitialConditions::Generated {
+                generator: generator.name().into(),
+                params,
+            },
+            bodies: Some(1000),
+            dt: Some(generator.recommended_dt()),
+            ..Default::default()
+        }
+        .in_code_units()
+    }
+
+    /// One scenario per registered generator that no built-in scenario
+    /// already runs, at its defaults
+    pub fn generators() -> Vec<Scenario> {
+        let builtin = Scenario::builtin();
+        ic_registry::REGISTRY
+            .iter()
+            .filter(|g| {
+                !builtin
+                    .iter()
+                    .any(|s| s.initial.generator() == Some(g.name()))
+            })
+            .map(|g| Scenario::generated(*g, Vec::new()))
+            .collect()
+    }
+
+    /// Every `.ron` file in `dir`, sorted by file name; unreadable ones are skipped
+    pub fn scan_dir(dir: &Path) -> Vec<Scenario> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "ron"))
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .filter_map(|p| match Scenario::load(p) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    warn!("skipping scenario {e}");
+                    None
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,230 @@
+//! Body selection by clicking, plus the orbital-element readout for it.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::constants::PhysicsConstants;
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, fill, tr};
+use crate::neighbors::SpatialIndex;
+use crate::units::CODE;
+use crate::{Bodies, BodyState, MainCamera, UiSelection, world_scale};
+
+/// Clicks farther than this from every body clear the selection (screen px)
+const PICK_RADIUS_PX: f32 = 12.0;
+/// A primary must supply at least this share of the total pull to count as dominant
+const DOMINANCE: f32 = 0.5;
+
+#[derive(Resource, Default)]
+pub struct Selection(pub Option<usize>);
+
+/// Osculating elements of the selected body's relative orbit around `primary`
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitalElements {
+    pub primary: usize,
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    /// Orbital period, `None` for unbound orbits
+    pub period: Option<f64>,
+}
+
+/// Left click selects the nearest body under the cursor
+pub fn pick_body(
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut selection: ResMut<Selection>,
+    bodies: Res<Bodies>,
+    index: Res<SpatialIndex>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<MainCamera>>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Ok(window) = win_q.get_single() else {
+        return;
+    };
+    let Ok((camera, cam_tf, proj)) = cam_q.get_single() else {
+        return;
+    };
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|c| camera.viewport_to_world_2d(cam_tf, c).ok())
+    else {
+        return;
+    };
+
+    let half = Vec2::new(window.width(), window.height()) / 2.0;
+    let pick_radius = PICK_RADIUS_PX * proj.scale;
+    // Candidates from the index in sim coordinates, then the exact test on
+    // the displayed (interpolated) positions
+    let scale = world_scale(window);
+    let slack = 2.0 * pick_radius / scale.min_element();
+    selection.0 = index
+        .neighbors_within(cursor / scale, slack)
+        .filter_map(|i| Some((i, bodies.data.get(i)?)))
+        .map(|(i, b)| (i, (Vec2::new(b.disp_x, b.disp_y) - half).distance(cursor)))
+        .filter(|&(_, d)| d <= pick_radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i);
+    if let Some(i) = selection.0 {
+        info!("Selected body {i}");
+    }
+}
+
+/// Pin key toggles the selected body between free and fixed. Both state
+/// buffers are updated so a step in flight doesn't undo it.
+pub fn toggle_pin(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    selection: Res<Selection>,
+    mut bodies: ResMut<Bodies>,
+) {
+    if !bindings.just_pressed(&keys, Action::TogglePin) {
+        return;
+    }
+    let Some(i) = selection.0.filter(|&i| i < bodies.data.len()) else {
+        return;
+    };
+    let fixed = !bodies.data[i].fixed;
+    let Bodies { data, next, .. } = &mut *bodies;
+    for b in [data.get_mut(i), next.get_mut(i)].into_iter().flatten() {
+        b.fixed = fixed;
+        if fixed {
+            b.vx = 0.0;
+            b.vy = 0.0;
+        }
+    }
+    info!("Body {i} {}", if fixed { "pinned" } else { "released" });
+}
+
+/// Ring around the selected body (square if it is pinned)
+pub fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    bodies: Res<Bodies>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    let (Some(i), Ok(window)) = (selection.0, win_q.get_single()) else {
+        return;
+    };
+    let Some(b) = bodies.data.get(i) else {
+        return;
+    };
+    let scale = cam_q.get_single().map_or(1.0, |p| p.scale);
+    let half = Vec2::new(window.width(), window.height()) / 2.0;
+    let center = Vec2::new(b.disp_x, b.disp_y) - half;
+    let color = Color::srgb(1.0, 1.0, 0.3);
+    if b.fixed {
+        gizmos.rect_2d(
+            Isometry2d::from_translation(center),
+            Vec2::splat(16.0 * scale),
+            color,
+        );
+    } else {
+        gizmos.circle_2d(center, 8.0 * scale, color);
+    }
+}
+
+/// The body pulling hardest on `i`, if it dominates the total pull (G cancels out)
+pub fn dominant_primary(data: &[BodyState], i: usize) -> Option<usize> {
+    let bi = &data[i];
+    let mut best: Option<(usize, f32)> = None;
+    let mut total = 0.0f32;
+    for (j, bj) in data.iter().enumerate() {
+        if j == i {
+            continue;
+        }
+        let r2 = (bj.x - bi.x).powi(2) + (bj.y - bi.y).powi(2);
+        if r2 == 0.0 {
+            continue;
+        }
+        let pull = bj.mass / r2;
+        total += pull;
+        if best.is_none_or(|(_, p)| pull > p) {
+            best = Some((j, pull));
+        }
+    }
+    let (j, pull) = best?;
+    (data[j].mass > bi.mass && pull >= DOMINANCE * total).then_some(j)
+}
+
+pub fn orbital_elements(
+    body: &BodyState,
+    primary: &BodyState,
+    primary_index: usize,
+    g: f32,
+) -> OrbitalElements {
+    let mu = g as f64 * (body.mass as f64 + primary.mass as f64);
+    let (rx, ry) = ((body.x - primary.x) as f64, (body.y - primary.y) as f64);
+    let (vx, vy) = ((body.vx - primary.vx) as f64, (body.vy - primary.vy) as f64);
+    let r = (rx * rx + ry * ry).sqrt();
+    let v2 = vx * vx + vy * vy;
+
+    // Specific orbital energy and angular momentum
+    let energy = 0.5 * v2 - mu / r;
+    let h = rx * vy - ry * vx;
+
+    let semi_major_axis = -mu / (2.0 * energy);
+    let eccentricity = (1.0 + 2.0 * energy * h * h / (mu * mu)).max(0.0).sqrt();
+    let period =
+        (energy < 0.0).then(|| 2.0 * std::f64::consts::PI * (semi_major_axis.powi(3) / mu).sqrt());
+
+    OrbitalElements {
+        primary: primary_index,
+        semi_major_axis,
+        eccentricity,
+        period,
+    }
+}
+
+pub fn update_selection_text(
+    selection: Res<Selection>,
+    bodies: Res<Bodies>,
+    constants: Res<PhysicsConstants>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<UiSelection>>,
+) {
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    let lang = *lang;
+    let text = match selection.0.filter(|&i| i < bodies.data.len()) {
+        None => String::new(),
+        Some(i) => match dominant_primary(&bodies.data, i) {
+            None => fill(
+                tr(
+                    "body #{}: no dominant central mass",
+                    "천체 #{}: 지배적인 중심 질량 없음",
+                )
+                .get(lang),
+                &[&i],
+            ),
+            Some(j) => {
+                let el =
+                    orbital_elements(&bodies.data[i], &bodies.data[j], j, constants.gravitation);
+                let period =
+                    el.period
+                        .map_or(tr("unbound", "비속박").get(lang).to_string(), |p| {
+                            format!(
+                                "{:.2E} {}",
+                                p * CODE.time / 3.154E7,
+                                tr("year", "년").get(lang)
+                            )
+                        });
+                let header = fill(
+                    tr("body #{} around #{}", "천체 #{} (중심 #{})").get(lang),
+                    &[&i, &el.primary],
+                );
+                format!(
+                    "{header}\na = {:.2E} m   e = {:.3}   T = {period}",
+                    el.semi_major_axis * CODE.length,
+                    el.eccentricity
+                )
+            }
+        },
+    };
+    if t.sections[0].value != text {
+        t.sections[0].value = text;
+    }
+}
@@ -0,0 +1,125 @@
+//! User preferences persisted across runs.
+
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::MainCamera;
+use crate::accessibility::Accessibility;
+use crate::coloring::ColorMode;
+use crate::colormap::Colormap;
+use crate::hud::HudLayout;
+use crate::locale::Language;
+use crate::menu::MenuChoice;
+use crate::zoom_view::ZoomView;
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct UserSettings {
+    pub color_mode: ColorMode,
+    pub colormap: Colormap,
+    pub language: Language,
+    pub accessibility: Accessibility,
+    pub zoom_view: bool,
+    pub last_scenario: Option<String>,
+    /// Main camera orthographic scale
+    pub camera_zoom: f32,
+    pub hud: HudLayout,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            color_mode: ColorMode::default(),
+            colormap: Colormap::default(),
+            language: Language::default(),
+            accessibility: Accessibility::default(),
+            zoom_view: false,
+            last_scenario: None,
+            camera_zoom: 1.0,
+            hud: HudLayout::default(),
+        }
+    }
+}
+
+impl UserSettings {
+    /// `$XDG_CONFIG_HOME/bevy_nbody_leapfrog/settings.ron`, falling back to
+    /// `~/.config/...` and finally the working directory
+    pub fn path() -> PathBuf {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+        match base {
+            Some(dir) => dir.join("bevy_nbody_leapfrog").join("settings.ron"),
+            None => PathBuf::from("settings.ron"),
+        }
+    }
+
+    /// Saved settings, or defaults when there are none (or they don't parse)
+    pub fn load() -> Self {
+        let path = Self::path();
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        ron::from_str(&text).unwrap_or_else(|e| {
+            warn!("ignoring unreadable settings {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(&path, text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+/// Restore the saved zoom once the camera exists
+pub fn apply_camera_zoom(
+    settings: Res<UserSettings>,
+    mut q: Query<&mut OrthographicProjection, With<MainCamera>>,
+) {
+    for mut proj in q.iter_mut() {
+        proj.scale = settings.camera_zoom;
+    }
+}
+
+/// Collect the current preferences and write them when the app closes
+pub fn save_on_exit(
+    mut exit: EventReader<AppExit>,
+    mut settings: ResMut<UserSettings>,
+    color_mode: Res<ColorMode>,
+    colormap: Res<Colormap>,
+    language: Res<Language>,
+    accessibility: Res<Accessibility>,
+    zoom_view: Res<ZoomView>,
+    hud: Res<HudLayout>,
+    choice: Res<MenuChoice>,
+    cam_q: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    if exit.read().next().is_none() {
+        return;
+    }
+    settings.color_mode = *color_mode;
+    settings.colormap = *colormap;
+    settings.language = *language;
+    settings.accessibility = *accessibility;
+    settings.zoom_view = zoom_view.enabled;
+    settings.hud = hud.clone();
+    settings.last_scenario = choice
+        .scenarios
+        .get(choice.selected)
+        .map(|s| s.name.clone());
+    if let Ok(proj) = cam_q.get_single() {
+        settings.camera_zoom = proj.scale;
+    }
+    if let Err(e) = settings.save() {
+        error!("failed to save settings: {e}");
+    }
+}
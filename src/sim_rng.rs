@@ -0,0 +1,181 @@
+//! Seeded source of everything random in the interactive app: initial
+//! conditions, spawned and emitted bodies and the stochastic kicks all draw
+//! from it, so a run is reproduced by its seed and its inputs.
+//!
+//! The generator is Xoshiro256++ (Blackman & Vigna 2019): four words of
+//! state, a few shifts and rotations per draw, and a jump function that
+//! skips 2^128 draws. Work split across threads takes one jumped stream
+//! per fixed-size chunk, so its draws don't depend on the thread count.
+
+use bevy::prelude::*;
+use rand::{Error, RngCore, SeedableRng};
+
+/// Skips 2^128 draws, see [`Xoshiro256PlusPlus::jump`]
+const JUMP: [u64; 4] = [
+    0x180e_c6d3_3cfd_0aba,
+    0xd5a6_1266_f0c9_392c,
+    0xa958_2618_e03f_c9aa,
+    0x39ab_dc45_29b1_661c,
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Xoshiro256PlusPlus {
+    s: [u64; 4],
+}
+
+impl Xoshiro256PlusPlus {
+    /// Advance as if 2^128 values had been drawn
+    pub fn jump(&mut self) {
+        let mut t = [0u64; 4];
+        for word in JUMP {
+            for bit in 0..64 {
+                if word & (1 << bit) != 0 {
+                    for (t, s) in t.iter_mut().zip(self.s) {
+                        *t ^= s;
+                    }
+                }
+                self.next_u64();
+            }
+        }
+        self.s = t;
+    }
+
+    /// A stream for independent work: this generator as it is, while it
+    /// jumps ahead, so the two never overlap
+    pub fn split(&mut self) -> Self {
+        let stream = self.clone();
+        self.jump();
+        stream
+    }
+}
+
+impl RngCore for Xoshiro256PlusPlus {
+    fn next_u32(&mut self) -> u32 {
+        // The high bits are the better ones
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let out = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        out
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Xoshiro256PlusPlus {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: [u8; 32]) -> Self {
+        let mut s = [0u64; 4];
+        for (word, bytes) in s.iter_mut().zip(seed.chunks_exact(8)) {
+            *word = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        // The all-zero state never leaves zero
+        if s == [0; 4] {
+            return Self::seed_from_u64(0);
+        }
+        Self { s }
+    }
+
+    /// SplitMix64 expansion of the seed, as recommended by the authors
+    fn seed_from_u64(mut state: u64) -> Self {
+        let mut s = [0u64; 4];
+        for word in s.iter_mut() {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *word = z ^ (z >> 31);
+        }
+        Self { s }
+    }
+}
+
+#[derive(Resource, Deref, DerefMut, Clone)]
+pub struct SimRng(Xoshiro256PlusPlus);
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self(Xoshiro256PlusPlus::seed_from_u64(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// State [1, 2, 3, 4], as in the reference test vectors
+    fn reference() -> Xoshiro256PlusPlus {
+        let mut seed = [0u8; 32];
+        for (bytes, word) in seed.chunks_exact_mut(8).zip([1u64, 2, 3, 4]) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        Xoshiro256PlusPlus::from_seed(seed)
+    }
+
+    #[test]
+    fn matches_the_reference_outputs() {
+        let mut rng = reference();
+        let expected = [
+            41943041,
+            58720359,
+            3588806011781223,
+            3591011842654386,
+            9228616714210784205,
+            9973669472204895162,
+            14011001112246962877,
+            12406186145184390807,
+            15849039046786891736,
+            10450023813501588000,
+        ];
+        for e in expected {
+            assert_eq!(rng.next_u64(), e);
+        }
+    }
+
+    #[test]
+    fn jump_matches_the_reference() {
+        let mut rng = reference();
+        for _ in 0..10 {
+            rng.next_u64();
+        }
+        rng.jump();
+        assert_eq!(
+            rng.s,
+            [
+                9316003916687536039,
+                13984078969637322866,
+                10935917572558535934,
+                10356532856428607291,
+            ]
+        );
+        let expected = [
+            8998556026673787160,
+            14860850672632069730,
+            6793339527323295379,
+            9551689320381348118,
+        ];
+        for e in expected {
+            assert_eq!(rng.next_u64(), e);
+        }
+    }
+}
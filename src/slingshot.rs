@@ -0,0 +1,363 @@
+//! Slingshot challenge: drag from the left edge to launch a probe and steer it
+//! by gravity assists through the target rings. Less launch speed (fuel) and
+//! a shorter flight score higher.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::Deserialize;
+
+use crate::locale::{Language, fill, tr};
+use crate::morton::BodiesReordered;
+use crate::physics::PendingBodies;
+use crate::units::CODE;
+use crate::{Bodies, BodyState, MAX_X, MAX_Y, MainCamera, world_scale};
+
+/// Points per ring passed
+const RING_POINTS: f32 = 1000.0;
+/// Points lost per km/s of launch speed
+const FUEL_PENALTY: f32 = 20.0;
+/// Points lost per year of flight
+const TIME_PENALTY: f32 = 10.0;
+/// The probe is lost once it is this many half-widths from the origin
+const LOST_FACTOR: f32 = 1.2;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Planet {
+    pub position: (f32, f32),
+    pub mass: f32,
+    /// The probe crashes inside this radius (m)
+    pub radius: f32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Ring {
+    pub center: (f32, f32),
+    pub radius: f32,
+}
+
+/// Game layout, part of a scenario file
+#[derive(Deserialize, Debug, Clone)]
+pub struct SlingshotConfig {
+    /// Pinned massive bodies
+    pub planets: Vec<Planet>,
+    pub rings: Vec<Ring>,
+    /// x coordinate of the launch edge (m)
+    pub launch_x: f32,
+    /// Launch speed per world unit of drag (m/s)
+    pub speed_per_px: f32,
+    pub probe_mass: f32,
+    /// Flights longer than this (s) end without a score
+    pub max_time: f32,
+}
+
+impl SlingshotConfig {
+    pub fn planet_bodies(&self) -> Vec<BodyState> {
+        self.planets
+            .iter()
+            .map(|p| {
+                let mut b = BodyState::new();
+                b.mass = p.mass;
+                b.x = p.position.0;
+                b.y = p.position.1;
+                b.x_prev = b.x;
+                b.y_prev = b.y;
+                b.fixed = true;
+                b
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameStatus {
+    Aiming,
+    Flying,
+    Finished { score: f32 },
+    Crashed,
+    Lost,
+    OutOfTime,
+}
+
+#[derive(Resource)]
+pub struct SlingshotGame {
+    pub config: Option<SlingshotConfig>,
+    pub status: GameStatus,
+    /// Index of the probe body, once it exists
+    probe: Option<usize>,
+    /// World position where the current drag started
+    drag_start: Option<Vec2>,
+    launch_time: f32,
+    launch_step: u64,
+    launch_speed: f32,
+    passed: Vec<bool>,
+    attempts: u32,
+    best: Option<f32>,
+}
+
+impl SlingshotGame {
+    pub fn remap(&mut self, ev: &BodiesReordered) {
+        self.probe = self.probe.map(|i| ev.remap(i));
+    }
+
+    pub fn new(config: Option<SlingshotConfig>) -> Self {
+        let rings = config.as_ref().map_or(0, |c| c.rings.len());
+        Self {
+            config,
+            status: GameStatus::Aiming,
+            probe: None,
+            drag_start: None,
+            launch_time: 0.0,
+            launch_step: 0,
+            launch_speed: 0.0,
+            passed: vec![false; rings],
+            attempts: 0,
+            best: None,
+        }
+    }
+}
+
+impl Default for SlingshotGame {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Run condition: true while no game is being played
+pub fn game_inactive(game: Res<SlingshotGame>) -> bool {
+    game.config.is_none()
+}
+
+fn cursor_world(window: &Window, camera: &Camera, cam_tf: &GlobalTransform) -> Option<Vec2> {
+    window
+        .cursor_position()
+        .and_then(|c| camera.viewport_to_world_2d(cam_tf, c).ok())
+}
+
+/// Press near the left edge, pull back and release: the probe flies opposite
+/// to the drag, faster the longer it is. A new launch reuses the old probe.
+pub fn launch_probe(
+    buttons: Res<ButtonInput<MouseButton>>,
+    bodies: Res<Bodies>,
+    mut game: ResMut<SlingshotGame>,
+    mut pending: ResMut<PendingBodies>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let Some(config) = game.config.clone() else {
+        return;
+    };
+    let (Ok(window), Ok((camera, cam_tf))) = (win_q.get_single(), cam_q.get_single()) else {
+        return;
+    };
+    let Some(cursor) = cursor_world(window, camera, cam_tf) else {
+        return;
+    };
+    if buttons.just_pressed(MouseButton::Left) {
+        game.drag_start = Some(cursor);
+    }
+    if !buttons.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(start) = game.drag_start.take() else {
+        return;
+    };
+    let pull = start - cursor;
+    if pull.length() < 1.0 {
+        return;
+    }
+
+    let scale = world_scale(window);
+    let mut probe = BodyState::new();
+    probe.mass = config.probe_mass;
+    probe.x = config.launch_x;
+    probe.y = (start.y / scale.y).clamp(-MAX_Y, MAX_Y);
+    probe.x_prev = probe.x;
+    probe.y_prev = probe.y;
+    let v = pull * config.speed_per_px;
+    probe.vx = v.x;
+    probe.vy = v.y;
+
+    match game.probe {
+        Some(i) => pending.replaced.push((i, probe)),
+        None => {
+            game.probe = Some(bodies.data.len() + pending.added.len());
+            pending.added.push(probe);
+        }
+    }
+    game.status = GameStatus::Flying;
+    game.launch_time = bodies.elapsed_time;
+    game.launch_step = bodies.step;
+    game.launch_speed = v.length();
+    game.passed.iter_mut().for_each(|p| *p = false);
+    game.attempts += 1;
+    info!(
+        "Probe launched at {:.2E} m/s",
+        CODE.velocity(game.launch_speed)
+    );
+}
+
+/// Ring passes, crashes, escapes and the final score
+pub fn update_game(bodies: Res<Bodies>, mut game: ResMut<SlingshotGame>) {
+    if game.status != GameStatus::Flying {
+        return;
+    }
+    let (Some(config), Some(i)) = (game.config.clone(), game.probe) else {
+        return;
+    };
+    // The probe joins between steps; a step already in flight at launch
+    // still completes with the old state, so wait for the one after it
+    if bodies.step < game.launch_step + 2 {
+        return;
+    }
+    let Some(p) = bodies.data.get(i).copied() else {
+        return;
+    };
+    let dist = |(cx, cy): (f32, f32)| ((p.x - cx).powi(2) + (p.y - cy).powi(2)).sqrt();
+
+    for (k, ring) in config.rings.iter().enumerate() {
+        if !game.passed[k] && dist(ring.center) <= ring.radius {
+            game.passed[k] = true;
+            info!("Ring {} passed", k + 1);
+        }
+    }
+    let flight = bodies.elapsed_time - game.launch_time;
+    game.status = if config
+        .planets
+        .iter()
+        .any(|pl| dist(pl.position) <= pl.radius)
+    {
+        GameStatus::Crashed
+    } else if p.x.abs() > LOST_FACTOR * MAX_X || p.y.abs() > LOST_FACTOR * MAX_Y {
+        GameStatus::Lost
+    } else if flight > config.max_time {
+        GameStatus::OutOfTime
+    } else if game.passed.iter().all(|&p| p) {
+        let score = RING_POINTS * game.passed.len() as f32
+            - FUEL_PENALTY * CODE.velocity(game.launch_speed) / 1.0E3
+            - TIME_PENALTY * CODE.time(flight) / 3.154E7;
+        game.best = Some(game.best.map_or(score, |b: f32| b.max(score)));
+        GameStatus::Finished { score }
+    } else {
+        GameStatus::Flying
+    };
+    if game.status != GameStatus::Flying {
+        info!("Slingshot: {:?}", game.status);
+    }
+}
+
+/// Rings (green once passed), the launch edge and the drag line
+pub fn draw_game(
+    mut gizmos: Gizmos,
+    game: Res<SlingshotGame>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let Some(config) = &game.config else {
+        return;
+    };
+    let Ok(window) = win_q.get_single() else {
+        return;
+    };
+    let scale = world_scale(window);
+    for (ring, &passed) in config.rings.iter().zip(game.passed.iter()) {
+        let color = if passed {
+            Color::srgb(0.3, 1.0, 0.4)
+        } else {
+            Color::srgb(1.0, 0.8, 0.2)
+        };
+        gizmos.circle_2d(
+            Vec2::new(ring.center.0, ring.center.1) * scale,
+            ring.radius * scale.x,
+            color,
+        );
+    }
+    let edge = config.launch_x * scale.x;
+    gizmos.line_2d(
+        Vec2::new(edge, -MAX_Y * scale.y),
+        Vec2::new(edge, MAX_Y * scale.y),
+        Color::srgba(0.6, 0.6, 1.0, 0.4),
+    );
+    if let (Some(start), Ok((camera, cam_tf))) = (game.drag_start, cam_q.get_single()) {
+        if let Some(cursor) = cursor_world(window, camera, cam_tf) {
+            gizmos.line_2d(start, cursor, Color::srgb(1.0, 0.4, 0.4));
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct GameText;
+
+pub fn spawn_game_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 20.0,
+                color: Color::srgb(1.0, 0.9, 0.5),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            top: Val::Px(140.0),
+            ..Default::default()
+        }),
+        GameText,
+    ));
+}
+
+pub fn update_game_text(
+    game: Res<SlingshotGame>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<GameText>>,
+) {
+    if !game.is_changed() && !lang.is_changed() {
+        return;
+    }
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    if game.config.is_none() {
+        t.sections[0].value = String::new();
+        return;
+    }
+    let lang = *lang;
+    let passed = game.passed.iter().filter(|&&p| p).count();
+    let status = match game.status {
+        GameStatus::Aiming => tr(
+            "drag back from the left edge and release to launch",
+            "왼쪽 가장자리에서 뒤로 끌었다가 놓아 발사",
+        )
+        .get(lang)
+        .to_string(),
+        GameStatus::Flying => fill(
+            tr("flying: rings {}/{}", "비행 중: 고리 {}/{}").get(lang),
+            &[&passed, &game.passed.len()],
+        ),
+        GameStatus::Finished { score } => fill(
+            tr("all rings! score {}", "모든 고리 통과! 점수 {}").get(lang),
+            &[&format!("{score:.0}")],
+        ),
+        GameStatus::Crashed => tr("crashed, launch again", "충돌, 다시 발사")
+            .get(lang)
+            .to_string(),
+        GameStatus::Lost => tr(
+            "probe lost in space, launch again",
+            "탐사선이 우주에서 사라짐, 다시 발사",
+        )
+        .get(lang)
+        .to_string(),
+        GameStatus::OutOfTime => tr("out of time, launch again", "시간 초과, 다시 발사")
+            .get(lang)
+            .to_string(),
+    };
+    let best = game.best.map_or(String::new(), |b| {
+        format!("   {} {b:.0}", tr("best", "최고").get(lang))
+    });
+    let header = fill(
+        tr("Slingshot  attempt {}", "슬링샷  시도 {}").get(lang),
+        &[&game.attempts],
+    );
+    t.sections[0].value = format!("{header}{best}\n{status}");
+}
@@ -0,0 +1,63 @@
+//! Body snapshots saved to / loaded from RON files, in SI.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Bodies;
+use crate::units::CODE;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct BodyRecord {
+    pub mass: f32,
+    #[serde(default)]
+    pub charge: f32,
+    #[serde(default)]
+    pub fixed: bool,
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    /// Simulated time (s)
+    pub time: f32,
+    pub step: u64,
+    pub bodies: Vec<BodyRecord>,
+}
+
+impl Snapshot {
+    pub fn capture(bodies: &Bodies) -> Self {
+        Self {
+            time: CODE.time(bodies.elapsed_time),
+            step: bodies.step,
+            bodies: bodies
+                .data
+                .iter()
+                .map(|b| BodyRecord {
+                    mass: CODE.mass(b.mass),
+                    charge: CODE.charge(b.charge),
+                    fixed: b.fixed,
+                    x: CODE.length(b.x),
+                    y: CODE.length(b.y),
+                    vx: CODE.velocity(b.vx),
+                    vy: CODE.velocity(b.vy),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        ron::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
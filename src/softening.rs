@@ -0,0 +1,99 @@
+//! Softening kernels for the inverse-square pair laws. Both kernels are
+//! written as an effective separation so those laws keep their plain
+//! `S / r²` and `-S / r` forms; the other laws soften on their own (see
+//! [`ForceLaw::radial_accel`](crate::force_law::ForceLaw::radial_accel)).
+//!
+//! `softening` in the physics config is always the Plummer-equivalent
+//! length ε; the spline kernel uses the usual support radius h = 2.8 ε
+//! (Monaghan & Lattanzio 1985, as in GADGET), beyond which the force is
+//! exactly Newtonian.
+
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::PhysicsConstants;
+
+/// Spline support radius per unit Plummer-equivalent softening
+const SPLINE_SUPPORT: f32 = 2.8;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Reflect)]
+pub enum SofteningKernel {
+    /// a = S r / (r² + ε²)^{3/2}, never exactly Newtonian
+    #[default]
+    Plummer,
+    /// Cubic spline with compact support
+    Spline,
+}
+
+impl SofteningKernel {
+    /// Squared separation that turns `S / r²` into the softened force
+    /// magnitude at true separation `r > 0` with softening `eps`
+    pub fn force_r2(self, r: f32, eps: f32) -> f32 {
+        if eps <= 0.0 {
+            return r * r;
+        }
+        match self {
+            SofteningKernel::Plummer => (r * r + eps * eps).powf(1.5) / r,
+            SofteningKernel::Spline => {
+                let h = SPLINE_SUPPORT * eps;
+                let u = r / h;
+                if u >= 1.0 {
+                    return r * r;
+                }
+                // Force per unit S and separation vector: a = S d f(u)
+                let f = if u < 0.5 {
+                    10.666_667 + u * u * (32.0 * u - 38.4)
+                } else {
+                    21.333_334 - 48.0 * u + 38.4 * u * u
+                        - 10.666_667 * u * u * u
+                        - 0.066_666_67 / (u * u * u)
+                } / (h * h * h);
+                1.0 / (r * f)
+            }
+        }
+    }
+
+    /// Separation that turns `-S / r` into the softened pair potential
+    pub fn potential_r(self, r: f64, eps: f64) -> f64 {
+        if eps <= 0.0 {
+            return r;
+        }
+        match self {
+            SofteningKernel::Plummer => (r * r + eps * eps).sqrt(),
+            SofteningKernel::Spline => {
+                let h = SPLINE_SUPPORT as f64 * eps;
+                let u = r / h;
+                if u >= 1.0 {
+                    return r;
+                }
+                // Potential per unit S: φ(u) / h
+                let phi = if u < 0.5 {
+                    -2.8 + u * u * (5.333_333_333_333 + u * u * (6.4 * u - 9.6))
+                } else {
+                    -3.2 + 0.066_666_666_667 / u
+                        + u * u
+                            * (10.666_666_666_667 + u * (-16.0 + u * (9.6 - 2.133_333_333_333 * u)))
+                };
+                -h / phi
+            }
+        }
+    }
+}
+
+/// Softening length of one body: the configured length, scaled as m^{1/3}
+/// when a reference mass is set
+fn body_length(constants: &PhysicsConstants, mass: f32) -> f32 {
+    match constants.softening_reference_mass {
+        Some(m_ref) if m_ref > 0.0 => constants.softening * (mass.max(0.0) / m_ref).cbrt(),
+        _ => constants.softening,
+    }
+}
+
+/// Softening of a pair, the quadratic mean of both members' lengths
+pub fn pair_length(constants: &PhysicsConstants, mi: f32, mj: f32) -> f32 {
+    if constants.softening_reference_mass.is_none() {
+        return constants.softening;
+    }
+    let (ei, ej) = (body_length(constants, mi), body_length(constants, mj));
+    (0.5 * (ei * ei + ej * ej)).sqrt()
+}
@@ -0,0 +1,293 @@
+//! Live histogram of body speeds in the center-of-mass frame, optionally
+//! with the 2D Maxwell–Boltzmann (Rayleigh) distribution of the same mean
+//! square speed, so relaxation toward equilibrium can be watched.
+
+use bevy::prelude::*;
+
+use crate::diagnostics::is_due;
+use crate::hud::{HudItem, Overlay};
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, fill, tr};
+use crate::units::CODE;
+use crate::{Bodies, BodyState};
+
+const BINS: usize = 32;
+/// Plot size (px)
+const PANEL_WIDTH: f32 = 256.0;
+const PANEL_HEIGHT: f32 = 120.0;
+/// Speeds above this many rms speeds are left out, so a few escapers don't
+/// squeeze the bulk into the first bins
+const RANGE_RMS: f32 = 3.0;
+const DEFAULT_INTERVAL: u64 = 10;
+const BAR_COLOR: Color = Color::srgb(0.45, 0.7, 1.0);
+const FIT_COLOR: Color = Color::srgb(1.0, 0.6, 0.2);
+
+#[derive(Resource)]
+pub struct SpeedHistogram {
+    pub panel: bool,
+    /// Recount every this many steps
+    pub interval: u64,
+    /// Show the Maxwell–Boltzmann fit
+    pub fit: bool,
+    last_step: Option<u64>,
+    counts: [u32; BINS],
+    /// Bodies per bin the fit predicts
+    expected: [f32; BINS],
+    /// Upper edge of the last bin
+    max_speed: f32,
+    rms_speed: f32,
+    /// Bodies faster than `max_speed`
+    above: usize,
+}
+
+impl Default for SpeedHistogram {
+    fn default() -> Self {
+        Self {
+            panel: false,
+            interval: DEFAULT_INTERVAL,
+            fit: true,
+            last_step: None,
+            counts: [0; BINS],
+            expected: [0.0; BINS],
+            max_speed: 0.0,
+            rms_speed: 0.0,
+            above: 0,
+        }
+    }
+}
+
+impl SpeedHistogram {
+    fn count(&mut self, data: &[BodyState]) {
+        let (mut px, mut py, mut mass) = (0.0f64, 0.0f64, 0.0f64);
+        for b in data {
+            px += b.mass as f64 * b.vx as f64;
+            py += b.mass as f64 * b.vy as f64;
+            mass += b.mass as f64;
+        }
+        let bulk = if mass > 0.0 {
+            Vec2::new((px / mass) as f32, (py / mass) as f32)
+        } else {
+            Vec2::ZERO
+        };
+        let speeds: Vec<f32> = data
+            .iter()
+            .map(|b| (Vec2::new(b.vx, b.vy) - bulk).length())
+            .collect();
+        let n = speeds.len();
+        let mean_square =
+            speeds.iter().map(|&v| v as f64 * v as f64).sum::<f64>() / n.max(1) as f64;
+        self.rms_speed = mean_square.sqrt() as f32;
+        let fastest = speeds.iter().copied().fold(0.0, f32::max);
+        self.max_speed = fastest.min(RANGE_RMS * self.rms_speed);
+
+        self.counts = [0; BINS];
+        self.above = 0;
+        let width = self.max_speed / BINS as f32;
+        for v in speeds {
+            if v > self.max_speed {
+                self.above += 1;
+            } else if width > 0.0 {
+                self.counts[((v / width) as usize).min(BINS - 1)] += 1;
+            } else {
+                self.counts[0] += 1;
+            }
+        }
+
+        // Rayleigh CDF 1 - exp(-v² / 2σ²), with 2σ² the mean square speed
+        let cdf =
+            |v: f32| 1.0 - (-(v as f64 * v as f64) / mean_square.max(f64::MIN_POSITIVE)).exp();
+        for (k, e) in self.expected.iter_mut().enumerate() {
+            let (lo, hi) = (k as f32 * width, (k + 1) as f32 * width);
+            *e = (n as f64 * (cdf(hi) - cdf(lo))) as f32;
+        }
+    }
+}
+
+pub fn toggle_speed_histogram(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut histogram: ResMut<SpeedHistogram>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleSpeedHistogram) {
+        histogram.panel = !histogram.panel;
+        histogram.last_step = None;
+    }
+}
+
+/// Recount every `interval` steps while the panel is shown
+pub fn update_speed_histogram(bodies: Res<Bodies>, mut histogram: ResMut<SpeedHistogram>) {
+    if !histogram.panel || !is_due(histogram.last_step, bodies.step, histogram.interval) {
+        return;
+    }
+    histogram.last_step = Some(bodies.step);
+    histogram.count(&bodies.data);
+}
+
+#[derive(Component)]
+pub struct SpeedHistogramPanel;
+
+#[derive(Component)]
+pub struct SpeedHistogramText;
+
+#[derive(Component)]
+pub struct SpeedBar(usize);
+
+#[derive(Component)]
+pub struct SpeedFitMark(usize);
+
+pub fn spawn_speed_histogram(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 14.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.0),
+                    bottom: Val::Px(120.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..Default::default()
+                },
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            SpeedHistogramPanel,
+            HudItem(Overlay::SpeedHistogram),
+        ))
+        .with_children(|panel| {
+            panel.spawn((TextBundle::from_section("", style), SpeedHistogramText));
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(PANEL_WIDTH),
+                        height: Val::Px(PANEL_HEIGHT),
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(1.0),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.78)),
+                    ..Default::default()
+                })
+                .with_children(|plot| {
+                    for k in 0..BINS {
+                        plot.spawn(NodeBundle {
+                            style: Style {
+                                flex_grow: 1.0,
+                                height: Val::Percent(100.0),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        })
+                        .with_children(|column| {
+                            column.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        bottom: Val::Px(0.0),
+                                        width: Val::Percent(100.0),
+                                        height: Val::Percent(0.0),
+                                        ..Default::default()
+                                    },
+                                    background_color: BackgroundColor(BAR_COLOR),
+                                    ..Default::default()
+                                },
+                                SpeedBar(k),
+                            ));
+                            column.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        bottom: Val::Percent(0.0),
+                                        width: Val::Percent(100.0),
+                                        height: Val::Px(2.0),
+                                        ..Default::default()
+                                    },
+                                    background_color: BackgroundColor(FIT_COLOR),
+                                    ..Default::default()
+                                },
+                                SpeedFitMark(k),
+                            ));
+                        });
+                    }
+                });
+        });
+}
+
+pub fn update_speed_histogram_panel(
+    histogram: Res<SpeedHistogram>,
+    lang: Res<Language>,
+    mut panel_q: Query<&mut Visibility, (With<SpeedHistogramPanel>, Without<SpeedFitMark>)>,
+    mut bar_q: Query<(&SpeedBar, &mut Style), Without<SpeedFitMark>>,
+    mut fit_q: Query<(&SpeedFitMark, &mut Style, &mut Visibility)>,
+    mut text_q: Query<&mut Text, With<SpeedHistogramText>>,
+) {
+    if !histogram.is_changed() && !lang.is_changed() {
+        return;
+    }
+    for mut vis in panel_q.iter_mut() {
+        *vis = if histogram.panel {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if !histogram.panel {
+        return;
+    }
+    let tallest = histogram
+        .counts
+        .iter()
+        .map(|&c| c as f32)
+        .chain(
+            histogram
+                .fit
+                .then_some(histogram.expected)
+                .into_iter()
+                .flatten(),
+        )
+        .fold(1.0, f32::max);
+    for (bar, mut style) in bar_q.iter_mut() {
+        style.height = Val::Percent(100.0 * histogram.counts[bar.0] as f32 / tallest);
+    }
+    for (mark, mut style, mut vis) in fit_q.iter_mut() {
+        style.bottom = Val::Percent(100.0 * histogram.expected[mark.0] / tallest);
+        *vis = if histogram.fit {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if let Ok(mut t) = text_q.get_single_mut() {
+        let lang = *lang;
+        let mut text = fill(
+            tr(
+                "speed (km/s): 0 … {}, rms {}",
+                "속력 (km/s): 0 … {}, 제곱평균 {}",
+            )
+            .get(lang),
+            &[
+                &format!("{:.2}", CODE.velocity(histogram.max_speed) / 1.0E3),
+                &format!("{:.2}", CODE.velocity(histogram.rms_speed) / 1.0E3),
+            ],
+        );
+        if histogram.above > 0 {
+            text += &fill(
+                tr(", {} faster not shown", ", 더 빠른 {}개 생략").get(lang),
+                &[&histogram.above],
+            );
+        }
+        if histogram.fit {
+            text += "\n";
+            text += tr(
+                "orange: Maxwell–Boltzmann fit",
+                "주황색: 맥스웰–볼츠만 분포",
+            )
+            .get(lang);
+        }
+        t.sections[0].value = text;
+    }
+}
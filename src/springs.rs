@@ -0,0 +1,167 @@
+//! Harmonic links between pairs of bodies, integrated with the self-forces.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::keybindings::{Action, KeyBindings};
+use crate::morton::BodiesReordered;
+use crate::physics::{Boundary, PhysicsSettings, min_image};
+use crate::selection::Selection;
+use crate::summation::CompensatedSum;
+use crate::{Bodies, BodyState};
+
+/// New links default to this oscillation period, in steps of the current dt
+const DEFAULT_PERIOD_STEPS: f32 = 200.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Spring {
+    pub i: usize,
+    pub j: usize,
+    /// Separation at which the link is relaxed
+    pub rest_length: f32,
+    /// Spring constant
+    pub stiffness: f32,
+}
+
+impl Spring {
+    /// Link relaxed at the current separation. Without an explicit stiffness
+    /// the pair oscillates with a period of DEFAULT_PERIOD_STEPS steps.
+    pub fn between(
+        data: &[BodyState],
+        i: usize,
+        j: usize,
+        stiffness: Option<f32>,
+        dt: f32,
+    ) -> Self {
+        let (a, b) = (&data[i], &data[j]);
+        let rest_length = ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt();
+        let stiffness = stiffness.unwrap_or_else(|| {
+            let mu = a.mass as f64 * b.mass as f64 / (a.mass as f64 + b.mass as f64);
+            let omega = std::f64::consts::TAU / (DEFAULT_PERIOD_STEPS as f64 * dt as f64);
+            (mu * omega * omega) as f32
+        });
+        Self {
+            i,
+            j,
+            rest_length,
+            stiffness,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Springs {
+    pub links: Vec<Spring>,
+    /// First body of a link being made with the link key
+    anchor: Option<usize>,
+}
+
+impl Springs {
+    pub fn remap(&mut self, ev: &BodiesReordered) {
+        for s in self.links.iter_mut() {
+            (s.i, s.j) = (ev.remap(s.i), ev.remap(s.j));
+        }
+        self.anchor = self.anchor.map(|i| ev.remap(i));
+    }
+}
+
+/// Separation vector from i to j, extension beyond the rest length and length
+fn stretch(s: &Spring, snap: &[[f32; 4]], boundary: Boundary) -> Option<(f64, f64, f64, f64)> {
+    let (p, q) = (snap.get(s.i)?, snap.get(s.j)?);
+    let (mut dx, mut dy) = (q[0] - p[0], q[1] - p[1]);
+    if boundary.is_periodic() {
+        dx = min_image(dx);
+        dy = min_image(dy);
+    }
+    let (dx, dy) = (dx as f64, dy as f64);
+    let r = (dx * dx + dy * dy).sqrt();
+    Some((dx, dy, r - s.rest_length as f64, r))
+}
+
+/// Hooke forces F = -k (r - L) along each link, as accelerations
+pub fn add_accelerations(
+    links: &[Spring],
+    snap: &[[f32; 4]],
+    boundary: Boundary,
+    accel: &mut [[f32; 2]],
+) {
+    for s in links {
+        let Some((dx, dy, ext, r)) = stretch(s, snap, boundary) else {
+            continue;
+        };
+        if r == 0.0 {
+            continue;
+        }
+        // Pull along i → j when stretched, push when compressed
+        let f = s.stiffness as f64 * ext / r;
+        let (fx, fy) = (f * dx, f * dy);
+        accel[s.i][0] += (fx / snap[s.i][2] as f64) as f32;
+        accel[s.i][1] += (fy / snap[s.i][2] as f64) as f32;
+        accel[s.j][0] -= (fx / snap[s.j][2] as f64) as f32;
+        accel[s.j][1] -= (fy / snap[s.j][2] as f64) as f32;
+    }
+}
+
+/// Elastic energy ½ k (r - L)^2 summed over the links
+pub fn potential_energy(links: &[Spring], snap: &[[f32; 4]], boundary: Boundary) -> f64 {
+    links
+        .iter()
+        .filter_map(|s| {
+            stretch(s, snap, boundary).map(|(_, _, ext, _)| 0.5 * s.stiffness as f64 * ext * ext)
+        })
+        .sum::<CompensatedSum>()
+        .value()
+}
+
+/// The link key remembers the selected body; selecting a second one links them
+pub fn link_selected(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    selection: Res<Selection>,
+    bodies: Res<Bodies>,
+    settings: Res<PhysicsSettings>,
+    mut springs: ResMut<Springs>,
+) {
+    if bindings.just_pressed(&keys, Action::LinkBodies) {
+        springs.anchor = selection.0;
+        if let Some(i) = selection.0 {
+            info!("Linking from body {i}: select the other end");
+        }
+        return;
+    }
+    let Some(i) = springs.anchor else {
+        return;
+    };
+    if !selection.is_changed() {
+        return;
+    }
+    springs.anchor = None;
+    let Some(j) = selection.0.filter(|&j| j != i && j < bodies.data.len()) else {
+        return;
+    };
+    let spring = Spring::between(&bodies.data, i, j, None, settings.dt);
+    info!(
+        "Linked bodies {i} and {j} (k = {:.2E} N/m)",
+        spring.stiffness
+    );
+    springs.links.push(spring);
+}
+
+pub fn draw_springs(
+    mut gizmos: Gizmos,
+    springs: Res<Springs>,
+    bodies: Res<Bodies>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(window) = win_q.get_single() else {
+        return;
+    };
+    let half = Vec2::new(window.width(), window.height()) / 2.0;
+    let world = |b: &BodyState| Vec2::new(b.disp_x, b.disp_y) - half;
+    for s in &springs.links {
+        let (Some(a), Some(b)) = (bodies.data.get(s.i), bodies.data.get(s.j)) else {
+            continue;
+        };
+        gizmos.line_2d(world(a), world(b), Color::srgb(1.0, 0.6, 0.2));
+    }
+}
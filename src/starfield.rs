@@ -0,0 +1,159 @@
+//! Procedural starfield and faint nebulae behind the bodies, drawn by a
+//! background camera on its own render layer. The background follows the
+//! main camera with some parallax, so panning and zooming stay visible.
+
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use rand::{Rng, SeedableRng};
+
+use crate::MainCamera;
+use crate::keybindings::{Action, KeyBindings};
+use crate::point_sprites::{self, Point, PointMaterial, PointParams};
+use crate::sim_rng::Xoshiro256PlusPlus;
+
+/// Render layer seen only by the background camera
+pub const LAYER: usize = 1;
+const SEED: u64 = 0x5747_4152;
+const STARS: usize = 3000;
+const NEBULAE: usize = 12;
+/// Half-size of the square the background is scattered over (world units)
+const EXTENT: f32 = 4000.0;
+/// How far the background follows the main camera: 0 pinned, 1 rigid
+const PARALLAX: f32 = 0.3;
+
+#[derive(Component)]
+pub struct BackgroundCamera;
+
+#[derive(Component)]
+pub struct Starfield;
+
+/// Star tint by temperature: mostly white, some blue and orange
+fn star_color(rng: &mut Xoshiro256PlusPlus) -> [f32; 4] {
+    let brightness = rng.gen_range(0.15..0.8f32).powi(2);
+    let tint: [f32; 3] = match rng.gen_range(0..10) {
+        0..=1 => [0.7, 0.8, 1.0],
+        2 => [1.0, 0.8, 0.6],
+        _ => [1.0, 1.0, 1.0],
+    };
+    [
+        tint[0] * brightness,
+        tint[1] * brightness,
+        tint[2] * brightness,
+        1.0,
+    ]
+}
+
+pub fn spawn_starfield(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PointMaterial>>,
+) {
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: -1,
+            clear_color: ClearColorConfig::Custom(Color::BLACK),
+            ..Default::default()
+        },
+        RenderLayers::layer(LAYER),
+        BackgroundCamera,
+    ));
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(SEED);
+    let position = |rng: &mut Xoshiro256PlusPlus| {
+        Vec2::new(
+            rng.gen_range(-EXTENT..EXTENT),
+            rng.gen_range(-EXTENT..EXTENT),
+        )
+    };
+    // Nebulae: large, faint, fully soft blobs fixed in world size
+    let nebulae: Vec<Point> = (0..NEBULAE)
+        .map(|_| {
+            let hue = rng.gen_range(180.0..320.0);
+            let c = Color::hsl(hue, 0.6, 0.5).to_linear();
+            Point {
+                position: position(&mut rng),
+                size: rng.gen_range(600.0..1600.0),
+                color: [c.red, c.green, c.blue, 0.05],
+            }
+        })
+        .collect();
+    // Stars: tiny points that keep their screen size at any zoom
+    let stars: Vec<Point> = (0..STARS)
+        .map(|_| Point {
+            position: position(&mut rng),
+            size: rng.gen_range(0.5..2.5),
+            color: star_color(&mut rng),
+        })
+        .collect();
+
+    for (points, params) in [
+        (
+            nebulae,
+            PointParams {
+                attenuation: 0.0,
+                softness: 1.0,
+                intensity: 1.0,
+            },
+        ),
+        (
+            stars,
+            PointParams {
+                attenuation: 1.0,
+                softness: 0.6,
+                intensity: 1.0,
+            },
+        ),
+    ] {
+        let mesh = point_sprites::new_mesh(points.into_iter());
+        commands.spawn((
+            Mesh2d(meshes.add(mesh)),
+            MeshMaterial2d(materials.add(PointMaterial {
+                params,
+                additive: false,
+            })),
+            Transform::default(),
+            RenderLayers::layer(LAYER),
+            Starfield,
+        ));
+    }
+}
+
+/// Follow the main camera at a fraction of its motion and zoom
+pub fn follow_main_camera(
+    main_q: Query<
+        (&Transform, &OrthographicProjection),
+        (With<MainCamera>, Without<BackgroundCamera>),
+    >,
+    mut bg_q: Query<(&mut Transform, &mut OrthographicProjection), With<BackgroundCamera>>,
+) {
+    let (Ok((main_tf, main_proj)), Ok((mut tf, mut proj))) =
+        (main_q.get_single(), bg_q.get_single_mut())
+    else {
+        return;
+    };
+    let translation = (main_tf.translation.truncate() * PARALLAX).extend(tf.translation.z);
+    if tf.translation != translation {
+        tf.translation = translation;
+    }
+    let scale = main_proj.scale.powf(PARALLAX);
+    if proj.scale != scale {
+        proj.scale = scale;
+    }
+}
+
+pub fn toggle_starfield(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut q: Query<&mut Visibility, With<Starfield>>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleStarfield) {
+        return;
+    }
+    for mut vis in q.iter_mut() {
+        *vis = match *vis {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
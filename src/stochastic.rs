@@ -0,0 +1,101 @@
+//! Random kicks (Brownian heating): an Ornstein–Uhlenbeck acceleration per body.
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng, distributions::Standard};
+use serde::Deserialize;
+
+use crate::keybindings::{Action, KeyBindings};
+use crate::morton::BodiesReordered;
+use crate::sim_rng::Xoshiro256PlusPlus;
+use crate::units::{CODE, SI};
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct StochasticConfig {
+    /// RMS of each acceleration component (m/s^2)
+    pub amplitude: f32,
+    /// Correlation time of the noise (s); 0 gives white kicks every step
+    pub correlation_time: f32,
+}
+
+impl Default for StochasticConfig {
+    /// The kicks the toggle key switches on, in code units
+    fn default() -> Self {
+        Self {
+            amplitude: SI.acceleration(1.0E-9),
+            correlation_time: SI.time(2.0E08),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct StochasticKicks {
+    pub enabled: bool,
+    pub config: StochasticConfig,
+    rng: Xoshiro256PlusPlus,
+    state: Vec<[f32; 2]>,
+}
+
+impl StochasticKicks {
+    /// Enabled when the scenario asks for it, otherwise available via the toggle key
+    pub fn new(config: Option<StochasticConfig>, seed: u64) -> Self {
+        Self {
+            enabled: config.is_some(),
+            config: config.unwrap_or_default(),
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+            state: Vec::new(),
+        }
+    }
+
+    /// Keep each body's noise with the body when the bodies are reordered
+    pub fn remap(&mut self, ev: &BodiesReordered) {
+        ev.permute(&mut self.state);
+    }
+
+    /// Advance the noise by dt and add it to the accelerations
+    pub fn apply(&mut self, accel: &mut [[f32; 2]], dt: f32) {
+        if !self.enabled {
+            return;
+        }
+        self.state.resize(accel.len(), [0.0; 2]);
+
+        // Exact OU update: a ← a·e^{-dt/τ} + σ·sqrt(1 - e^{-2dt/τ})·ξ
+        let decay = if self.config.correlation_time > 0.0 {
+            (-dt / self.config.correlation_time).exp()
+        } else {
+            0.0
+        };
+        let spread = self.config.amplitude * (1.0 - decay * decay).sqrt();
+        for (s, a) in self.state.iter_mut().zip(accel.iter_mut()) {
+            let (g0, g1) = gaussian_pair(&mut self.rng);
+            s[0] = s[0] * decay + spread * g0;
+            s[1] = s[1] * decay + spread * g1;
+            a[0] += s[0];
+            a[1] += s[1];
+        }
+    }
+}
+
+/// Two independent standard normal samples (Box–Muller)
+pub fn gaussian_pair<R: Rng>(rng: &mut R) -> (f32, f32) {
+    let u1: f32 = rng.sample::<f32, _>(Standard).max(f32::MIN_POSITIVE);
+    let u2: f32 = rng.sample(Standard);
+    let r = (-2.0 * u1.ln()).sqrt();
+    let (s, c) = (std::f32::consts::TAU * u2).sin_cos();
+    (r * c, r * s)
+}
+
+pub fn toggle_kicks(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut kicks: ResMut<StochasticKicks>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleKicks) {
+        kicks.enabled = !kicks.enabled;
+        info!(
+            "Stochastic kicks {} (σ = {:.2E} m/s², τ = {:.2E} s)",
+            if kicks.enabled { "on" } else { "off" },
+            CODE.acceleration(kicks.config.amplitude),
+            CODE.time(kicks.config.correlation_time)
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Cluster structure diagnostics: center of density, core radius and
+//! Lagrangian radii, recomputed every few physics steps.
+
+use bevy::prelude::*;
+
+use crate::diagnostics::{DiagnosticsLog, is_due};
+use crate::locale::{Language, tr};
+use crate::units::CODE;
+use crate::{Bodies, BodyState, UiStructure};
+
+/// Neighbours used for the local density estimate (Casertano & Hut 1985)
+const DENSITY_NEIGHBORS: usize = 6;
+const LAGRANGIAN_FRACTIONS: [f32; 3] = [0.1, 0.5, 0.9];
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Structure {
+    pub center_of_density: Vec2,
+    pub core_radius: f32,
+    /// 10/50/90% Lagrangian radii around the center of density; [1] is the half-mass radius
+    pub lagrangian_radii: [f32; 3],
+}
+
+#[derive(Resource)]
+pub struct StructureDiagnostics {
+    /// Recompute every `interval` physics steps
+    pub interval: u64,
+    pub last_step: Option<u64>,
+    pub latest: Option<Structure>,
+}
+
+impl Default for StructureDiagnostics {
+    fn default() -> Self {
+        Self {
+            interval: 30,
+            last_step: None,
+            latest: None,
+        }
+    }
+}
+
+pub fn compute(data: &[BodyState]) -> Option<Structure> {
+    if data.len() <= DENSITY_NEIGHBORS {
+        return None;
+    }
+
+    // Local surface density from the k-th nearest neighbour
+    let mut density = vec![0.0f64; data.len()];
+    for (i, bi) in data.iter().enumerate() {
+        let mut nearest = [(f32::MAX, 0.0f32); DENSITY_NEIGHBORS];
+        for (j, bj) in data.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let r2 = (bj.x - bi.x).powi(2) + (bj.y - bi.y).powi(2);
+            if r2 < nearest[DENSITY_NEIGHBORS - 1].0 {
+                // Insertion into the sorted k-nearest list
+                let mut k = DENSITY_NEIGHBORS - 1;
+                while k > 0 && nearest[k - 1].0 > r2 {
+                    nearest[k] = nearest[k - 1];
+                    k -= 1;
+                }
+                nearest[k] = (r2, bj.mass);
+            }
+        }
+        let r2_k = nearest[DENSITY_NEIGHBORS - 1].0 as f64;
+        // Mass of the k-1 inner neighbours over the enclosing area
+        let m: f64 = nearest[..DENSITY_NEIGHBORS - 1]
+            .iter()
+            .map(|&(_, m)| m as f64)
+            .sum();
+        density[i] = if r2_k > 0.0 {
+            m / (std::f64::consts::PI * r2_k)
+        } else {
+            0.0
+        };
+    }
+
+    // Density-weighted center and core radius
+    let rho_sum: f64 = density.iter().sum();
+    if rho_sum <= 0.0 {
+        return None;
+    }
+    let (mut cx, mut cy) = (0.0f64, 0.0f64);
+    for (b, rho) in data.iter().zip(density.iter()) {
+        cx += rho * b.x as f64;
+        cy += rho * b.y as f64;
+    }
+    cx /= rho_sum;
+    cy /= rho_sum;
+    let mut r2_weighted = 0.0f64;
+    for (b, rho) in data.iter().zip(density.iter()) {
+        r2_weighted += rho * ((b.x as f64 - cx).powi(2) + (b.y as f64 - cy).powi(2));
+    }
+    let core_radius = (r2_weighted / rho_sum).sqrt() as f32;
+
+    // Lagrangian radii: sort by radius, walk the cumulative mass
+    let center = Vec2::new(cx as f32, cy as f32);
+    let mut by_radius: Vec<(f32, f32)> = data
+        .iter()
+        .map(|b| ((Vec2::new(b.x, b.y) - center).length(), b.mass))
+        .collect();
+    by_radius.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let total: f64 = by_radius.iter().map(|&(_, m)| m as f64).sum();
+    let mut radii = [0.0f32; 3];
+    let mut cumulative = 0.0f64;
+    let mut next = 0;
+    for &(r, m) in &by_radius {
+        cumulative += m as f64;
+        while next < radii.len() && cumulative >= LAGRANGIAN_FRACTIONS[next] as f64 * total {
+            radii[next] = r;
+            next += 1;
+        }
+    }
+
+    Some(Structure {
+        center_of_density: center,
+        core_radius,
+        lagrangian_radii: radii,
+    })
+}
+
+pub fn update_structure(
+    bodies: Res<Bodies>,
+    mut diag: ResMut<StructureDiagnostics>,
+    mut log: ResMut<DiagnosticsLog>,
+) {
+    let step = bodies.step;
+    if !is_due(diag.last_step, step, diag.interval) {
+        return;
+    }
+    diag.last_step = Some(step);
+    diag.latest = compute(&bodies.data);
+
+    if let Some(s) = diag.latest {
+        log.record(
+            "structure",
+            bodies.elapsed_time,
+            &["cod_x", "cod_y", "r_core", "r10", "r50", "r90"],
+            &[
+                s.center_of_density.x,
+                s.center_of_density.y,
+                s.core_radius,
+                s.lagrangian_radii[0],
+                s.lagrangian_radii[1],
+                s.lagrangian_radii[2],
+            ]
+            .map(|v| CODE.length(v) as f64),
+        );
+    }
+}
+
+pub fn update_structure_text(
+    diag: Res<StructureDiagnostics>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<UiStructure>>,
+) {
+    if !diag.is_changed() && !lang.is_changed() {
+        return;
+    }
+    let Some(s) = diag.latest else {
+        return;
+    };
+    if let Ok(mut t) = q.get_single_mut() {
+        let radii = s.lagrangian_radii.map(|r| CODE.length(r));
+        t.sections[0].value = format!(
+            "{}: {:.2E} m   {}: {:.2E} m\n{} 10/50/90%: {:.2E} / {:.2E} / {:.2E} m",
+            tr("core radius", "코어 반경").get(*lang),
+            CODE.length(s.core_radius),
+            tr("half-mass radius", "반질량 반경").get(*lang),
+            radii[1],
+            tr("Lagrangian radii", "라그랑주 반경").get(*lang),
+            radii[0],
+            radii[1],
+            radii[2],
+        );
+    }
+}
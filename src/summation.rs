@@ -0,0 +1,76 @@
+//! Compensated (Kahan–Babuška / Neumaier) summation for the energy and
+//! momentum totals, where terms span many orders of magnitude.
+
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+/// Running sum carrying the low-order bits lost by each addition
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    pub fn add(&mut self, x: f64) {
+        let t = self.sum + x;
+        // Recover whichever operand lost its low bits (Neumaier 1974)
+        self.compensation += if self.sum.abs() >= x.abs() {
+            (self.sum - t) + x
+        } else {
+            (x - t) + self.sum
+        };
+        self.sum = t;
+    }
+
+    pub fn value(self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+impl AddAssign<f64> for CompensatedSum {
+    fn add_assign(&mut self, x: f64) {
+        self.add(x);
+    }
+}
+
+impl Sum<f64> for CompensatedSum {
+    fn sum<I: Iterator<Item = f64>>(iter: I) -> Self {
+        let mut s = Self::default();
+        for x in iter {
+            s.add(x);
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1e30 mass term, a million unit terms, then the 1e30 term removed
+    fn wide_range() -> impl Iterator<Item = f64> {
+        std::iter::once(1.0E30)
+            .chain(std::iter::repeat_n(1.0, 1_000_000))
+            .chain(std::iter::once(-1.0E30))
+    }
+
+    #[test]
+    fn keeps_small_terms_next_to_huge_ones() {
+        let naive: f64 = wide_range().sum();
+        let compensated = wide_range().sum::<CompensatedSum>().value();
+        assert_eq!(compensated, 1.0E6);
+        assert_ne!(
+            naive, 1.0E6,
+            "naive summation was expected to lose the unit terms"
+        );
+    }
+
+    #[test]
+    fn recovers_the_larger_operand_too() {
+        // Neumaier's case where plain Kahan fails: the new term dominates the sum
+        let terms = [1.0, 1.0E100, 1.0, -1.0E100];
+        assert_eq!(terms.iter().copied().sum::<f64>(), 0.0);
+        assert_eq!(terms.iter().copied().sum::<CompensatedSum>().value(), 2.0);
+    }
+}
@@ -0,0 +1,55 @@
+//! Berendsen velocity-rescaling thermostat for molecular-dynamics runs.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::BodyState;
+use crate::force_law::ForceLaw;
+use crate::summation::CompensatedSum;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct ThermostatConfig {
+    /// Target temperature in reduced units (ε / k_B)
+    pub temperature: f32,
+    /// Relaxation time towards the target (s); a few hundred steps is gentle
+    pub relaxation_time: f32,
+}
+
+/// Thermostat for the current run, if its scenario asks for one
+#[derive(Resource, Default)]
+pub struct Thermostat(pub Option<ThermostatConfig>);
+
+impl Thermostat {
+    /// Rescale velocities by λ = sqrt(1 + dt/τ (T0/T − 1)). Temperatures are
+    /// measured in the Lennard-Jones energy unit, so other laws are left alone.
+    pub fn apply(&self, data: &mut [BodyState], dt: f32, law: ForceLaw) {
+        let (Some(cfg), ForceLaw::LennardJones(lj)) = (self.0, law) else {
+            return;
+        };
+        if data.is_empty() {
+            return;
+        }
+        let t = reduced_temperature(data, lj.epsilon);
+        if t <= 0.0 {
+            return;
+        }
+        let ratio = cfg.temperature as f64 / t;
+        let lambda = (1.0 + dt as f64 / cfg.relaxation_time as f64 * (ratio - 1.0))
+            .max(0.0)
+            .sqrt() as f32;
+        for b in data.iter_mut() {
+            b.vx *= lambda;
+            b.vy *= lambda;
+        }
+    }
+}
+
+/// k_B T / ε from the kinetic energy; in 2D each body carries k_B T on average
+pub fn reduced_temperature(data: &[BodyState], epsilon: f32) -> f64 {
+    let ke: f64 = data
+        .iter()
+        .map(|b| 0.5 * b.mass as f64 * (b.vx * b.vx + b.vy * b.vy) as f64)
+        .sum::<CompensatedSum>()
+        .value();
+    ke / (data.len() as f64 * epsilon as f64)
+}
@@ -0,0 +1,168 @@
+//! Per-phase timing budget. The tracing spans already placed around the
+//! expensive work (force pass, energies, kicks, visuals, ...) are timed by
+//! a log layer, and the main Update chain is cut into phases (input,
+//! analysis, visuals, UI) by marker systems between its groups. Built with
+//! `--features inspector`, a Performance window lists every span and phase
+//! with its time in the last frame, the average over the last
+//! `ROLLING_FRAMES` frames and a bar relative to the frame time.
+//!
+//! Nested spans are listed by their path ("force/neighbor_grid") and their
+//! time is included in their parent's. The force pass runs on the async
+//! pool and is counted in the frame it finished in.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use bevy::log::BoxedLayer;
+use bevy::log::tracing_subscriber::Layer;
+use bevy::log::tracing_subscriber::layer::Context;
+use bevy::log::tracing_subscriber::registry::LookupSpan;
+use bevy::prelude::*;
+use bevy::utils::tracing::{Subscriber, span};
+
+/// Time spent under each span path or phase since the last frame was shown
+static SPENT: Mutex<Option<HashMap<String, Duration>>> = Mutex::new(None);
+
+fn record(name: &str, elapsed: Duration) {
+    let mut spent = SPENT.lock().unwrap();
+    *spent
+        .get_or_insert_with(HashMap::new)
+        .entry(name.to_string())
+        .or_default() += elapsed;
+}
+
+/// For `LogPlugin::custom_layer`: time every span, only when the timing
+/// window can be shown
+pub fn span_layer(_app: &mut App) -> Option<BoxedLayer> {
+    cfg!(feature = "inspector").then(|| Box::new(SpanTimer) as BoxedLayer)
+}
+
+struct SpanTimer;
+
+/// When the span was last entered
+struct Entered(Instant);
+
+impl<S> Layer<S> for SpanTimer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().replace(Entered(Instant::now()));
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let Some(Entered(start)) = span.extensions_mut().remove::<Entered>() else {
+            return;
+        };
+        let path: Vec<&str> = span.scope().from_root().map(|s| s.name()).collect();
+        record(&path.join("/"), start.elapsed());
+    }
+}
+
+/// End of the last phase of the Update chain
+#[derive(Resource)]
+pub struct PhaseClock(Instant);
+
+impl Default for PhaseClock {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+
+pub fn begin_phases(mut clock: ResMut<PhaseClock>) {
+    clock.0 = Instant::now();
+}
+
+/// Marker system: the time since the previous marker goes to `name`
+pub fn end_phase(name: &'static str) -> impl FnMut(ResMut<PhaseClock>) {
+    move |mut clock: ResMut<PhaseClock>| {
+        let now = Instant::now();
+        record(name, now - clock.0);
+        clock.0 = now;
+    }
+}
+
+pub struct TimingPlugin;
+
+impl Plugin for TimingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhaseClock>();
+        #[cfg(feature = "inspector")]
+        app.init_resource::<window::TimingHistory>()
+            .add_systems(Update, window::show_timings);
+    }
+}
+
+#[cfg(feature = "inspector")]
+mod window {
+    use std::collections::{BTreeMap, VecDeque};
+
+    use bevy::prelude::*;
+    use bevy_inspector_egui::bevy_egui::EguiContexts;
+    use bevy_inspector_egui::egui;
+
+    use super::SPENT;
+
+    /// Frames averaged per row
+    const ROLLING_FRAMES: usize = 60;
+
+    /// Recent per-frame times (ms) of each row, by name
+    #[derive(Resource, Default)]
+    pub struct TimingHistory {
+        rows: BTreeMap<String, VecDeque<f32>>,
+    }
+
+    pub fn show_timings(
+        time: Res<Time<Real>>,
+        mut history: ResMut<TimingHistory>,
+        mut contexts: EguiContexts,
+    ) {
+        let spent = SPENT.lock().unwrap().take().unwrap_or_default();
+        for (name, samples) in history.rows.iter_mut() {
+            let ms = spent.get(name).map_or(0.0, |d| d.as_secs_f32() * 1e3);
+            samples.push_back(ms);
+        }
+        for (name, d) in spent {
+            history
+                .rows
+                .entry(name)
+                .or_insert_with(|| VecDeque::from([d.as_secs_f32() * 1e3]));
+        }
+        for samples in history.rows.values_mut() {
+            while samples.len() > ROLLING_FRAMES {
+                samples.pop_front();
+            }
+        }
+
+        let frame_ms = (time.delta_secs() * 1e3).max(f32::EPSILON);
+        egui::Window::new("Performance").show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("frame {frame_ms:.2} ms"));
+            egui::Grid::new("timings").striped(true).show(ui, |ui| {
+                ui.label("span / phase");
+                ui.label("last ms");
+                ui.label("avg ms");
+                ui.label("share of the frame");
+                ui.end_row();
+                for (name, samples) in &history.rows {
+                    let last = samples.back().copied().unwrap_or(0.0);
+                    let avg = samples.iter().sum::<f32>() / samples.len().max(1) as f32;
+                    ui.label(name);
+                    ui.label(format!("{last:.2}"));
+                    ui.label(format!("{avg:.2}"));
+                    ui.add(
+                        egui::ProgressBar::new((avg / frame_ms).min(1.0))
+                            .desired_width(160.0)
+                            .text(format!("{:.0}%", 100.0 * avg / frame_ms)),
+                    );
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
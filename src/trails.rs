@@ -0,0 +1,313 @@
+//! Long-exposure trails without per-body history: a camera draws the body
+//! points into an offscreen image on top of last frame's image, dimmed by
+//! the decay factor. The result is added onto the background, behind the
+//! live points. Two images swap roles every frame, since a pass can't read
+//! the texture it draws into.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::mesh::MeshVertexBufferLayoutRef;
+use bevy::render::render_resource::{
+    AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, Extent3d,
+    RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, TextureDimension,
+    TextureFormat, TextureUsages,
+};
+use bevy::render::view::RenderLayers;
+use bevy::sprite::{AlphaMode2d, Material2d, Material2dKey};
+use bevy::window::PrimaryWindow;
+
+use crate::MainCamera;
+use crate::keybindings::{Action, KeyBindings};
+use crate::starfield::{self, BackgroundCamera};
+
+const SHADER_PATH: &str = "shaders/trail_composite.wgsl";
+
+/// Render layer seen only by the trail camera (besides the bodies)
+pub const LAYER: usize = 2;
+pub const DECAY_RANGE: (f32, f32) = (0.8, 0.995);
+const DECAY_STEP: f32 = 0.005;
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Trails {
+    pub enabled: bool,
+    /// Brightness kept per frame
+    pub decay: f32,
+    images: [Handle<Image>; 2],
+    /// Index of the image drawn into this frame
+    front: usize,
+}
+
+impl Trails {
+    pub fn set_decay(&mut self, decay: f32) {
+        self.decay = decay.clamp(DECAY_RANGE.0, DECAY_RANGE.1);
+    }
+}
+
+#[derive(Component)]
+pub struct TrailCamera;
+
+/// Last frame's image, dimmed, underneath this frame's points (which sit at z = 0)
+#[derive(Component)]
+pub struct TrailFade;
+
+/// The accumulated image, shown behind the live points
+#[derive(Component)]
+pub struct TrailComposite;
+
+/// Adds a texture onto whatever is behind it
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct TrailMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    pub image: Handle<Image>,
+}
+
+impl Material2d for TrailMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let additive = BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::One,
+            operation: BlendOperation::Add,
+        };
+        if let Some(target) = descriptor
+            .fragment
+            .as_mut()
+            .and_then(|f| f.targets.first_mut())
+            .and_then(|t| t.as_mut())
+        {
+            target.blend = Some(BlendState {
+                color: additive,
+                alpha: BlendComponent::OVER,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A black, HDR render target
+fn trail_image(width: u32, height: u32) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        // Half floats, so dim trails keep fading instead of sticking at 1/255
+        &[0; 8],
+        TextureFormat::Rgba16Float,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+pub fn spawn_trails(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TrailMaterial>>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+) {
+    let (width, height) = win_q
+        .get_single()
+        .map(|w| (w.physical_width(), w.physical_height()))
+        .unwrap_or((1, 1));
+    let handles = [
+        images.add(trail_image(width, height)),
+        images.add(trail_image(width, height)),
+    ];
+
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: -2,
+            is_active: false,
+            hdr: true,
+            target: RenderTarget::Image(handles[0].clone()),
+            clear_color: ClearColorConfig::Custom(Color::BLACK),
+            ..Default::default()
+        },
+        Tonemapping::None,
+        Msaa::Off,
+        RenderLayers::layer(LAYER),
+        TrailCamera,
+    ));
+    commands.spawn((
+        Sprite::from_image(handles[1].clone()),
+        Transform::default(),
+        Visibility::Hidden,
+        RenderLayers::layer(LAYER),
+        TrailFade,
+    ));
+    commands.spawn((
+        Mesh2d(meshes.add(Rectangle::new(1.0, 1.0))),
+        MeshMaterial2d(materials.add(TrailMaterial {
+            image: handles[0].clone(),
+        })),
+        // Above the stars and nebulae
+        Transform::from_xyz(0.0, 0.0, 1.0),
+        Visibility::Hidden,
+        RenderLayers::layer(starfield::LAYER),
+        TrailComposite,
+    ));
+    commands.insert_resource(Trails {
+        enabled: false,
+        decay: 0.95,
+        images: handles,
+        front: 0,
+    });
+}
+
+pub fn toggle_trails(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut trails: ResMut<Trails>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleTrails) {
+        trails.enabled = !trails.enabled;
+    }
+    let step = if bindings.just_pressed(&keys, Action::TrailDecayUp) {
+        DECAY_STEP
+    } else if bindings.just_pressed(&keys, Action::TrailDecayDown) {
+        -DECAY_STEP
+    } else {
+        return;
+    };
+    let decay = trails.decay + step;
+    trails.set_decay(decay);
+    info!("Trail decay: {:.3} per frame", trails.decay);
+}
+
+fn blank(trails: &Trails, images: &mut Assets<Image>) {
+    for handle in &trails.images {
+        if let Some(image) = images.get_mut(handle) {
+            image.data.fill(0);
+        }
+    }
+}
+
+/// A new run starts without the last one's trails
+pub fn clear_trails(trails: Res<Trails>, mut images: ResMut<Assets<Image>>) {
+    blank(&trails, &mut images);
+}
+
+/// Swap the images, keep the trail camera on the main camera's view and
+/// the two screen-filling quads on theirs
+pub fn update_trails(
+    mut trails: ResMut<Trails>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<TrailMaterial>>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    main_q: Query<
+        (&Transform, &OrthographicProjection),
+        (
+            With<MainCamera>,
+            Without<TrailCamera>,
+            Without<BackgroundCamera>,
+        ),
+    >,
+    bg_q: Query<
+        (&Transform, &OrthographicProjection),
+        (With<BackgroundCamera>, Without<TrailCamera>),
+    >,
+    mut cam_q: Query<(&mut Camera, &mut Transform, &mut OrthographicProjection), With<TrailCamera>>,
+    mut fade_q: Query<
+        (&mut Sprite, &mut Transform, &mut Visibility),
+        (With<TrailFade>, Without<Camera>, Without<TrailComposite>),
+    >,
+    mut composite_q: Query<
+        (
+            &MeshMaterial2d<TrailMaterial>,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        (With<TrailComposite>, Without<Camera>, Without<TrailFade>),
+    >,
+) {
+    let (
+        Ok(window),
+        Ok((main_tf, main_proj)),
+        Ok((bg_tf, bg_proj)),
+        Ok((mut cam, mut cam_tf, mut cam_proj)),
+        Ok((mut fade, mut fade_tf, mut fade_vis)),
+        Ok((material, mut composite_tf, mut composite_vis)),
+    ) = (
+        win_q.get_single(),
+        main_q.get_single(),
+        bg_q.get_single(),
+        cam_q.get_single_mut(),
+        fade_q.get_single_mut(),
+        composite_q.get_single_mut(),
+    )
+    else {
+        return;
+    };
+
+    let visibility = if trails.enabled {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    fade_vis.set_if_neq(visibility);
+    composite_vis.set_if_neq(visibility);
+    if cam.is_active != trails.enabled {
+        if trails.enabled {
+            blank(&trails, &mut images);
+        }
+        cam.is_active = trails.enabled;
+    }
+    if !trails.enabled {
+        return;
+    }
+
+    // Follow window resizes; resizing also blanks the image
+    let size = Extent3d {
+        width: window.physical_width().max(1),
+        height: window.physical_height().max(1),
+        depth_or_array_layers: 1,
+    };
+    for handle in &trails.images {
+        if let Some(image) = images.get_mut(handle) {
+            if image.texture_descriptor.size != size {
+                image.resize(size);
+            }
+        }
+    }
+
+    trails.front ^= 1;
+    let front = trails.images[trails.front].clone();
+    let back = trails.images[trails.front ^ 1].clone();
+    cam.target = RenderTarget::Image(front.clone());
+    *cam_tf = *main_tf;
+    *cam_proj = main_proj.clone();
+
+    let screen = window.size();
+    fade.image = back;
+    fade.color = Color::linear_rgb(trails.decay, trails.decay, trails.decay);
+    fade.custom_size = Some(screen);
+    *fade_tf = Transform::from_translation(main_tf.translation.truncate().extend(-1.0))
+        .with_scale(Vec3::splat(main_proj.scale));
+
+    if let Some(material) = materials.get_mut(&material.0) {
+        material.image = front;
+    }
+    *composite_tf = Transform::from_translation(bg_tf.translation.truncate().extend(1.0))
+        .with_scale((screen * bg_proj.scale).extend(1.0));
+}
@@ -0,0 +1,295 @@
+//! Guided tutorial: a scripted sequence of small scenarios with prompts that
+//! advance when the user does what they ask (pause, zoom, select, ...).
+
+use bevy::prelude::*;
+
+use crate::MainCamera;
+use crate::constants::PhysicsConstants;
+use crate::keybindings::{Action, KeyBindings};
+use crate::locale::{Language, Tr, fill, tr};
+use crate::menu::{AppState, start_run};
+use crate::scenario::{BodySpec, InitialConditions, Scenario};
+use crate::selection::Selection;
+use crate::sim_rng::SimRng;
+
+/// What the user has to do to finish a stage
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Goal {
+    Pause,
+    Resume,
+    /// Change the camera zoom
+    Zoom,
+    Select,
+    /// Just watch for this many wall-clock seconds
+    Watch(f32),
+}
+
+struct Stage {
+    /// Scenario to (re)start when the stage begins; `None` keeps the current run
+    scenario: Option<fn() -> Scenario>,
+    prompt: Tr,
+    goal: Goal,
+}
+
+const STAGES: &[Stage] = &[
+    Stage {
+        scenario: Some(two_body),
+        prompt: tr(
+            "Two stars orbit their common center of mass.\nPress Space to pause.",
+            "두 별이 공통 질량 중심을 돌고 있습니다.\nSpace 키를 눌러 일시정지하세요.",
+        ),
+        goal: Goal::Pause,
+    },
+    Stage {
+        scenario: None,
+        prompt: tr(
+            "Paused. Press Space again to resume.",
+            "일시정지되었습니다. Space 키를 다시 눌러 재개하세요.",
+        ),
+        goal: Goal::Resume,
+    },
+    Stage {
+        scenario: None,
+        prompt: tr(
+            "Scroll the mouse wheel to zoom in or out.",
+            "마우스 휠을 굴려 확대하거나 축소하세요.",
+        ),
+        goal: Goal::Zoom,
+    },
+    Stage {
+        scenario: Some(three_body),
+        prompt: tr(
+            "Three stars released from rest: the three-body problem is chaotic.\nClick a star to select it.",
+            "정지 상태에서 놓인 세 별: 삼체 문제는 혼돈계입니다.\n별을 클릭해 선택하세요.",
+        ),
+        goal: Goal::Select,
+    },
+    Stage {
+        scenario: None,
+        prompt: tr(
+            "The ring follows the selected star; its orbit is shown at the bottom right.\nWatch how close encounters fling stars around.",
+            "고리가 선택한 별을 따라가고, 궤도는 오른쪽 아래에 표시됩니다.\n근접 조우가 별들을 어떻게 튕겨 내는지 지켜보세요.",
+        ),
+        goal: Goal::Watch(15.0),
+    },
+    Stage {
+        scenario: Some(cluster_collapse),
+        prompt: tr(
+            "A cold cloud of stars has no motion to resist gravity and collapses.\nWatch it fall together and bounce back.",
+            "차가운 별 구름은 중력에 맞설 운동이 없어 붕괴합니다.\n함께 떨어졌다가 다시 튀어 오르는 모습을 지켜보세요.",
+        ),
+        goal: Goal::Watch(25.0),
+    },
+];
+
+fn two_body() -> Scenario {
+    // a = 2e14 m, M1 = 1e33 kg, M2 = 2e32 kg on circular orbits about the origin
+    Scenario {
+        name: "Tutorial: two-body orbit".into(),
+        initial: InitialConditions::Explicit(vec![
+            BodySpec {
+                position: (-3.33E13, 0.0),
+                velocity: (0.0, -3.33E03),
+                mass: 1.0E33,
+            },
+            BodySpec {
+                position: (1.667E14, 0.0),
+                velocity: (0.0, 1.667E04),
+                mass: 2.0E32,
+            },
+        ]),
+        dt: Some(1.0E08),
+        ..Default::default()
+    }
+    .in_code_units()
+}
+
+fn three_body() -> Scenario {
+    // Burrau's Pythagorean problem: masses 3, 4, 5 at rest on a 3-4-5 triangle
+    let unit = 5.0E13;
+    let body = |x: f32, y: f32, m: f32| BodySpec {
+        position: (x * unit, y * unit),
+        velocity: (0.0, 0.0),
+        mass: m * 1.0E32,
+    };
+    Scenario {
+        name: "Tutorial: three-body chaos".into(),
+        initial: InitialConditions::Explicit(vec![
+            body(1.0, 3.0, 3.0),
+            body(-2.0, -1.0, 4.0),
+            body(1.0, -1.0, 5.0),
+        ]),
+        dt: Some(2.0E07),
+        ..Default::default()
+    }
+    .in_code_units()
+}
+
+fn cluster_collapse() -> Scenario {
+    Scenario {
+        name: "Tutorial: cold collapse".into(),
+        initial: InitialConditions::generated("uniform", &[("size", 6.0E14)]),
+        bodies: Some(300),
+        dt: Some(5.0E07),
+        ..Default::default()
+    }
+    .in_code_units()
+}
+
+#[derive(Resource, Default)]
+pub struct Tutorial {
+    /// Current stage, `None` outside the tutorial
+    stage: Option<usize>,
+    /// Stage start (wall-clock seconds) for the watch goals
+    started: f32,
+    /// Camera zoom when the stage started
+    zoom: f32,
+    finished: bool,
+}
+
+impl Tutorial {
+    pub fn active(&self) -> bool {
+        self.stage.is_some()
+    }
+}
+
+/// Start the scenario of a stage, if it has one
+fn enter_stage(
+    index: usize,
+    commands: &mut Commands,
+    constants: &PhysicsConstants,
+    rng: &mut SimRng,
+) {
+    let Some(make) = STAGES[index].scenario else {
+        return;
+    };
+    let scenario = make();
+    let count = scenario.bodies.unwrap_or(0);
+    start_run(commands, scenario, count, constants, rng);
+}
+
+/// Menu key: begin the tutorial at its first stage
+pub fn start_tutorial(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time<Real>>,
+    constants: Res<PhysicsConstants>,
+    mut rng: ResMut<SimRng>,
+    mut tutorial: ResMut<Tutorial>,
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !bindings.just_pressed(&keys, Action::MenuTutorial) {
+        return;
+    }
+    *tutorial = Tutorial {
+        stage: Some(0),
+        started: time.elapsed_secs(),
+        zoom: 0.0,
+        finished: false,
+    };
+    enter_stage(0, &mut commands, &constants, &mut rng);
+    next_state.set(AppState::Running);
+}
+
+/// Leaving for the menu ends the tutorial
+pub fn stop_tutorial(mut tutorial: ResMut<Tutorial>, mut q: Query<&mut Text, With<TutorialText>>) {
+    *tutorial = Tutorial::default();
+    // The UI systems don't run in the menu, so clear the prompt here
+    if let Ok(mut t) = q.get_single_mut() {
+        t.sections[0].value.clear();
+    }
+}
+
+/// Check the current goal and move on once it is met
+pub fn advance_tutorial(
+    time: Res<Time<Real>>,
+    state: Res<State<AppState>>,
+    selection: Res<Selection>,
+    constants: Res<PhysicsConstants>,
+    mut rng: ResMut<SimRng>,
+    cam_q: Query<&OrthographicProjection, With<MainCamera>>,
+    mut tutorial: ResMut<Tutorial>,
+    mut commands: Commands,
+) {
+    let Some(index) = tutorial.stage else {
+        return;
+    };
+    let zoom = cam_q.get_single().map_or(1.0, |p| p.scale);
+    if tutorial.zoom == 0.0 {
+        tutorial.zoom = zoom;
+    }
+    let now = time.elapsed_secs();
+    let done = match STAGES[index].goal {
+        Goal::Pause => *state.get() == AppState::Paused,
+        Goal::Resume => *state.get() == AppState::Running,
+        Goal::Zoom => (zoom - tutorial.zoom).abs() > 1e-3,
+        Goal::Select => selection.0.is_some(),
+        Goal::Watch(seconds) => now - tutorial.started >= seconds,
+    };
+    if !done {
+        return;
+    }
+    let next = index + 1;
+    if next == STAGES.len() {
+        tutorial.stage = None;
+        tutorial.finished = true;
+        return;
+    }
+    tutorial.stage = Some(next);
+    tutorial.started = now;
+    tutorial.zoom = zoom;
+    enter_stage(next, &mut commands, &constants, &mut rng);
+}
+
+#[derive(Component)]
+pub struct TutorialText;
+
+pub fn spawn_tutorial_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 20.0,
+                color: Color::srgb(0.7, 0.9, 1.0),
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            right: Val::Px(20.0),
+            bottom: Val::Px(80.0),
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        }),
+        TutorialText,
+    ));
+}
+
+pub fn update_tutorial_text(
+    tutorial: Res<Tutorial>,
+    lang: Res<Language>,
+    mut q: Query<&mut Text, With<TutorialText>>,
+) {
+    if !tutorial.is_changed() && !lang.is_changed() {
+        return;
+    }
+    let Ok(mut t) = q.get_single_mut() else {
+        return;
+    };
+    t.sections[0].value = match tutorial.stage {
+        Some(i) => fill(
+            tr("Tutorial {}/{}\n{}", "튜토리얼 {}/{}\n{}").get(*lang),
+            &[&(i + 1), &STAGES.len(), &STAGES[i].prompt.get(*lang)],
+        ),
+        None if tutorial.finished => tr(
+            "Tutorial complete! Press Esc for the scenario menu.",
+            "튜토리얼 완료! Esc 키를 눌러 시나리오 메뉴로 가세요.",
+        )
+        .get(*lang)
+        .to_string(),
+        None => String::new(),
+    };
+}
@@ -0,0 +1,212 @@
+//! Units. The simulation runs in code units ([`CODE`]): G and the Coulomb
+//! constant are 1, and positions, masses, speeds and the timestep all sit
+//! within a few decades of 1 instead of at 1e14 or 1e29, well inside the
+//! f32 range even once squared or cubed. Values come in from scenario
+//! files, the constants file, the console and the command line in SI (or a
+//! scenario's own units) and are converted once, on the way in; readouts
+//! and exports convert back with [`CODE`].
+//!
+//! A scenario names its units and every dimensional value in it is
+//! converted when it is loaded:
+//!
+//! ```ron
+//! (
+//!     units: Astronomical,
+//!     initial: Generated(generator: "plummer", params: [("scale_radius", 300.0)]),
+//!     dt: Some(0.5),
+//! )
+//! ```
+
+use serde::Deserialize;
+
+/// Gravitational constant (m^3 kg^-1 s^-2)
+pub const G_SI: f64 = 6.67E-11;
+/// Coulomb constant (N m^2 C^-2)
+pub const K_SI: f64 = 8.99E09;
+
+/// Astronomical unit (m)
+const AU: f64 = 1.495_978_707E11;
+/// Solar mass (kg)
+const SOLAR_MASS: f64 = 1.989E30;
+/// Julian year (s)
+const YEAR: f64 = 3.155_76E7;
+
+/// Size of the code units in SI: 1e14 m (the initial field spans ±5),
+/// 1e30 kg, and the time and charge units that make G and k exactly 1
+pub const CODE: UnitScale = UnitScale {
+    length: 1.0E14,
+    mass: 1.0E30,
+    // sqrt(length³ / (G mass))
+    time: 1.224_438_799_945_752E11,
+    // mass sqrt(G / k)
+    charge: 8.613_567_692_141_091E19,
+};
+
+/// Size of the SI units in code units: converts SI values into code units
+pub const SI: UnitScale = CODE.inverse();
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum UnitSystem {
+    /// Meters, kilograms, seconds
+    #[default]
+    Si,
+    /// AU, solar masses, years
+    Astronomical,
+    /// G = 1 with these length (m) and mass (kg) units; the time unit
+    /// follows as sqrt(length³ / (G mass))
+    NBody { length: f64, mass: f64 },
+    /// The simulation's own units, see [`CODE`]
+    Code,
+}
+
+impl UnitSystem {
+    /// Converts values in this system into code units. Charges are in
+    /// coulombs in every system but this one.
+    pub fn scale(self) -> UnitScale {
+        let (length, mass, time, charge) = match self {
+            UnitSystem::Si => (1.0, 1.0, 1.0, 1.0),
+            UnitSystem::Astronomical => (AU, SOLAR_MASS, YEAR, 1.0),
+            UnitSystem::NBody { length, mass } => {
+                (length, mass, (length.powi(3) / (G_SI * mass)).sqrt(), 1.0)
+            }
+            UnitSystem::Code => return UnitScale::ONE,
+        };
+        UnitScale {
+            length: length * SI.length,
+            mass: mass * SI.mass,
+            time: time * SI.time,
+            charge: charge * SI.charge,
+        }
+    }
+}
+
+/// Size of one length, mass, time and charge unit of one system measured
+/// in another. The methods take a value in the first system and return it
+/// in the second.
+#[derive(Clone, Copy, Debug)]
+pub struct UnitScale {
+    pub length: f64,
+    pub mass: f64,
+    pub time: f64,
+    pub charge: f64,
+}
+
+impl UnitScale {
+    const ONE: UnitScale = UnitScale {
+        length: 1.0,
+        mass: 1.0,
+        time: 1.0,
+        charge: 1.0,
+    };
+
+    /// The conversion the other way
+    pub const fn inverse(&self) -> UnitScale {
+        UnitScale {
+            length: 1.0 / self.length,
+            mass: 1.0 / self.mass,
+            time: 1.0 / self.time,
+            charge: 1.0 / self.charge,
+        }
+    }
+
+    const fn apply(value: f32, factor: f64) -> f32 {
+        (value as f64 * factor) as f32
+    }
+
+    pub const fn length(&self, v: f32) -> f32 {
+        Self::apply(v, self.length)
+    }
+
+    pub const fn mass(&self, v: f32) -> f32 {
+        Self::apply(v, self.mass)
+    }
+
+    pub const fn time(&self, v: f32) -> f32 {
+        Self::apply(v, self.time)
+    }
+
+    pub const fn charge(&self, v: f32) -> f32 {
+        Self::apply(v, self.charge)
+    }
+
+    /// Rates: angular velocities, fractions per unit time
+    pub const fn rate(&self, v: f32) -> f32 {
+        Self::apply(v, 1.0 / self.time)
+    }
+
+    pub const fn velocity(&self, v: f32) -> f32 {
+        Self::apply(v, self.length / self.time)
+    }
+
+    pub const fn acceleration(&self, v: f32) -> f32 {
+        Self::apply(v, self.length / (self.time * self.time))
+    }
+
+    pub const fn energy(&self, v: f32) -> f32 {
+        self.energy_f64(v as f64) as f32
+    }
+
+    /// Energies summed in f64
+    pub const fn energy_f64(&self, v: f64) -> f64 {
+        v * self.mass * self.length * self.length / (self.time * self.time)
+    }
+
+    /// Mass per area
+    pub const fn surface_density(&self, v: f64) -> f64 {
+        v * self.mass / (self.length * self.length)
+    }
+
+    pub const fn per_mass(&self, v: f32) -> f32 {
+        Self::apply(v, self.charge / self.mass)
+    }
+
+    /// Spring constants (force per length)
+    pub const fn stiffness(&self, v: f32) -> f32 {
+        Self::apply(v, self.mass / (self.time * self.time))
+    }
+
+    /// The gravitational constant
+    pub const fn gravitation(&self, v: f32) -> f32 {
+        Self::apply(
+            v,
+            self.length * self.length * self.length / (self.mass * self.time * self.time),
+        )
+    }
+
+    /// The Coulomb constant
+    pub const fn coulomb(&self, v: f32) -> f32 {
+        Self::apply(
+            v,
+            self.mass * self.length * self.length * self.length
+                / (self.time * self.time * self.charge * self.charge),
+        )
+    }
+
+    pub const fn point(&self, p: (f32, f32)) -> (f32, f32) {
+        (self.length(p.0), self.length(p.1))
+    }
+
+    pub const fn velocity2(&self, v: (f32, f32)) -> (f32, f32) {
+        (self.velocity(v.0), self.velocity(v.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_units_make_g_and_k_one() {
+        assert!((SI.gravitation(G_SI as f32) - 1.0).abs() < 1e-6);
+        assert!((SI.coulomb(K_SI as f32) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        let s = UnitSystem::Astronomical.scale();
+        let back = s.inverse();
+        assert!((back.length(s.length(300.0)) - 300.0).abs() < 1e-3);
+        assert_eq!(UnitSystem::Code.scale().velocity(2.5), 2.5);
+        assert!((UnitSystem::Si.scale().length(5.0E14) - 5.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,167 @@
+//! Video export: every rendered frame is captured and piped as raw pixels
+//! into an `ffmpeg` child process, which encodes the mp4 or webm directly.
+//! While recording, each frame advances time by exactly 1/fps, so the video
+//! plays at the simulation's own pace however slowly frames render.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::time::TimeUpdateStrategy;
+
+pub const DEFAULT_FPS: u32 = 30;
+
+#[derive(Resource, Default)]
+pub struct VideoExport {
+    /// Output file; `None` when not recording
+    path: Option<PathBuf>,
+    fps: u32,
+    /// Frames to record, `None` until the app closes
+    frames: Option<u64>,
+    requested: u64,
+    written: u64,
+    ffmpeg: Option<(Child, ChildStdin)>,
+}
+
+impl VideoExport {
+    pub fn new(path: PathBuf, fps: u32, duration: Option<f64>) -> Self {
+        let fps = fps.max(1);
+        Self {
+            path: Some(path),
+            fps,
+            frames: duration.map(|s| (s * fps as f64).round().max(1.0) as u64),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Fixed time step per frame while recording
+    pub fn time_strategy(&self) -> TimeUpdateStrategy {
+        if self.is_recording() {
+            TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / self.fps as f64))
+        } else {
+            TimeUpdateStrategy::Automatic
+        }
+    }
+
+    /// Start ffmpeg once the first frame tells us its size and pixel layout
+    fn spawn_ffmpeg(&self, image: &Image) -> Result<(Child, ChildStdin), String> {
+        let path = self.path.as_ref().ok_or("not recording")?;
+        let pix_fmt = match image.texture_descriptor.format {
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => "bgra",
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => "rgba",
+            other => return Err(format!("unsupported frame format {other:?}")),
+        };
+        let size = image.texture_descriptor.size;
+        let codec: &[&str] = if path.extension().is_some_and(|e| e == "webm") {
+            &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "30"]
+        } else {
+            // yuv420p needs even dimensions
+            &[
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            ]
+        };
+        let mut child = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                pix_fmt,
+            ])
+            .args(["-s", &format!("{}x{}", size.width, size.height)])
+            .args(["-r", &self.fps.to_string(), "-i", "-"])
+            .args(codec)
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to start ffmpeg: {e}"))?;
+        let stdin = child.stdin.take().ok_or("ffmpeg has no stdin")?;
+        info!("Recording {} at {} fps", path.display(), self.fps);
+        Ok((child, stdin))
+    }
+
+    /// Close ffmpeg's input and wait for it to write the file
+    fn finish(&mut self) {
+        let Some((mut child, stdin)) = self.ffmpeg.take() else {
+            return;
+        };
+        drop(stdin);
+        match child.wait() {
+            Ok(status) if status.success() => {
+                if let Some(path) = &self.path {
+                    info!("Wrote {} frames to {}", self.written, path.display());
+                }
+            }
+            Ok(status) => error!("ffmpeg exited with {status}"),
+            Err(e) => error!("failed to wait for ffmpeg: {e}"),
+        }
+        self.path = None;
+    }
+}
+
+/// Ask for a capture of every frame until the requested duration is covered
+pub fn capture_frame(mut commands: Commands, mut video: ResMut<VideoExport>) {
+    if !video.is_recording() || video.frames.is_some_and(|n| video.requested >= n) {
+        return;
+    }
+    video.requested += 1;
+    commands
+        .spawn(Screenshot::primary_window())
+        .observe(write_frame);
+}
+
+fn write_frame(
+    trigger: Trigger<ScreenshotCaptured>,
+    mut video: ResMut<VideoExport>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if !video.is_recording() {
+        return;
+    }
+    let image = &trigger.event().0;
+    if video.ffmpeg.is_none() {
+        match video.spawn_ffmpeg(image) {
+            Ok(ffmpeg) => video.ffmpeg = Some(ffmpeg),
+            Err(e) => {
+                error!("{e}");
+                video.path = None;
+                return;
+            }
+        }
+    }
+    let Some((_, stdin)) = video.ffmpeg.as_mut() else {
+        return;
+    };
+    if let Err(e) = stdin.write_all(&image.data) {
+        error!("failed to write video frame: {e}");
+        video.finish();
+        return;
+    }
+    video.written += 1;
+    if video.frames == Some(video.written) {
+        video.finish();
+        exit.send(AppExit::Success);
+    }
+}
+
+/// Finish the file when the app closes before the duration is reached
+pub fn finish_on_exit(mut exit: EventReader<AppExit>, mut video: ResMut<VideoExport>) {
+    if exit.read().next().is_some() {
+        video.finish();
+    }
+}
@@ -0,0 +1,115 @@
+//! Picture-in-picture zoom view: a second camera rendering a magnified region
+//! around the selected body (or the cursor) into a corner viewport.
+
+use bevy::prelude::*;
+use bevy::render::camera::{ClearColorConfig, Viewport};
+use bevy::window::PrimaryWindow;
+
+use crate::Bodies;
+use crate::MainCamera;
+use crate::keybindings::{Action, KeyBindings};
+use crate::selection::Selection;
+
+/// PiP size as a fraction of the window's shorter side
+const VIEW_FRACTION: f32 = 0.3;
+const VIEW_MARGIN_PX: f32 = 20.0;
+
+#[derive(Component)]
+pub struct ZoomCamera;
+
+#[derive(Resource)]
+pub struct ZoomView {
+    pub enabled: bool,
+    /// Magnification relative to the main camera
+    pub magnification: f32,
+}
+
+impl Default for ZoomView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            magnification: 5.0,
+        }
+    }
+}
+
+pub fn spawn_zoom_camera(mut commands: Commands) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                is_active: false,
+                clear_color: ClearColorConfig::Custom(Color::srgb(0.04, 0.04, 0.08)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ZoomCamera,
+    ));
+}
+
+pub fn toggle_zoom_view(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut view: ResMut<ZoomView>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleZoomView) {
+        view.enabled = !view.enabled;
+    }
+}
+
+pub fn update_zoom_view(
+    view: Res<ZoomView>,
+    selection: Res<Selection>,
+    bodies: Res<Bodies>,
+    win_q: Query<&Window, With<PrimaryWindow>>,
+    main_q: Query<
+        (&Camera, &GlobalTransform, &OrthographicProjection),
+        (With<MainCamera>, Without<ZoomCamera>),
+    >,
+    mut zoom_q: Query<(&mut Camera, &mut Transform, &mut OrthographicProjection), With<ZoomCamera>>,
+) {
+    let Ok((mut cam, mut tf, mut proj)) = zoom_q.get_single_mut() else {
+        return;
+    };
+    cam.is_active = view.enabled;
+    if !view.enabled {
+        return;
+    }
+    let (Ok(window), Ok((main_cam, main_gtf, main_proj))) =
+        (win_q.get_single(), main_q.get_single())
+    else {
+        return;
+    };
+
+    // Corner viewport in physical pixels (bottom right, above the selection panel)
+    let sf = window.scale_factor();
+    let side = window.width().min(window.height()) * VIEW_FRACTION;
+    let pos = Vec2::new(
+        window.width() - side - VIEW_MARGIN_PX,
+        window.height() - side - 4.0 * VIEW_MARGIN_PX,
+    );
+    cam.viewport = Some(Viewport {
+        physical_position: (pos * sf).as_uvec2(),
+        physical_size: UVec2::splat((side * sf) as u32).max(UVec2::ONE),
+        ..Default::default()
+    });
+
+    // Follow the selected body, else the cursor, else the main view center
+    let half = Vec2::new(window.width(), window.height()) / 2.0;
+    let target = selection
+        .0
+        .and_then(|i| bodies.data.get(i))
+        .map(|b| Vec2::new(b.disp_x, b.disp_y) - half)
+        .or_else(|| {
+            window
+                .cursor_position()
+                .and_then(|c| main_cam.viewport_to_world_2d(main_gtf, c).ok())
+        })
+        .unwrap_or(main_gtf.translation().truncate());
+    tf.translation.x = target.x;
+    tf.translation.y = target.y;
+
+    // Same world units per screen pixel as the main view, magnified
+    proj.scale = main_proj.scale / view.magnification.max(1.0);
+}